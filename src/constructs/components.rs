@@ -1,11 +1,17 @@
 use serde::Serialize;
-use std::{collections::HashSet, str::FromStr};
-use time::OffsetDateTime;
+use std::convert::Infallible;
+use std::{collections::HashSet, fmt, str::FromStr};
+use time::{
+    format_description::{self, well_known::Rfc3339},
+    Date, OffsetDateTime, PrimitiveDateTime,
+};
+use ulid::Ulid;
 use uuid::Uuid;
 
-use crate::sql::ValidationError;
+use crate::sql::{quote_identifier, quote_identifiers, ValidationError};
 
 use super::functions::Function;
+use super::statements::{SelectColumn, SelectStatement};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum DataTypeRaw {
@@ -20,12 +26,76 @@ pub enum DataTypeRaw {
     String,
 }
 
+impl DataTypeRaw {
+    /// Whether values of this type can be widened to `target` without any loss of information.
+    pub fn is_widenable_to(&self, target: &DataTypeRaw) -> bool {
+        self == target
+            || (self.as_unsigned_bits().is_some()
+                && self.as_unsigned_bits() <= target.as_unsigned_bits())
+    }
+
+    /// Whether values of this type can be converted to `target` with `DataInstanceRaw::coerce`.
+    pub fn is_coercible_to(&self, target: &DataTypeRaw) -> bool {
+        let is_integer = |raw_type: &DataTypeRaw| raw_type.as_unsigned_bits().is_some();
+        self == target
+            || match (self, target) {
+                (Self::String, _) | (_, Self::String) => {
+                    !matches!(self, Self::Bool) && !matches!(target, Self::Bool)
+                }
+                (Self::Bool, target) => is_integer(target),
+                (source, Self::Bool) => is_integer(source),
+                (source, target) => is_integer(source) && is_integer(target),
+            }
+    }
+
+    pub fn as_unsigned_bits(&self) -> Option<u8> {
+        match self {
+            Self::UInt8 => Some(8),
+            Self::UInt16 => Some(16),
+            Self::UInt32 => Some(32),
+            Self::UInt64 => Some(64),
+            Self::UInt128 => Some(128),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DataTypeRaw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::UInt8 => "UINT8",
+                Self::UInt16 => "UINT16",
+                Self::UInt32 => "UINT32",
+                Self::UInt64 => "UINT64",
+                Self::UInt128 => "UINT128",
+                Self::Bool => "BOOL",
+                Self::Timestamp => "TIMESTAMP",
+                Self::Uuid => "UUID",
+                Self::String => "STRING",
+            }
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DataType {
     pub raw_type: DataTypeRaw,
     pub is_nullable: bool,
 }
 
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_nullable {
+            write!(f, "NULLABLE({})", self.raw_type)
+        } else {
+            write!(f, "{}", self.raw_type)
+        }
+    }
+}
+
 impl FromStr for DataTypeRaw {
     type Err = String;
 
@@ -48,7 +118,7 @@ impl FromStr for DataTypeRaw {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize)]
 #[serde(untagged)]
 pub enum DataInstanceRaw {
     UInt8(u8),
@@ -62,6 +132,145 @@ pub enum DataInstanceRaw {
     String(String),
 }
 
+impl DataInstanceRaw {
+    pub fn get_type(&self) -> DataTypeRaw {
+        match self {
+            Self::UInt8(_) => DataTypeRaw::UInt8,
+            Self::UInt16(_) => DataTypeRaw::UInt16,
+            Self::UInt32(_) => DataTypeRaw::UInt32,
+            Self::UInt64(_) => DataTypeRaw::UInt64,
+            Self::UInt128(_) => DataTypeRaw::UInt128,
+            Self::Bool(_) => DataTypeRaw::Bool,
+            Self::Timestamp(_) => DataTypeRaw::Timestamp,
+            Self::Uuid(_) => DataTypeRaw::Uuid,
+            Self::String(_) => DataTypeRaw::String,
+        }
+    }
+
+    /// Unsigned integer value widened to `u128`, if this is an unsigned integer.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Self::UInt8(value) => Some(u128::from(*value)),
+            Self::UInt16(value) => Some(u128::from(*value)),
+            Self::UInt32(value) => Some(u128::from(*value)),
+            Self::UInt64(value) => Some(u128::from(*value)),
+            Self::UInt128(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Convert this value to `target` type, which is possible according to this matrix (rows are source types):
+    ///
+    /// |           | UINT*                  | BOOL       | TIMESTAMP                  | UUID                | STRING             |
+    /// | --------- | ---------------------- | ---------- | -------------------------- | ------------------- | ------------------ |
+    /// | UINT*     | if the value fits      | true if ≠0 |                            |                     | decimal            |
+    /// | BOOL      | 1 or 0                 | as is      |                            |                     |                    |
+    /// | TIMESTAMP |                        |            | as is                      |                     | RFC 3339           |
+    /// | UUID      |                        |            |                            | as is               | hyphenated         |
+    /// | STRING    | decimal, if it fits    |            | RFC 3339, or without offset as UTC | hyphenated or ULID | as is      |
+    ///
+    /// Values that don't fit or can't be parsed result in an error.
+    pub fn coerce(self, target: DataTypeRaw) -> Result<Self, String> {
+        if self.get_type() == target {
+            return Ok(self);
+        }
+        let out_of_range = || format!("Value {:?} is out of range for type {}", self, target);
+        let unparsable = || format!("Value {:?} cannot be parsed as type {}", self, target);
+        let integer = match (&self, target) {
+            (Self::String(value), _) if target.as_unsigned_bits().is_some() => {
+                Some(value.trim().parse::<u128>().map_err(|_| unparsable())?)
+            }
+            (Self::Bool(value), _) => Some(u128::from(*value)),
+            _ => self.as_u128(),
+        };
+        match (&self, integer, target) {
+            (_, Some(value), DataTypeRaw::UInt8) => u8::try_from(value)
+                .map(Self::UInt8)
+                .map_err(|_| out_of_range()),
+            (_, Some(value), DataTypeRaw::UInt16) => u16::try_from(value)
+                .map(Self::UInt16)
+                .map_err(|_| out_of_range()),
+            (_, Some(value), DataTypeRaw::UInt32) => u32::try_from(value)
+                .map(Self::UInt32)
+                .map_err(|_| out_of_range()),
+            (_, Some(value), DataTypeRaw::UInt64) => u64::try_from(value)
+                .map(Self::UInt64)
+                .map_err(|_| out_of_range()),
+            (_, Some(value), DataTypeRaw::UInt128) => Ok(Self::UInt128(value)),
+            (Self::String(value), _, DataTypeRaw::Timestamp) => parse_timestamp(value)
+                .map(Self::Timestamp)
+                .ok_or_else(unparsable),
+            (Self::String(value), _, DataTypeRaw::Uuid) => Uuid::parse_str(value.trim())
+                .ok()
+                .or_else(|| Ulid::from_string(value.trim()).ok().map(Uuid::from))
+                .map(Self::Uuid)
+                .ok_or_else(unparsable),
+            (Self::String(_), _, _) | (Self::Bool(_), _, _) => Err(format!(
+                "Value of type {} cannot be converted to type {}",
+                self.get_type(),
+                target
+            )),
+            (_, Some(value), DataTypeRaw::Bool) => Ok(Self::Bool(value != 0)),
+            (_, Some(value), DataTypeRaw::String) => Ok(Self::String(value.to_string())),
+            (Self::Timestamp(value), _, DataTypeRaw::String) => value
+                .format(&Rfc3339)
+                .map(Self::String)
+                .map_err(|error| error.to_string()),
+            (Self::Uuid(value), _, DataTypeRaw::String) => {
+                Ok(Self::String(value.to_hyphenated().to_string()))
+            }
+            _ => Err(format!(
+                "Value of type {} cannot be converted to type {}",
+                self.get_type(),
+                target
+            )),
+        }
+    }
+}
+
+/// SQL literal of the value, e.g. `'foo'` or `'2077-01-01T21:37:00Z'::TIMESTAMP`.
+impl fmt::Display for DataInstanceRaw {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", if *value { "TRUE" } else { "FALSE" }),
+            Self::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Self::Timestamp(_) | Self::Uuid(_) => match self.clone().coerce(DataTypeRaw::String) {
+                Ok(Self::String(value)) => write!(f, "'{}'::{}", value, self.get_type()),
+                _ => Err(fmt::Error),
+            },
+            _ => write!(
+                f,
+                "{}",
+                self.as_u128().expect("Other values are unsigned integers")
+            ),
+        }
+    }
+}
+
+/// Parse a timestamp in RFC 3339 format, or in a similar one without the offset (taken to be UTC),
+/// optionally with a space instead of `T`, or even without seconds or time altogether, e.g. `2077-01-01T21:37`.
+fn parse_timestamp(candidate: &str) -> Option<OffsetDateTime> {
+    let candidate = candidate.trim().replacen(' ', "T", 1);
+    if let Ok(timestamp) = OffsetDateTime::parse(&candidate, &Rfc3339) {
+        return Some(timestamp);
+    }
+    for description in [
+        "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond]",
+        "[year]-[month]-[day]T[hour]:[minute]:[second]",
+        "[year]-[month]-[day]T[hour]:[minute]",
+    ] {
+        let format = format_description::parse(description).expect("Format description is valid");
+        if let Ok(timestamp) = PrimitiveDateTime::parse(&candidate, &format) {
+            return Some(timestamp.assume_utc());
+        }
+    }
+    let format =
+        format_description::parse("[year]-[month]-[day]").expect("Format description is valid");
+    Date::parse(&candidate, &format)
+        .ok()
+        .map(|date| date.midnight().assume_utc())
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 #[serde(untagged)]
 pub enum DataInstance {
@@ -70,14 +279,49 @@ pub enum DataInstance {
     Null,
 }
 
+impl DataInstance {
+    /// The underlying value, or `None` for NULL.
+    pub fn as_raw(&self) -> Option<&DataInstanceRaw> {
+        match self {
+            Self::Direct(value) | Self::Nullable(value) => Some(value),
+            Self::Null => None,
+        }
+    }
+
+    /// Like `as_raw`, but consuming.
+    pub fn into_raw(self) -> Option<DataInstanceRaw> {
+        match self {
+            Self::Direct(value) | Self::Nullable(value) => Some(value),
+            Self::Null => None,
+        }
+    }
+
+    /// Make this value storable in a column of type `data_type`, i.e. coerce it to the right raw type
+    /// and wrap it according to the column's nullability.
+    pub fn conform(self, data_type: &DataType) -> Result<Self, String> {
+        match self.into_raw() {
+            Some(value) => {
+                let value = value.coerce(data_type.raw_type)?;
+                Ok(if data_type.is_nullable {
+                    Self::Nullable(value)
+                } else {
+                    Self::Direct(value)
+                })
+            }
+            None if data_type.is_nullable => Ok(Self::Null),
+            None => Err(format!("NULL is not allowed for type {}", data_type)),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum DataDefinition {
     // A column identifier.
     Identifier(String),
     // A constant value.
     Const(DataInstance),
-    // A function call.
-    FunctionCall(Function),
+    // A function call with its arguments.
+    FunctionCall(Function, Vec<Expression>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -85,6 +329,300 @@ pub enum Expression {
     Atom(DataDefinition),
     /// LHS = RHS
     Equal(Box<Self>, Box<Self>),
+    /// LHS != RHS (or LHS <> RHS)
+    NotEqual(Box<Self>, Box<Self>),
+    /// LHS < RHS
+    LessThan(Box<Self>, Box<Self>),
+    /// LHS <= RHS
+    LessThanOrEqual(Box<Self>, Box<Self>),
+    /// LHS > RHS
+    GreaterThan(Box<Self>, Box<Self>),
+    /// LHS >= RHS
+    GreaterThanOrEqual(Box<Self>, Box<Self>),
+    /// LHS AND RHS
+    And(Box<Self>, Box<Self>),
+    /// LHS OR RHS
+    Or(Box<Self>, Box<Self>),
+    /// NOT operand
+    Not(Box<Self>),
+    /// operand IS NULL
+    IsNull(Box<Self>),
+    /// operand IN (items)
+    In(Box<Self>, Vec<Self>),
+    /// operand BETWEEN low AND high
+    Between(Box<Self>, Box<Self>, Box<Self>),
+    /// value LIKE pattern ESCAPE character
+    Like(Box<Self>, Box<Self>, char),
+    /// LHS @ RHS, i.e. the distance between the two according to the METRIC KEY's metric
+    Distance(Box<Self>, Box<Self>),
+    /// CAST(operand AS type) or operand::type
+    Cast(Box<Self>, DataTypeRaw),
+    /// (SELECT ...), i.e. the value of the subquery's only column in its only row, or NULL if there are no rows
+    Subquery(Box<SelectStatement>),
+    /// operand IN (SELECT ...)
+    InSubquery(Box<Self>, Box<SelectStatement>),
+    /// EXISTS (SELECT ...), i.e. whether the subquery returns any rows
+    Exists(Box<SelectStatement>),
+}
+
+impl Expression {
+    /// Direct subexpressions of this expression, including arguments of function calls.
+    pub fn get_operands(&self) -> Vec<&Self> {
+        match self {
+            Self::Atom(DataDefinition::FunctionCall(_, arguments)) => arguments.iter().collect(),
+            Self::Atom(_) | Self::Subquery(_) | Self::Exists(_) => Vec::new(),
+            Self::Equal(lhs, rhs)
+            | Self::NotEqual(lhs, rhs)
+            | Self::LessThan(lhs, rhs)
+            | Self::LessThanOrEqual(lhs, rhs)
+            | Self::GreaterThan(lhs, rhs)
+            | Self::GreaterThanOrEqual(lhs, rhs)
+            | Self::And(lhs, rhs)
+            | Self::Or(lhs, rhs)
+            | Self::Like(lhs, rhs, _)
+            | Self::Distance(lhs, rhs) => vec![lhs, rhs],
+            Self::Not(operand)
+            | Self::IsNull(operand)
+            | Self::Cast(operand, _)
+            | Self::InSubquery(operand, _) => vec![operand],
+            Self::In(operand, items) => std::iter::once(operand.as_ref()).chain(items).collect(),
+            Self::Between(operand, low, high) => vec![operand, low, high],
+        }
+    }
+
+    /// Copy this expression, substituting each subexpression for which `replacement` returns something.
+    /// Replacement goes top-down, so substitutes are not looked into.
+    pub fn replace(&self, replacement: &mut impl FnMut(&Self) -> Option<Self>) -> Self {
+        match self.try_replace(&mut |subexpression| {
+            Ok::<Option<Self>, Infallible>(replacement(subexpression))
+        }) {
+            Ok(expression) => expression,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `replace`, but with a fallible `replacement`.
+    pub fn try_replace<E>(
+        &self,
+        replacement: &mut impl FnMut(&Self) -> Result<Option<Self>, E>,
+    ) -> Result<Self, E> {
+        if let Some(substitute) = replacement(self)? {
+            return Ok(substitute);
+        }
+        let mut replace_boxed = |operand: &Self| -> Result<Box<Self>, E> {
+            Ok(Box::new(operand.try_replace(replacement)?))
+        };
+        Ok(match self {
+            Self::Atom(DataDefinition::FunctionCall(function, arguments)) => {
+                Self::Atom(DataDefinition::FunctionCall(
+                    *function,
+                    arguments
+                        .iter()
+                        .map(|argument| Ok(*replace_boxed(argument)?))
+                        .collect::<Result<Vec<Self>, E>>()?,
+                ))
+            }
+            Self::Atom(_) | Self::Subquery(_) | Self::Exists(_) => self.clone(),
+            Self::Equal(lhs, rhs) => Self::Equal(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::NotEqual(lhs, rhs) => Self::NotEqual(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::LessThan(lhs, rhs) => Self::LessThan(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::LessThanOrEqual(lhs, rhs) => {
+                Self::LessThanOrEqual(replace_boxed(lhs)?, replace_boxed(rhs)?)
+            }
+            Self::GreaterThan(lhs, rhs) => {
+                Self::GreaterThan(replace_boxed(lhs)?, replace_boxed(rhs)?)
+            }
+            Self::GreaterThanOrEqual(lhs, rhs) => {
+                Self::GreaterThanOrEqual(replace_boxed(lhs)?, replace_boxed(rhs)?)
+            }
+            Self::And(lhs, rhs) => Self::And(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::Or(lhs, rhs) => Self::Or(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::Not(operand) => Self::Not(replace_boxed(operand)?),
+            Self::IsNull(operand) => Self::IsNull(replace_boxed(operand)?),
+            Self::In(operand, items) => Self::In(
+                replace_boxed(operand)?,
+                items
+                    .iter()
+                    .map(|item| Ok(*replace_boxed(item)?))
+                    .collect::<Result<Vec<Self>, E>>()?,
+            ),
+            Self::Between(operand, low, high) => Self::Between(
+                replace_boxed(operand)?,
+                replace_boxed(low)?,
+                replace_boxed(high)?,
+            ),
+            Self::Like(value, pattern, escape) => {
+                Self::Like(replace_boxed(value)?, replace_boxed(pattern)?, *escape)
+            }
+            Self::Distance(lhs, rhs) => Self::Distance(replace_boxed(lhs)?, replace_boxed(rhs)?),
+            Self::Cast(operand, data_type) => Self::Cast(replace_boxed(operand)?, *data_type),
+            Self::InSubquery(operand, query) => {
+                Self::InSubquery(replace_boxed(operand)?, query.clone())
+            }
+        })
+    }
+
+    /// Subqueries of this expression, in the order that `replace` comes across them. Subqueries nested
+    /// in those are not included.
+    pub fn get_subqueries(&self) -> Vec<&SelectStatement> {
+        match self {
+            Self::Subquery(query) | Self::Exists(query) => vec![query],
+            Self::InSubquery(operand, query) => std::iter::once(query.as_ref())
+                .chain(operand.get_subqueries())
+                .collect(),
+            _ => self
+                .get_operands()
+                .into_iter()
+                .flat_map(Self::get_subqueries)
+                .collect(),
+        }
+    }
+
+    /// Whether this expression calls an aggregate function anywhere.
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Self::Atom(DataDefinition::FunctionCall(function, _)) if function.is_aggregate() => {
+                true
+            }
+            _ => self
+                .get_operands()
+                .into_iter()
+                .any(Expression::contains_aggregate),
+        }
+    }
+
+    /// Whether this expression has the same value for every row, i.e. refers to no columns, aggregates
+    /// or subqueries.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            Self::Atom(DataDefinition::Identifier(_))
+            | Self::Subquery(_)
+            | Self::InSubquery(..)
+            | Self::Exists(_) => false,
+            Self::Atom(DataDefinition::FunctionCall(function, _)) if function.is_aggregate() => {
+                false
+            }
+            _ => self.get_operands().into_iter().all(Expression::is_constant),
+        }
+    }
+
+    /// Name of the column that this expression produces in query results, e.g. `width` for `width`
+    /// or `count` for `COUNT(*)`.
+    pub fn get_default_name(&self) -> String {
+        match self {
+            // Same as in Postgres, a qualified column is named without the qualifier
+            Self::Atom(DataDefinition::Identifier(column_name)) => column_name
+                .split_once('.')
+                .map_or(column_name.as_str(), |(_, column_name)| column_name)
+                .to_string(),
+            Self::Atom(DataDefinition::FunctionCall(function, _)) => {
+                function.get_name().to_lowercase()
+            }
+            // Same as in Postgres, a subquery is named after its column
+            Self::Subquery(query) => match query.columns.as_slice() {
+                [SelectColumn::Expression {
+                    alias: Some(alias), ..
+                }] => alias.clone(),
+                [SelectColumn::Expression {
+                    expression,
+                    alias: None,
+                }] => expression.get_default_name(),
+                _ => "?column?".to_string(),
+            },
+            Self::Exists(_) => "exists".to_string(),
+            // Same as in Postgres, a cast is named after its operand, or after the type if that has no name
+            Self::Cast(operand, data_type) => match operand.get_default_name() {
+                name if name == "?column?" => data_type.to_string().to_lowercase(),
+                name => name,
+            },
+            // Same as in Postgres
+            _ => "?column?".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DataDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Identifier(column_name) => write!(f, "{}", quote_identifier(column_name)),
+            Self::Const(value) => match value.as_raw() {
+                Some(value) => write!(f, "{}", value),
+                None => write!(f, "NULL"),
+            },
+            // COUNT without arguments counts all rows
+            Self::FunctionCall(Function::Count, arguments) if arguments.is_empty() => {
+                write!(f, "COUNT(*)")
+            }
+            Self::FunctionCall(function, arguments) => {
+                write!(f, "{}(", function.get_name())?;
+                for (argument_index, argument) in arguments.iter().enumerate() {
+                    if argument_index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// SQL text of the expression. Operands that are expressions themselves are parenthesized,
+/// so that the text reads the same regardless of operator precedence.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operand = |expression: &Self| match expression {
+            Self::Atom(_) | Self::Cast(..) | Self::Subquery(_) => expression.to_string(),
+            _ => format!("({})", expression),
+        };
+        match self {
+            Self::Atom(definition) => write!(f, "{}", definition),
+            Self::Equal(lhs, rhs) => write!(f, "{} = {}", operand(lhs), operand(rhs)),
+            Self::NotEqual(lhs, rhs) => write!(f, "{} != {}", operand(lhs), operand(rhs)),
+            Self::LessThan(lhs, rhs) => write!(f, "{} < {}", operand(lhs), operand(rhs)),
+            Self::LessThanOrEqual(lhs, rhs) => write!(f, "{} <= {}", operand(lhs), operand(rhs)),
+            Self::GreaterThan(lhs, rhs) => write!(f, "{} > {}", operand(lhs), operand(rhs)),
+            Self::GreaterThanOrEqual(lhs, rhs) => {
+                write!(f, "{} >= {}", operand(lhs), operand(rhs))
+            }
+            Self::And(lhs, rhs) => write!(f, "{} AND {}", operand(lhs), operand(rhs)),
+            Self::Or(lhs, rhs) => write!(f, "{} OR {}", operand(lhs), operand(rhs)),
+            Self::Not(inner) => match inner.as_ref() {
+                Self::IsNull(inner) => write!(f, "{} IS NOT NULL", operand(inner)),
+                _ => write!(f, "NOT {}", operand(inner)),
+            },
+            Self::IsNull(inner) => write!(f, "{} IS NULL", operand(inner)),
+            Self::In(inner, items) => write!(
+                f,
+                "{} IN ({})",
+                operand(inner),
+                items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Between(inner, low, high) => write!(
+                f,
+                "{} BETWEEN {} AND {}",
+                operand(inner),
+                operand(low),
+                operand(high)
+            ),
+            Self::Like(value, pattern, escape) => {
+                write!(f, "{} LIKE {}", operand(value), operand(pattern))?;
+                if *escape != '\\' {
+                    write!(f, " ESCAPE '{}'", escape)?;
+                }
+                Ok(())
+            }
+            Self::Distance(lhs, rhs) => write!(f, "{} @ {}", operand(lhs), operand(rhs)),
+            Self::Cast(inner, data_type) => write!(f, "{}::{}", operand(inner), data_type),
+            Self::Subquery(query) => write!(f, "({})", query),
+            Self::InSubquery(inner, query) => write!(f, "{} IN ({})", operand(inner), query),
+            Self::Exists(query) => write!(f, "EXISTS ({})", query),
+        }
+    }
 }
 
 pub trait Validatable {
@@ -92,11 +630,12 @@ pub trait Validatable {
     fn validate(&self) -> Result<(), ValidationError>;
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ColumnDefinition {
     pub name: String,
     pub data_type: DataType,
     pub primary_key: bool,
+    pub metric_key: bool,
     pub default: Option<DataDefinition>,
 }
 
@@ -105,42 +644,319 @@ impl Validatable for ColumnDefinition {
         if self.name.is_empty() {
             return Err(ValidationError("A column must have a name".into()));
         }
+        if self.metric_key && self.data_type.raw_type.as_unsigned_bits().is_none() {
+            return Err(ValidationError(format!(
+                "A METRIC KEY column must be of an unsigned integer type, not {}",
+                self.data_type
+            )));
+        }
         Ok(())
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TableDefinition {
     // Table name.
     pub name: String,
     // Column definitions.
     pub columns: Vec<ColumnDefinition>,
+    // Constraints beyond column types and keys.
+    pub constraints: Vec<ConstraintDefinition>,
 }
 
 impl TableDefinition {
     pub fn new(name: String, columns: Vec<ColumnDefinition>) -> Self {
-        TableDefinition { name, columns }
+        TableDefinition {
+            name,
+            columns,
+            constraints: Vec::new(),
+        }
     }
 
-    pub fn get_primary_key(&self) -> &ColumnDefinition {
+    /// Indexes of the key columns, which rows are ordered by in the table's B+ tree, in order of significance.
+    /// For a table that's its PRIMARY KEY - either the PRIMARY KEY column, or the columns listed
+    /// in the PRIMARY KEY constraint. B+ trees of indexes are keyed by all of their columns.
+    pub fn get_key_indexes(&self) -> Vec<usize> {
+        if let Some(column_names) = self.get_primary_key_constraint_columns() {
+            return column_names
+                .iter()
+                .map(|column_name| {
+                    self.get_column_index(column_name)
+                        .expect("PRIMARY KEY columns must exist in the table")
+                })
+                .collect();
+        }
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.primary_key)
+            .map(|(column_index, _)| column_index)
+            .collect()
+    }
+
+    /// Names of the key columns, in order of significance.
+    pub fn get_key_column_names(&self) -> Vec<String> {
+        self.get_key_indexes()
+            .into_iter()
+            .map(|column_index| self.columns[column_index].name.clone())
+            .collect()
+    }
+
+    /// Whether the column is (a part of) the PRIMARY KEY.
+    pub fn is_key_column(&self, column_name: &str) -> bool {
+        self.get_key_column_names()
+            .iter()
+            .any(|key_column_name| key_column_name == column_name)
+    }
+
+    fn get_primary_key_constraint_columns(&self) -> Option<&Vec<String>> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match &constraint.kind {
+                ConstraintKind::PrimaryKey(column_names) => Some(column_names),
+                _ => None,
+            })
+    }
+
+    pub fn get_metric_key(&self) -> Option<&ColumnDefinition> {
+        self.columns.iter().find(|column| column.metric_key)
+    }
+
+    pub fn get_column(&self, column_name: &str) -> Option<&ColumnDefinition> {
         self.columns
             .iter()
-            .find(|column| column.primary_key)
-            .expect("A table must have a PRIMARY KEY column")
+            .find(|column| column.name == column_name)
+    }
+
+    pub fn get_column_index(&self, column_name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name == column_name)
+    }
+
+    pub fn get_column_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect()
+    }
+
+    /// Make sure that the constraint makes sense for this table.
+    fn validate_constraint(
+        &self,
+        constraint: &ConstraintDefinition,
+    ) -> Result<(), ValidationError> {
+        for column_name in constraint.get_column_names() {
+            if self.get_column(&column_name).is_none() {
+                return Err(ValidationError(format!(
+                    "Column `{}` does not exist in table definition",
+                    column_name
+                )));
+            }
+        }
+        match &constraint.kind {
+            ConstraintKind::PrimaryKey(column_names) => {
+                let mut seen_column_names: HashSet<&str> = HashSet::new();
+                for column_name in column_names {
+                    if !seen_column_names.insert(column_name) {
+                        return Err(ValidationError(format!(
+                            "Column `{}` is in the PRIMARY KEY more than once",
+                            column_name
+                        )));
+                    }
+                    if self.get_column(column_name).unwrap().data_type.is_nullable {
+                        return Err(ValidationError(format!(
+                            "Column `{}` is nullable, so it cannot be in the PRIMARY KEY",
+                            column_name
+                        )));
+                    }
+                }
+                Ok(())
+            }
+            ConstraintKind::Unique(_) => constraint
+                .get_backing_index()
+                .expect("A UNIQUE constraint is backed by an index")
+                .validate(),
+            ConstraintKind::Check(condition) => {
+                if condition.contains_aggregate() {
+                    return Err(ValidationError(
+                        "A CHECK condition cannot use aggregate functions".into(),
+                    ));
+                }
+                condition.coerce_literals(self)?.validate_condition(self)
+            }
+        }
     }
 }
 
-impl Validatable for TableDefinition {
+/// Secondary index of a table, i.e. a B+ tree of the indexed columns' values along with PRIMARY KEY values,
+/// which allows finding rows by the indexed columns without scanning the whole table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexDefinition {
+    pub name: String,
+    /// Indexed columns, in the order that entries are sorted by.
+    pub column_names: Vec<String>,
+    /// Whether no two rows may have the same values in indexed columns (unless any of them is NULL).
+    pub unique: bool,
+}
+
+impl IndexDefinition {
+    /// Name of the index's B+ tree file in the table's directory.
+    pub fn get_file_name(&self) -> String {
+        format!("{}.index", self.name)
+    }
+}
+
+impl Validatable for IndexDefinition {
     fn validate(&self) -> Result<(), ValidationError> {
-        if self.name.is_empty() {
-            return Err(ValidationError("A table must have a name".into()));
+        // The name becomes part of a file name
+        if self.name.contains(['.', '/', '\\']) {
+            return Err(ValidationError(format!(
+                "Index name `{}` must not contain `.`, `/` or `\\`",
+                self.name
+            )));
+        }
+        let mut column_names: HashSet<&str> = HashSet::new();
+        for column_name in &self.column_names {
+            if !column_names.insert(column_name) {
+                return Err(ValidationError(format!(
+                    "Column `{}` is indexed more than once",
+                    column_name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConstraintKind {
+    /// Rows are identified by values of the columns, and ordered by them lexicographically in the table's B+ tree.
+    PrimaryKey(Vec<String>),
+    /// No two rows may have the same values in the columns (unless any of them is NULL).
+    /// Enforced by a UNIQUE index of the same name as the constraint.
+    Unique(Vec<String>),
+    /// Every row must satisfy the condition. Same as in standard SQL, NULL outcome counts as satisfied.
+    Check(Expression),
+}
+
+/// Named constraint of a table, declared either along with a column or separately in CREATE TABLE.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConstraintDefinition {
+    pub name: String,
+    pub kind: ConstraintKind,
+}
+
+impl ConstraintDefinition {
+    /// Names of the columns the constraint is concerned with, in order of first appearance.
+    pub fn get_column_names(&self) -> Vec<String> {
+        fn collect_identifiers(expression: &Expression, column_names: &mut Vec<String>) {
+            if let Expression::Atom(DataDefinition::Identifier(column_name)) = expression {
+                if !column_names.contains(column_name) {
+                    column_names.push(column_name.clone());
+                }
+            }
+            for operand in expression.get_operands() {
+                collect_identifiers(operand, column_names);
+            }
+        }
+        match &self.kind {
+            ConstraintKind::PrimaryKey(column_names) | ConstraintKind::Unique(column_names) => {
+                column_names.clone()
+            }
+            ConstraintKind::Check(condition) => {
+                let mut column_names = Vec::new();
+                collect_identifiers(condition, &mut column_names);
+                column_names
+            }
         }
+    }
+
+    /// Index enforcing the constraint, if it's a UNIQUE one.
+    pub fn get_backing_index(&self) -> Option<IndexDefinition> {
+        match &self.kind {
+            ConstraintKind::Unique(column_names) => Some(IndexDefinition {
+                name: self.name.clone(),
+                column_names: column_names.clone(),
+                unique: true,
+            }),
+            ConstraintKind::PrimaryKey(_) | ConstraintKind::Check(_) => None,
+        }
+    }
+
+    /// Copy of the constraint with references to a column renamed.
+    pub fn rename_column(&self, column_name: &str, new_column_name: &str) -> Self {
+        let rename = |column_names: &Vec<String>| {
+            column_names
+                .iter()
+                .map(|name| match name == column_name {
+                    true => new_column_name.to_string(),
+                    false => name.clone(),
+                })
+                .collect()
+        };
+        let kind = match &self.kind {
+            ConstraintKind::PrimaryKey(column_names) => {
+                ConstraintKind::PrimaryKey(rename(column_names))
+            }
+            ConstraintKind::Unique(column_names) => ConstraintKind::Unique(rename(column_names)),
+            ConstraintKind::Check(condition) => {
+                ConstraintKind::Check(condition.replace(&mut |expression| match expression {
+                    Expression::Atom(DataDefinition::Identifier(name)) if name == column_name => {
+                        Some(Expression::Atom(DataDefinition::Identifier(
+                            new_column_name.to_string(),
+                        )))
+                    }
+                    _ => None,
+                }))
+            }
+        };
+        ConstraintDefinition {
+            name: self.name.clone(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ConstraintDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConstraintKind::PrimaryKey(column_names) => {
+                write!(f, "PRIMARY KEY ({})", quote_identifiers(column_names))
+            }
+            ConstraintKind::Unique(column_names) => {
+                write!(f, "UNIQUE ({})", quote_identifiers(column_names))
+            }
+            ConstraintKind::Check(condition) => write!(f, "CHECK ({})", condition),
+        }
+    }
+}
+
+/// Table names become directory names, so they must not be able to escape the database's directory.
+/// That rules out absolute paths too, as they start with `/` or contain `\`.
+pub fn validate_table_name(table_name: &str) -> Result<(), ValidationError> {
+    if table_name.is_empty() {
+        return Err(ValidationError("A table must have a name".into()));
+    }
+    if table_name.contains(['.', '/', '\\']) {
+        return Err(ValidationError(format!(
+            "Table name `{}` must not contain `.`, `/` or `\\`",
+            table_name
+        )));
+    }
+    Ok(())
+}
+
+impl Validatable for TableDefinition {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_name(&self.name)?;
         if self.columns.is_empty() {
             return Err(ValidationError(
                 "A table must have at least one column".into(),
             ));
         }
         let mut primary_key_count = 0;
+        let mut metric_key_count = 0;
         let mut column_names: HashSet<String> = HashSet::new();
         for (column_index, column) in self.columns.iter().enumerate() {
             if column_names.contains(&column.name) {
@@ -153,6 +969,9 @@ impl Validatable for TableDefinition {
             if column.primary_key {
                 primary_key_count += 1;
             }
+            if column.metric_key {
+                metric_key_count += 1;
+            }
             if let Err(column_error) = column.validate() {
                 return Err(ValidationError(format!(
                     "Problem at column {}: {}",
@@ -161,12 +980,53 @@ impl Validatable for TableDefinition {
                 )));
             }
         }
-        if primary_key_count != 1 {
+        let primary_key_constraint_count = self
+            .constraints
+            .iter()
+            .filter(|constraint| matches!(constraint.kind, ConstraintKind::PrimaryKey(_)))
+            .count();
+        match (primary_key_constraint_count, primary_key_count) {
+            (0, 1) | (1, 0) => (),
+            (0, _) => {
+                return Err(ValidationError(format!(
+                    "A table must have exactly 1 PRIMARY KEY column, not {}",
+                    primary_key_count
+                )))
+            }
+            (1, _) => {
+                return Err(ValidationError(
+                    "A table cannot have both a PRIMARY KEY column and a PRIMARY KEY constraint"
+                        .into(),
+                ))
+            }
+            _ => {
+                return Err(ValidationError(format!(
+                    "A table can have at most 1 PRIMARY KEY constraint, not {}",
+                    primary_key_constraint_count
+                )))
+            }
+        }
+        if metric_key_count > 1 {
             return Err(ValidationError(format!(
-                "A table must have exactly 1 PRIMARY KEY column, not {}",
-                primary_key_count
+                "A table can have at most 1 METRIC KEY column, not {}",
+                metric_key_count
             )));
         }
+        let mut constraint_names: HashSet<&str> = HashSet::new();
+        for constraint in &self.constraints {
+            if !constraint_names.insert(&constraint.name) {
+                return Err(ValidationError(format!(
+                    "There is more than one constraint with name `{}` in table definition",
+                    constraint.name
+                )));
+            }
+            if let Err(constraint_error) = self.validate_constraint(constraint) {
+                return Err(ValidationError(format!(
+                    "Problem at constraint `{}`: {}",
+                    constraint.name, constraint_error.0
+                )));
+            }
+        }
         Ok(())
     }
 }
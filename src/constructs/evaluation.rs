@@ -0,0 +1,929 @@
+use std::cmp::Ordering;
+
+use crate::sql::ValidationError;
+
+use super::components::{
+    DataDefinition, DataInstance, DataInstanceRaw, DataType, DataTypeRaw, Expression,
+    TableDefinition,
+};
+
+/// A row that expressions can be evaluated against, with values matched up to column names.
+#[derive(Debug, Clone, Copy)]
+pub struct RowContext<'r> {
+    pub column_names: &'r [String],
+    pub values: &'r [DataInstance],
+}
+
+impl<'r> RowContext<'r> {
+    pub fn new(column_names: &'r [String], values: &'r [DataInstance]) -> Self {
+        RowContext {
+            column_names,
+            values,
+        }
+    }
+
+    pub fn get_value(&self, column_name: &str) -> Result<&'r DataInstance, String> {
+        match self
+            .column_names
+            .iter()
+            .position(|name| name == column_name)
+        {
+            Some(column_index) => Ok(&self.values[column_index]),
+            None => Err(format!("Column `{}` does not exist", column_name)),
+        }
+    }
+}
+
+/// Compare two non-NULL values. Unsigned integers of different sizes are comparable with each other,
+/// but otherwise both values must be of the same type.
+pub fn compare_values(lhs: &DataInstanceRaw, rhs: &DataInstanceRaw) -> Result<Ordering, String> {
+    if let (Some(lhs), Some(rhs)) = (lhs.as_u128(), rhs.as_u128()) {
+        return Ok(lhs.cmp(&rhs));
+    }
+    if lhs.get_type() != rhs.get_type() {
+        return Err(format!(
+            "Values of types {} and {} cannot be compared",
+            lhs.get_type(),
+            rhs.get_type()
+        ));
+    }
+    Ok(lhs.cmp(rhs))
+}
+
+/// Whether values of the two types can be compared with `compare_values`.
+pub fn are_types_comparable(lhs: &DataTypeRaw, rhs: &DataTypeRaw) -> bool {
+    lhs.is_widenable_to(rhs) || rhs.is_widenable_to(lhs)
+}
+
+/// Compare two values, with NULL on either side making the result unknown (`None`).
+fn compare_instances(lhs: &DataInstance, rhs: &DataInstance) -> Result<Option<Ordering>, String> {
+    match (lhs.as_raw(), rhs.as_raw()) {
+        (Some(lhs), Some(rhs)) => compare_values(lhs, rhs).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Make sure that values of the two types can be compared. The NULL literal (`None`) can be compared with anything.
+fn validate_comparable(
+    lhs_type: &Option<DataType>,
+    rhs_type: &Option<DataType>,
+) -> Result<(), ValidationError> {
+    if let (Some(lhs_type), Some(rhs_type)) = (lhs_type, rhs_type) {
+        if !are_types_comparable(&lhs_type.raw_type, &rhs_type.raw_type) {
+            return Err(ValidationError(format!(
+                "Values of types {} and {} cannot be compared",
+                lhs_type, rhs_type
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Convert `operand` to the type of `other` if it's a literal that couldn't be compared with `other` otherwise.
+fn coerce_literal_for_comparison(
+    operand: Expression,
+    other: &Expression,
+    table: &TableDefinition,
+) -> Result<Expression, ValidationError> {
+    let value = match &operand {
+        Expression::Atom(DataDefinition::Const(value)) => match value.as_raw() {
+            Some(value) => value,
+            None => return Ok(operand),
+        },
+        _ => return Ok(operand),
+    };
+    // Invalid expressions are left for validation to report
+    let other_type = match other.infer_type(table) {
+        Ok(Some(other_type)) => other_type.raw_type,
+        _ => return Ok(operand),
+    };
+    if are_types_comparable(&value.get_type(), &other_type)
+        || !value.get_type().is_coercible_to(&other_type)
+    {
+        return Ok(operand);
+    }
+    let value = value.clone().coerce(other_type).map_err(ValidationError)?;
+    Ok(Expression::Atom(DataDefinition::Const(
+        DataInstance::Direct(value),
+    )))
+}
+
+/// Whether values of the type may be NULL. The NULL literal (`None`) obviously is.
+fn is_nullable_type(data_type: &Option<DataType>) -> bool {
+    data_type
+        .as_ref()
+        .is_none_or(|data_type| data_type.is_nullable)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LikePatternElement {
+    Literal(char),
+    /// `_`
+    AnyCharacter,
+    /// `%`
+    AnySequence,
+}
+
+fn parse_like_pattern(pattern: &str, escape: char) -> Result<Vec<LikePatternElement>, String> {
+    let mut elements = Vec::new();
+    let mut characters = pattern.chars();
+    while let Some(character) = characters.next() {
+        elements.push(match character {
+            _ if character == escape => match characters.next() {
+                Some(escaped_character) => LikePatternElement::Literal(escaped_character),
+                None => {
+                    return Err(format!(
+                        "LIKE pattern `{}` must not end with escape character `{}`",
+                        pattern, escape
+                    ))
+                }
+            },
+            '%' => LikePatternElement::AnySequence,
+            '_' => LikePatternElement::AnyCharacter,
+            _ => LikePatternElement::Literal(character),
+        });
+    }
+    Ok(elements)
+}
+
+/// Whether the value matches the LIKE pattern, in which `%` stands for any sequence of characters, `_` stands for
+/// any single character, and `escape` makes the character following it literal.
+pub fn matches_like_pattern(value: &str, pattern: &str, escape: char) -> Result<bool, String> {
+    let elements = parse_like_pattern(pattern, escape)?;
+    let characters: Vec<char> = value.chars().collect();
+    let (mut element_index, mut character_index) = (0, 0);
+    // Where to resume after a mismatch: the element after the last `%`, and the character it started absorbing at.
+    // Only the last `%` needs to be retried, as it can absorb anything the previous ones could
+    let mut last_any_sequence: Option<(usize, usize)> = None;
+    while character_index < characters.len() {
+        match elements.get(element_index) {
+            Some(LikePatternElement::AnySequence) => {
+                element_index += 1;
+                last_any_sequence = Some((element_index, character_index));
+                continue;
+            }
+            Some(LikePatternElement::AnyCharacter) => {
+                element_index += 1;
+                character_index += 1;
+                continue;
+            }
+            Some(LikePatternElement::Literal(literal))
+                if *literal == characters[character_index] =>
+            {
+                element_index += 1;
+                character_index += 1;
+                continue;
+            }
+            _ => (),
+        }
+        match last_any_sequence {
+            // Let the last `%` absorb one more character and try again
+            Some((resume_element_index, absorbed_from)) => {
+                element_index = resume_element_index;
+                character_index = absorbed_from + 1;
+                last_any_sequence = Some((resume_element_index, absorbed_from + 1));
+            }
+            None => return Ok(false),
+        }
+    }
+    Ok(elements[element_index..]
+        .iter()
+        .all(|element| *element == LikePatternElement::AnySequence))
+}
+
+fn and_truth_values(lhs: Option<bool>, rhs: Option<bool>) -> Option<bool> {
+    match (lhs, rhs) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or_truth_values(lhs: Option<bool>, rhs: Option<bool>) -> Option<bool> {
+    match (lhs, rhs) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+/// Interpret the value as an operand of a logical operator: TRUE, FALSE, or unknown (NULL).
+fn as_truth_value(value: DataInstance) -> Result<Option<bool>, String> {
+    match value {
+        DataInstance::Direct(DataInstanceRaw::Bool(value))
+        | DataInstance::Nullable(DataInstanceRaw::Bool(value)) => Ok(Some(value)),
+        DataInstance::Null => Ok(None),
+        other => Err(format!(
+            "A logical operand must evaluate to a BOOL, instead got {:?}",
+            other
+        )),
+    }
+}
+
+fn from_truth_value(value: Option<bool>) -> DataInstance {
+    match value {
+        Some(value) => DataInstance::Direct(DataInstanceRaw::Bool(value)),
+        None => DataInstance::Null,
+    }
+}
+
+impl Expression {
+    /// The test that the ordering of operands must pass for this comparison to be true, e.g. `Ordering::is_lt` for `<`.
+    fn get_ordering_test(&self) -> fn(Ordering) -> bool {
+        match self {
+            Self::Equal(..) => Ordering::is_eq,
+            Self::NotEqual(..) => Ordering::is_ne,
+            Self::LessThan(..) => Ordering::is_lt,
+            Self::LessThanOrEqual(..) => Ordering::is_le,
+            Self::GreaterThan(..) => Ordering::is_gt,
+            Self::GreaterThanOrEqual(..) => Ordering::is_ge,
+            _ => unreachable!("Only comparisons have an ordering test"),
+        }
+    }
+
+    /// Compute the value of this expression for the given row.
+    pub fn evaluate(&self, row: &RowContext) -> Result<DataInstance, String> {
+        match self {
+            Self::Atom(DataDefinition::Identifier(column_name)) => {
+                Ok(row.get_value(column_name)?.clone())
+            }
+            Self::Atom(DataDefinition::Const(value)) => Ok(value.clone()),
+            Self::Atom(DataDefinition::FunctionCall(function, arguments)) => function.call(
+                arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(row))
+                    .collect::<Result<Vec<DataInstance>, String>>()?,
+            ),
+            Self::Equal(lhs, rhs)
+            | Self::NotEqual(lhs, rhs)
+            | Self::LessThan(lhs, rhs)
+            | Self::LessThanOrEqual(lhs, rhs)
+            | Self::GreaterThan(lhs, rhs)
+            | Self::GreaterThanOrEqual(lhs, rhs) => {
+                // Comparing anything with NULL gives NULL
+                let ordering = compare_instances(&lhs.evaluate(row)?, &rhs.evaluate(row)?)?;
+                Ok(from_truth_value(ordering.map(self.get_ordering_test())))
+            }
+            // Three-valued logic: NULL means unknown, so it only matters if the other operand doesn't decide alone
+            Self::And(lhs, rhs) => Ok(from_truth_value(and_truth_values(
+                as_truth_value(lhs.evaluate(row)?)?,
+                as_truth_value(rhs.evaluate(row)?)?,
+            ))),
+            Self::Or(lhs, rhs) => Ok(from_truth_value(or_truth_values(
+                as_truth_value(lhs.evaluate(row)?)?,
+                as_truth_value(rhs.evaluate(row)?)?,
+            ))),
+            Self::Not(operand) => Ok(from_truth_value(
+                as_truth_value(operand.evaluate(row)?)?.map(|value| !value),
+            )),
+            Self::IsNull(operand) => Ok(DataInstance::Direct(DataInstanceRaw::Bool(
+                operand.evaluate(row)?.as_raw().is_none(),
+            ))),
+            // Same as a chain of ORed equalities
+            Self::In(operand, items) => {
+                let operand = operand.evaluate(row)?;
+                let mut result = Some(false);
+                for item in items {
+                    let is_equal =
+                        compare_instances(&operand, &item.evaluate(row)?)?.map(Ordering::is_eq);
+                    result = or_truth_values(result, is_equal);
+                    if result == Some(true) {
+                        break;
+                    }
+                }
+                Ok(from_truth_value(result))
+            }
+            Self::Between(operand, low, high) => {
+                let operand = operand.evaluate(row)?;
+                let is_above_low =
+                    compare_instances(&operand, &low.evaluate(row)?)?.map(Ordering::is_ge);
+                let is_below_high =
+                    compare_instances(&operand, &high.evaluate(row)?)?.map(Ordering::is_le);
+                Ok(from_truth_value(and_truth_values(
+                    is_above_low,
+                    is_below_high,
+                )))
+            }
+            Self::Distance(lhs, rhs) => {
+                match (lhs.evaluate(row)?.into_raw(), rhs.evaluate(row)?.into_raw()) {
+                    (Some(lhs), Some(rhs)) => match (lhs.as_u128(), rhs.as_u128()) {
+                        // Hamming distance, the only supported metric
+                        (Some(lhs), Some(rhs)) => Ok(DataInstance::Direct(DataInstanceRaw::UInt8(
+                            (lhs ^ rhs).count_ones() as u8,
+                        ))),
+                        _ => Err(format!(
+                            "`@` requires unsigned integer operands, instead got {:?} and {:?}",
+                            lhs, rhs
+                        )),
+                    },
+                    _ => Ok(DataInstance::Null),
+                }
+            }
+            Self::Cast(operand, data_type) => match operand.evaluate(row)? {
+                DataInstance::Direct(value) => Ok(DataInstance::Direct(value.coerce(*data_type)?)),
+                DataInstance::Nullable(value) => {
+                    Ok(DataInstance::Nullable(value.coerce(*data_type)?))
+                }
+                DataInstance::Null => Ok(DataInstance::Null),
+            },
+            Self::Like(value, pattern, escape) => {
+                match (
+                    value.evaluate(row)?.into_raw(),
+                    pattern.evaluate(row)?.into_raw(),
+                ) {
+                    (
+                        Some(DataInstanceRaw::String(value)),
+                        Some(DataInstanceRaw::String(pattern)),
+                    ) => Ok(DataInstance::Direct(DataInstanceRaw::Bool(
+                        matches_like_pattern(&value, &pattern, *escape)?,
+                    ))),
+                    (None, _) | (_, None) => Ok(DataInstance::Null),
+                    (value, pattern) => Err(format!(
+                        "LIKE requires STRING operands, instead got {:?} and {:?}",
+                        value, pattern
+                    )),
+                }
+            }
+            // Subqueries are only valid in queries, which run them and substitute their results beforehand
+            Self::Subquery(_) | Self::InSubquery(..) | Self::Exists(_) => {
+                Err("A subquery cannot be used here".to_string())
+            }
+        }
+    }
+
+    /// Whether the row satisfies this expression used as a condition. NULL does not satisfy a condition.
+    pub fn is_satisfied_by(&self, row: &RowContext) -> Result<bool, String> {
+        match self.evaluate(row)? {
+            DataInstance::Direct(DataInstanceRaw::Bool(value))
+            | DataInstance::Nullable(DataInstanceRaw::Bool(value)) => Ok(value),
+            DataInstance::Null => Ok(false),
+            other => Err(format!(
+                "A condition must evaluate to a BOOL, instead got {:?}",
+                other
+            )),
+        }
+    }
+
+    /// Determine the type of this expression's values in the context of `table`.
+    /// `None` means that the expression is the NULL literal, which fits any nullable type.
+    pub fn infer_type(&self, table: &TableDefinition) -> Result<Option<DataType>, ValidationError> {
+        match self {
+            Self::Atom(DataDefinition::Identifier(column_name)) => {
+                match table.get_column(column_name) {
+                    Some(column) => Ok(Some(column.data_type.clone())),
+                    None => Err(ValidationError(format!(
+                        "Column `{}` does not exist in table `{}`",
+                        column_name, table.name
+                    ))),
+                }
+            }
+            Self::Atom(DataDefinition::Const(value)) => Ok(value.as_raw().map(|value| DataType {
+                raw_type: value.get_type(),
+                is_nullable: false,
+            })),
+            // Aggregates are only valid where rows are being grouped, which replaces them beforehand
+            Self::Atom(DataDefinition::FunctionCall(function, _)) if function.is_aggregate() => {
+                Err(ValidationError(format!(
+                    "Aggregate function `{}` cannot be used here",
+                    function.get_name()
+                )))
+            }
+            // Likewise subqueries are only valid in queries, which run them beforehand
+            Self::Subquery(_) | Self::InSubquery(..) | Self::Exists(_) => Err(ValidationError(
+                "A subquery cannot be used here".to_string(),
+            )),
+            Self::Atom(DataDefinition::FunctionCall(function, arguments)) => {
+                let argument_types = arguments
+                    .iter()
+                    .map(|argument| argument.infer_type(table))
+                    .collect::<Result<Vec<Option<DataType>>, ValidationError>>()?;
+                function
+                    .validate_constant_arguments(arguments)
+                    .map_err(ValidationError)?;
+                function
+                    .infer_return_type(&argument_types)
+                    .map(Some)
+                    .map_err(ValidationError)
+            }
+            Self::Equal(lhs, rhs)
+            | Self::NotEqual(lhs, rhs)
+            | Self::LessThan(lhs, rhs)
+            | Self::LessThanOrEqual(lhs, rhs)
+            | Self::GreaterThan(lhs, rhs)
+            | Self::GreaterThanOrEqual(lhs, rhs) => {
+                let lhs_type = lhs.infer_type(table)?;
+                let rhs_type = rhs.infer_type(table)?;
+                validate_comparable(&lhs_type, &rhs_type)?;
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable: is_nullable_type(&lhs_type) || is_nullable_type(&rhs_type),
+                }))
+            }
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                let is_lhs_nullable = lhs.infer_truth_value_nullability(table)?;
+                let is_rhs_nullable = rhs.infer_truth_value_nullability(table)?;
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable: is_lhs_nullable || is_rhs_nullable,
+                }))
+            }
+            Self::Not(operand) => Ok(Some(DataType {
+                raw_type: DataTypeRaw::Bool,
+                is_nullable: operand.infer_truth_value_nullability(table)?,
+            })),
+            Self::IsNull(operand) => {
+                operand.infer_type(table)?;
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable: false,
+                }))
+            }
+            Self::In(operand, items) => {
+                let operand_type = operand.infer_type(table)?;
+                let mut is_nullable = is_nullable_type(&operand_type);
+                for item in items {
+                    let item_type = item.infer_type(table)?;
+                    validate_comparable(&operand_type, &item_type)?;
+                    is_nullable |= is_nullable_type(&item_type);
+                }
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable,
+                }))
+            }
+            Self::Between(operand, low, high) => {
+                let operand_type = operand.infer_type(table)?;
+                let low_type = low.infer_type(table)?;
+                let high_type = high.infer_type(table)?;
+                validate_comparable(&operand_type, &low_type)?;
+                validate_comparable(&operand_type, &high_type)?;
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable: [operand_type, low_type, high_type]
+                        .iter()
+                        .any(is_nullable_type),
+                }))
+            }
+            Self::Distance(lhs, rhs) => {
+                let is_metric_key = |operand: &Expression| match operand {
+                    Self::Atom(DataDefinition::Identifier(column_name)) => table
+                        .get_column(column_name)
+                        .is_some_and(|column| column.metric_key),
+                    _ => false,
+                };
+                if !is_metric_key(lhs) && !is_metric_key(rhs) {
+                    return Err(ValidationError(
+                        "The `@` distance operator is only supported for METRIC KEY columns".into(),
+                    ));
+                }
+                let mut is_nullable = false;
+                for operand in [lhs, rhs] {
+                    match operand.infer_type(table)? {
+                        Some(operand_type) if operand_type.raw_type.as_unsigned_bits().is_some() => {
+                            is_nullable |= operand_type.is_nullable
+                        }
+                        None => is_nullable = true,
+                        Some(other_type) => {
+                            return Err(ValidationError(format!(
+                                "`@` requires operands of an unsigned integer type, instead found type {}",
+                                other_type
+                            )))
+                        }
+                    }
+                }
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::UInt8,
+                    is_nullable,
+                }))
+            }
+            Self::Cast(operand, data_type) => {
+                let operand_type = operand.infer_type(table)?;
+                if let Some(operand_type) = &operand_type {
+                    if !operand_type.raw_type.is_coercible_to(data_type) {
+                        return Err(ValidationError(format!(
+                            "Type {} cannot be cast to type {}",
+                            operand_type.raw_type, data_type
+                        )));
+                    }
+                }
+                // Constants are converted right away, so that e.g. an unparsable literal is rejected upfront.
+                // Values from rows can only fail to convert during execution
+                if operand.is_constant() {
+                    if let Ok(Some(value)) = operand
+                        .evaluate(&RowContext::new(&[], &[]))
+                        .map(DataInstance::into_raw)
+                    {
+                        value.coerce(*data_type).map_err(ValidationError)?;
+                    }
+                }
+                Ok(Some(DataType {
+                    raw_type: *data_type,
+                    is_nullable: is_nullable_type(&operand_type),
+                }))
+            }
+            Self::Like(value, pattern, _) => {
+                let mut is_nullable = false;
+                for operand in [value, pattern] {
+                    match operand.infer_type(table)? {
+                        Some(DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: is_operand_nullable,
+                        }) => is_nullable |= is_operand_nullable,
+                        None => is_nullable = true,
+                        Some(other_type) => {
+                            return Err(ValidationError(format!(
+                                "LIKE requires operands of type STRING, instead found type {}",
+                                other_type
+                            )))
+                        }
+                    }
+                }
+                Ok(Some(DataType {
+                    raw_type: DataTypeRaw::Bool,
+                    is_nullable,
+                }))
+            }
+        }
+    }
+
+    /// Implicitly convert literals compared with values of another type, according to the same rules as CAST,
+    /// e.g. so that `created_at > '2077-01-01'` compares timestamps instead of failing validation.
+    pub fn coerce_literals(&self, table: &TableDefinition) -> Result<Self, ValidationError> {
+        self.try_replace(&mut |subexpression| {
+            let coerce_pair =
+                |lhs: &Self, rhs: &Self| -> Result<(Box<Self>, Box<Self>), ValidationError> {
+                    let lhs = lhs.coerce_literals(table)?;
+                    let rhs = rhs.coerce_literals(table)?;
+                    let lhs = coerce_literal_for_comparison(lhs, &rhs, table)?;
+                    let rhs = coerce_literal_for_comparison(rhs, &lhs, table)?;
+                    Ok((Box::new(lhs), Box::new(rhs)))
+                };
+            let coerce_against = |item: &Self, operand: &Self| {
+                coerce_literal_for_comparison(item.coerce_literals(table)?, operand, table)
+            };
+            Ok(Some(match subexpression {
+                Self::Equal(lhs, rhs)
+                | Self::NotEqual(lhs, rhs)
+                | Self::LessThan(lhs, rhs)
+                | Self::LessThanOrEqual(lhs, rhs)
+                | Self::GreaterThan(lhs, rhs)
+                | Self::GreaterThanOrEqual(lhs, rhs) => {
+                    let rebuild: fn(Box<Self>, Box<Self>) -> Self = match subexpression {
+                        Self::Equal(..) => Self::Equal,
+                        Self::NotEqual(..) => Self::NotEqual,
+                        Self::LessThan(..) => Self::LessThan,
+                        Self::LessThanOrEqual(..) => Self::LessThanOrEqual,
+                        Self::GreaterThan(..) => Self::GreaterThan,
+                        _ => Self::GreaterThanOrEqual,
+                    };
+                    let (lhs, rhs) = coerce_pair(lhs, rhs)?;
+                    rebuild(lhs, rhs)
+                }
+                Self::In(operand, items) => {
+                    let operand = operand.coerce_literals(table)?;
+                    let items = items
+                        .iter()
+                        .map(|item| coerce_against(item, &operand))
+                        .collect::<Result<Vec<Self>, ValidationError>>()?;
+                    Self::In(Box::new(operand), items)
+                }
+                Self::Between(operand, low, high) => {
+                    let operand = operand.coerce_literals(table)?;
+                    let low = coerce_against(low, &operand)?;
+                    let high = coerce_against(high, &operand)?;
+                    Self::Between(Box::new(operand), Box::new(low), Box::new(high))
+                }
+                _ => return Ok(None),
+            }))
+        })
+    }
+
+    /// Make sure that this expression can be an operand of a logical operator, returning whether it's nullable.
+    fn infer_truth_value_nullability(
+        &self,
+        table: &TableDefinition,
+    ) -> Result<bool, ValidationError> {
+        match self.infer_type(table)? {
+            Some(DataType {
+                raw_type: DataTypeRaw::Bool,
+                is_nullable,
+            }) => Ok(is_nullable),
+            None => Ok(true),
+            Some(other_type) => Err(ValidationError(format!(
+                "A logical operand must be of type BOOL, instead found type {}",
+                other_type
+            ))),
+        }
+    }
+
+    /// Make sure that this expression can be used as a condition (e.g. in WHERE), i.e. that it's boolean.
+    pub fn validate_condition(&self, table: &TableDefinition) -> Result<(), ValidationError> {
+        match self.infer_type(table)? {
+            Some(DataType {
+                raw_type: DataTypeRaw::Bool,
+                ..
+            })
+            | None => Ok(()),
+            Some(other_type) => Err(ValidationError(format!(
+                "A condition must be of type BOOL, instead found type {}",
+                other_type
+            ))),
+        }
+    }
+
+    /// Make sure that values of this expression can be stored in a column of type `data_type`.
+    pub fn validate_assignable_to(
+        &self,
+        data_type: &DataType,
+        table: &TableDefinition,
+    ) -> Result<(), ValidationError> {
+        // Constants are checked by value, so that e.g. a small integer literal fits into a UINT8 column
+        if let Self::Atom(DataDefinition::Const(value)) = self {
+            return value
+                .clone()
+                .conform(data_type)
+                .map(|_| ())
+                .map_err(ValidationError);
+        }
+        match self.infer_type(table)? {
+            Some(value_type) if !value_type.raw_type.is_widenable_to(&data_type.raw_type) => {
+                Err(ValidationError(format!(
+                    "Value of type {} cannot be assigned to type {}",
+                    value_type, data_type
+                )))
+            }
+            Some(value_type) if value_type.is_nullable && !data_type.is_nullable => {
+                Err(ValidationError(format!(
+                    "Value of type {} cannot be assigned to non-nullable type {}",
+                    value_type, data_type
+                )))
+            }
+            None if !data_type.is_nullable => Err(ValidationError(format!(
+                "NULL is not allowed for type {}",
+                data_type
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod evaluation_tests {
+    use super::*;
+    use crate::constructs::components::ColumnDefinition;
+    use pretty_assertions::assert_eq;
+
+    fn get_test_table() -> TableDefinition {
+        TableDefinition::new(
+            "photos".into(),
+            vec![
+                ColumnDefinition {
+                    name: "url".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::String,
+                        is_nullable: false,
+                    },
+                    primary_key: true,
+                    metric_key: false,
+                    default: None,
+                },
+                ColumnDefinition {
+                    name: "width".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::UInt64,
+                        is_nullable: true,
+                    },
+                    primary_key: false,
+                    metric_key: false,
+                    default: None,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn equality_compares_integers_of_different_sizes() {
+        let column_names = get_test_table().get_column_names();
+        let values = vec![
+            DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+            DataInstance::Nullable(DataInstanceRaw::UInt64(1280)),
+        ];
+        let expression = Expression::Equal(
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+            Box::new(Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::UInt32(1280)),
+            ))),
+        );
+        assert_eq!(
+            expression.is_satisfied_by(&RowContext::new(&column_names, &values)),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn equality_with_null_is_not_satisfied() {
+        let column_names = get_test_table().get_column_names();
+        let values = vec![
+            DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+            DataInstance::Null,
+        ];
+        let expression = Expression::Equal(
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+        );
+        let row = RowContext::new(&column_names, &values);
+        assert_eq!(expression.evaluate(&row), Ok(DataInstance::Null));
+        assert_eq!(expression.is_satisfied_by(&row), Ok(false));
+    }
+
+    #[test]
+    fn assignment_of_string_to_integer_is_invalid() {
+        let table = get_test_table();
+        let expression = Expression::Atom(DataDefinition::Identifier("url".into()));
+        assert_eq!(
+            expression.validate_assignable_to(&table.columns[1].data_type, &table),
+            Err(ValidationError(
+                "Value of type STRING cannot be assigned to type NULLABLE(UINT64)".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn logic_with_null_is_three_valued() {
+        let column_names = get_test_table().get_column_names();
+        let values = vec![
+            DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+            DataInstance::Null,
+        ];
+        let row = RowContext::new(&column_names, &values);
+        let unknown = Box::new(Expression::GreaterThan(
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+            Box::new(Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::UInt32(800)),
+            ))),
+        ));
+        let truth = |value: bool| {
+            Box::new(Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::Bool(value)),
+            )))
+        };
+        assert_eq!(
+            Expression::And(unknown.clone(), truth(false)).evaluate(&row),
+            Ok(DataInstance::Direct(DataInstanceRaw::Bool(false)))
+        );
+        assert_eq!(
+            Expression::And(unknown.clone(), truth(true)).evaluate(&row),
+            Ok(DataInstance::Null)
+        );
+        assert_eq!(
+            Expression::Or(unknown.clone(), truth(true)).evaluate(&row),
+            Ok(DataInstance::Direct(DataInstanceRaw::Bool(true)))
+        );
+        assert_eq!(
+            Expression::Or(unknown.clone(), truth(false)).evaluate(&row),
+            Ok(DataInstance::Null)
+        );
+        assert_eq!(
+            Expression::Not(unknown.clone()).evaluate(&row),
+            Ok(DataInstance::Null)
+        );
+        assert_eq!(Expression::Not(unknown).is_satisfied_by(&row), Ok(false));
+    }
+
+    #[test]
+    fn logical_operand_must_be_boolean() {
+        let table = get_test_table();
+        let expression = Expression::And(
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+            Box::new(Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::Bool(true)),
+            ))),
+        );
+        assert_eq!(
+            expression.validate_condition(&table),
+            Err(ValidationError(
+                "A logical operand must be of type BOOL, instead found type NULLABLE(UINT64)"
+                    .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn like_patterns_match() {
+        assert_eq!(
+            matches_like_pattern("https://example.pl/a.png", "https://%.pl/%", '\\'),
+            Ok(true)
+        );
+        assert_eq!(
+            matches_like_pattern("https://example.com/a.png", "https://%.pl/%", '\\'),
+            Ok(false)
+        );
+        assert_eq!(matches_like_pattern("abc", "a_c", '\\'), Ok(true));
+        assert_eq!(matches_like_pattern("abbc", "a_c", '\\'), Ok(false));
+        assert_eq!(matches_like_pattern("aXbXc", "%X%c", '\\'), Ok(true));
+        assert_eq!(matches_like_pattern("", "%", '\\'), Ok(true));
+        assert_eq!(matches_like_pattern("50%", "50!%", '!'), Ok(true));
+        assert_eq!(matches_like_pattern("500", "50!%", '!'), Ok(false));
+        assert_eq!(
+            matches_like_pattern("50", "50!", '!'),
+            Err("LIKE pattern `50!` must not end with escape character `!`".to_string())
+        );
+    }
+
+    #[test]
+    fn in_with_null_item_is_unknown_unless_matched() {
+        let column_names = get_test_table().get_column_names();
+        let values = vec![
+            DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+            DataInstance::Nullable(DataInstanceRaw::UInt64(800)),
+        ];
+        let row = RowContext::new(&column_names, &values);
+        let item = |value: DataInstance| Expression::Atom(DataDefinition::Const(value));
+        let width = Box::new(Expression::Atom(DataDefinition::Identifier("width".into())));
+        assert_eq!(
+            Expression::In(
+                width.clone(),
+                vec![
+                    item(DataInstance::Null),
+                    item(DataInstance::Direct(DataInstanceRaw::UInt32(800)))
+                ]
+            )
+            .evaluate(&row),
+            Ok(DataInstance::Direct(DataInstanceRaw::Bool(true)))
+        );
+        assert_eq!(
+            Expression::In(
+                width,
+                vec![
+                    item(DataInstance::Null),
+                    item(DataInstance::Direct(DataInstanceRaw::UInt32(1280)))
+                ]
+            )
+            .evaluate(&row),
+            Ok(DataInstance::Null)
+        );
+    }
+
+    #[test]
+    fn coercion_follows_matrix() {
+        assert_eq!(
+            DataInstanceRaw::UInt8(200).coerce(DataTypeRaw::UInt128),
+            Ok(DataInstanceRaw::UInt128(200))
+        );
+        assert_eq!(
+            DataInstanceRaw::UInt64(300).coerce(DataTypeRaw::UInt8),
+            Err("Value UInt64(300) is out of range for type UINT8".into())
+        );
+        assert_eq!(
+            DataInstanceRaw::UInt8(2).coerce(DataTypeRaw::Bool),
+            Ok(DataInstanceRaw::Bool(true))
+        );
+        assert_eq!(
+            DataInstanceRaw::Bool(true).coerce(DataTypeRaw::UInt16),
+            Ok(DataInstanceRaw::UInt16(1))
+        );
+        assert_eq!(
+            DataInstanceRaw::String("2077-01-01T21:37".into())
+                .coerce(DataTypeRaw::Timestamp)
+                .and_then(|value| value.coerce(DataTypeRaw::String)),
+            Ok(DataInstanceRaw::String("2077-01-01T21:37:00Z".into()))
+        );
+        assert_eq!(
+            DataInstanceRaw::String("01ARZ3NDEKTSV4RRFFQ69G5FAV".into())
+                .coerce(DataTypeRaw::Uuid)
+                .and_then(|value| value.coerce(DataTypeRaw::String)),
+            Ok(DataInstanceRaw::String(
+                "01563e3a-b5d3-d676-4c61-efb99302bd5b".into()
+            ))
+        );
+        assert_eq!(
+            DataInstanceRaw::String("tomorrow".into()).coerce(DataTypeRaw::Timestamp),
+            Err("Value String(\"tomorrow\") cannot be parsed as type TIMESTAMP".into())
+        );
+        assert_eq!(
+            DataInstanceRaw::Bool(true).coerce(DataTypeRaw::String),
+            Err("Value of type BOOL cannot be converted to type STRING".into())
+        );
+        assert!(!DataTypeRaw::Timestamp.is_coercible_to(&DataTypeRaw::UInt64));
+    }
+
+    #[test]
+    fn literals_are_coerced_for_comparison() {
+        let table = get_test_table();
+        let expression = Expression::In(
+            Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+            vec![Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::String("1280".into())),
+            ))],
+        );
+        assert_eq!(
+            expression.coerce_literals(&table),
+            Ok(Expression::In(
+                Box::new(Expression::Atom(DataDefinition::Identifier("width".into()))),
+                vec![Expression::Atom(DataDefinition::Const(
+                    DataInstance::Direct(DataInstanceRaw::UInt64(1280),)
+                ))],
+            ))
+        );
+    }
+}
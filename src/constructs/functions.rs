@@ -1,36 +1,412 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
 
-use time::OffsetDateTime;
+use time::{Date, Duration, Month, OffsetDateTime, Time};
 use ulid::Ulid;
 use uuid::Uuid;
 
-use super::components::DataInstanceRaw;
+use super::components::{
+    DataDefinition, DataInstance, DataInstanceRaw, DataType, DataTypeRaw, Expression,
+};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Function {
     Ulid,
     Now,
+    /// COUNT(*) or COUNT(expression), the latter only counting non-NULL values.
+    Count,
+    Min,
+    Max,
+    /// Average of unsigned integers, rounded down as there are no fractional types.
+    Avg,
+    /// Number of characters in a string.
+    Length,
+    Lower,
+    Upper,
+    /// SUBSTR(string, start, [length]), with characters counted from 1.
+    Substr,
+    /// Concatenation of strings, with NULLs skipped.
+    Concat,
+    /// DATE_TRUNC(field, timestamp), e.g. DATE_TRUNC('day', seen_at).
+    DateTrunc,
+    /// EXTRACT(field FROM timestamp), e.g. EXTRACT(year FROM seen_at).
+    Extract,
+    ToUnixMicros,
+    /// Number of set bits.
+    Popcount,
+    BitAnd,
+    BitXor,
+    /// The first non-NULL argument.
+    Coalesce,
+    /// Timestamp that a ULID (stored as a UUID) was generated at.
+    UlidTime,
 }
 
 impl Function {
-    pub fn call(&self) -> DataInstanceRaw {
+    /// Whether this function computes a single value out of many rows.
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, Self::Count | Self::Min | Self::Max | Self::Avg)
+    }
+
+    /// Whether a NULL argument always makes the result NULL.
+    fn is_strict(&self) -> bool {
+        !matches!(self, Self::Concat | Self::Coalesce)
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Ulid => "ULID",
+            Self::Now => "NOW",
+            Self::Count => "COUNT",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+            Self::Avg => "AVG",
+            Self::Length => "LENGTH",
+            Self::Lower => "LOWER",
+            Self::Upper => "UPPER",
+            Self::Substr => "SUBSTR",
+            Self::Concat => "CONCAT",
+            Self::DateTrunc => "DATE_TRUNC",
+            Self::Extract => "EXTRACT",
+            Self::ToUnixMicros => "TO_UNIX_MICROS",
+            Self::Popcount => "POPCOUNT",
+            Self::BitAnd => "BIT_AND",
+            Self::BitXor => "BIT_XOR",
+            Self::Coalesce => "COALESCE",
+            Self::UlidTime => "ULID_TIME",
+        }
+    }
+
+    fn get_argument_count_range(&self) -> RangeInclusive<usize> {
         match self {
-            Self::Ulid => DataInstanceRaw::Uuid(Uuid::from(Ulid::new())),
-            Self::Now => DataInstanceRaw::Timestamp(OffsetDateTime::now_utc()),
+            Self::Ulid | Self::Now => 0..=0,
+            Self::Count => 0..=1,
+            Self::Min
+            | Self::Max
+            | Self::Avg
+            | Self::Length
+            | Self::Lower
+            | Self::Upper
+            | Self::ToUnixMicros
+            | Self::Popcount
+            | Self::UlidTime => 1..=1,
+            Self::DateTrunc | Self::Extract | Self::BitAnd | Self::BitXor => 2..=2,
+            Self::Substr => 2..=3,
+            Self::Concat | Self::Coalesce => 1..=usize::MAX,
+        }
+    }
+
+    /// Make sure that argument at `index` (if provided at all) is of the required kind.
+    /// The NULL literal (`None`) fits any kind.
+    fn validate_argument(
+        &self,
+        argument_types: &[Option<DataType>],
+        index: usize,
+        kind: ArgumentKind,
+    ) -> Result<(), String> {
+        match argument_types.get(index) {
+            Some(Some(argument_type)) if !kind.accepts(&argument_type.raw_type) => Err(format!(
+                "Argument {} of function `{}` must be {}, instead found type {}",
+                index + 1,
+                self.get_name(),
+                kind,
+                argument_type
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Determine the type of this function's values, given the types of its arguments
+    /// (`None` meaning the NULL literal). Arguments of COUNT(*) are empty.
+    pub fn infer_return_type(
+        &self,
+        argument_types: &[Option<DataType>],
+    ) -> Result<DataType, String> {
+        let argument_count_range = self.get_argument_count_range();
+        if !argument_count_range.contains(&argument_types.len()) {
+            return Err(format!(
+                "Function `{}` takes {} argument(s), not {}",
+                self.get_name(),
+                match (argument_count_range.start(), argument_count_range.end()) {
+                    (start, end) if start == end => start.to_string(),
+                    (start, &usize::MAX) => format!("at least {}", start),
+                    (start, end) => format!("{} to {}", start, end),
+                },
+                argument_types.len()
+            ));
         }
+        let is_any_argument_nullable = argument_types
+            .iter()
+            .any(|argument_type| argument_type.as_ref().is_none_or(|it| it.is_nullable));
+        let raw_type = match self {
+            Self::Ulid => DataTypeRaw::Uuid,
+            Self::Now => DataTypeRaw::Timestamp,
+            Self::Count => DataTypeRaw::UInt64,
+            // These are NULL if there are no non-NULL values to aggregate
+            Self::Min | Self::Max | Self::Avg => {
+                let kind = if *self == Self::Avg {
+                    ArgumentKind::UnsignedInteger
+                } else {
+                    ArgumentKind::Any
+                };
+                self.validate_argument(argument_types, 0, kind)?;
+                return match &argument_types[0] {
+                    Some(argument_type) => Ok(DataType {
+                        raw_type: argument_type.raw_type,
+                        is_nullable: true,
+                    }),
+                    None => Err(format!(
+                        "Function `{}` cannot take a NULL literal argument",
+                        self.get_name()
+                    )),
+                };
+            }
+            Self::Length => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::String),
+                )?;
+                DataTypeRaw::UInt64
+            }
+            Self::Lower | Self::Upper => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::String),
+                )?;
+                DataTypeRaw::String
+            }
+            Self::Substr => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::String),
+                )?;
+                self.validate_argument(argument_types, 1, ArgumentKind::UnsignedInteger)?;
+                self.validate_argument(argument_types, 2, ArgumentKind::UnsignedInteger)?;
+                DataTypeRaw::String
+            }
+            Self::Concat => {
+                for index in 0..argument_types.len() {
+                    self.validate_argument(
+                        argument_types,
+                        index,
+                        ArgumentKind::Exactly(DataTypeRaw::String),
+                    )?;
+                }
+                // NULLs are skipped, so the result never is NULL
+                return Ok(DataType {
+                    raw_type: DataTypeRaw::String,
+                    is_nullable: false,
+                });
+            }
+            Self::DateTrunc | Self::Extract => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::String),
+                )?;
+                self.validate_argument(
+                    argument_types,
+                    1,
+                    ArgumentKind::Exactly(DataTypeRaw::Timestamp),
+                )?;
+                if *self == Self::DateTrunc {
+                    DataTypeRaw::Timestamp
+                } else {
+                    DataTypeRaw::UInt64
+                }
+            }
+            Self::ToUnixMicros => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::Timestamp),
+                )?;
+                DataTypeRaw::UInt64
+            }
+            Self::Popcount => {
+                self.validate_argument(argument_types, 0, ArgumentKind::UnsignedInteger)?;
+                DataTypeRaw::UInt8
+            }
+            Self::BitAnd | Self::BitXor => {
+                self.validate_argument(argument_types, 0, ArgumentKind::UnsignedInteger)?;
+                self.validate_argument(argument_types, 1, ArgumentKind::UnsignedInteger)?;
+                get_widest_type(argument_types)?.unwrap_or(DataTypeRaw::UInt32)
+            }
+            Self::Coalesce => {
+                return match get_widest_type(argument_types)? {
+                    Some(raw_type) => Ok(DataType {
+                        raw_type,
+                        // Only NULL if all arguments can be NULL
+                        is_nullable: argument_types.iter().all(|argument_type| {
+                            argument_type.as_ref().is_none_or(|it| it.is_nullable)
+                        }),
+                    }),
+                    None => Err(format!(
+                        "Function `{}` needs at least one argument other than the NULL literal",
+                        self.get_name()
+                    )),
+                };
+            }
+            Self::UlidTime => {
+                self.validate_argument(
+                    argument_types,
+                    0,
+                    ArgumentKind::Exactly(DataTypeRaw::Uuid),
+                )?;
+                DataTypeRaw::Timestamp
+            }
+        };
+        Ok(DataType {
+            raw_type,
+            is_nullable: self.is_strict() && is_any_argument_nullable,
+        })
+    }
+
+    /// Make sure that constant arguments make sense, e.g. that the field of DATE_TRUNC exists.
+    pub fn validate_constant_arguments(&self, arguments: &[Expression]) -> Result<(), String> {
+        if let (
+            Self::DateTrunc | Self::Extract,
+            Some(Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                DataInstanceRaw::String(field),
+            )))),
+        ) = (self, arguments.first())
+        {
+            let field = TimeField::from_str(field)?;
+            if *self == Self::DateTrunc {
+                field.validate_truncatable()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute the value of this scalar function for the given arguments.
+    pub fn call(&self, arguments: Vec<DataInstance>) -> Result<DataInstance, String> {
+        if self.is_aggregate() {
+            return Err(format!(
+                "Aggregate function `{}` cannot be used here",
+                self.get_name()
+            ));
+        }
+        if *self == Self::Coalesce {
+            return Ok(arguments
+                .into_iter()
+                .find(|argument| argument.as_raw().is_some())
+                .unwrap_or(DataInstance::Null));
+        }
+        if self.is_strict() && arguments.iter().any(|argument| argument.as_raw().is_none()) {
+            return Ok(DataInstance::Null);
+        }
+        let arguments: Vec<DataInstanceRaw> = arguments
+            .into_iter()
+            .filter_map(DataInstance::into_raw)
+            .collect();
+        let result = match (self, arguments.as_slice()) {
+            (Self::Ulid, []) => DataInstanceRaw::Uuid(Uuid::from(Ulid::new())),
+            (Self::Now, []) => DataInstanceRaw::Timestamp(OffsetDateTime::now_utc()),
+            (Self::Length, [DataInstanceRaw::String(value)]) => {
+                DataInstanceRaw::UInt64(value.chars().count() as u64)
+            }
+            (Self::Lower, [DataInstanceRaw::String(value)]) => {
+                DataInstanceRaw::String(value.to_lowercase())
+            }
+            (Self::Upper, [DataInstanceRaw::String(value)]) => {
+                DataInstanceRaw::String(value.to_uppercase())
+            }
+            (Self::Substr, [DataInstanceRaw::String(value), start, rest @ ..]) => {
+                let start = start.as_u128().ok_or_else(|| self.get_argument_error())?;
+                // Same as in Postgres, the start can be 0, which still counts towards the length
+                let end = match rest {
+                    [] => u128::MAX,
+                    [length] => start.saturating_add(
+                        length.as_u128().ok_or_else(|| self.get_argument_error())?,
+                    ),
+                    _ => return Err(self.get_argument_error()),
+                };
+                DataInstanceRaw::String(
+                    value
+                        .chars()
+                        .zip(1u128..)
+                        .filter(|(_, position)| (start..end).contains(position))
+                        .map(|(character, _)| character)
+                        .collect(),
+                )
+            }
+            (Self::Concat, _) => {
+                let mut result = String::new();
+                for argument in &arguments {
+                    match argument {
+                        DataInstanceRaw::String(value) => result.push_str(value),
+                        _ => return Err(self.get_argument_error()),
+                    }
+                }
+                DataInstanceRaw::String(result)
+            }
+            (
+                Self::DateTrunc,
+                [DataInstanceRaw::String(field), DataInstanceRaw::Timestamp(timestamp)],
+            ) => DataInstanceRaw::Timestamp(TimeField::from_str(field)?.truncate(*timestamp)?),
+            (
+                Self::Extract,
+                [DataInstanceRaw::String(field), DataInstanceRaw::Timestamp(timestamp)],
+            ) => DataInstanceRaw::UInt64(TimeField::from_str(field)?.extract(*timestamp)?),
+            (Self::ToUnixMicros, [DataInstanceRaw::Timestamp(timestamp)]) => {
+                DataInstanceRaw::UInt64(
+                    u64::try_from(timestamp.unix_timestamp_nanos() / 1000).map_err(|_| {
+                        format!(
+                            "Timestamp {} cannot be represented as microseconds since the Unix epoch",
+                            timestamp
+                        )
+                    })?,
+                )
+            }
+            (Self::Popcount, [value]) => DataInstanceRaw::UInt8(
+                value.as_u128().ok_or_else(|| self.get_argument_error())?.count_ones() as u8,
+            ),
+            (Self::BitAnd | Self::BitXor, [lhs, rhs]) => {
+                let (lhs_value, rhs_value) = match (lhs.as_u128(), rhs.as_u128()) {
+                    (Some(lhs_value), Some(rhs_value)) => (lhs_value, rhs_value),
+                    _ => return Err(self.get_argument_error()),
+                };
+                let result = if *self == Self::BitAnd {
+                    lhs_value & rhs_value
+                } else {
+                    lhs_value ^ rhs_value
+                };
+                // The result fits into the wider of the two types
+                let result_type = if lhs.get_type().is_widenable_to(&rhs.get_type()) {
+                    rhs.get_type()
+                } else {
+                    lhs.get_type()
+                };
+                DataInstanceRaw::UInt128(result).coerce(result_type)?
+            }
+            (Self::UlidTime, [DataInstanceRaw::Uuid(value)]) => {
+                let timestamp_ms = Ulid::from(*value).timestamp_ms();
+                DataInstanceRaw::Timestamp(
+                    OffsetDateTime::from_unix_timestamp_nanos(
+                        i128::from(timestamp_ms) * 1_000_000,
+                    )
+                    .map_err(|error| error.to_string())?,
+                )
+            }
+            _ => return Err(self.get_argument_error()),
+        };
+        Ok(DataInstance::Direct(result))
+    }
+
+    fn get_argument_error(&self) -> String {
+        format!(
+            "Function `{}` was called with invalid arguments",
+            self.get_name()
+        )
     }
 }
 
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "keyword `{}`",
-            match self {
-                Self::Ulid => "ULID",
-                Self::Now => "NOW",
-            }
-        )
+        write!(f, "keyword `{}`", self.get_name())
     }
 }
 
@@ -41,6 +417,23 @@ impl FromStr for Function {
         match candidate.to_lowercase().as_str() {
             "ulid" => Ok(Self::Ulid),
             "now" => Ok(Self::Now),
+            "count" => Ok(Self::Count),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "avg" => Ok(Self::Avg),
+            "length" => Ok(Self::Length),
+            "lower" => Ok(Self::Lower),
+            "upper" => Ok(Self::Upper),
+            "substr" => Ok(Self::Substr),
+            "concat" => Ok(Self::Concat),
+            "date_trunc" => Ok(Self::DateTrunc),
+            "extract" => Ok(Self::Extract),
+            "to_unix_micros" => Ok(Self::ToUnixMicros),
+            "popcount" => Ok(Self::Popcount),
+            "bit_and" => Ok(Self::BitAnd),
+            "bit_xor" => Ok(Self::BitXor),
+            "coalesce" => Ok(Self::Coalesce),
+            "ulid_time" => Ok(Self::UlidTime),
             _ => Err(format!(
                 "`{}` does not refer to a supported function",
                 candidate
@@ -48,3 +441,313 @@ impl FromStr for Function {
         }
     }
 }
+
+/// Kind of values that a function argument accepts.
+#[derive(Debug, Clone, Copy)]
+enum ArgumentKind {
+    Any,
+    UnsignedInteger,
+    Exactly(DataTypeRaw),
+}
+
+impl ArgumentKind {
+    fn accepts(&self, raw_type: &DataTypeRaw) -> bool {
+        match self {
+            Self::Any => true,
+            Self::UnsignedInteger => raw_type.as_unsigned_bits().is_some(),
+            Self::Exactly(expected_type) => raw_type == expected_type,
+        }
+    }
+}
+
+impl fmt::Display for ArgumentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "of any type"),
+            Self::UnsignedInteger => write!(f, "of an unsigned integer type"),
+            Self::Exactly(expected_type) => write!(f, "of type {}", expected_type),
+        }
+    }
+}
+
+/// The type that all arguments can be widened to, `None` if all arguments are the NULL literal.
+fn get_widest_type(argument_types: &[Option<DataType>]) -> Result<Option<DataTypeRaw>, String> {
+    let mut widest_type: Option<DataTypeRaw> = None;
+    for argument_type in argument_types.iter().flatten() {
+        widest_type = match widest_type {
+            None => Some(argument_type.raw_type),
+            Some(current) if argument_type.raw_type.is_widenable_to(&current) => Some(current),
+            Some(current) if current.is_widenable_to(&argument_type.raw_type) => {
+                Some(argument_type.raw_type)
+            }
+            Some(current) => {
+                return Err(format!(
+                    "Arguments of types {} and {} cannot be combined",
+                    current, argument_type.raw_type
+                ))
+            }
+        };
+    }
+    Ok(widest_type)
+}
+
+/// Part of a timestamp, as used by DATE_TRUNC and EXTRACT.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimeField {
+    Microsecond,
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// ISO week, starting on Monday.
+    Week,
+    Month,
+    Year,
+    /// Day of the week, from 0 for Sunday to 6 for Saturday.
+    DayOfWeek,
+    /// Day of the year, from 1.
+    DayOfYear,
+    /// Seconds since the Unix epoch.
+    Epoch,
+}
+
+impl TimeField {
+    fn validate_truncatable(&self) -> Result<(), String> {
+        match self {
+            Self::DayOfWeek | Self::DayOfYear | Self::Epoch => Err(format!(
+                "Timestamps cannot be truncated to field {:?}",
+                self
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Round `timestamp` down to the start of the field's unit, e.g. the start of the day.
+    pub fn truncate(&self, timestamp: OffsetDateTime) -> Result<OffsetDateTime, String> {
+        self.validate_truncatable()?;
+        let unit_nanoseconds: i128 = match self {
+            Self::Microsecond => 1_000,
+            Self::Millisecond => 1_000_000,
+            Self::Second => 1_000_000_000,
+            Self::Minute => 60 * 1_000_000_000,
+            Self::Hour => 3_600 * 1_000_000_000,
+            Self::Day | Self::Week => 86_400 * 1_000_000_000,
+            Self::Month | Self::Year => {
+                let month = if *self == Self::Month {
+                    timestamp.month()
+                } else {
+                    Month::January
+                };
+                let date = Date::from_calendar_date(timestamp.year(), month, 1)
+                    .map_err(|error| error.to_string())?;
+                return Ok(timestamp.replace_date(date).replace_time(Time::MIDNIGHT));
+            }
+            _ => unreachable!("Only truncatable fields get here"),
+        };
+        let nanoseconds = timestamp.unix_timestamp_nanos();
+        let truncated = OffsetDateTime::from_unix_timestamp_nanos(
+            nanoseconds - nanoseconds.rem_euclid(unit_nanoseconds),
+        )
+        .map_err(|error| error.to_string())?;
+        Ok(if *self == Self::Week {
+            truncated - Duration::days(i64::from(truncated.weekday().number_days_from_monday()))
+        } else {
+            truncated
+        })
+    }
+
+    /// Get the field's value out of `timestamp`. Sub-second fields are only the fractional part of the second.
+    pub fn extract(&self, timestamp: OffsetDateTime) -> Result<u64, String> {
+        let out_of_range = || format!("Field {:?} of {} is out of range", self, timestamp);
+        Ok(match self {
+            Self::Microsecond => u64::from(timestamp.microsecond()),
+            Self::Millisecond => u64::from(timestamp.millisecond()),
+            Self::Second => u64::from(timestamp.second()),
+            Self::Minute => u64::from(timestamp.minute()),
+            Self::Hour => u64::from(timestamp.hour()),
+            Self::Day => u64::from(timestamp.day()),
+            Self::Week => u64::from(timestamp.iso_week()),
+            Self::Month => u64::from(u8::from(timestamp.month())),
+            Self::Year => u64::try_from(timestamp.year()).map_err(|_| out_of_range())?,
+            Self::DayOfWeek => u64::from(timestamp.weekday().number_days_from_sunday()),
+            Self::DayOfYear => u64::from(timestamp.ordinal()),
+            Self::Epoch => u64::try_from(timestamp.unix_timestamp()).map_err(|_| out_of_range())?,
+        })
+    }
+}
+
+impl FromStr for TimeField {
+    type Err = String;
+
+    fn from_str(candidate: &str) -> std::result::Result<Self, Self::Err> {
+        match candidate.to_lowercase().as_str() {
+            "microsecond" | "microseconds" => Ok(Self::Microsecond),
+            "millisecond" | "milliseconds" => Ok(Self::Millisecond),
+            "second" | "seconds" => Ok(Self::Second),
+            "minute" | "minutes" => Ok(Self::Minute),
+            "hour" | "hours" => Ok(Self::Hour),
+            "day" | "days" => Ok(Self::Day),
+            "week" | "weeks" => Ok(Self::Week),
+            "month" | "months" => Ok(Self::Month),
+            "year" | "years" => Ok(Self::Year),
+            "dow" => Ok(Self::DayOfWeek),
+            "doy" => Ok(Self::DayOfYear),
+            "epoch" => Ok(Self::Epoch),
+            _ => Err(format!(
+                "`{}` does not refer to a supported time field",
+                candidate
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod functions_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use time::PrimitiveDateTime;
+
+    fn utc(date: (i32, Month, u8), time: (u8, u8, u8, u32)) -> OffsetDateTime {
+        PrimitiveDateTime::new(
+            Date::from_calendar_date(date.0, date.1, date.2).unwrap(),
+            Time::from_hms_micro(time.0, time.1, time.2, time.3).unwrap(),
+        )
+        .assume_utc()
+    }
+
+    fn string(value: &str) -> DataInstance {
+        DataInstance::Direct(DataInstanceRaw::String(value.into()))
+    }
+
+    fn timestamp(value: OffsetDateTime) -> DataInstance {
+        DataInstance::Direct(DataInstanceRaw::Timestamp(value))
+    }
+
+    #[test]
+    fn string_functions_work() {
+        assert_eq!(
+            Function::Length.call(vec![string("żółw")]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt64(4)))
+        );
+        assert_eq!(Function::Upper.call(vec![string("Abc")]), Ok(string("ABC")));
+        assert_eq!(
+            Function::Substr.call(vec![
+                string("emdrive"),
+                DataInstance::Direct(DataInstanceRaw::UInt32(3)),
+                DataInstance::Direct(DataInstanceRaw::UInt8(3)),
+            ]),
+            Ok(string("dri"))
+        );
+        assert_eq!(
+            Function::Concat.call(vec![string("a"), DataInstance::Null, string("b")]),
+            Ok(string("ab"))
+        );
+        assert_eq!(
+            Function::Lower.call(vec![DataInstance::Null]),
+            Ok(DataInstance::Null)
+        );
+    }
+
+    #[test]
+    fn time_functions_work() {
+        let seen_at = utc((2077, Month::January, 7), (21, 37, 12, 345678));
+        assert_eq!(
+            Function::DateTrunc.call(vec![string("week"), timestamp(seen_at)]),
+            Ok(timestamp(utc((2077, Month::January, 4), (0, 0, 0, 0))))
+        );
+        assert_eq!(
+            Function::DateTrunc.call(vec![string("month"), timestamp(seen_at)]),
+            Ok(timestamp(utc((2077, Month::January, 1), (0, 0, 0, 0))))
+        );
+        assert_eq!(
+            Function::DateTrunc.call(vec![string("hour"), timestamp(seen_at)]),
+            Ok(timestamp(utc((2077, Month::January, 7), (21, 0, 0, 0))))
+        );
+        assert_eq!(
+            Function::Extract.call(vec![string("millisecond"), timestamp(seen_at)]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt64(345)))
+        );
+        assert_eq!(
+            Function::Extract.call(vec![string("dow"), timestamp(seen_at)]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt64(4)))
+        );
+        assert_eq!(
+            Function::ToUnixMicros.call(vec![timestamp(utc(
+                (1970, Month::January, 1),
+                (0, 0, 1, 0)
+            ))]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt64(1_000_000)))
+        );
+    }
+
+    #[test]
+    fn ulid_time_extracts_timestamp() {
+        // The timestamp takes the 48 most significant bits
+        let ulid = Ulid(1_000 << 80 | 42);
+        assert_eq!(
+            Function::UlidTime.call(vec![DataInstance::Direct(DataInstanceRaw::Uuid(
+                Uuid::from(ulid)
+            ))]),
+            Ok(timestamp(utc((1970, Month::January, 1), (0, 0, 1, 0))))
+        );
+    }
+
+    #[test]
+    fn bit_functions_work() {
+        assert_eq!(
+            Function::BitXor.call(vec![
+                DataInstance::Direct(DataInstanceRaw::UInt8(0b1100)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(0b1010)),
+            ]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt64(0b0110)))
+        );
+        assert_eq!(
+            Function::Popcount.call(vec![DataInstance::Direct(DataInstanceRaw::UInt32(0b1011))]),
+            Ok(DataInstance::Direct(DataInstanceRaw::UInt8(3)))
+        );
+    }
+
+    #[test]
+    fn return_types_are_checked() {
+        let string_type = Some(DataType {
+            raw_type: DataTypeRaw::String,
+            is_nullable: false,
+        });
+        let nullable_uint8_type = Some(DataType {
+            raw_type: DataTypeRaw::UInt8,
+            is_nullable: true,
+        });
+        assert_eq!(
+            Function::Popcount.infer_return_type(std::slice::from_ref(&string_type)),
+            Err(
+                "Argument 1 of function `POPCOUNT` must be of an unsigned integer type, instead found type STRING"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            Function::Substr.infer_return_type(std::slice::from_ref(&string_type)),
+            Err("Function `SUBSTR` takes 2 to 3 argument(s), not 1".to_string())
+        );
+        assert_eq!(
+            Function::Coalesce.infer_return_type(&[
+                nullable_uint8_type,
+                Some(DataType {
+                    raw_type: DataTypeRaw::UInt32,
+                    is_nullable: false
+                })
+            ]),
+            Ok(DataType {
+                raw_type: DataTypeRaw::UInt32,
+                is_nullable: false
+            })
+        );
+        assert_eq!(
+            Function::Length.infer_return_type(&[None]),
+            Ok(DataType {
+                raw_type: DataTypeRaw::UInt64,
+                is_nullable: true
+            })
+        );
+    }
+}
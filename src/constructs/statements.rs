@@ -1,48 +1,559 @@
-use crate::sql::ValidationError;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::sql::{quote_identifier, ValidationError};
 
 use super::components::*;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct CreateTableStatement {
+    /// Database (schema) to create the table in, with `None` meaning the default one.
+    pub schema_name: Option<String>,
     pub table: TableDefinition,
     pub if_not_exists: bool,
 }
 
 impl Validatable for CreateTableStatement {
     fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table.name)?;
         self.table.validate()
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct CreateIndexStatement {
+    /// Database (schema) of the indexed table, with `None` meaning the default one.
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub index: IndexDefinition,
+    pub if_not_exists: bool,
+}
+
+impl Validatable for CreateIndexStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)?;
+        self.index.validate()
+    }
+}
+
+/// Item of an inserted row.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum InsertValue {
+    Value(DataInstance),
+    /// `DEFAULT`, i.e. the column's DEFAULT, or NULL if it has none.
+    Default,
+}
+
+/// Where the rows to insert come from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InsertSource {
+    /// `VALUES (...), (...)`, one list of values per row.
+    Values(Vec<Vec<InsertValue>>),
+    /// `SELECT ...`, with result columns matched up to the insert's columns by position.
+    Select(Box<SelectStatement>),
+}
+
+/// What to do with an inserted row whose PRIMARY KEY value is already taken.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConflictAction {
+    /// Skip the row.
+    DoNothing,
+    /// Update the existing row instead. Values of the row that was to be inserted are available
+    /// to the assignments as `excluded.<column>`.
+    DoUpdate {
+        assignments: Vec<Assignment>,
+        where_clause: Option<Expression>,
+    },
+}
+
+/// `ON CONFLICT (<columns>) DO ...` of INSERT.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OnConflict {
+    pub column_names: Vec<String>,
+    pub action: ConflictAction,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct InsertStatement {
+    pub schema_name: Option<String>,
     pub table_name: String,
     pub column_names: Vec<String>,
-    pub values: Vec<DataInstance>,
+    pub source: InsertSource,
+    pub on_conflict: Option<OnConflict>,
+    /// Columns of the RETURNING clause, computed from affected rows. Empty if there's no such clause.
+    pub returning: Vec<SelectColumn>,
 }
 
 impl Validatable for InsertStatement {
     fn validate(&self) -> Result<(), ValidationError> {
-        Ok(()) // TODO: Add checks
+        validate_table_reference(&self.schema_name, &self.table_name)?;
+        let mut column_names: HashSet<&str> = HashSet::new();
+        for column_name in &self.column_names {
+            if !column_names.insert(column_name) {
+                return Err(ValidationError(format!(
+                    "Column `{}` is specified more than once",
+                    column_name
+                )));
+            }
+        }
+        if let Some(OnConflict {
+            action: ConflictAction::DoUpdate { assignments, .. },
+            ..
+        }) = &self.on_conflict
+        {
+            validate_assignments(assignments)?;
+        }
+        match &self.source {
+            InsertSource::Values(_) => Ok(()),
+            InsertSource::Select(select) => select.validate(),
+        }
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum SelectColumn {
     All,
-    Identifier(String),
+    /// An expression, optionally named with AS.
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Item of an ORDER BY clause.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub descending: bool,
+    /// Whether NULLs go before other values. By default NULLs are treated as larger than any other value,
+    /// so they go last in ascending order and first in descending order.
+    pub nulls_first: bool,
+}
+
+/// Table read by a query, as in `gaggle.photos_seen AS p`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableReference {
+    /// Database (schema) of the table, with `None` meaning the default one.
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub alias: Option<String>,
+}
+
+impl TableReference {
+    /// Name that the table's columns can be qualified with in the query, i.e. the alias if there is one.
+    pub fn get_qualifier(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.table_name)
+    }
+}
+
+impl fmt::Display for TableReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(schema_name) = &self.schema_name {
+            write!(f, "{}.", quote_identifier(schema_name))?;
+        }
+        write!(f, "{}", quote_identifier(&self.table_name))?;
+        if let Some(alias) = &self.alias {
+            write!(f, " AS {}", quote_identifier(alias))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum JoinKind {
+    /// `[INNER] JOIN`, i.e. only pairs of rows satisfying the condition.
+    Inner,
+    /// `LEFT [OUTER] JOIN`, i.e. like INNER, plus rows of the left side that have no pair, with NULLs on the right.
+    Left,
+}
+
+/// `JOIN <table> ON <condition>` of SELECT.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableReference,
+    pub condition: Expression,
+}
+
+/// `<name> AS (<query>)` of WITH, i.e. a query whose result the rest of the statement can read like a table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CommonTableExpression {
+    pub name: String,
+    pub query: SelectStatement,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct SelectStatement {
+    /// Queries of the WITH clause, in order. Each can read results of the ones before it.
+    pub with: Vec<CommonTableExpression>,
     pub columns: Vec<SelectColumn>,
-    /// String means table name
-    pub source: String,
+    pub source: TableReference,
+    /// Tables joined to the source, in order.
+    pub joins: Vec<Join>,
     pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl SelectStatement {
+    /// All expressions of the query, not counting those of its WITH queries.
+    pub fn get_expressions(&self) -> Vec<&Expression> {
+        let mut expressions = Vec::new();
+        for column in &self.columns {
+            if let SelectColumn::Expression { expression, .. } = column {
+                expressions.push(expression);
+            }
+        }
+        expressions.extend(self.joins.iter().map(|join| &join.condition));
+        expressions.extend(&self.where_clause);
+        expressions.extend(&self.group_by);
+        expressions.extend(&self.having);
+        expressions.extend(self.order_by.iter().map(|item| &item.expression));
+        expressions
+    }
+}
+
+fn validate_subqueries(expression: &Expression) -> Result<(), ValidationError> {
+    for subquery in expression.get_subqueries() {
+        subquery.validate()?;
+    }
+    Ok(())
 }
 
 impl Validatable for SelectStatement {
     fn validate(&self) -> Result<(), ValidationError> {
-        Ok(()) // TODO: Add checks
+        let mut common_table_names = HashSet::new();
+        for common_table in &self.with {
+            if !common_table_names.insert(&common_table.name) {
+                return Err(ValidationError(format!(
+                    "WITH query name `{}` is specified more than once",
+                    common_table.name
+                )));
+            }
+            common_table.query.validate()?;
+        }
+        for expression in self.get_expressions() {
+            validate_subqueries(expression)?;
+        }
+        let mut qualifiers = HashSet::new();
+        for table in std::iter::once(&self.source).chain(self.joins.iter().map(|join| &join.table))
+        {
+            validate_table_reference(&table.schema_name, &table.table_name)?;
+            if !qualifiers.insert(table.get_qualifier()) {
+                return Err(ValidationError(format!(
+                    "Table name `{}` is specified more than once. Use an alias to tell the tables apart.",
+                    table.get_qualifier()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// SQL text of the query.
+impl fmt::Display for SelectStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join_texts = |texts: Vec<String>| texts.join(", ");
+        if !self.with.is_empty() {
+            write!(
+                f,
+                "WITH {} ",
+                join_texts(
+                    self.with
+                        .iter()
+                        .map(|common_table| format!(
+                            "{} AS ({})",
+                            quote_identifier(&common_table.name),
+                            common_table.query
+                        ))
+                        .collect()
+                )
+            )?;
+        }
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| match column {
+                SelectColumn::All => "*".to_string(),
+                SelectColumn::Expression {
+                    expression,
+                    alias: Some(alias),
+                } => format!("{} AS {}", expression, quote_identifier(alias)),
+                SelectColumn::Expression {
+                    expression,
+                    alias: None,
+                } => expression.to_string(),
+            })
+            .collect();
+        write!(f, "SELECT {} FROM {}", join_texts(columns), self.source)?;
+        for join in &self.joins {
+            let keyword = match join.kind {
+                JoinKind::Inner => "JOIN",
+                JoinKind::Left => "LEFT JOIN",
+            };
+            write!(f, " {} {} ON {}", keyword, join.table, join.condition)?;
+        }
+        if let Some(where_clause) = &self.where_clause {
+            write!(f, " WHERE {}", where_clause)?;
+        }
+        if !self.group_by.is_empty() {
+            write!(
+                f,
+                " GROUP BY {}",
+                join_texts(self.group_by.iter().map(Expression::to_string).collect())
+            )?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {}", having)?;
+        }
+        if !self.order_by.is_empty() {
+            let items = self
+                .order_by
+                .iter()
+                .map(|item| {
+                    let mut text = item.expression.to_string();
+                    if item.descending {
+                        text.push_str(" DESC");
+                    }
+                    if item.nulls_first != item.descending {
+                        text.push_str(match item.nulls_first {
+                            true => " NULLS FIRST",
+                            false => " NULLS LAST",
+                        });
+                    }
+                    text
+                })
+                .collect();
+            write!(f, " ORDER BY {}", join_texts(items))?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// `EXPLAIN [ANALYZE] <query>`, i.e. the plan that the query would be executed with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExplainStatement {
+    /// Whether to actually execute the query, so that real row counts are known too.
+    pub analyze: bool,
+    pub select: SelectStatement,
+}
+
+impl Validatable for ExplainStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.select.validate()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Assignment {
+    pub column_name: String,
+    pub value: Expression,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UpdateStatement {
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub assignments: Vec<Assignment>,
+    pub where_clause: Option<Expression>,
+    /// Columns of the RETURNING clause, computed from affected rows. Empty if there's no such clause.
+    pub returning: Vec<SelectColumn>,
+}
+
+/// Make sure that no column is assigned more than once.
+fn validate_assignments(assignments: &[Assignment]) -> Result<(), ValidationError> {
+    let mut column_names: HashSet<&str> = HashSet::new();
+    for assignment in assignments {
+        if !column_names.insert(&assignment.column_name) {
+            return Err(ValidationError(format!(
+                "Column `{}` is assigned more than once",
+                assignment.column_name
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl Validatable for UpdateStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)?;
+        validate_assignments(&self.assignments)?;
+        for assignment in &self.assignments {
+            validate_subqueries(&assignment.value)?;
+        }
+        self.where_clause
+            .as_ref()
+            .map_or(Ok(()), validate_subqueries)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeleteStatement {
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub where_clause: Option<Expression>,
+    /// Columns of the RETURNING clause, computed from affected rows. Empty if there's no such clause.
+    pub returning: Vec<SelectColumn>,
+}
+
+impl Validatable for DeleteStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)?;
+        self.where_clause
+            .as_ref()
+            .map_or(Ok(()), validate_subqueries)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DropTableStatement {
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub if_exists: bool,
+}
+
+impl Validatable for DropTableStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DropIndexStatement {
+    /// Database (schema) of the index, with `None` meaning the default one.
+    pub schema_name: Option<String>,
+    pub index_name: String,
+    pub if_exists: bool,
+}
+
+impl Validatable for DropIndexStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        self.schema_name
+            .as_deref()
+            .map_or(Ok(()), validate_database_name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TruncateStatement {
+    pub schema_name: Option<String>,
+    pub table_name: String,
+}
+
+impl Validatable for TruncateStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)
+    }
+}
+
+/// `ANALYZE [table]`, i.e. gathering statistics of the table's data, or of all tables' if none is named.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AnalyzeStatement {
+    pub schema_name: Option<String>,
+    pub table_name: Option<String>,
+}
+
+impl Validatable for AnalyzeStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        match &self.table_name {
+            Some(table_name) => validate_table_reference(&self.schema_name, table_name),
+            None => self
+                .schema_name
+                .as_deref()
+                .map_or(Ok(()), validate_database_name),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AlterTableAction {
+    AddColumn(ColumnDefinition),
+    DropColumn(String),
+    RenameColumn {
+        column_name: String,
+        new_column_name: String,
+    },
+    RenameTo(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AlterTableStatement {
+    pub schema_name: Option<String>,
+    pub table_name: String,
+    pub action: AlterTableAction,
+}
+
+impl Validatable for AlterTableStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_table_reference(&self.schema_name, &self.table_name)?;
+        match &self.action {
+            AlterTableAction::AddColumn(column) => column.validate(),
+            AlterTableAction::RenameTo(new_table_name) if new_table_name.contains('.') => {
+                Err(ValidationError(format!(
+                    "Table `{}` can only be renamed within its database, so the new name must not contain `.`",
+                    self.table_name
+                )))
+            }
+            AlterTableAction::RenameTo(new_table_name) => validate_table_name(new_table_name),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CreateDatabaseStatement {
+    pub database_name: String,
+    pub if_not_exists: bool,
+}
+
+impl Validatable for CreateDatabaseStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_database_name(&self.database_name)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DropDatabaseStatement {
+    pub database_name: String,
+    pub if_exists: bool,
+}
+
+impl Validatable for DropDatabaseStatement {
+    fn validate(&self) -> Result<(), ValidationError> {
+        validate_database_name(&self.database_name)
+    }
+}
+
+/// Database names become directory names, so they must not be able to escape the data directory.
+fn validate_database_name(database_name: &str) -> Result<(), ValidationError> {
+    if database_name.is_empty() {
+        return Err(ValidationError("A database must have a name".into()));
+    }
+    if database_name.contains(['.', '/', '\\']) {
+        return Err(ValidationError(format!(
+            "Database name `{}` must not contain `.`, `/` or `\\`",
+            database_name
+        )));
+    }
+    Ok(())
+}
+
+/// Check the names of a referenced table and its database, which both become parts of paths.
+fn validate_table_reference(
+    schema_name: &Option<String>,
+    table_name: &str,
+) -> Result<(), ValidationError> {
+    if let Some(schema_name) = schema_name {
+        validate_database_name(schema_name)?;
     }
+    validate_table_name(table_name)
 }
@@ -0,0 +1,356 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::constructs::{
+    components::{
+        ColumnDefinition, DataDefinition, DataInstance, DataInstanceRaw, DataType, Expression,
+        TableDefinition,
+    },
+    evaluation::{compare_values, RowContext},
+    functions::Function,
+};
+use crate::sql::ValidationError;
+use crate::storage::Row;
+
+/// Running state of one aggregate function call within one group.
+#[derive(Debug, Clone)]
+enum Accumulator {
+    Count(u64),
+    Min(Option<DataInstanceRaw>),
+    Max(Option<DataInstanceRaw>),
+    Avg { sum: u128, count: u128 },
+}
+
+impl Accumulator {
+    fn new(function: Function) -> Self {
+        match function {
+            Function::Count => Self::Count(0),
+            Function::Min => Self::Min(None),
+            Function::Max => Self::Max(None),
+            Function::Avg => Self::Avg { sum: 0, count: 0 },
+            _ => unreachable!("Only aggregate functions have an accumulator"),
+        }
+    }
+
+    /// Take a row into account, with `None` standing for the argument-less COUNT(*).
+    /// Otherwise NULL values are skipped, like in other databases.
+    fn update(&mut self, argument: Option<DataInstance>) -> Result<(), String> {
+        let value = match argument.map(DataInstance::into_raw) {
+            None => None,
+            Some(None) => return Ok(()),
+            Some(Some(value)) => Some(value),
+        };
+        match (self, value) {
+            (Self::Count(count), _) => *count += 1,
+            (Self::Min(extreme), Some(value)) => replace_extreme(extreme, value, Ordering::Less)?,
+            (Self::Max(extreme), Some(value)) => {
+                replace_extreme(extreme, value, Ordering::Greater)?
+            }
+            (Self::Avg { sum, count }, Some(value)) => {
+                let value = value.as_u128().ok_or_else(|| {
+                    format!("AVG requires unsigned integers, instead got {:?}", value)
+                })?;
+                *sum = sum.checked_add(value).ok_or_else(|| {
+                    "Sum of AVG values is out of range for type UINT128".to_string()
+                })?;
+                *count += 1;
+            }
+            (_, None) => unreachable!("Only COUNT can go without an argument"),
+        }
+        Ok(())
+    }
+
+    /// Final value of the aggregate, of type `data_type`.
+    fn finish(self, data_type: &DataType) -> Result<DataInstance, String> {
+        match self {
+            Self::Count(count) => Ok(DataInstance::Direct(DataInstanceRaw::UInt64(count))),
+            Self::Min(extreme) | Self::Max(extreme) => {
+                Ok(extreme.map_or(DataInstance::Null, DataInstance::Nullable))
+            }
+            Self::Avg { count: 0, .. } => Ok(DataInstance::Null),
+            // An average never exceeds the largest value, so it fits into the type of values
+            Self::Avg { sum, count } => Ok(DataInstance::Nullable(
+                DataInstanceRaw::UInt128(sum / count).coerce(data_type.raw_type)?,
+            )),
+        }
+    }
+}
+
+fn replace_extreme(
+    extreme: &mut Option<DataInstanceRaw>,
+    value: DataInstanceRaw,
+    wanted_ordering: Ordering,
+) -> Result<(), String> {
+    let is_more_extreme = match extreme {
+        Some(current) => compare_values(&value, current)? == wanted_ordering,
+        None => true,
+    };
+    if is_more_extreme {
+        *extreme = Some(value);
+    }
+    Ok(())
+}
+
+/// Aggregate function call used in the query.
+#[derive(Debug)]
+struct AggregateCall {
+    /// The whole call, for matching it up in expressions.
+    expression: Expression,
+    function: Function,
+    /// Empty for COUNT(*).
+    arguments: Vec<Expression>,
+}
+
+fn collect_aggregate_calls(expression: &Expression, aggregate_calls: &mut Vec<AggregateCall>) {
+    match expression {
+        Expression::Atom(DataDefinition::FunctionCall(function, arguments))
+            if function.is_aggregate() =>
+        {
+            if !aggregate_calls
+                .iter()
+                .any(|aggregate_call| aggregate_call.expression == *expression)
+            {
+                aggregate_calls.push(AggregateCall {
+                    expression: expression.clone(),
+                    function: *function,
+                    arguments: arguments.clone(),
+                });
+            }
+        }
+        _ => {
+            for operand in expression.get_operands() {
+                collect_aggregate_calls(operand, aggregate_calls);
+            }
+        }
+    }
+}
+
+/// Hash aggregation: rows are put into groups by the values of GROUP BY expressions, and each group
+/// becomes a single row of GROUP BY values followed by results of aggregate function calls.
+#[derive(Debug)]
+pub struct Aggregation {
+    group_by: Vec<Expression>,
+    aggregate_calls: Vec<AggregateCall>,
+    /// Shape of aggregated rows, which expressions after aggregation are evaluated against.
+    pub table: TableDefinition,
+}
+
+impl Aggregation {
+    /// Plan aggregation of `table` rows, with aggregate function calls collected from `expressions`.
+    pub fn plan(
+        table: &TableDefinition,
+        group_by: &[Expression],
+        expressions: &[&Expression],
+    ) -> Result<Self, ValidationError> {
+        let mut columns = Vec::with_capacity(group_by.len());
+        for (group_index, expression) in group_by.iter().enumerate() {
+            let data_type = expression.infer_type(table)?.ok_or_else(|| {
+                ValidationError("A GROUP BY expression cannot be the NULL literal".into())
+            })?;
+            let name = match expression {
+                Expression::Atom(DataDefinition::Identifier(column_name)) => column_name.clone(),
+                _ => format!("#group{}", group_index),
+            };
+            columns.push(get_aggregated_column(name, data_type));
+        }
+        let mut aggregate_calls = Vec::new();
+        for expression in expressions {
+            collect_aggregate_calls(expression, &mut aggregate_calls);
+        }
+        for (aggregate_index, aggregate_call) in aggregate_calls.iter().enumerate() {
+            // Nested aggregate calls are rejected here, as arguments are typed in the context of the original table
+            let argument_types = aggregate_call
+                .arguments
+                .iter()
+                .map(|argument| argument.infer_type(table))
+                .collect::<Result<Vec<Option<DataType>>, ValidationError>>()?;
+            let data_type = aggregate_call
+                .function
+                .infer_return_type(&argument_types)
+                .map_err(ValidationError)?;
+            columns.push(get_aggregated_column(
+                format!("#aggregate{}", aggregate_index),
+                data_type,
+            ));
+        }
+        Ok(Aggregation {
+            group_by: group_by.to_vec(),
+            aggregate_calls,
+            table: TableDefinition::new(table.name.clone(), columns),
+        })
+    }
+
+    /// Translate an expression over rows of `table` into one over aggregated rows. Columns of `table`
+    /// can only be referenced through GROUP BY expressions or inside aggregate function calls.
+    pub fn rewrite(
+        &self,
+        expression: &Expression,
+        table: &TableDefinition,
+    ) -> Result<Expression, ValidationError> {
+        let rewritten_expression = expression.replace(&mut |subexpression| {
+            let column_index = match self
+                .group_by
+                .iter()
+                .position(|group_expression| group_expression == subexpression)
+            {
+                Some(group_index) => group_index,
+                None => {
+                    self.group_by.len()
+                        + self.aggregate_calls.iter().position(|aggregate_call| {
+                            aggregate_call.expression == *subexpression
+                        })?
+                }
+            };
+            Some(Expression::Atom(DataDefinition::Identifier(
+                self.table.columns[column_index].name.clone(),
+            )))
+        });
+        self.validate_grouped(&rewritten_expression, table)?;
+        Ok(rewritten_expression)
+    }
+
+    fn validate_grouped(
+        &self,
+        expression: &Expression,
+        table: &TableDefinition,
+    ) -> Result<(), ValidationError> {
+        if let Expression::Atom(DataDefinition::Identifier(column_name)) = expression {
+            if self.table.get_column(column_name).is_none() {
+                return Err(ValidationError(
+                    if table.get_column(column_name).is_some() {
+                        format!(
+                        "Column `{}` must appear in the GROUP BY clause or be used in an aggregate function",
+                        column_name
+                    )
+                    } else {
+                        format!(
+                            "Column `{}` does not exist in table `{}`",
+                            column_name, table.name
+                        )
+                    },
+                ));
+            }
+        }
+        for operand in expression.get_operands() {
+            self.validate_grouped(operand, table)?;
+        }
+        Ok(())
+    }
+
+    /// Whether rows are put into groups by GROUP BY expressions, instead of all forming a single one.
+    pub fn is_grouped(&self) -> bool {
+        !self.group_by.is_empty()
+    }
+
+    /// Translate an expression over aggregated rows back into one over rows of the original table,
+    /// i.e. undo `rewrite`.
+    pub fn restore(&self, expression: &Expression) -> Expression {
+        expression.replace(&mut |subexpression| match subexpression {
+            Expression::Atom(DataDefinition::Identifier(column_name)) => {
+                let column_index = self.table.get_column_index(column_name)?;
+                Some(match self.group_by.get(column_index) {
+                    Some(group_expression) => group_expression.clone(),
+                    None => self.aggregate_calls[column_index - self.group_by.len()]
+                        .expression
+                        .clone(),
+                })
+            }
+            _ => None,
+        })
+    }
+
+    fn get_blank_accumulators(&self) -> Vec<Accumulator> {
+        self.aggregate_calls
+            .iter()
+            .map(|aggregate_call| Accumulator::new(aggregate_call.function))
+            .collect()
+    }
+
+    /// Turn rows (with values matching `column_names`) into aggregated rows, one per group, in order of
+    /// groups' first appearance.
+    pub fn aggregate(&self, column_names: &[String], rows: Vec<Row>) -> Result<Vec<Row>, String> {
+        let mut group_indexes = HashMap::<Vec<Option<DataInstanceRaw>>, usize>::new();
+        let mut groups = Vec::<(Vec<DataInstance>, Vec<Accumulator>)>::new();
+        for row in rows {
+            let context = RowContext::new(column_names, &row.0);
+            let group_values = self
+                .group_by
+                .iter()
+                .map(|expression| expression.evaluate(&context))
+                .collect::<Result<Vec<DataInstance>, String>>()?;
+            // All NULLs fall into the same group
+            let group_key = group_values
+                .iter()
+                .map(|value| value.as_raw().cloned())
+                .collect();
+            let group_index = *group_indexes.entry(group_key).or_insert_with(|| {
+                groups.push((group_values, self.get_blank_accumulators()));
+                groups.len() - 1
+            });
+            for (aggregate_call, accumulator) in self
+                .aggregate_calls
+                .iter()
+                .zip(groups[group_index].1.iter_mut())
+            {
+                let argument = match aggregate_call.arguments.first() {
+                    Some(argument) => Some(argument.evaluate(&context)?),
+                    None => None,
+                };
+                accumulator.update(argument)?;
+            }
+        }
+        // Without GROUP BY all rows form a single group, even if there are none
+        if self.group_by.is_empty() && groups.is_empty() {
+            groups.push((Vec::new(), self.get_blank_accumulators()));
+        }
+        let aggregate_columns = &self.table.columns[self.group_by.len()..];
+        groups
+            .into_iter()
+            .map(|(mut values, accumulators)| {
+                for (accumulator, column) in accumulators.into_iter().zip(aggregate_columns) {
+                    values.push(accumulator.finish(&column.data_type)?);
+                }
+                Ok(Row(values))
+            })
+            .collect()
+    }
+}
+
+/// Description of the aggregation, e.g. `GROUP BY domain: COUNT(*), MAX(width)`.
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |expressions: &mut dyn Iterator<Item = &Expression>| {
+            expressions
+                .map(|expression| expression.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        if self.is_grouped() {
+            write!(f, "GROUP BY {}", join(&mut self.group_by.iter()))?;
+            if !self.aggregate_calls.is_empty() {
+                write!(f, ": ")?;
+            }
+        }
+        write!(
+            f,
+            "{}",
+            join(
+                &mut self
+                    .aggregate_calls
+                    .iter()
+                    .map(|aggregate_call| &aggregate_call.expression)
+            )
+        )
+    }
+}
+
+fn get_aggregated_column(name: String, data_type: DataType) -> ColumnDefinition {
+    ColumnDefinition {
+        name,
+        data_type,
+        primary_key: false,
+        metric_key: false,
+        default: None,
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+
+use crate::config;
+use crate::constructs::components::{
+    ColumnDefinition, DataDefinition, DataInstance, DataInstanceRaw, DataType, Expression,
+    IndexDefinition, TableDefinition,
+};
+use crate::storage::filesystem::{write_table_file, TABLE_DATA_FILE_NAME};
+use crate::storage::paging::{construct_blank_table, Key};
+use crate::storage::Row;
+
+use super::{read, write, ConstraintError, ExecutorError};
+
+/// Positions of the index's columns in rows of `table`.
+fn get_indexed_column_indexes(table: &TableDefinition, index: &IndexDefinition) -> Vec<usize> {
+    index
+        .column_names
+        .iter()
+        .map(|column_name| {
+            table
+                .get_column_index(column_name)
+                .expect("Indexed columns must exist in the table")
+        })
+        .collect()
+}
+
+/// Positions of the entry's columns in rows of `table`: indexed columns followed by the PRIMARY KEY columns.
+fn get_entry_column_indexes(table: &TableDefinition, index: &IndexDefinition) -> Vec<usize> {
+    let mut column_indexes = get_indexed_column_indexes(table, index);
+    column_indexes.extend(table.get_key_indexes());
+    column_indexes
+}
+
+/// Shape of the index's entries: indexed columns followed by the table's PRIMARY KEY. All of them make up
+/// the key of the index's B+ tree, so that entries of rows with the same indexed values are still distinct.
+pub fn get_entry_table(table: &TableDefinition, index: &IndexDefinition) -> TableDefinition {
+    TableDefinition::new(
+        table.name.clone(),
+        get_entry_column_indexes(table, index)
+            .into_iter()
+            .map(|column_index| {
+                let column = &table.columns[column_index];
+                ColumnDefinition {
+                    name: column.name.clone(),
+                    // Entries never contain NULLs
+                    data_type: DataType {
+                        raw_type: column.data_type.raw_type,
+                        is_nullable: false,
+                    },
+                    primary_key: true,
+                    metric_key: false,
+                    default: None,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// The row's entry in the index, or `None` if any of the indexed values is NULL. Such rows aren't indexed,
+/// as NULL never equals anything, nor does it fall into any range - so they can't be found through an index.
+fn get_entry(table: &TableDefinition, index: &IndexDefinition, row: &Row) -> Option<Row> {
+    get_entry_column_indexes(table, index)
+        .into_iter()
+        .map(|column_index| {
+            row.0[column_index]
+                .as_raw()
+                .map(|value| DataInstance::Direct(value.clone()))
+        })
+        .collect::<Option<Vec<DataInstance>>>()
+        .map(Row)
+}
+
+/// Indexed values of the entry, i.e. its key without the `key_length` PRIMARY KEY values at the end.
+fn get_entry_values(entry: &Row, key_length: usize) -> Key {
+    read::get_row_key(
+        entry,
+        &(0..entry.0.len() - key_length).collect::<Vec<usize>>(),
+    )
+}
+
+/// PRIMARY KEY of the row that the entry stands for, i.e. the last `key_length` values of the entry.
+fn get_entry_primary_key(entry: &Row, key_length: usize) -> Key {
+    read::get_row_key(
+        entry,
+        &(entry.0.len() - key_length..entry.0.len()).collect::<Vec<usize>>(),
+    )
+}
+
+fn duplicate_value_error(
+    table: &TableDefinition,
+    index: &IndexDefinition,
+    values: &[DataInstanceRaw],
+) -> ExecutorError {
+    let is_constraint = table
+        .constraints
+        .iter()
+        .any(|constraint| constraint.name == index.name);
+    ConstraintError(match is_constraint {
+        true => format!(
+            "Value {:?} violates UNIQUE constraint `{}` of table `{}`",
+            values, index.name, table.name
+        ),
+        false => format!(
+            "Value {:?} of UNIQUE index `{}` already exists in table `{}`",
+            values, index.name, table.name
+        ),
+    })
+    .into()
+}
+
+/// Write the index's B+ tree file anew, with entries of all the given rows of `table`.
+pub async fn build_index(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+    index: &IndexDefinition,
+    rows: &[Row],
+) -> Result<(), ExecutorError> {
+    let entries: Vec<Row> = rows
+        .iter()
+        .filter_map(|row| get_entry(table, index, row))
+        .collect();
+    if index.unique {
+        let mut seen_values = HashSet::with_capacity(entries.len());
+        for entry in &entries {
+            let values = get_entry_values(entry, table.get_key_indexes().len());
+            if !seen_values.insert(values.clone()) {
+                return Err(duplicate_value_error(table, index, &values));
+            }
+        }
+    }
+    write_table_file(
+        config,
+        schema,
+        &table.name,
+        &index.get_file_name(),
+        construct_blank_table(),
+    )
+    .await?;
+    write::b_tree_file_apply_changes(
+        config,
+        schema,
+        &get_entry_table(table, index),
+        &index.get_file_name(),
+        Vec::new(),
+        entries,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Make sure that changed rows of `table` don't break any of its UNIQUE indexes, before the changes are written.
+/// Changes are pairs of old and new versions of rows, with `None` standing for a row that didn't exist before
+/// or doesn't exist anymore.
+pub async fn check_unique_indexes(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+    indexes: &[IndexDefinition],
+    changes: &[(Option<&Row>, Option<&Row>)],
+) -> Result<(), ExecutorError> {
+    let key_indexes = table.get_key_indexes();
+    for index in indexes.iter().filter(|index| index.unique) {
+        let entry_table = get_entry_table(table, index);
+        let file_name = index.get_file_name();
+        // Rows whose current values are going away, so they don't count as duplicates
+        let mut vacating_primary_keys = HashSet::new();
+        let mut claiming_entries = Vec::new();
+        for (old_row, new_row) in changes {
+            let old_entry = old_row.and_then(|old_row| get_entry(table, index, old_row));
+            let new_entry = new_row.and_then(|new_row| get_entry(table, index, new_row));
+            if old_entry == new_entry {
+                continue;
+            }
+            if let Some(old_row) = old_row {
+                vacating_primary_keys.insert(read::get_row_key(old_row, &key_indexes));
+            }
+            if let Some(new_entry) = new_entry {
+                claiming_entries.push(new_entry);
+            }
+        }
+        let mut claimed_values = HashMap::with_capacity(claiming_entries.len());
+        for entry in claiming_entries {
+            let values = get_entry_values(&entry, key_indexes.len());
+            let primary_key = get_entry_primary_key(&entry, key_indexes.len());
+            if claimed_values
+                .insert(values.clone(), primary_key.clone())
+                .is_some_and(|other_primary_key| other_primary_key != primary_key)
+            {
+                return Err(duplicate_value_error(table, index, &values));
+            }
+            let existing_entries = read::read_rows_in_range(
+                config,
+                schema,
+                &entry_table,
+                &file_name,
+                &Bound::Included(values.clone()),
+                &Bound::Included(values.clone()),
+            )
+            .await?;
+            for existing_entry in existing_entries {
+                let existing_primary_key =
+                    get_entry_primary_key(&existing_entry, key_indexes.len());
+                if existing_primary_key != primary_key
+                    && !vacating_primary_keys.contains(&existing_primary_key)
+                {
+                    return Err(duplicate_value_error(table, index, &values));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keep indexes of `table` in line with changed rows, which have already been written to the table.
+/// Changes are like for `check_unique_indexes`.
+pub async fn update_indexes(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+    indexes: &[IndexDefinition],
+    changes: &[(Option<&Row>, Option<&Row>)],
+) -> Result<(), String> {
+    for index in indexes {
+        let mut keys_to_delete = Vec::new();
+        let mut entries_to_insert = Vec::new();
+        for (old_row, new_row) in changes {
+            let old_entry = old_row.and_then(|old_row| get_entry(table, index, old_row));
+            let new_entry = new_row.and_then(|new_row| get_entry(table, index, new_row));
+            if old_entry == new_entry {
+                continue;
+            }
+            if let Some(old_entry) = old_entry {
+                keys_to_delete.push(
+                    old_entry
+                        .0
+                        .into_iter()
+                        .map(|value| value.into_raw().unwrap())
+                        .collect(),
+                );
+            }
+            entries_to_insert.extend(new_entry);
+        }
+        if keys_to_delete.is_empty() && entries_to_insert.is_empty() {
+            continue;
+        }
+        write::b_tree_file_apply_changes(
+            config,
+            schema,
+            &get_entry_table(table, index),
+            &index.get_file_name(),
+            keys_to_delete,
+            entries_to_insert,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Empty all indexes of `table`, e.g. because the table was truncated.
+pub async fn clear_indexes(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+    indexes: &[IndexDefinition],
+) -> Result<(), ExecutorError> {
+    for index in indexes {
+        build_index(config, schema, table, index, &[]).await?;
+    }
+    Ok(())
+}
+
+/// How rows that may satisfy a WHERE clause are found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccessPath {
+    /// Reading all rows of the table.
+    FullScan,
+    /// Reading a range of the table's own B+ tree, by PRIMARY KEY values.
+    PrimaryKeyRange {
+        lower_bound: Bound<Key>,
+        upper_bound: Bound<Key>,
+    },
+    /// Reading a range of the index's B+ tree, then looking up rows by PRIMARY KEY values found there.
+    /// Bounds may cover just the first few columns of the index.
+    IndexRange {
+        index: IndexDefinition,
+        lower_bound: Bound<Key>,
+        upper_bound: Bound<Key>,
+    },
+    /// Reading rows whose METRIC KEY value is one of `values`, which have been found in the metric index
+    /// (plus rows with NULL there if `include_nulls`). The metric index doesn't point to rows, so they're
+    /// still read from the whole table - unless there are no such values, in which case nothing is read.
+    MetricValues {
+        values: HashSet<u128>,
+        include_nulls: bool,
+    },
+}
+
+/// Condition on a single column that an index can be used for.
+enum ColumnCondition {
+    Equal(DataInstanceRaw),
+    Lower(Bound<DataInstanceRaw>),
+    Upper(Bound<DataInstanceRaw>),
+}
+
+/// Constant value of the expression, converted to the type of the column it's compared with.
+/// Values that don't convert exactly (e.g. ones out of range of the column's type) are not used for lookups.
+fn get_comparable_value(
+    expression: &Expression,
+    column: &ColumnDefinition,
+) -> Option<DataInstanceRaw> {
+    let value = match expression {
+        Expression::Atom(DataDefinition::Const(value)) => value.as_raw()?.clone(),
+        _ => return None,
+    };
+    let target = column.data_type.raw_type;
+    if value.get_type() != target
+        && (value.as_u128().is_none() || target.as_unsigned_bits().is_none())
+    {
+        return None;
+    }
+    value.coerce(target).ok()
+}
+
+/// Gather simple conditions on columns, of the form `column <operator> constant` (or the other way around),
+/// from the top-level AND chain of a WHERE clause.
+fn collect_column_conditions(
+    expression: &Expression,
+    table: &TableDefinition,
+    conditions: &mut Vec<(usize, ColumnCondition)>,
+) {
+    let (lhs, rhs, make_condition): (_, _, fn(DataInstanceRaw, bool) -> ColumnCondition) =
+        match expression {
+            Expression::And(lhs, rhs) => {
+                collect_column_conditions(lhs, table, conditions);
+                collect_column_conditions(rhs, table, conditions);
+                return;
+            }
+            Expression::Between(operand, low, high) => {
+                collect_column_conditions(
+                    &Expression::GreaterThanOrEqual(operand.clone(), low.clone()),
+                    table,
+                    conditions,
+                );
+                collect_column_conditions(
+                    &Expression::LessThanOrEqual(operand.clone(), high.clone()),
+                    table,
+                    conditions,
+                );
+                return;
+            }
+            // The boolean tells whether the column is on the right, which flips the direction of the comparison
+            Expression::Equal(lhs, rhs) => (lhs, rhs, |value, _| ColumnCondition::Equal(value)),
+            Expression::LessThan(lhs, rhs) => (lhs, rhs, |value, flipped| match flipped {
+                false => ColumnCondition::Upper(Bound::Excluded(value)),
+                true => ColumnCondition::Lower(Bound::Excluded(value)),
+            }),
+            Expression::LessThanOrEqual(lhs, rhs) => (lhs, rhs, |value, flipped| match flipped {
+                false => ColumnCondition::Upper(Bound::Included(value)),
+                true => ColumnCondition::Lower(Bound::Included(value)),
+            }),
+            Expression::GreaterThan(lhs, rhs) => (lhs, rhs, |value, flipped| match flipped {
+                false => ColumnCondition::Lower(Bound::Excluded(value)),
+                true => ColumnCondition::Upper(Bound::Excluded(value)),
+            }),
+            Expression::GreaterThanOrEqual(lhs, rhs) => {
+                (lhs, rhs, |value, flipped| match flipped {
+                    false => ColumnCondition::Lower(Bound::Included(value)),
+                    true => ColumnCondition::Upper(Bound::Included(value)),
+                })
+            }
+            _ => return,
+        };
+    for (column_side, value_side, flipped) in [(lhs, rhs, false), (rhs, lhs, true)] {
+        if let Expression::Atom(DataDefinition::Identifier(column_name)) = column_side.as_ref() {
+            if let Some(column_index) = table.get_column_index(column_name) {
+                if let Some(value) = get_comparable_value(value_side, &table.columns[column_index])
+                {
+                    conditions.push((column_index, make_condition(value, flipped)));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Pick the tighter of two lower bounds, or of two upper bounds if `is_upper`.
+fn tighten_bound(
+    current: Bound<DataInstanceRaw>,
+    candidate: Bound<DataInstanceRaw>,
+    is_upper: bool,
+) -> Bound<DataInstanceRaw> {
+    let value = |bound: &Bound<DataInstanceRaw>| match bound {
+        Bound::Included(value) | Bound::Excluded(value) => Some(value.clone()),
+        Bound::Unbounded => None,
+    };
+    match (value(&current), value(&candidate)) {
+        (_, None) => current,
+        (None, _) => candidate,
+        (Some(current_value), Some(candidate_value)) => {
+            let is_candidate_tighter = match is_upper {
+                false => candidate_value > current_value,
+                true => candidate_value < current_value,
+            };
+            if is_candidate_tighter
+                || (candidate_value == current_value && matches!(candidate, Bound::Excluded(_)))
+            {
+                candidate
+            } else {
+                current
+            }
+        }
+    }
+}
+
+/// Bounds of a B+ tree keyed by the given columns, along with how many leading columns they pin down
+/// with equality and whether they also limit the range of the column after those.
+fn get_key_bounds(
+    key_column_indexes: &[usize],
+    conditions: &[(usize, ColumnCondition)],
+) -> (Bound<Key>, Bound<Key>, usize, bool) {
+    let mut prefix = Key::new();
+    for key_column_index in key_column_indexes {
+        match conditions
+            .iter()
+            .find_map(|(column_index, condition)| match condition {
+                ColumnCondition::Equal(value) if column_index == key_column_index => Some(value),
+                _ => None,
+            }) {
+            Some(value) => prefix.push(value.clone()),
+            None => break,
+        }
+    }
+    let equal_count = prefix.len();
+    let (mut lower_bound, mut upper_bound) = (Bound::Unbounded, Bound::Unbounded);
+    if let Some(range_column_index) = key_column_indexes.get(equal_count) {
+        for (column_index, condition) in conditions {
+            if column_index != range_column_index {
+                continue;
+            }
+            match condition {
+                ColumnCondition::Lower(bound) => {
+                    lower_bound = tighten_bound(lower_bound, bound.clone(), false)
+                }
+                ColumnCondition::Upper(bound) => {
+                    upper_bound = tighten_bound(upper_bound, bound.clone(), true)
+                }
+                ColumnCondition::Equal(_) => (),
+            }
+        }
+    }
+    let is_range_limited =
+        !matches!(lower_bound, Bound::Unbounded) || !matches!(upper_bound, Bound::Unbounded);
+    let extend_prefix = |bound: Bound<DataInstanceRaw>| match bound {
+        Bound::Included(value) => Bound::Included([prefix.clone(), vec![value]].concat()),
+        Bound::Excluded(value) => Bound::Excluded([prefix.clone(), vec![value]].concat()),
+        Bound::Unbounded if prefix.is_empty() => Bound::Unbounded,
+        Bound::Unbounded => Bound::Included(prefix.clone()),
+    };
+    (
+        extend_prefix(lower_bound),
+        extend_prefix(upper_bound),
+        equal_count,
+        is_range_limited,
+    )
+}
+
+/// Choose how to find rows satisfying the WHERE clause: through the PRIMARY KEY or an index that the clause's
+/// equality and range conditions can narrow down, or by reading the whole table if there's no such one.
+/// The clause still has to be checked against each row found, as only some of its conditions may be covered.
+pub fn plan_access_path(
+    table: &TableDefinition,
+    indexes: &[IndexDefinition],
+    where_clause: Option<&Expression>,
+) -> AccessPath {
+    let mut conditions = Vec::new();
+    if let Some(where_clause) = where_clause {
+        collect_column_conditions(where_clause, table, &mut conditions);
+    }
+    if conditions.is_empty() {
+        return AccessPath::FullScan;
+    }
+    let (lower_bound, upper_bound, equal_count, is_range_limited) =
+        get_key_bounds(&table.get_key_indexes(), &conditions);
+    // The PRIMARY KEY wins ties, as rows can be read from its B+ tree directly
+    let mut best_score = (equal_count, is_range_limited);
+    let mut best_path = AccessPath::PrimaryKeyRange {
+        lower_bound,
+        upper_bound,
+    };
+    for index in indexes {
+        let (lower_bound, upper_bound, equal_count, is_range_limited) =
+            get_key_bounds(&get_indexed_column_indexes(table, index), &conditions);
+        if (equal_count, is_range_limited) > best_score {
+            best_score = (equal_count, is_range_limited);
+            best_path = AccessPath::IndexRange {
+                index: index.clone(),
+                lower_bound,
+                upper_bound,
+            };
+        }
+    }
+    if best_score == (0, false) {
+        return AccessPath::FullScan;
+    }
+    best_path
+}
+
+/// Read rows of `table` along the access path, in PRIMARY KEY order.
+pub async fn read_rows(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+    access_path: &AccessPath,
+) -> Result<Vec<Row>, String> {
+    match access_path {
+        AccessPath::FullScan => read::read_all_rows(config, schema, table).await,
+        AccessPath::MetricValues {
+            values,
+            include_nulls,
+        } => {
+            if values.is_empty() && !include_nulls {
+                return Ok(Vec::new());
+            }
+            let metric_key_index = table
+                .columns
+                .iter()
+                .position(|column| column.metric_key)
+                .expect("Metric values can only be read from a table with a METRIC KEY");
+            let mut rows = read::read_all_rows(config, schema, table).await?;
+            rows.retain(|row| match row.0[metric_key_index].as_raw() {
+                Some(value) => value.as_u128().is_some_and(|value| values.contains(&value)),
+                None => *include_nulls,
+            });
+            Ok(rows)
+        }
+        AccessPath::PrimaryKeyRange {
+            lower_bound,
+            upper_bound,
+        } => {
+            read::read_rows_in_range(
+                config,
+                schema,
+                table,
+                TABLE_DATA_FILE_NAME,
+                lower_bound,
+                upper_bound,
+            )
+            .await
+        }
+        AccessPath::IndexRange {
+            index,
+            lower_bound,
+            upper_bound,
+        } => {
+            let entries = read::read_rows_in_range(
+                config,
+                schema,
+                &get_entry_table(table, index),
+                &index.get_file_name(),
+                lower_bound,
+                upper_bound,
+            )
+            .await?;
+            let key_length = table.get_key_indexes().len();
+            let mut primary_keys: Vec<Key> = entries
+                .iter()
+                .map(|entry| get_entry_primary_key(entry, key_length))
+                .collect();
+            primary_keys.sort_unstable();
+            Ok(read::find_rows(config, schema, table, &primary_keys)
+                .await?
+                .into_iter()
+                .flatten()
+                .collect())
+        }
+    }
+}
@@ -1,20 +1,53 @@
+mod aggregate;
+mod indexes;
+mod order;
+mod plan;
 mod read;
+mod statistics;
 mod write;
 
+use futures::future::{BoxFuture, FutureExt};
 use parking_lot::Mutex;
+use std::collections::HashSet;
 use std::io;
 use std::sync::Arc;
 
 use crate::config;
-use crate::storage::system::SystemTable;
+use crate::constructs::evaluation::RowContext;
+use crate::constructs::statements::{
+    AlterTableAction, AlterTableStatement, AnalyzeStatement, Assignment, CommonTableExpression,
+    ConflictAction, CreateDatabaseStatement, CreateIndexStatement, CreateTableStatement,
+    DeleteStatement, DropDatabaseStatement, DropIndexStatement, DropTableStatement,
+    ExplainStatement, InsertSource, InsertStatement, InsertValue, JoinKind, OnConflict,
+    SelectColumn, SelectStatement, TruncateStatement, UpdateStatement,
+};
+use crate::sql::{
+    parse_constraint_kind, parse_data_definition, parse_expressions, parse_identifiers,
+    quote_identifiers, ValidationError,
+};
+use crate::storage::encoding::GlobalCount;
+use crate::storage::filesystem::{
+    create_schema_dir, does_schema_dir_exist, does_table_file_exist, remove_schema_dir,
+    remove_table_dir, remove_table_file, rename_table_dir, write_table_file, TABLE_DATA_FILE_NAME,
+};
+use crate::storage::index::Index;
+use crate::storage::paging::{construct_blank_table, Key};
+use crate::storage::system::{SystemTable, DEFAULT_SCHEMA_NAME, SYSTEM_SCHEMA_NAME};
 use crate::{
-    constructs::components::{DataInstance, DataInstanceRaw, TableDefinition},
+    constructs::components::{
+        validate_table_name, ColumnDefinition, ConstraintDefinition, ConstraintKind,
+        DataDefinition, DataInstance, DataInstanceRaw, DataType, Expression, IndexDefinition,
+        TableDefinition, Validatable,
+    },
     sql::Statement,
     storage::{NamedRow, Row},
 };
-use serde::{ser::SerializeSeq, Serialize, Serializer};
+use serde::{ser::SerializeMap, ser::SerializeSeq, Serialize, Serializer};
+use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 use tracing::*;
+use ulid::Generator;
+use uuid::Uuid;
 
 const MAX_IN_FLIGHT_REQUESTS: usize = 100;
 
@@ -24,6 +57,26 @@ pub struct QueryResult {
     pub rows: Vec<Row>,
 }
 
+impl QueryResult {
+    /// Result of a statement that doesn't return any data.
+    pub fn empty() -> Self {
+        QueryResult {
+            column_names: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Result of a data-modifying statement, containing the number of rows affected.
+    pub fn rows_affected(count: usize) -> Self {
+        QueryResult {
+            column_names: vec!["rows_affected".to_string()],
+            rows: vec![Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(
+                count as u64,
+            ))])],
+        }
+    }
+}
+
 impl Serialize for QueryResult {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -37,12 +90,751 @@ impl Serialize for QueryResult {
     }
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("ExecutionError: {0}")]
+pub struct ExecutionError(pub String);
+
+impl Serialize for ExecutionError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("type", "execution")?;
+        map.serialize_entry("message", &self.0)?;
+        map.end()
+    }
+}
+
+/// The statement would break a constraint of the data, e.g. by duplicating a PRIMARY KEY value.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("ConstraintError: {0}")]
+pub struct ConstraintError(pub String);
+
+impl Serialize for ConstraintError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("type", "constraint")?;
+        map.serialize_entry("message", &self.0)?;
+        map.end()
+    }
+}
+
+/// Reason why the executor could not carry out a statement.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ExecutorError {
+    /// The statement doesn't make sense in light of the current catalog, e.g. it refers to a missing column.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+    /// The statement was valid, but something went wrong while carrying it out.
+    #[error(transparent)]
+    Execution(#[from] ExecutionError),
+    /// The statement was valid, but carrying it out would break a constraint.
+    #[error(transparent)]
+    Constraint(#[from] ConstraintError),
+}
+
+impl Serialize for ExecutorError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Validation(error) => error.serialize(serializer),
+            Self::Execution(error) => error.serialize(serializer),
+            Self::Constraint(error) => error.serialize(serializer),
+        }
+    }
+}
+
+impl From<String> for ExecutorError {
+    fn from(message: String) -> Self {
+        Self::Execution(ExecutionError(message))
+    }
+}
+
+impl From<io::Error> for ExecutorError {
+    fn from(error: io::Error) -> Self {
+        Self::Execution(ExecutionError(error.to_string()))
+    }
+}
+
+pub type ExecutorResult = Result<QueryResult, ExecutorError>;
+
 /// Payload with a statement and a sender to return the result to.
-pub type ExecutorPayload = (Statement, oneshot::Sender<QueryResult>);
+pub type ExecutorPayload = (Statement, oneshot::Sender<ExecutorResult>);
+
+/// A user table known to the executor.
+#[derive(Debug, Clone, PartialEq)]
+struct CatalogEntry {
+    /// ID of the table's record in `system.tables`.
+    id: Uuid,
+    schema_name: String,
+    table: TableDefinition,
+    /// IDs of the columns' records in `system.columns`, in the same order as `table.columns`.
+    column_ids: Vec<Uuid>,
+    /// Indexes of the table, other than that of the PRIMARY KEY.
+    indexes: Vec<IndexDefinition>,
+    /// IDs of the indexes' records in `system.indexes`, in the same order as `indexes`.
+    index_ids: Vec<Uuid>,
+    /// IDs of the constraints' records in `system.constraints`, in the same order as `table.constraints`.
+    constraint_ids: Vec<Uuid>,
+    /// Statistics gathered by the latest ANALYZE of the table, if there was one.
+    statistics: Option<Arc<statistics::TableStatistics>>,
+    /// IDs of the column statistics' records in `system.statistics`, in the same order as `table.columns`.
+    statistic_ids: Vec<Uuid>,
+}
+
+/// Record of a system table, read back when rebuilding the catalog.
+struct SystemRecord {
+    column_names: Vec<String>,
+    row: Row,
+}
+
+impl SystemRecord {
+    fn get(&self, column_name: &str) -> Option<&DataInstanceRaw> {
+        self.column_names
+            .iter()
+            .position(|name| name == column_name)
+            .and_then(|column_index| self.row.0[column_index].as_raw())
+    }
+
+    fn get_invalid_value_error(&self, column_name: &str) -> String {
+        format!(
+            "System record has an invalid value in column `{}`: {:?}",
+            column_name,
+            self.get(column_name)
+        )
+    }
+
+    fn get_uuid(&self, column_name: &str) -> Result<Uuid, String> {
+        match self.get(column_name) {
+            Some(DataInstanceRaw::Uuid(value)) => Ok(*value),
+            _ => Err(self.get_invalid_value_error(column_name)),
+        }
+    }
+
+    fn get_string(&self, column_name: &str) -> Result<String, String> {
+        self.get_optional_string(column_name)?
+            .ok_or_else(|| self.get_invalid_value_error(column_name))
+    }
+
+    fn get_optional_string(&self, column_name: &str) -> Result<Option<String>, String> {
+        match self.get(column_name) {
+            Some(DataInstanceRaw::String(value)) => Ok(Some(value.clone())),
+            None => Ok(None),
+            _ => Err(self.get_invalid_value_error(column_name)),
+        }
+    }
+
+    fn get_bool(&self, column_name: &str) -> Result<bool, String> {
+        match self.get(column_name) {
+            Some(DataInstanceRaw::Bool(value)) => Ok(*value),
+            _ => Err(self.get_invalid_value_error(column_name)),
+        }
+    }
+
+    fn get_count(&self, column_name: &str) -> Result<u64, String> {
+        self.get(column_name)
+            .and_then(DataInstanceRaw::as_u128)
+            .map(|value| value as u64)
+            .ok_or_else(|| self.get_invalid_value_error(column_name))
+    }
+
+    fn belongs_to_table(&self, table_id: Uuid) -> bool {
+        self.get_uuid("table_id") == Ok(table_id)
+    }
+}
+
+/// Rebuild statistics of the table from its non-empty set of records in `system.statistics`,
+/// returning them along with the IDs of the records in the same order as the table's columns.
+fn load_statistics(
+    table: &TableDefinition,
+    records: &[&SystemRecord],
+) -> Result<(statistics::TableStatistics, Vec<Uuid>), String> {
+    let mut columns = Vec::with_capacity(table.columns.len());
+    let mut ids = Vec::with_capacity(table.columns.len());
+    for column in &table.columns {
+        let record = records
+            .iter()
+            .find(|record| record.get_string("column_name").as_ref() == Ok(&column.name))
+            .ok_or_else(|| {
+                format!(
+                    "Statistics of table `{}` are missing column `{}`",
+                    table.name, column.name
+                )
+            })?;
+        let histogram_bounds = record.get_string("histogram_bounds")?;
+        let histogram_bounds = match histogram_bounds.is_empty() {
+            true => Vec::new(),
+            false => parse_expressions(&histogram_bounds)
+                .map_err(|error| error.to_string())?
+                .iter()
+                .map(|bound| {
+                    // Literals are read back as the smallest type that fits them
+                    bound
+                        .evaluate(&RowContext::new(&[], &[]))
+                        .ok()
+                        .and_then(|bound| bound.as_raw().cloned())
+                        .and_then(|bound| bound.coerce(column.data_type.raw_type).ok())
+                        .ok_or_else(|| format!("Invalid histogram bound `{}`", bound))
+                })
+                .collect::<Result<Vec<DataInstanceRaw>, String>>()?,
+        };
+        let distance_counts = record
+            .get_optional_string("distance_counts")?
+            .map(|distance_counts| {
+                distance_counts
+                    .split(", ")
+                    .map(|count| {
+                        count
+                            .parse()
+                            .map_err(|_| format!("Invalid distance count `{}`", count))
+                    })
+                    .collect::<Result<Vec<GlobalCount>, String>>()
+            })
+            .transpose()?;
+        columns.push(statistics::ColumnStatistics {
+            column_name: column.name.clone(),
+            null_count: record.get_count("null_count")? as usize,
+            distinct_count: record.get_count("distinct_count")?,
+            histogram_bounds,
+            distance_counts,
+        });
+        ids.push(record.get_uuid("id")?);
+    }
+    let record = records[0];
+    let analyzed_at = match record.get("analyzed_at") {
+        Some(DataInstanceRaw::Timestamp(analyzed_at)) => *analyzed_at,
+        _ => return Err(record.get_invalid_value_error("analyzed_at")),
+    };
+    Ok((
+        statistics::TableStatistics {
+            row_count: record.get_count("row_count")?,
+            sampled_row_count: record.get_count("sampled_row_count")? as usize,
+            columns,
+            analyzed_at,
+        },
+        ids,
+    ))
+}
+
+/// Substitute references to SELECT column aliases with the expressions they stand for.
+/// Columns of `shadowing_table`, if provided, take precedence over aliases of the same name.
+fn resolve_aliases(
+    expression: &Expression,
+    aliases: &[(String, Expression)],
+    shadowing_table: Option<&TableDefinition>,
+) -> Expression {
+    expression.replace(&mut |subexpression| match subexpression {
+        Expression::Atom(DataDefinition::Identifier(name))
+            if shadowing_table.is_none_or(|table| table.get_column(name).is_none()) =>
+        {
+            aliases
+                .iter()
+                .find(|(alias, _)| alias == name)
+                .map(|(_, aliased_expression)| aliased_expression.clone())
+        }
+        _ => None,
+    })
+}
+
+/// Result of a WITH query, which the rest of the query reads like a table.
+#[derive(Clone)]
+struct CommonTable {
+    /// Definition of the rows, named after the WITH query.
+    table: TableDefinition,
+    rows: Arc<Vec<Row>>,
+}
+
+/// Output column of a planned query, whose type is unknown if it's the NULL literal.
+struct OutputColumn {
+    data_type: Option<DataType>,
+    /// Whether the column is just a METRIC KEY column passed on.
+    metric_key: bool,
+}
+
+/// Where rows of a table read by a query come from.
+enum RowOrigin {
+    /// The table stored in the schema.
+    Stored(String),
+    /// A WITH query, already executed.
+    Computed(Arc<Vec<Row>>),
+}
+
+/// Table read by a query, along with the qualifier of its columns.
+struct QueriedTable {
+    qualifier: String,
+    origin: RowOrigin,
+    table: TableDefinition,
+    /// Whether the table is on the right side of a LEFT JOIN, so its columns may be NULL in joined rows.
+    is_null_extended: bool,
+}
+
+/// Table of a query that encloses a subquery, along with the qualifier of its columns. Subqueries are executed
+/// before the queries enclosing them, so these tables are only known in order to reject references to them.
+#[derive(Clone)]
+struct EnclosingTable {
+    qualifier: String,
+    table: TableDefinition,
+}
+
+impl EnclosingTable {
+    /// Tables of the query along with those of queries enclosing it, which its subqueries are enclosed by.
+    fn extend(enclosing_tables: &[Self], queried_tables: &[QueriedTable]) -> Vec<Self> {
+        enclosing_tables
+            .iter()
+            .cloned()
+            .chain(queried_tables.iter().map(|queried_table| Self {
+                qualifier: queried_table.qualifier.clone(),
+                table: queried_table.table.clone(),
+            }))
+            .collect()
+    }
+}
+
+/// Error for a subquery referring to a column of an enclosing query.
+fn correlated_subquery_error(column_reference: &str) -> ValidationError {
+    ValidationError(format!(
+        "Correlated subqueries are not supported, but `{}` refers to a table of an enclosing query",
+        column_reference
+    ))
+}
+
+/// Definition of rows that the query's FROM clause produces. Joined rows have the columns of all tables,
+/// qualified, as in `p.url`.
+fn get_joined_table_definition(queried_tables: &[QueriedTable]) -> TableDefinition {
+    if let [queried_table] = queried_tables {
+        return queried_table.table.clone();
+    }
+    let columns = queried_tables
+        .iter()
+        .flat_map(|queried_table| {
+            queried_table
+                .table
+                .columns
+                .iter()
+                .map(|column| ColumnDefinition {
+                    name: format!("{}.{}", queried_table.qualifier, column.name),
+                    data_type: DataType {
+                        raw_type: column.data_type.raw_type,
+                        is_nullable: column.data_type.is_nullable || queried_table.is_null_extended,
+                    },
+                    primary_key: false,
+                    // Distances are computed only from METRIC KEY columns
+                    metric_key: column.metric_key,
+                    default: None,
+                })
+        })
+        .collect();
+    let name = queried_tables
+        .iter()
+        .map(|queried_table| queried_table.qualifier.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    TableDefinition::new(name, columns)
+}
+
+/// Make column references match the joined table definition: with a single table, qualifiers are removed,
+/// otherwise unqualified columns are qualified with the table that has them - as in `url` becoming `p.url`.
+/// References to `enclosing_tables` are rejected, as correlated subqueries aren't supported.
+fn resolve_column_references(
+    expression: &Expression,
+    queried_tables: &[QueriedTable],
+    enclosing_tables: &[EnclosingTable],
+) -> Result<Expression, ValidationError> {
+    expression.try_replace(&mut |subexpression| {
+        let name = match subexpression {
+            Expression::Atom(DataDefinition::Identifier(name)) => name,
+            _ => return Ok(None),
+        };
+        match name.split_once('.') {
+            Some((qualifier, column_name)) => {
+                if !queried_tables
+                    .iter()
+                    .any(|queried_table| queried_table.qualifier == qualifier)
+                {
+                    if enclosing_tables
+                        .iter()
+                        .any(|enclosing_table| enclosing_table.qualifier == qualifier)
+                    {
+                        return Err(correlated_subquery_error(name));
+                    }
+                    return Err(ValidationError(format!(
+                        "Table `{}` is not referenced in the FROM clause",
+                        qualifier
+                    )));
+                }
+                Ok((queried_tables.len() == 1).then(|| {
+                    Expression::Atom(DataDefinition::Identifier(column_name.to_string()))
+                }))
+            }
+            None => {
+                let mut having_column = queried_tables
+                    .iter()
+                    .filter(|queried_table| queried_table.table.get_column(name).is_some());
+                match (having_column.next(), having_column.next()) {
+                    (Some(_), None) if queried_tables.len() == 1 => Ok(None),
+                    (Some(queried_table), None) => Ok(Some(Expression::Atom(
+                        DataDefinition::Identifier(format!("{}.{}", queried_table.qualifier, name)),
+                    ))),
+                    (Some(_), Some(_)) => Err(ValidationError(format!(
+                        "Column reference `{}` is ambiguous, as more than one table has such a column",
+                        name
+                    ))),
+                    (None, _)
+                        if enclosing_tables.iter().any(|enclosing_table| {
+                            enclosing_table.table.get_column(name).is_some()
+                        }) =>
+                    {
+                        Err(correlated_subquery_error(name))
+                    }
+                    (None, _) => Ok(None),
+                }
+            }
+        }
+    })
+}
+
+/// Values of the single column of a subquery's result, given as the number of columns and the rows.
+fn get_single_column_values(
+    (column_count, rows): (usize, Vec<Row>),
+    usage: &str,
+) -> Result<Vec<DataInstance>, ValidationError> {
+    if column_count != 1 {
+        return Err(ValidationError(format!(
+            "A subquery {} must return a single column, instead it returns {}",
+            usage, column_count
+        )));
+    }
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| row.0.into_iter().next())
+        .collect())
+}
+
+/// Substitute subqueries of the expression with constants from their results, which are given in the order
+/// of `Expression::get_subqueries`.
+fn substitute_subqueries(
+    expression: &Expression,
+    results: &mut impl Iterator<Item = (usize, Vec<Row>)>,
+) -> Result<Expression, ExecutorError> {
+    const MISSING_RESULT: &str = "each subquery has a result";
+    expression.try_replace(&mut |subexpression| {
+        Ok(Some(match subexpression {
+            Expression::Subquery(_) => {
+                let result = results.next().expect(MISSING_RESULT);
+                let values = get_single_column_values(result, "used as an expression")?;
+                if values.len() > 1 {
+                    return Err(ExecutionError(
+                        "A subquery used as an expression returned more than one row".to_string(),
+                    )
+                    .into());
+                }
+                // Same as in Postgres, no rows make the value NULL
+                let value = values.into_iter().next().unwrap_or(DataInstance::Null);
+                Expression::Atom(DataDefinition::Const(value))
+            }
+            Expression::InSubquery(operand, _) => {
+                // The query comes before subqueries of the operand
+                let result = results.next().expect(MISSING_RESULT);
+                let values = get_single_column_values(result, "used with IN")?;
+                let operand = substitute_subqueries(operand, results)?;
+                Expression::In(
+                    Box::new(operand),
+                    values
+                        .into_iter()
+                        .map(|value| Expression::Atom(DataDefinition::Const(value)))
+                        .collect(),
+                )
+            }
+            Expression::Exists(_) => {
+                let (_, rows) = results.next().expect(MISSING_RESULT);
+                Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                    DataInstanceRaw::Bool(!rows.is_empty()),
+                )))
+            }
+            _ => return Ok(None),
+        }))
+    })
+}
+
+/// Turn SELECT columns into output expressions over `table`, with `*` expanded, along with names
+/// of output columns and aliases paired with the expressions they stand for.
+fn expand_select_columns(
+    table: &TableDefinition,
+    select_columns: Vec<SelectColumn>,
+) -> (Vec<Expression>, Vec<String>, Vec<(String, Expression)>) {
+    let mut output_expressions = Vec::<Expression>::new();
+    let mut output_column_names = Vec::<String>::new();
+    let mut aliases = Vec::<(String, Expression)>::new();
+    for select_column in select_columns {
+        match select_column {
+            SelectColumn::All => {
+                for column in &table.columns {
+                    let expression =
+                        Expression::Atom(DataDefinition::Identifier(column.name.clone()));
+                    output_column_names.push(expression.get_default_name());
+                    output_expressions.push(expression);
+                }
+            }
+            SelectColumn::Expression { expression, alias } => {
+                output_column_names.push(match alias {
+                    Some(alias) => {
+                        aliases.push((alias.clone(), expression.clone()));
+                        alias
+                    }
+                    None => expression.get_default_name(),
+                });
+                output_expressions.push(expression);
+            }
+        }
+    }
+    (
+        output_expressions,
+        make_names_unique(output_column_names),
+        aliases,
+    )
+}
+
+/// Suffix repeated names with `_2`, `_3` and so on, e.g. for `SELECT substr(a, 1, 1), substr(b, 1, 1)`,
+/// as rows are returned as objects keyed by column name, which would otherwise lose values.
+fn make_names_unique(names: Vec<String>) -> Vec<String> {
+    let mut taken_names: HashSet<String> = names.iter().cloned().collect();
+    let mut seen_names = HashSet::<String>::new();
+    names
+        .into_iter()
+        .map(|name| {
+            if seen_names.insert(name.clone()) {
+                return name;
+            }
+            let unique_name = (2..)
+                .map(|suffix| format!("{}_{}", name, suffix))
+                .find(|candidate| !taken_names.contains(candidate))
+                .expect("Some suffix is always free");
+            taken_names.insert(unique_name.clone());
+            unique_name
+        })
+        .collect()
+}
+
+/// Validated RETURNING clause of a data-modifying statement.
+struct Returning {
+    column_names: Vec<String>,
+    expressions: Vec<Expression>,
+}
+
+impl Returning {
+    /// Validate RETURNING columns against `table`. `None` means that there's no RETURNING clause.
+    fn plan(
+        table: &TableDefinition,
+        returning: Vec<SelectColumn>,
+    ) -> Result<Option<Self>, ValidationError> {
+        if returning.is_empty() {
+            return Ok(None);
+        }
+        let (expressions, column_names, _) = expand_select_columns(table, returning);
+        let expressions = expressions
+            .iter()
+            .map(|expression| {
+                let expression = expression.coerce_literals(table)?;
+                // Aggregates are rejected here too, as each affected row is returned on its own
+                expression.infer_type(table)?;
+                Ok(expression)
+            })
+            .collect::<Result<Vec<Expression>, ValidationError>>()?;
+        Ok(Some(Returning {
+            column_names,
+            expressions,
+        }))
+    }
+
+    /// Result of a data-modifying statement: RETURNING values computed from affected rows of `table`,
+    /// or just the number of those rows if there's no RETURNING clause.
+    fn get_result<'r>(
+        returning: Option<Self>,
+        table: &TableDefinition,
+        affected_rows: impl ExactSizeIterator<Item = &'r Row>,
+    ) -> ExecutorResult {
+        let returning = match returning {
+            Some(returning) => returning,
+            None => return Ok(QueryResult::rows_affected(affected_rows.len())),
+        };
+        let table_column_names = table.get_column_names();
+        let rows = affected_rows
+            .map(|row| {
+                let context = RowContext::new(&table_column_names, &row.0);
+                Ok(Row(returning
+                    .expressions
+                    .iter()
+                    .map(|expression| expression.evaluate(&context))
+                    .collect::<Result<Vec<DataInstance>, String>>()?))
+            })
+            .collect::<Result<Vec<Row>, String>>()?;
+        Ok(QueryResult {
+            column_names: returning.column_names,
+            rows,
+        })
+    }
+}
+
+/// Validated ON CONFLICT action.
+enum ConflictResolution {
+    DoNothing,
+    /// Assignments as pairs of column indexes and values, with values and the condition evaluated
+    /// against the existing row's columns followed by `excluded.` ones.
+    DoUpdate {
+        assignments: Vec<(usize, Expression)>,
+        where_clause: Option<Expression>,
+    },
+}
+
+/// CHECK constraints of a table, ready to be evaluated against its rows.
+struct Checks {
+    conditions: Vec<(String, Expression)>,
+}
+
+impl Checks {
+    fn plan(table: &TableDefinition) -> Result<Self, ValidationError> {
+        let mut conditions = Vec::new();
+        for constraint in &table.constraints {
+            if let ConstraintKind::Check(condition) = &constraint.kind {
+                conditions.push((constraint.name.clone(), condition.coerce_literals(table)?));
+            }
+        }
+        Ok(Checks { conditions })
+    }
+
+    /// Make sure that the row satisfies all the conditions. Same as in standard SQL, NULL outcome is fine.
+    fn enforce(&self, table: &TableDefinition, row: &Row) -> Result<(), ExecutorError> {
+        let column_names = table.get_column_names();
+        let context = RowContext::new(&column_names, &row.0);
+        for (constraint_name, condition) in &self.conditions {
+            if let Some(DataInstanceRaw::Bool(false)) = condition.evaluate(&context)?.as_raw() {
+                return Err(ConstraintError(format!(
+                    "Row violates CHECK constraint `{}` of table `{}`",
+                    constraint_name, table.name
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Make sure that assignments of UPDATE (or of ON CONFLICT DO UPDATE) fit columns of `table`,
+/// with values evaluated in the context of `value_table`. Returns pairs of column indexes and values.
+fn validate_assignments(
+    table: &TableDefinition,
+    assignments: &[Assignment],
+    value_table: &TableDefinition,
+) -> Result<Vec<(usize, Expression)>, ValidationError> {
+    let mut validated_assignments = Vec::with_capacity(assignments.len());
+    for assignment in assignments {
+        let column_index = table
+            .get_column_index(&assignment.column_name)
+            .ok_or_else(|| {
+                ValidationError(format!(
+                    "Column `{}` does not exist in table `{}`",
+                    assignment.column_name, table.name
+                ))
+            })?;
+        let column = &table.columns[column_index];
+        if table.is_key_column(&column.name) {
+            // Changing the primary key would mean moving the row to another place in the B+ tree
+            return Err(ValidationError(format!(
+                "Column `{}` is {} PRIMARY KEY of table `{}`, so it cannot be updated",
+                column.name,
+                describe_key_membership(table),
+                table.name
+            )));
+        }
+        assignment
+            .value
+            .validate_assignable_to(&column.data_type, value_table)
+            .map_err(|error| {
+                ValidationError(format!("Problem at column `{}`: {}", column.name, error.0))
+            })?;
+        validated_assignments.push((column_index, assignment.value.coerce_literals(value_table)?));
+    }
+    Ok(validated_assignments)
+}
+
+/// Definition of rows that ON CONFLICT DO UPDATE works with: columns of the existing row, followed by
+/// columns of the row that was to be inserted, prefixed with `excluded.`.
+fn get_excluded_table(table: &TableDefinition) -> TableDefinition {
+    let mut columns = table.columns.clone();
+    columns.extend(table.columns.iter().map(|column| ColumnDefinition {
+        name: format!("excluded.{}", column.name),
+        ..column.clone()
+    }));
+    TableDefinition::new(table.name.clone(), columns)
+}
+
+/// Error for a column that was given no value, while it has no DEFAULT and isn't nullable either.
+fn missing_value_error(column: &ColumnDefinition) -> ValidationError {
+    ValidationError(format!(
+        "Column `{}` must be provided a value, as it has no DEFAULT and is not nullable",
+        column.name
+    ))
+}
+
+/// PRIMARY KEY value for messages, which for single-column keys is just the value of the column.
+fn format_key(key: &Key) -> String {
+    match key.as_slice() {
+        [value] => format!("{:?}", value),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// PRIMARY KEY column(s) for messages, e.g. "PRIMARY KEY column `id`".
+fn describe_primary_key(table: &TableDefinition) -> String {
+    match table.get_key_column_names().as_slice() {
+        [column_name] => format!("PRIMARY KEY column `{}`", column_name),
+        column_names => format!(
+            "PRIMARY KEY columns {}",
+            column_names
+                .iter()
+                .map(|column_name| format!("`{}`", column_name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// How a key column relates to the PRIMARY KEY, i.e. whether it's "the" PRIMARY KEY or "part of the" composite one.
+fn describe_key_membership(table: &TableDefinition) -> &'static str {
+    match table.get_key_indexes().len() {
+        1 => "the",
+        _ => "part of the",
+    }
+}
+
+/// Schema (database) that a statement refers to, which is the default one if not specified.
+fn resolve_schema_name(schema_name: &Option<String>) -> &str {
+    schema_name.as_deref().unwrap_or(DEFAULT_SCHEMA_NAME)
+}
+
+/// Make sure that the schema is not one that user statements must not modify.
+fn ensure_schema_is_writable(schema_name: &str) -> Result<(), ValidationError> {
+    if schema_name == SYSTEM_SCHEMA_NAME {
+        return Err(ValidationError(format!(
+            "Database `{}` is managed by Emdrive itself, so it cannot be modified",
+            schema_name
+        )));
+    }
+    Ok(())
+}
 
 pub struct Executor {
     config: config::Config,
-    tables: Arc<Mutex<Vec<TableDefinition>>>,
+    tables: Arc<Mutex<Vec<CatalogEntry>>>,
+    /// Generator of IDs for system records. They are monotonic, so that records of a table's constraints
+    /// and indexes are read back in the order in which they were created.
+    record_id_generator: Mutex<Generator>,
     rx: Option<mpsc::Receiver<ExecutorPayload>>,
 }
 
@@ -51,6 +843,7 @@ impl Executor {
         Executor {
             config: config.clone(),
             tables: Arc::new(Mutex::new(Vec::new())),
+            record_id_generator: Mutex::new(Generator::new()),
             rx: None,
         }
     }
@@ -63,13 +856,135 @@ impl Executor {
 
     pub async fn bootstrap(&mut self) -> Result<(), io::Error> {
         debug!("⬆️ Bootstraping the executor...");
+        create_schema_dir(&self.config, DEFAULT_SCHEMA_NAME).await?;
         for table in SystemTable::ALL {
             let table_definition = table.get_definition();
-            write::ensure_table_file_exists(&self.config, &table_definition).await?;
+            write::ensure_table_file_exists(&self.config, SYSTEM_SCHEMA_NAME, &table_definition)
+                .await?;
         }
+        let tables = self
+            .load_catalog()
+            .await
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        debug!("📚 Loaded {} tables from the catalog", tables.len());
+        *self.tables.lock() = tables;
         Ok(())
     }
 
+    /// Read all records of a system table.
+    async fn read_system_records(
+        &self,
+        system_table: SystemTable,
+    ) -> Result<Vec<SystemRecord>, String> {
+        let definition = system_table.get_definition();
+        let column_names = definition.get_column_names();
+        Ok(
+            read::read_all_rows(&self.config, SYSTEM_SCHEMA_NAME, &definition)
+                .await?
+                .into_iter()
+                .map(|row| SystemRecord {
+                    column_names: column_names.clone(),
+                    row,
+                })
+                .collect(),
+        )
+    }
+
+    /// Rebuild catalog entries of user tables from their records in the system tables.
+    async fn load_catalog(&self) -> Result<Vec<CatalogEntry>, String> {
+        let column_records = self.read_system_records(SystemTable::Columns).await?;
+        let index_records = self.read_system_records(SystemTable::Indexes).await?;
+        let constraint_records = self.read_system_records(SystemTable::Constraints).await?;
+        let statistic_records = self.read_system_records(SystemTable::Statistics).await?;
+        let mut entries = Vec::new();
+        for table_record in self.read_system_records(SystemTable::Tables).await? {
+            let id = table_record.get_uuid("id")?;
+            let mut columns = Vec::new();
+            for record in column_records
+                .iter()
+                .filter(|record| record.belongs_to_table(id))
+            {
+                let default = match record.get_optional_string("default_value")? {
+                    Some(default) => {
+                        Some(parse_data_definition(&default).map_err(|error| error.to_string())?)
+                    }
+                    None => None,
+                };
+                let column = ColumnDefinition {
+                    name: record.get_string("column_name")?,
+                    data_type: DataType {
+                        raw_type: record.get_string("raw_type")?.parse()?,
+                        is_nullable: record.get_bool("is_nullable")?,
+                    },
+                    primary_key: record.get_bool("is_primary_key")?,
+                    metric_key: record.get_bool("is_metric_key")?,
+                    default,
+                };
+                columns.push((
+                    record.get_count("position")?,
+                    record.get_uuid("id")?,
+                    column,
+                ));
+            }
+            columns.sort_by_key(|(position, ..)| *position);
+            let (column_ids, columns): (Vec<Uuid>, Vec<ColumnDefinition>) = columns
+                .into_iter()
+                .map(|(_, column_id, column)| (column_id, column))
+                .unzip();
+            let mut table = TableDefinition::new(table_record.get_string("table_name")?, columns);
+            let mut constraint_ids = Vec::new();
+            for record in constraint_records
+                .iter()
+                .filter(|record| record.belongs_to_table(id))
+            {
+                table.constraints.push(ConstraintDefinition {
+                    name: record.get_string("constraint_name")?,
+                    kind: parse_constraint_kind(&record.get_string("definition")?)
+                        .map_err(|error| error.to_string())?,
+                });
+                constraint_ids.push(record.get_uuid("id")?);
+            }
+            let mut indexes = Vec::new();
+            let mut index_ids = Vec::new();
+            for record in index_records
+                .iter()
+                .filter(|record| record.belongs_to_table(id))
+            {
+                indexes.push(IndexDefinition {
+                    name: record.get_string("index_name")?,
+                    column_names: parse_identifiers(&record.get_string("column_names")?)
+                        .map_err(|error| error.to_string())?,
+                    unique: record.get_bool("is_unique")?,
+                });
+                index_ids.push(record.get_uuid("id")?);
+            }
+            let table_statistic_records: Vec<&SystemRecord> = statistic_records
+                .iter()
+                .filter(|record| record.belongs_to_table(id))
+                .collect();
+            let (statistics, statistic_ids) = match table_statistic_records.is_empty() {
+                true => (None, Vec::new()),
+                false => {
+                    let (statistics, statistic_ids) =
+                        load_statistics(&table, &table_statistic_records)?;
+                    (Some(Arc::new(statistics)), statistic_ids)
+                }
+            };
+            entries.push(CatalogEntry {
+                id,
+                schema_name: table_record.get_string("schema_name")?,
+                table,
+                column_ids,
+                indexes,
+                index_ids,
+                constraint_ids,
+                statistics,
+                statistic_ids,
+            });
+        }
+        Ok(entries)
+    }
+
     pub async fn start(&mut self) -> Result<(), io::Error> {
         let mut rx = self
             .rx
@@ -80,14 +995,4386 @@ impl Executor {
         while let Some(payload) = rx.recv().await {
             let (statement, tx) = payload;
             debug!("➡️ Executing statement: {:?}", statement);
-            // TODO: Implement real query execution
-            let result = QueryResult {
-                column_names: vec!["id".to_string()],
-                rows: vec![Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(1))])],
-            };
-            tx.send(result).unwrap();
+            let result = self.execute(statement).await;
+            if tx.send(result).is_err() {
+                debug!("↩️ Statement result could not be returned, as the requester is gone");
+            }
         }
         debug!("🎗 Executor disengaged");
         Ok(())
     }
+
+    async fn execute(&self, statement: Statement) -> ExecutorResult {
+        match statement {
+            Statement::CreateTable(create_table) => self.execute_create_table(create_table).await,
+            Statement::Insert(insert) => self.execute_insert(insert).await,
+            Statement::Select(select) => self.execute_select(select).await,
+            Statement::Update(update) => self.execute_update(update).await,
+            Statement::Delete(delete) => self.execute_delete(delete).await,
+            Statement::Truncate(truncate) => self.execute_truncate(truncate).await,
+            Statement::DropTable(drop_table) => self.execute_drop_table(drop_table).await,
+            Statement::AlterTable(alter_table) => self.execute_alter_table(alter_table).await,
+            Statement::CreateDatabase(create_database) => {
+                self.execute_create_database(create_database).await
+            }
+            Statement::DropDatabase(drop_database) => {
+                self.execute_drop_database(drop_database).await
+            }
+            Statement::CreateIndex(create_index) => self.execute_create_index(create_index).await,
+            Statement::DropIndex(drop_index) => self.execute_drop_index(drop_index).await,
+            Statement::Explain(explain) => self.execute_explain(explain).await,
+            Statement::Analyze(analyze) => self.execute_analyze(analyze).await,
+        }
+    }
+
+    /// Find the definition of a table. Tables of the `system` schema can be found too, but only for reading.
+    fn get_table_definition(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<TableDefinition, ValidationError> {
+        let table = if schema_name == SYSTEM_SCHEMA_NAME {
+            SystemTable::ALL
+                .iter()
+                .map(SystemTable::get_definition)
+                .find(|table| table.name == table_name)
+        } else {
+            self.tables
+                .lock()
+                .iter()
+                .find(|entry| entry.schema_name == schema_name && entry.table.name == table_name)
+                .map(|entry| entry.table.clone())
+        };
+        table.ok_or_else(|| {
+            ValidationError(format!(
+                "Table `{}.{}` does not exist",
+                schema_name, table_name
+            ))
+        })
+    }
+
+    /// Find the definition of a table which is about to be modified.
+    fn get_writable_table_definition(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<TableDefinition, ValidationError> {
+        ensure_schema_is_writable(schema_name)?;
+        self.get_table_definition(schema_name, table_name)
+    }
+
+    /// Find the indexes of a table, other than that of the PRIMARY KEY. System tables have none.
+    fn get_indexes(&self, schema_name: &str, table_name: &str) -> Vec<IndexDefinition> {
+        self.tables
+            .lock()
+            .iter()
+            .find(|entry| entry.schema_name == schema_name && entry.table.name == table_name)
+            .map(|entry| entry.indexes.clone())
+            .unwrap_or_default()
+    }
+
+    /// Find the statistics of a table, if it's been analyzed. System tables never are.
+    fn get_statistics(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Option<Arc<statistics::TableStatistics>> {
+        self.tables
+            .lock()
+            .iter()
+            .find(|entry| entry.schema_name == schema_name && entry.table.name == table_name)
+            .and_then(|entry| entry.statistics.clone())
+    }
+
+    /// Find the catalog entry of a user table.
+    fn get_catalog_entry(
+        &self,
+        schema_name: &str,
+        table_name: &str,
+    ) -> Result<CatalogEntry, ValidationError> {
+        self.tables
+            .lock()
+            .iter()
+            .find(|entry| entry.schema_name == schema_name && entry.table.name == table_name)
+            .cloned()
+            .ok_or_else(|| {
+                ValidationError(format!(
+                    "Table `{}.{}` does not exist",
+                    schema_name, table_name
+                ))
+            })
+    }
+
+    /// Put the updated catalog entry in place of the old one with the same ID.
+    fn replace_catalog_entry(&self, entry: CatalogEntry) {
+        let mut tables = self.tables.lock();
+        if let Some(old_entry) = tables.iter_mut().find(|old_entry| old_entry.id == entry.id) {
+            *old_entry = entry;
+        }
+    }
+
+    fn generate_record_id(&self) -> Uuid {
+        Uuid::from(
+            self.record_id_generator
+                .lock()
+                .generate()
+                .expect("Random bits of ULIDs don't run out within a millisecond"),
+        )
+    }
+
+    /// Record the table in `system.tables` and its columns in `system.columns`, returning the catalog entry.
+    async fn register_table(
+        &self,
+        schema_name: &str,
+        table: TableDefinition,
+    ) -> Result<CatalogEntry, String> {
+        let id = self.generate_record_id();
+        write::b_tree_insert(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &SystemTable::Tables.get_definition(),
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::Uuid(id)),
+                DataInstance::Direct(DataInstanceRaw::String(schema_name.into())),
+                DataInstance::Direct(DataInstanceRaw::String(table.name.clone())),
+            ]),
+        )
+        .await?;
+        let mut column_ids = Vec::with_capacity(table.columns.len());
+        for (position, column) in table.columns.iter().enumerate() {
+            column_ids.push(self.register_column(id, column, position).await?);
+        }
+        let mut constraint_ids = Vec::with_capacity(table.constraints.len());
+        for constraint in &table.constraints {
+            constraint_ids.push(self.register_constraint(id, constraint).await?);
+        }
+        Ok(CatalogEntry {
+            id,
+            schema_name: schema_name.to_string(),
+            table,
+            column_ids,
+            indexes: Vec::new(),
+            index_ids: Vec::new(),
+            constraint_ids,
+            statistics: None,
+            statistic_ids: Vec::new(),
+        })
+    }
+
+    /// Record the column, which is at `position` among columns of the table, in `system.columns`,
+    /// returning the ID of the record.
+    async fn register_column(
+        &self,
+        table_id: Uuid,
+        column: &ColumnDefinition,
+        position: usize,
+    ) -> Result<Uuid, String> {
+        let id = self.generate_record_id();
+        write::b_tree_insert(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &SystemTable::Columns.get_definition(),
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::Uuid(id)),
+                DataInstance::Direct(DataInstanceRaw::Uuid(table_id)),
+                DataInstance::Direct(DataInstanceRaw::String(column.name.clone())),
+                DataInstance::Direct(DataInstanceRaw::String(
+                    column.data_type.raw_type.to_string(),
+                )),
+                DataInstance::Direct(DataInstanceRaw::Bool(column.data_type.is_nullable)),
+                DataInstance::Direct(DataInstanceRaw::UInt16(position as u16)),
+                DataInstance::Direct(DataInstanceRaw::Bool(column.primary_key)),
+                DataInstance::Direct(DataInstanceRaw::Bool(column.metric_key)),
+                match &column.default {
+                    Some(default) => {
+                        DataInstance::Nullable(DataInstanceRaw::String(default.to_string()))
+                    }
+                    None => DataInstance::Null,
+                },
+            ]),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// Record the index in `system.indexes`, returning the ID of the record.
+    async fn register_index(
+        &self,
+        table_id: Uuid,
+        index: &IndexDefinition,
+    ) -> Result<Uuid, String> {
+        let id = self.generate_record_id();
+        write::b_tree_insert(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &SystemTable::Indexes.get_definition(),
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::Uuid(id)),
+                DataInstance::Direct(DataInstanceRaw::Uuid(table_id)),
+                DataInstance::Direct(DataInstanceRaw::String(index.name.clone())),
+                DataInstance::Direct(DataInstanceRaw::String(quote_identifiers(
+                    &index.column_names,
+                ))),
+                DataInstance::Direct(DataInstanceRaw::Bool(index.unique)),
+            ]),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// Record the constraint in `system.constraints`, returning the ID of the record.
+    async fn register_constraint(
+        &self,
+        table_id: Uuid,
+        constraint: &ConstraintDefinition,
+    ) -> Result<Uuid, String> {
+        let id = self.generate_record_id();
+        let kind = match constraint.kind {
+            ConstraintKind::PrimaryKey(_) => "PRIMARY KEY",
+            ConstraintKind::Unique(_) => "UNIQUE",
+            ConstraintKind::Check(_) => "CHECK",
+        };
+        write::b_tree_insert(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &SystemTable::Constraints.get_definition(),
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::Uuid(id)),
+                DataInstance::Direct(DataInstanceRaw::Uuid(table_id)),
+                DataInstance::Direct(DataInstanceRaw::String(constraint.name.clone())),
+                DataInstance::Direct(DataInstanceRaw::String(kind.into())),
+                DataInstance::Direct(DataInstanceRaw::String(constraint.to_string())),
+            ]),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// Record statistics of the table's columns in `system.statistics`, returning the IDs of the records.
+    async fn register_statistics(
+        &self,
+        table_id: Uuid,
+        statistics: &statistics::TableStatistics,
+    ) -> Result<Vec<Uuid>, String> {
+        let mut ids = Vec::with_capacity(statistics.columns.len());
+        let mut rows = Vec::with_capacity(statistics.columns.len());
+        for column in &statistics.columns {
+            let id = self.generate_record_id();
+            let histogram_bounds = column
+                .histogram_bounds
+                .iter()
+                .map(DataInstanceRaw::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            let distance_counts = match &column.distance_counts {
+                Some(distance_counts) => DataInstance::Nullable(DataInstanceRaw::String(
+                    distance_counts
+                        .iter()
+                        .map(u64::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                )),
+                None => DataInstance::Null,
+            };
+            rows.push(Row(vec![
+                DataInstance::Direct(DataInstanceRaw::Uuid(id)),
+                DataInstance::Direct(DataInstanceRaw::Uuid(table_id)),
+                DataInstance::Direct(DataInstanceRaw::String(column.column_name.clone())),
+                DataInstance::Direct(DataInstanceRaw::UInt64(statistics.row_count)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(statistics.sampled_row_count as u64)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(column.null_count as u64)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(column.distinct_count)),
+                DataInstance::Direct(DataInstanceRaw::String(histogram_bounds)),
+                distance_counts,
+                DataInstance::Direct(DataInstanceRaw::Timestamp(statistics.analyzed_at)),
+            ]));
+            ids.push(id);
+        }
+        write::b_tree_insert_rows(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &SystemTable::Statistics.get_definition(),
+            rows,
+        )
+        .await?;
+        Ok(ids)
+    }
+
+    /// Forget statistics of the table, e.g. because its columns have changed.
+    async fn discard_statistics(&self, entry: &mut CatalogEntry) -> Result<(), String> {
+        for statistic_id in entry.statistic_ids.drain(..) {
+            self.delete_system_record(SystemTable::Statistics, statistic_id)
+                .await?;
+        }
+        entry.statistics = None;
+        Ok(())
+    }
+
+    /// Remove the record with the given ID from a system table.
+    async fn delete_system_record(
+        &self,
+        system_table: SystemTable,
+        id: Uuid,
+    ) -> Result<(), String> {
+        write::b_tree_delete(
+            &self.config,
+            SYSTEM_SCHEMA_NAME,
+            &system_table.get_definition(),
+            &vec![DataInstanceRaw::Uuid(id)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Set a column of the record with the given ID in a system table.
+    async fn update_system_record(
+        &self,
+        system_table: SystemTable,
+        id: Uuid,
+        column_name: &str,
+        value: DataInstance,
+    ) -> Result<(), String> {
+        let definition = system_table.get_definition();
+        let column_index = definition
+            .get_column_index(column_name)
+            .expect("System table columns are known in advance");
+        let key_indexes = definition.get_key_indexes();
+        let id = vec![DataInstanceRaw::Uuid(id)];
+        write::b_tree_update_rows(&self.config, SYSTEM_SCHEMA_NAME, &definition, |row| {
+            if read::get_row_key(row, &key_indexes) != id {
+                return Ok(None);
+            }
+            let mut new_values = row.0.clone();
+            new_values[column_index] = value.clone();
+            Ok(Some(Row(new_values)))
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Remove the records of the table, its columns, indexes, constraints and statistics from the system tables.
+    async fn unregister_table(&self, entry: &CatalogEntry) -> Result<(), String> {
+        self.delete_system_record(SystemTable::Tables, entry.id)
+            .await?;
+        for column_id in &entry.column_ids {
+            self.delete_system_record(SystemTable::Columns, *column_id)
+                .await?;
+        }
+        for index_id in &entry.index_ids {
+            self.delete_system_record(SystemTable::Indexes, *index_id)
+                .await?;
+        }
+        for constraint_id in &entry.constraint_ids {
+            self.delete_system_record(SystemTable::Constraints, *constraint_id)
+                .await?;
+        }
+        for statistic_id in &entry.statistic_ids {
+            self.delete_system_record(SystemTable::Statistics, *statistic_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn execute_create_table(&self, create_table: CreateTableStatement) -> ExecutorResult {
+        let CreateTableStatement {
+            schema_name,
+            table,
+            if_not_exists,
+        } = create_table;
+        let schema = resolve_schema_name(&schema_name);
+        ensure_schema_is_writable(schema)?;
+        if !does_schema_dir_exist(&self.config, schema).await {
+            return Err(ValidationError(format!("Database `{}` does not exist", schema)).into());
+        }
+        // A table left behind without a catalog entry is not overwritten either
+        let is_table_recorded = self
+            .read_system_records(SystemTable::Tables)
+            .await?
+            .iter()
+            .any(|record| {
+                record.get_string("schema_name").as_deref() == Ok(schema)
+                    && record.get_string("table_name").as_ref() == Ok(&table.name)
+            });
+        if self.get_table_definition(schema, &table.name).is_ok()
+            || is_table_recorded
+            || does_table_file_exist(&self.config, schema, &table.name, TABLE_DATA_FILE_NAME).await
+        {
+            if if_not_exists {
+                return Ok(QueryResult::empty());
+            }
+            return Err(ValidationError(format!(
+                "Table `{}.{}` already exists",
+                schema, table.name
+            ))
+            .into());
+        }
+        // UNIQUE constraints are enforced by indexes, whose names are unique within the database
+        let backing_indexes: Vec<IndexDefinition> = table
+            .constraints
+            .iter()
+            .filter_map(ConstraintDefinition::get_backing_index)
+            .collect();
+        for index in &backing_indexes {
+            if self.find_index_owner(schema, &index.name).is_some() {
+                return Err(ValidationError(format!(
+                    "Index `{}.{}` already exists",
+                    schema, index.name
+                ))
+                .into());
+            }
+        }
+        write_table_file(
+            &self.config,
+            schema,
+            &table.name,
+            TABLE_DATA_FILE_NAME,
+            construct_blank_table(),
+        )
+        .await?;
+        if table.get_metric_key().is_some() {
+            // Creates the index file
+            Index::new(schema, &table.name, &self.config);
+        }
+        for index in &backing_indexes {
+            indexes::build_index(&self.config, schema, &table, index, &[]).await?;
+        }
+        let mut entry = self.register_table(schema, table).await?;
+        for index in backing_indexes {
+            entry
+                .index_ids
+                .push(self.register_index(entry.id, &index).await?);
+            entry.indexes.push(index);
+        }
+        self.tables.lock().push(entry);
+        Ok(QueryResult::empty())
+    }
+
+    /// Keep the METRIC KEY index in line with changed rows, given as pairs of old and new versions,
+    /// with `None` standing for a row that didn't exist before or doesn't exist anymore.
+    fn update_metric_index<'r>(
+        &self,
+        schema: &str,
+        table: &TableDefinition,
+        changes: impl Iterator<Item = (Option<&'r Row>, Option<&'r Row>)>,
+    ) {
+        if let Some(metric_key_index) = table.columns.iter().position(|column| column.metric_key) {
+            let metric_value_at = |row: Option<&Row>| row?.0[metric_key_index].as_raw()?.as_u128();
+            let mut index = Index::new(schema, &table.name, &self.config);
+            for (old_row, new_row) in changes {
+                match (metric_value_at(old_row), metric_value_at(new_row)) {
+                    (Some(old_value), Some(new_value)) if old_value != new_value => {
+                        index.replace(old_value, new_value)
+                    }
+                    (None, Some(new_value)) => index.add(new_value),
+                    (Some(old_value), None) => index.remove(old_value),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Keep all indexes of the table in line with changed rows, which have already been written.
+    async fn update_indexes(
+        &self,
+        schema: &str,
+        table: &TableDefinition,
+        indexes: &[IndexDefinition],
+        changes: &[(Option<&Row>, Option<&Row>)],
+    ) -> Result<(), String> {
+        self.update_metric_index(schema, table, changes.iter().copied());
+        indexes::update_indexes(&self.config, schema, table, indexes, changes).await
+    }
+
+    /// Validate the ON CONFLICT clause of an INSERT into `table`.
+    fn plan_conflict_resolution(
+        table: &TableDefinition,
+        on_conflict: OnConflict,
+    ) -> Result<ConflictResolution, ValidationError> {
+        let key_column_names = table.get_key_column_names();
+        if on_conflict.column_names != key_column_names {
+            return Err(ValidationError(format!(
+                "The ON CONFLICT target must be the PRIMARY KEY of table `{}`, i.e. `({})`",
+                table.name,
+                key_column_names.join(", ")
+            )));
+        }
+        match on_conflict.action {
+            ConflictAction::DoNothing => Ok(ConflictResolution::DoNothing),
+            ConflictAction::DoUpdate {
+                assignments,
+                where_clause,
+            } => {
+                let value_table = get_excluded_table(table);
+                let assignments = validate_assignments(table, &assignments, &value_table)?;
+                let where_clause = where_clause
+                    .map(|where_clause| where_clause.coerce_literals(&value_table))
+                    .transpose()?;
+                if let Some(where_clause) = &where_clause {
+                    where_clause.validate_condition(&value_table)?;
+                }
+                Ok(ConflictResolution::DoUpdate {
+                    assignments,
+                    where_clause,
+                })
+            }
+        }
+    }
+
+    async fn execute_insert(&self, insert: InsertStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&insert.schema_name);
+        let table = self.get_writable_table_definition(schema, &insert.table_name)?;
+        for column_name in &insert.column_names {
+            if table.get_column(column_name).is_none() {
+                return Err(ValidationError(format!(
+                    "Column `{}` does not exist in table `{}`",
+                    column_name, table.name
+                ))
+                .into());
+            }
+        }
+        // Defaults may refer to the columns whose values are provided
+        let provided_table = TableDefinition::new(
+            table.name.clone(),
+            insert
+                .column_names
+                .iter()
+                .filter_map(|column_name| table.get_column(column_name).cloned())
+                .collect(),
+        );
+        // For each table column, the position of its value among the provided ones,
+        // and the DEFAULT to use if it's not provided
+        let mut column_fills = Vec::with_capacity(table.columns.len());
+        for column in &table.columns {
+            let value_index = insert
+                .column_names
+                .iter()
+                .position(|column_name| column_name == &column.name);
+            let default = column.default.clone().map(Expression::Atom);
+            if let Some(default) = &default {
+                default
+                    .validate_assignable_to(&column.data_type, &provided_table)
+                    .map_err(|error| {
+                        ValidationError(format!("Problem at column `{}`: {}", column.name, error.0))
+                    })?;
+            } else if value_index.is_none() && !column.data_type.is_nullable {
+                return Err(missing_value_error(column).into());
+            }
+            column_fills.push((value_index, default));
+        }
+        let provided_rows = match insert.source {
+            InsertSource::Values(rows) => {
+                for values in &rows {
+                    if values.len() != insert.column_names.len() {
+                        return Err(ValidationError(format!(
+                            "{} columns were specified, but {} values were provided",
+                            insert.column_names.len(),
+                            values.len()
+                        ))
+                        .into());
+                    }
+                }
+                rows
+            }
+            InsertSource::Select(select) => {
+                let result = self.execute_select(*select).await?;
+                if result.column_names.len() != insert.column_names.len() {
+                    return Err(ValidationError(format!(
+                        "{} columns were specified, but the SELECT returns {} columns",
+                        insert.column_names.len(),
+                        result.column_names.len()
+                    ))
+                    .into());
+                }
+                result
+                    .rows
+                    .into_iter()
+                    .map(|row| row.0.into_iter().map(InsertValue::Value).collect())
+                    .collect()
+            }
+        };
+        // All rows are checked before any is written
+        let mut rows = Vec::with_capacity(provided_rows.len());
+        for values in provided_rows {
+            // DEFAULT items are NULL for the purposes of evaluating defaults
+            let context_values: Vec<DataInstance> = values
+                .iter()
+                .map(|value| match value {
+                    InsertValue::Value(value) => value.clone(),
+                    InsertValue::Default => DataInstance::Null,
+                })
+                .collect();
+            let context = RowContext::new(&insert.column_names, &context_values);
+            let mut row_values = Vec::with_capacity(table.columns.len());
+            for (column, (value_index, default)) in table.columns.iter().zip(&column_fills) {
+                let value = match (value_index.map(|value_index| &values[value_index]), default) {
+                    (Some(InsertValue::Value(value)), _) => value.clone(),
+                    (_, Some(default)) => default.evaluate(&context)?,
+                    (_, None) if column.data_type.is_nullable => DataInstance::Null,
+                    (_, None) => return Err(missing_value_error(column).into()),
+                };
+                row_values.push(value.conform(&column.data_type).map_err(|error| {
+                    ValidationError(format!("Problem at column `{}`: {}", column.name, error))
+                })?);
+            }
+            rows.push(Row(row_values));
+        }
+        let conflict_resolution = insert
+            .on_conflict
+            .map(|on_conflict| Self::plan_conflict_resolution(&table, on_conflict))
+            .transpose()?;
+        let returning = Returning::plan(&table, insert.returning)?;
+        let excluded_table = get_excluded_table(&table);
+        let excluded_column_names = excluded_table.get_column_names();
+        let key_indexes = table.get_key_indexes();
+        let mut new_rows = Vec::with_capacity(rows.len());
+        let mut replaced_rows = Vec::<(Row, Row)>::new();
+        // New versions of affected rows, in the order of the inserted ones
+        let mut affected_rows = Vec::with_capacity(rows.len());
+        let primary_keys: Vec<Key> = rows
+            .iter()
+            .map(|row| read::get_row_key(row, &key_indexes))
+            .collect();
+        let existing_rows = read::find_rows(&self.config, schema, &table, &primary_keys).await?;
+        let mut seen_primary_keys = HashSet::with_capacity(rows.len());
+        for ((row, primary_key), existing_row) in
+            rows.into_iter().zip(primary_keys).zip(existing_rows)
+        {
+            // A PRIMARY KEY value repeated within the statement conflicts with the row inserted before
+            let is_repeated = !seen_primary_keys.insert(primary_key.clone());
+            let existing_row = if is_repeated { None } else { existing_row };
+            if !is_repeated && existing_row.is_none() {
+                affected_rows.push(row.clone());
+                new_rows.push(row);
+                continue;
+            }
+            match &conflict_resolution {
+                None => {
+                    return Err(ConstraintError(format!(
+                        "Value {} of {} already exists in table `{}`",
+                        format_key(&primary_key),
+                        describe_primary_key(&table),
+                        table.name
+                    ))
+                    .into())
+                }
+                Some(ConflictResolution::DoNothing) => (),
+                Some(ConflictResolution::DoUpdate { .. }) if is_repeated => {
+                    return Err(ConstraintError(format!(
+                        "ON CONFLICT DO UPDATE cannot affect the row with PRIMARY KEY value {} a second time",
+                        format_key(&primary_key)
+                    ))
+                    .into())
+                }
+                Some(ConflictResolution::DoUpdate {
+                    assignments,
+                    where_clause,
+                }) => {
+                    let existing_row = existing_row.expect("The conflicting row exists");
+                    let context_values: Vec<DataInstance> =
+                        existing_row.0.iter().chain(&row.0).cloned().collect();
+                    let context = RowContext::new(&excluded_column_names, &context_values);
+                    if let Some(where_clause) = &where_clause {
+                        if !where_clause.is_satisfied_by(&context)? {
+                            continue;
+                        }
+                    }
+                    let mut new_values = existing_row.0.clone();
+                    for (column_index, value) in assignments {
+                        new_values[*column_index] = value
+                            .evaluate(&context)?
+                            .conform(&table.columns[*column_index].data_type)?;
+                    }
+                    affected_rows.push(Row(new_values.clone()));
+                    replaced_rows.push((existing_row, Row(new_values)));
+                }
+            }
+        }
+        let changes: Vec<(Option<&Row>, Option<&Row>)> = new_rows
+            .iter()
+            .map(|new_row| (None, Some(new_row)))
+            .chain(
+                replaced_rows
+                    .iter()
+                    .map(|(old_row, new_row)| (Some(old_row), Some(new_row))),
+            )
+            .collect();
+        let checks = Checks::plan(&table)?;
+        for (_, new_row) in &changes {
+            checks.enforce(&table, new_row.expect("Inserts don't remove rows"))?;
+        }
+        let indexes = self.get_indexes(schema, &table.name);
+        indexes::check_unique_indexes(&self.config, schema, &table, &indexes, &changes).await?;
+        write::b_tree_insert_rows(&self.config, schema, &table, new_rows.clone()).await?;
+        write::b_tree_rewrite_rows(&self.config, schema, &table, replaced_rows.clone()).await?;
+        self.update_indexes(schema, &table, &indexes, &changes)
+            .await?;
+        Returning::get_result(returning, &table, affected_rows.iter())
+    }
+
+    /// Execute subqueries of the expression, giving back the expression with their results in their place.
+    /// `enclosing_tables` are tables of the queries that the subqueries are part of.
+    async fn resolve_subqueries(
+        &self,
+        expression: &Expression,
+        common_tables: &[CommonTable],
+        enclosing_tables: &[EnclosingTable],
+    ) -> Result<Expression, ExecutorError> {
+        let mut results = Vec::new();
+        for subquery in expression.get_subqueries() {
+            let (mut plan, _) = self
+                .plan_select(subquery.clone(), common_tables, enclosing_tables)
+                .await?;
+            let rows = plan.execute(&self.config).await?;
+            results.push((plan.column_names.len(), rows));
+        }
+        substitute_subqueries(expression, &mut results.into_iter())
+    }
+
+    /// Execute WITH queries in order, each of which can read results of the ones before it, giving back
+    /// their results along with those of `common_tables`.
+    async fn execute_with(
+        &self,
+        with: Vec<CommonTableExpression>,
+        common_tables: &[CommonTable],
+        enclosing_tables: &[EnclosingTable],
+    ) -> Result<Vec<CommonTable>, ExecutorError> {
+        let mut common_tables = common_tables.to_vec();
+        for common_table_expression in with {
+            let (mut plan, output_columns) = self
+                .plan_select(
+                    common_table_expression.query,
+                    &common_tables,
+                    enclosing_tables,
+                )
+                .await?;
+            let rows = plan.execute(&self.config).await?;
+            let mut columns = Vec::with_capacity(output_columns.len());
+            for (name, output_column) in plan.column_names.into_iter().zip(output_columns) {
+                let data_type = output_column.data_type.ok_or_else(|| {
+                    ValidationError(format!(
+                        "Column `{}` of WITH query `{}` cannot be the NULL literal",
+                        name, common_table_expression.name
+                    ))
+                })?;
+                columns.push(ColumnDefinition {
+                    name,
+                    data_type,
+                    primary_key: false,
+                    metric_key: output_column.metric_key,
+                    default: None,
+                });
+            }
+            common_tables.push(CommonTable {
+                table: TableDefinition::new(common_table_expression.name, columns),
+                rows: Arc::new(rows),
+            });
+        }
+        Ok(common_tables)
+    }
+
+    /// Validate the query, then plan how to execute it. Results of WITH queries and subqueries are needed
+    /// for that, so those are executed right away. `common_tables` are results of WITH queries in scope,
+    /// `enclosing_tables` are tables of the queries that this one is a subquery of.
+    fn plan_select<'e>(
+        &'e self,
+        mut select: SelectStatement,
+        common_tables: &'e [CommonTable],
+        enclosing_tables: &'e [EnclosingTable],
+    ) -> BoxFuture<'e, Result<(plan::PlanNode, Vec<OutputColumn>), ExecutorError>> {
+        async move {
+            let common_tables = self
+                .execute_with(
+                    std::mem::take(&mut select.with),
+                    common_tables,
+                    enclosing_tables,
+                )
+                .await?;
+            let queried_tables = self.get_queried_tables(&select, &common_tables)?;
+            let subquery_enclosing_tables =
+                EnclosingTable::extend(enclosing_tables, &queried_tables);
+            // Unaliased output columns are named after their subqueries, so those are resolved once named
+            let aliased_expressions = select.columns.iter_mut().filter_map(|column| match column {
+                SelectColumn::Expression {
+                    expression,
+                    alias: Some(_),
+                } => Some(expression),
+                _ => None,
+            });
+            let expressions = aliased_expressions
+                .chain(select.joins.iter_mut().map(|join| &mut join.condition))
+                .chain(&mut select.where_clause)
+                .chain(&mut select.group_by)
+                .chain(&mut select.having)
+                .chain(select.order_by.iter_mut().map(|item| &mut item.expression));
+            for expression in expressions {
+                *expression = self
+                    .resolve_subqueries(expression, &common_tables, &subquery_enclosing_tables)
+                    .await?;
+            }
+            self.plan_resolved_select(select, queried_tables, &common_tables, enclosing_tables)
+                .await
+        }
+        .boxed()
+    }
+
+    /// Tables read by the query, from its FROM clause and joins, in order.
+    fn get_queried_tables(
+        &self,
+        select: &SelectStatement,
+        common_tables: &[CommonTable],
+    ) -> Result<Vec<QueriedTable>, ExecutorError> {
+        let references = std::iter::once((&select.source, false)).chain(
+            select
+                .joins
+                .iter()
+                .map(|join| (&join.table, join.kind == JoinKind::Left)),
+        );
+        let mut queried_tables = Vec::with_capacity(1 + select.joins.len());
+        for (reference, is_null_extended) in references {
+            // WITH queries shadow tables of the default schema, with later ones shadowing earlier ones
+            let common_table = match reference.schema_name {
+                Some(_) => None,
+                None => common_tables
+                    .iter()
+                    .rev()
+                    .find(|common_table| common_table.table.name == reference.table_name),
+            };
+            let (origin, table) = match common_table {
+                Some(common_table) => (
+                    RowOrigin::Computed(common_table.rows.clone()),
+                    common_table.table.clone(),
+                ),
+                None => {
+                    let schema = resolve_schema_name(&reference.schema_name).to_string();
+                    let table = self.get_table_definition(&schema, &reference.table_name)?;
+                    (RowOrigin::Stored(schema), table)
+                }
+            };
+            queried_tables.push(QueriedTable {
+                qualifier: reference.get_qualifier().to_string(),
+                origin,
+                table,
+                is_null_extended,
+            });
+        }
+        Ok(queried_tables)
+    }
+
+    /// Same as `plan_select`, but for a query whose WITH queries and subqueries are already executed, except
+    /// for subqueries of unaliased output columns, and whose `queried_tables` are already looked up.
+    async fn plan_resolved_select(
+        &self,
+        select: SelectStatement,
+        queried_tables: Vec<QueriedTable>,
+        common_tables: &[CommonTable],
+        enclosing_tables: &[EnclosingTable],
+    ) -> Result<(plan::PlanNode, Vec<OutputColumn>), ExecutorError> {
+        let join_kinds_and_conditions: Vec<(JoinKind, Expression)> = select
+            .joins
+            .into_iter()
+            .map(|join| (join.kind, join.condition))
+            .collect();
+        let table = get_joined_table_definition(&queried_tables);
+        let mut join_conditions = Vec::with_capacity(join_kinds_and_conditions.len());
+        for (join_index, (kind, condition)) in join_kinds_and_conditions.into_iter().enumerate() {
+            let condition =
+                resolve_column_references(&condition, &queried_tables, enclosing_tables)?
+                    .coerce_literals(&table)?;
+            // A join condition can only refer to tables up to the one being joined
+            let preceding_tables = &queried_tables[..join_index + 2];
+            if let Some(qualifier) = plan::get_referenced_qualifiers(&condition)
+                .into_iter()
+                .find(|qualifier| {
+                    !preceding_tables
+                        .iter()
+                        .any(|queried_table| queried_table.qualifier == *qualifier)
+                })
+            {
+                return Err(ValidationError(format!(
+                    "A join condition refers to table `{}`, which is only joined later",
+                    qualifier
+                ))
+                .into());
+            }
+            condition.validate_condition(&table)?;
+            join_conditions.push((kind, condition));
+        }
+        let (output_expressions, output_column_names, aliases) =
+            expand_select_columns(&table, select.columns);
+        let mut resolved_output_expressions = Vec::with_capacity(output_expressions.len());
+        for expression in &output_expressions {
+            let expression = self
+                .resolve_subqueries(
+                    expression,
+                    common_tables,
+                    &EnclosingTable::extend(enclosing_tables, &queried_tables),
+                )
+                .await?;
+            resolved_output_expressions.push(resolve_column_references(
+                &expression,
+                &queried_tables,
+                enclosing_tables,
+            )?);
+        }
+        let mut output_expressions = resolved_output_expressions;
+        let aliases = aliases
+            .into_iter()
+            .map(|(alias, expression)| {
+                Ok((
+                    alias,
+                    resolve_column_references(&expression, &queried_tables, enclosing_tables)?,
+                ))
+            })
+            .collect::<Result<Vec<(String, Expression)>, ValidationError>>()?;
+        // Aliases can be referenced in WHERE, where table columns take precedence over them,
+        // and in ORDER BY, where it's the other way around
+        let where_clause = select
+            .where_clause
+            .map(|where_clause| {
+                let where_clause =
+                    resolve_column_references(&where_clause, &queried_tables, enclosing_tables)?;
+                resolve_aliases(&where_clause, &aliases, Some(&table)).coerce_literals(&table)
+            })
+            .transpose()?;
+        if let Some(where_clause) = &where_clause {
+            where_clause.validate_condition(&table)?;
+        }
+        let mut order_by = select.order_by;
+        for item in &mut order_by {
+            item.expression = resolve_column_references(
+                &resolve_aliases(&item.expression, &aliases, None),
+                &queried_tables,
+                enclosing_tables,
+            )?;
+        }
+        let group_by = select
+            .group_by
+            .iter()
+            .map(|expression| {
+                resolve_column_references(expression, &queried_tables, enclosing_tables)
+            })
+            .collect::<Result<Vec<Expression>, ValidationError>>()?;
+        let mut having = select
+            .having
+            .map(|having| resolve_column_references(&having, &queried_tables, enclosing_tables))
+            .transpose()?;
+        let is_aggregating = !group_by.is_empty()
+            || having.is_some()
+            || output_expressions
+                .iter()
+                .chain(order_by.iter().map(|item| &item.expression))
+                .any(Expression::contains_aggregate);
+        let aggregation = if is_aggregating {
+            let aggregation = aggregate::Aggregation::plan(
+                &table,
+                &group_by,
+                &output_expressions
+                    .iter()
+                    .chain(having.iter())
+                    .chain(order_by.iter().map(|item| &item.expression))
+                    .collect::<Vec<&Expression>>(),
+            )?;
+            for expression in output_expressions
+                .iter_mut()
+                .chain(having.iter_mut())
+                .chain(order_by.iter_mut().map(|item| &mut item.expression))
+            {
+                *expression = aggregation.rewrite(expression, &table)?;
+            }
+            Some(aggregation)
+        } else {
+            None
+        };
+        // Everything past WHERE is evaluated against aggregated rows, if there's aggregation
+        let row_table = aggregation
+            .as_ref()
+            .map_or(&table, |aggregation| &aggregation.table);
+        for expression in output_expressions
+            .iter_mut()
+            .chain(having.iter_mut())
+            .chain(order_by.iter_mut().map(|item| &mut item.expression))
+        {
+            *expression = expression.coerce_literals(row_table)?;
+        }
+        let mut output_columns = Vec::with_capacity(output_expressions.len());
+        for expression in &output_expressions {
+            output_columns.push(OutputColumn {
+                data_type: expression.infer_type(row_table)?,
+                metric_key: matches!(
+                    expression,
+                    Expression::Atom(DataDefinition::Identifier(column_name))
+                        if row_table.get_column(column_name).is_some_and(|column| column.metric_key)
+                ),
+            });
+        }
+        if let Some(having) = &having {
+            having.validate_condition(row_table)?;
+        }
+        for item in &order_by {
+            item.expression.infer_type(row_table)?;
+        }
+        let is_joined = queried_tables.len() > 1;
+        let mut scans = Vec::with_capacity(queried_tables.len());
+        for queried_table in queried_tables {
+            let qualifier = is_joined.then_some(queried_table.qualifier);
+            let table = queried_table.table;
+            scans.push(match queried_table.origin {
+                RowOrigin::Stored(schema) => plan::LogicalPlan::Scan(Box::new(plan::TableSource {
+                    indexes: self.get_indexes(&schema, &table.name),
+                    row_count: read::read_row_count(&self.config, &schema, &table).await?,
+                    statistics: self.get_statistics(&schema, &table.name),
+                    qualifier,
+                    schema,
+                    table,
+                })),
+                RowOrigin::Computed(rows) => {
+                    plan::LogicalPlan::CommonTableScan(Box::new(plan::CommonTableSource {
+                        table,
+                        rows,
+                        qualifier,
+                    }))
+                }
+            });
+        }
+        let mut scans = scans.into_iter();
+        let mut logical_plan = scans.next().expect("there's always the FROM table");
+        for ((kind, condition), right) in join_conditions.into_iter().zip(scans) {
+            logical_plan = plan::LogicalPlan::Join {
+                left: Box::new(logical_plan),
+                right: Box::new(right),
+                kind,
+                condition,
+            };
+        }
+        if let Some(condition) = where_clause {
+            logical_plan = plan::LogicalPlan::Filter {
+                input: Box::new(logical_plan),
+                condition,
+            };
+        }
+        if let Some(aggregation) = aggregation {
+            logical_plan = plan::LogicalPlan::Aggregate {
+                input: Box::new(logical_plan),
+                aggregation: Box::new(aggregation),
+            };
+            if let Some(condition) = having {
+                logical_plan = plan::LogicalPlan::Filter {
+                    input: Box::new(logical_plan),
+                    condition,
+                };
+            }
+        }
+        if !order_by.is_empty() {
+            logical_plan = plan::LogicalPlan::Sort {
+                input: Box::new(logical_plan),
+                order_by,
+            };
+        }
+        let offset = select.offset.unwrap_or(0) as usize;
+        let limit = select.limit.map(|limit| limit as usize);
+        if offset > 0 || limit.is_some() {
+            logical_plan = plan::LogicalPlan::Limit {
+                input: Box::new(logical_plan),
+                offset,
+                limit,
+            };
+        }
+        logical_plan = plan::LogicalPlan::Project {
+            input: Box::new(logical_plan),
+            expressions: output_expressions,
+            column_names: output_column_names,
+        };
+        Ok((
+            plan::Planner::new(&self.config).plan(logical_plan.push_down_conditions()),
+            output_columns,
+        ))
+    }
+
+    async fn execute_select(&self, select: SelectStatement) -> ExecutorResult {
+        let (mut plan, _) = self.plan_select(select, &[], &[]).await?;
+        let rows = plan.execute(&self.config).await?;
+        Ok(QueryResult {
+            column_names: plan.column_names,
+            rows,
+        })
+    }
+
+    /// Describe the plan of the query, as rows of plan nodes. With ANALYZE the query is executed too,
+    /// so that actual row counts can be compared with estimated ones.
+    async fn execute_explain(&self, explain: ExplainStatement) -> ExecutorResult {
+        let (mut plan, _) = self.plan_select(explain.select, &[], &[]).await?;
+        let mut column_names = vec!["plan".to_string(), "estimated_rows".to_string()];
+        if explain.analyze {
+            plan.execute(&self.config).await?;
+            column_names.push("actual_rows".to_string());
+        }
+        let rows = plan
+            .flatten()
+            .into_iter()
+            .map(|(depth, node)| {
+                let text = match depth {
+                    0 => node.description.clone(),
+                    _ => format!("{}-> {}", "   ".repeat(depth - 1), node.description),
+                };
+                let mut values = vec![
+                    DataInstance::Direct(DataInstanceRaw::String(text)),
+                    DataInstance::Direct(DataInstanceRaw::UInt64(
+                        node.estimated_rows.round() as u64
+                    )),
+                ];
+                if explain.analyze {
+                    values.push(DataInstance::Direct(DataInstanceRaw::UInt64(
+                        node.actual_rows.unwrap_or(0) as u64,
+                    )));
+                }
+                Row(values)
+            })
+            .collect();
+        Ok(QueryResult { column_names, rows })
+    }
+
+    /// Gather statistics of the table, or of all user tables if none is named, for the planner to use.
+    async fn execute_analyze(&self, analyze: AnalyzeStatement) -> ExecutorResult {
+        let entries = match &analyze.table_name {
+            Some(table_name) => {
+                let schema = resolve_schema_name(&analyze.schema_name);
+                ensure_schema_is_writable(schema)?;
+                vec![self.get_catalog_entry(schema, table_name)?]
+            }
+            None => self.tables.lock().clone(),
+        };
+        for mut entry in entries {
+            let statistics =
+                statistics::analyze_table(&self.config, &entry.schema_name, &entry.table).await?;
+            self.discard_statistics(&mut entry).await?;
+            entry.statistic_ids = self.register_statistics(entry.id, &statistics).await?;
+            entry.statistics = Some(Arc::new(statistics));
+            self.replace_catalog_entry(entry);
+        }
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_update(&self, mut update: UpdateStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&update.schema_name);
+        let table = self.get_writable_table_definition(schema, &update.table_name)?;
+        let enclosing_tables = [EnclosingTable {
+            qualifier: table.name.clone(),
+            table: table.clone(),
+        }];
+        for expression in update
+            .assignments
+            .iter_mut()
+            .map(|assignment| &mut assignment.value)
+            .chain(&mut update.where_clause)
+        {
+            *expression = self
+                .resolve_subqueries(expression, &[], &enclosing_tables)
+                .await?;
+        }
+        let assignments = validate_assignments(&table, &update.assignments, &table)?;
+        let returning = Returning::plan(&table, update.returning)?;
+        let where_clause = update
+            .where_clause
+            .map(|where_clause| where_clause.coerce_literals(&table))
+            .transpose()?;
+        if let Some(where_clause) = &where_clause {
+            where_clause.validate_condition(&table)?;
+        }
+        let column_names = table.get_column_names();
+        let indexes = self.get_indexes(schema, &table.name);
+        let access_path = indexes::plan_access_path(&table, &indexes, where_clause.as_ref());
+        // All new versions of rows are computed and checked before any is written
+        let mut changes = Vec::new();
+        for row in indexes::read_rows(&self.config, schema, &table, &access_path).await? {
+            let context = RowContext::new(&column_names, &row.0);
+            if let Some(where_clause) = &where_clause {
+                if !where_clause.is_satisfied_by(&context)? {
+                    continue;
+                }
+            }
+            let mut new_values = row.0.clone();
+            for (column_index, value) in &assignments {
+                new_values[*column_index] = value
+                    .evaluate(&context)?
+                    .conform(&table.columns[*column_index].data_type)?;
+            }
+            changes.push((row, Row(new_values)));
+        }
+        let checks = Checks::plan(&table)?;
+        for (_, new_row) in &changes {
+            checks.enforce(&table, new_row)?;
+        }
+        let index_changes: Vec<(Option<&Row>, Option<&Row>)> = changes
+            .iter()
+            .map(|(old_row, new_row)| (Some(old_row), Some(new_row)))
+            .collect();
+        indexes::check_unique_indexes(&self.config, schema, &table, &indexes, &index_changes)
+            .await?;
+        write::b_tree_rewrite_rows(&self.config, schema, &table, changes.clone()).await?;
+        self.update_indexes(schema, &table, &indexes, &index_changes)
+            .await?;
+        Returning::get_result(
+            returning,
+            &table,
+            changes.iter().map(|(_, new_row)| new_row),
+        )
+    }
+
+    async fn execute_delete(&self, mut delete: DeleteStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&delete.schema_name);
+        let table = self.get_writable_table_definition(schema, &delete.table_name)?;
+        if let Some(where_clause) = &mut delete.where_clause {
+            let enclosing_tables = [EnclosingTable {
+                qualifier: table.name.clone(),
+                table: table.clone(),
+            }];
+            *where_clause = self
+                .resolve_subqueries(where_clause, &[], &enclosing_tables)
+                .await?;
+        }
+        let where_clause = delete
+            .where_clause
+            .map(|where_clause| where_clause.coerce_literals(&table))
+            .transpose()?;
+        if let Some(where_clause) = &where_clause {
+            where_clause.validate_condition(&table)?;
+        }
+        let returning = Returning::plan(&table, delete.returning)?;
+        let column_names = table.get_column_names();
+        let key_indexes = table.get_key_indexes();
+        // First find the rows to delete, and only then delete them one by one, as the tree changes shape along the way
+        let indexes = self.get_indexes(schema, &table.name);
+        let access_path = indexes::plan_access_path(&table, &indexes, where_clause.as_ref());
+        let mut primary_keys_to_delete = Vec::new();
+        for row in indexes::read_rows(&self.config, schema, &table, &access_path).await? {
+            if let Some(where_clause) = &where_clause {
+                if !where_clause.is_satisfied_by(&RowContext::new(&column_names, &row.0))? {
+                    continue;
+                }
+            }
+            primary_keys_to_delete.push(read::get_row_key(&row, &key_indexes));
+        }
+        let mut deleted_rows = Vec::new();
+        for primary_key in primary_keys_to_delete {
+            if let Some(deleted_row) =
+                write::b_tree_delete(&self.config, schema, &table, &primary_key).await?
+            {
+                deleted_rows.push(deleted_row);
+            }
+        }
+        let changes: Vec<(Option<&Row>, Option<&Row>)> = deleted_rows
+            .iter()
+            .map(|deleted_row| (Some(deleted_row), None))
+            .collect();
+        self.update_indexes(schema, &table, &indexes, &changes)
+            .await?;
+        Returning::get_result(returning, &table, deleted_rows.iter())
+    }
+
+    async fn execute_truncate(&self, truncate: TruncateStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&truncate.schema_name);
+        ensure_schema_is_writable(schema)?;
+        let mut entry = self.get_catalog_entry(schema, &truncate.table_name)?;
+        let table = entry.table.clone();
+        write_table_file(
+            &self.config,
+            schema,
+            &table.name,
+            TABLE_DATA_FILE_NAME,
+            construct_blank_table(),
+        )
+        .await?;
+        if table.get_metric_key().is_some() {
+            Index::new(schema, &table.name, &self.config).clear();
+        }
+        let indexes = self.get_indexes(schema, &table.name);
+        indexes::clear_indexes(&self.config, schema, &table, &indexes).await?;
+        // Statistics describe rows that are gone now, so plans mustn't be based on them anymore
+        self.discard_statistics(&mut entry).await?;
+        self.replace_catalog_entry(entry);
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_drop_table(&self, drop_table: DropTableStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&drop_table.schema_name);
+        ensure_schema_is_writable(schema)?;
+        let entry = match self.get_catalog_entry(schema, &drop_table.table_name) {
+            Ok(entry) => entry,
+            Err(_) if drop_table.if_exists => return Ok(QueryResult::empty()),
+            Err(error) => return Err(error.into()),
+        };
+        remove_table_dir(&self.config, schema, &entry.table.name).await?;
+        self.unregister_table(&entry).await?;
+        self.tables
+            .lock()
+            .retain(|other_entry| other_entry.id != entry.id);
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_alter_table(&self, alter_table: AlterTableStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&alter_table.schema_name);
+        ensure_schema_is_writable(schema)?;
+        let entry = self.get_catalog_entry(schema, &alter_table.table_name)?;
+        let are_columns_changed = !matches!(alter_table.action, AlterTableAction::RenameTo(_));
+        let mut entry = match alter_table.action {
+            AlterTableAction::AddColumn(column) => self.add_column(entry, column).await?,
+            AlterTableAction::DropColumn(column_name) => {
+                self.drop_column(entry, &column_name).await?
+            }
+            AlterTableAction::RenameColumn {
+                column_name,
+                new_column_name,
+            } => {
+                self.rename_column(entry, &column_name, new_column_name)
+                    .await?
+            }
+            AlterTableAction::RenameTo(new_table_name) => {
+                self.rename_table(entry, new_table_name).await?
+            }
+        };
+        if are_columns_changed {
+            self.discard_statistics(&mut entry).await?;
+        }
+        self.replace_catalog_entry(entry);
+        Ok(QueryResult::empty())
+    }
+
+    /// Add the column to the table, rewriting all existing rows with the column's DEFAULT value (or NULL).
+    async fn add_column(
+        &self,
+        mut entry: CatalogEntry,
+        column: ColumnDefinition,
+    ) -> Result<CatalogEntry, ExecutorError> {
+        let table = &entry.table;
+        if column.primary_key || column.metric_key {
+            return Err(ValidationError(format!(
+                "Column `{}` cannot be added as a PRIMARY KEY or METRIC KEY of existing table `{}`",
+                column.name, table.name
+            ))
+            .into());
+        }
+        let mut new_table = table.clone();
+        new_table.columns.push(column.clone());
+        new_table.validate()?;
+        let default = column.default.clone().map(Expression::Atom);
+        if let Some(default) = &default {
+            // The DEFAULT may refer to other columns of the row being filled
+            default
+                .validate_assignable_to(&column.data_type, table)
+                .map_err(|error| {
+                    ValidationError(format!("Problem at column `{}`: {}", column.name, error.0))
+                })?;
+        }
+        let column_names = table.get_column_names();
+        let rows = read::read_all_rows(&self.config, &entry.schema_name, table).await?;
+        if default.is_none() && !column.data_type.is_nullable && !rows.is_empty() {
+            return Err(ValidationError(format!(
+                "Column `{}` is not nullable and has no DEFAULT, so it cannot be filled in existing rows of table `{}`",
+                column.name, table.name
+            ))
+            .into());
+        }
+        let mut new_rows = Vec::with_capacity(rows.len());
+        for Row(mut values) in rows {
+            let value = match &default {
+                Some(default) => default
+                    .evaluate(&RowContext::new(&column_names, &values))?
+                    .conform(&column.data_type)?,
+                None => DataInstance::Null,
+            };
+            values.push(value);
+            new_rows.push(Row(values));
+        }
+        write::b_tree_rebuild(&self.config, &entry.schema_name, &new_table, new_rows).await?;
+        entry.column_ids.push(
+            self.register_column(entry.id, &column, table.columns.len())
+                .await?,
+        );
+        entry.table = new_table;
+        Ok(entry)
+    }
+
+    /// Drop the column from the table, rewriting all existing rows without it.
+    async fn drop_column(
+        &self,
+        mut entry: CatalogEntry,
+        column_name: &str,
+    ) -> Result<CatalogEntry, ExecutorError> {
+        let table = &entry.table;
+        let column_index = table.get_column_index(column_name).ok_or_else(|| {
+            ValidationError(format!(
+                "Column `{}` does not exist in table `{}`",
+                column_name, table.name
+            ))
+        })?;
+        let column = &table.columns[column_index];
+        if table.is_key_column(column_name) {
+            return Err(ValidationError(format!(
+                "Column `{}` is {} PRIMARY KEY of table `{}`, so it cannot be dropped",
+                column.name,
+                describe_key_membership(table),
+                table.name
+            ))
+            .into());
+        }
+        if let Some(constraint) = table.constraints.iter().find(|constraint| {
+            constraint
+                .get_column_names()
+                .iter()
+                .any(|name| name == column_name)
+        }) {
+            return Err(ValidationError(format!(
+                "Column `{}` is used by constraint `{}` of table `{}`, so it cannot be dropped",
+                column.name, constraint.name, table.name
+            ))
+            .into());
+        }
+        let mut new_table = table.clone();
+        new_table.columns.remove(column_index);
+        let mut rows = read::read_all_rows(&self.config, &entry.schema_name, table).await?;
+        for Row(values) in &mut rows {
+            values.remove(column_index);
+        }
+        write::b_tree_rebuild(&self.config, &entry.schema_name, &new_table, rows).await?;
+        if column.metric_key {
+            Index::new(&entry.schema_name, &table.name, &self.config).destroy()?;
+        }
+        let column_id = entry.column_ids.remove(column_index);
+        self.delete_system_record(SystemTable::Columns, column_id)
+            .await?;
+        // Columns that followed the dropped one move up
+        for (position, column_id) in entry.column_ids.iter().enumerate().skip(column_index) {
+            self.update_system_record(
+                SystemTable::Columns,
+                *column_id,
+                "position",
+                DataInstance::Direct(DataInstanceRaw::UInt16(position as u16)),
+            )
+            .await?;
+        }
+        // Indexes covering the column go away with it
+        let mut index_position = 0;
+        while index_position < entry.indexes.len() {
+            let index = &entry.indexes[index_position];
+            if !index.column_names.iter().any(|name| name == column_name) {
+                index_position += 1;
+                continue;
+            }
+            remove_table_file(
+                &self.config,
+                &entry.schema_name,
+                &table.name,
+                &index.get_file_name(),
+            )
+            .await?;
+            entry.indexes.remove(index_position);
+            let index_id = entry.index_ids.remove(index_position);
+            self.delete_system_record(SystemTable::Indexes, index_id)
+                .await?;
+        }
+        entry.table = new_table;
+        Ok(entry)
+    }
+
+    /// Rename the column. Rows don't contain column names, so this is purely a catalog change.
+    async fn rename_column(
+        &self,
+        mut entry: CatalogEntry,
+        column_name: &str,
+        new_column_name: String,
+    ) -> Result<CatalogEntry, ExecutorError> {
+        let table = &entry.table;
+        let column_index = table.get_column_index(column_name).ok_or_else(|| {
+            ValidationError(format!(
+                "Column `{}` does not exist in table `{}`",
+                column_name, table.name
+            ))
+        })?;
+        if table.get_column(&new_column_name).is_some() {
+            return Err(ValidationError(format!(
+                "Column `{}` already exists in table `{}`",
+                new_column_name, table.name
+            ))
+            .into());
+        }
+        self.update_system_record(
+            SystemTable::Columns,
+            entry.column_ids[column_index],
+            "column_name",
+            DataInstance::Direct(DataInstanceRaw::String(new_column_name.clone())),
+        )
+        .await?;
+        for (index, index_id) in entry.indexes.iter_mut().zip(&entry.index_ids) {
+            if !index.column_names.iter().any(|name| name == column_name) {
+                continue;
+            }
+            for indexed_column_name in &mut index.column_names {
+                if indexed_column_name == column_name {
+                    *indexed_column_name = new_column_name.clone();
+                }
+            }
+            self.update_system_record(
+                SystemTable::Indexes,
+                *index_id,
+                "column_names",
+                DataInstance::Direct(DataInstanceRaw::String(quote_identifiers(
+                    &index.column_names,
+                ))),
+            )
+            .await?;
+        }
+        for (constraint, constraint_id) in entry
+            .table
+            .constraints
+            .iter_mut()
+            .zip(&entry.constraint_ids)
+        {
+            if !constraint
+                .get_column_names()
+                .iter()
+                .any(|name| name == column_name)
+            {
+                continue;
+            }
+            *constraint = constraint.rename_column(column_name, &new_column_name);
+            self.update_system_record(
+                SystemTable::Constraints,
+                *constraint_id,
+                "definition",
+                DataInstance::Direct(DataInstanceRaw::String(constraint.to_string())),
+            )
+            .await?;
+        }
+        entry.table.columns[column_index].name = new_column_name;
+        Ok(entry)
+    }
+
+    /// Rename the table, which moves its directory.
+    async fn rename_table(
+        &self,
+        mut entry: CatalogEntry,
+        new_table_name: String,
+    ) -> Result<CatalogEntry, ExecutorError> {
+        // The new name becomes the name of the table's directory
+        validate_table_name(&new_table_name)?;
+        if self
+            .get_catalog_entry(&entry.schema_name, &new_table_name)
+            .is_ok()
+        {
+            return Err(ValidationError(format!(
+                "Table `{}.{}` already exists",
+                entry.schema_name, new_table_name
+            ))
+            .into());
+        }
+        rename_table_dir(
+            &self.config,
+            &entry.schema_name,
+            &entry.table.name,
+            &new_table_name,
+        )
+        .await?;
+        self.update_system_record(
+            SystemTable::Tables,
+            entry.id,
+            "table_name",
+            DataInstance::Direct(DataInstanceRaw::String(new_table_name.clone())),
+        )
+        .await?;
+        entry.table.name = new_table_name;
+        Ok(entry)
+    }
+
+    /// Find the catalog entry of the table that has the index with the given name, if any.
+    fn find_index_owner(&self, schema_name: &str, index_name: &str) -> Option<CatalogEntry> {
+        self.tables
+            .lock()
+            .iter()
+            .find(|entry| {
+                entry.schema_name == schema_name
+                    && entry.indexes.iter().any(|index| index.name == index_name)
+            })
+            .cloned()
+    }
+
+    async fn execute_create_index(&self, create_index: CreateIndexStatement) -> ExecutorResult {
+        let CreateIndexStatement {
+            schema_name,
+            table_name,
+            index,
+            if_not_exists,
+        } = create_index;
+        let schema = resolve_schema_name(&schema_name);
+        ensure_schema_is_writable(schema)?;
+        let mut entry = self.get_catalog_entry(schema, &table_name)?;
+        // Index names are unique within a database, like table names
+        if self.find_index_owner(schema, &index.name).is_some() {
+            if if_not_exists {
+                return Ok(QueryResult::empty());
+            }
+            return Err(ValidationError(format!(
+                "Index `{}.{}` already exists",
+                schema, index.name
+            ))
+            .into());
+        }
+        for column_name in &index.column_names {
+            if entry.table.get_column(column_name).is_none() {
+                return Err(ValidationError(format!(
+                    "Column `{}` does not exist in table `{}`",
+                    column_name, entry.table.name
+                ))
+                .into());
+            }
+        }
+        let rows = read::read_all_rows(&self.config, schema, &entry.table).await?;
+        if let Err(error) =
+            indexes::build_index(&self.config, schema, &entry.table, &index, &rows).await
+        {
+            // Don't leave a partially built index behind
+            if does_table_file_exist(&self.config, schema, &table_name, &index.get_file_name())
+                .await
+            {
+                remove_table_file(&self.config, schema, &table_name, &index.get_file_name())
+                    .await?;
+            }
+            return Err(error);
+        }
+        entry
+            .index_ids
+            .push(self.register_index(entry.id, &index).await?);
+        entry.indexes.push(index);
+        self.replace_catalog_entry(entry);
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_drop_index(&self, drop_index: DropIndexStatement) -> ExecutorResult {
+        let schema = resolve_schema_name(&drop_index.schema_name);
+        ensure_schema_is_writable(schema)?;
+        let mut entry = match self.find_index_owner(schema, &drop_index.index_name) {
+            Some(entry) => entry,
+            None if drop_index.if_exists => return Ok(QueryResult::empty()),
+            None => {
+                return Err(ValidationError(format!(
+                    "Index `{}.{}` does not exist",
+                    schema, drop_index.index_name
+                ))
+                .into())
+            }
+        };
+        if entry
+            .table
+            .constraints
+            .iter()
+            .any(|constraint| constraint.name == drop_index.index_name)
+        {
+            return Err(ValidationError(format!(
+                "Index `{}.{}` enforces a constraint of table `{}`, so it cannot be dropped",
+                schema, drop_index.index_name, entry.table.name
+            ))
+            .into());
+        }
+        let index_position = entry
+            .indexes
+            .iter()
+            .position(|index| index.name == drop_index.index_name)
+            .expect("The index belongs to the table");
+        let index = entry.indexes.remove(index_position);
+        let index_id = entry.index_ids.remove(index_position);
+        remove_table_file(
+            &self.config,
+            schema,
+            &entry.table.name,
+            &index.get_file_name(),
+        )
+        .await?;
+        self.delete_system_record(SystemTable::Indexes, index_id)
+            .await?;
+        self.replace_catalog_entry(entry);
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_create_database(
+        &self,
+        create_database: CreateDatabaseStatement,
+    ) -> ExecutorResult {
+        let schema = create_database.database_name.as_str();
+        ensure_schema_is_writable(schema)?;
+        if does_schema_dir_exist(&self.config, schema).await {
+            if create_database.if_not_exists {
+                return Ok(QueryResult::empty());
+            }
+            return Err(ValidationError(format!("Database `{}` already exists", schema)).into());
+        }
+        create_schema_dir(&self.config, schema).await?;
+        Ok(QueryResult::empty())
+    }
+
+    async fn execute_drop_database(&self, drop_database: DropDatabaseStatement) -> ExecutorResult {
+        let schema = drop_database.database_name.as_str();
+        ensure_schema_is_writable(schema)?;
+        if schema == DEFAULT_SCHEMA_NAME {
+            return Err(ValidationError(format!(
+                "Database `{}` is the default one, so it cannot be dropped",
+                schema
+            ))
+            .into());
+        }
+        if !does_schema_dir_exist(&self.config, schema).await {
+            if drop_database.if_exists {
+                return Ok(QueryResult::empty());
+            }
+            return Err(ValidationError(format!("Database `{}` does not exist", schema)).into());
+        }
+        let entries_dropped: Vec<CatalogEntry> = self
+            .tables
+            .lock()
+            .iter()
+            .filter(|entry| entry.schema_name == schema)
+            .cloned()
+            .collect();
+        for entry in &entries_dropped {
+            self.unregister_table(entry).await?;
+        }
+        self.tables
+            .lock()
+            .retain(|entry| entry.schema_name != schema);
+        remove_schema_dir(&self.config, schema).await?;
+        Ok(QueryResult::empty())
+    }
+}
+
+#[cfg(test)]
+mod executor_tests {
+    use super::*;
+    use crate::sql::parse_statements;
+    use pretty_assertions::assert_eq;
+    use rand::distributions::Alphanumeric;
+    use rand::{thread_rng, Rng};
+    use std::ops::Bound;
+    use time::OffsetDateTime;
+
+    async fn get_test_executor() -> Executor {
+        let data_directory_name: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+        let mut executor = Executor::new(&config::Config {
+            data_directory: format!("{}/{}", env!("TMPDIR"), data_directory_name),
+            ..Default::default()
+        });
+        executor.bootstrap().await.unwrap();
+        executor
+    }
+
+    /// The only statement of the input.
+    fn parse_single_statement(input: &str) -> Statement {
+        let mut statements = parse_statements(input).unwrap();
+        assert_eq!(statements.len(), 1, "Expected a single statement");
+        statements.remove(0)
+    }
+
+    async fn execute_sql(executor: &Executor, input: &str) -> ExecutorResult {
+        executor.execute(parse_single_statement(input)).await
+    }
+
+    #[tokio::test]
+    async fn update_rewrites_matching_rows() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width UINT32, hash UINT64 METRIC KEY)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width, hash) VALUES ('a.png', 1280, 11)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width, hash) VALUES ('b.png', 800, 12)",
+        )
+        .await
+        .unwrap();
+
+        let update_result = execute_sql(
+            &executor,
+            "UPDATE photos_seen SET width = 1920, hash = 15 WHERE url = 'b.png'",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            update_result.rows,
+            vec![Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(1))])]
+        );
+
+        let select_result = execute_sql(&executor, "SELECT url, width, hash FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            select_result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1280)),
+                    DataInstance::Direct(DataInstanceRaw::UInt64(11)),
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1920)),
+                    DataInstance::Direct(DataInstanceRaw::UInt64(15)),
+                ]),
+            ]
+        );
+        assert_eq!(
+            Index::new(DEFAULT_SCHEMA_NAME, "photos_seen", &executor.config).get_data(),
+            vec![11, 15]
+        );
+    }
+
+    #[tokio::test]
+    async fn update_rejects_primary_key_and_type_mismatch() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width UINT8)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "UPDATE photos_seen SET url = 'c.png'")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `url` is the PRIMARY KEY of table `photos_seen`, so it cannot be updated"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "UPDATE photos_seen SET width = 1280")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Problem at column `width`: Value UInt32(1280) is out of range for type UINT8"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "UPDATE photos_seen SET width = url")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Problem at column `width`: Value of type STRING cannot be assigned to type UINT8"
+                    .into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_matching_rows_and_metric_values() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT64 METRIC KEY)",
+        )
+        .await
+        .unwrap();
+        for (url, hash) in [("a.png", 11), ("b.png", 12), ("c.png", 13)] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, hash) VALUES ('{}', {})",
+                    url, hash
+                ),
+            )
+            .await
+            .unwrap();
+        }
+
+        let delete_result = execute_sql(&executor, "DELETE FROM photos_seen WHERE url = 'b.png'")
+            .await
+            .unwrap();
+        assert_eq!(
+            delete_result.rows,
+            vec![Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(1))])]
+        );
+        let select_result = execute_sql(&executor, "SELECT url FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            select_result.rows,
+            vec![
+                Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                    "a.png".into()
+                ))]),
+                Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                    "c.png".into()
+                ))]),
+            ]
+        );
+        assert_eq!(
+            Index::new(DEFAULT_SCHEMA_NAME, "photos_seen", &executor.config).get_data(),
+            vec![11, 13]
+        );
+
+        let delete_result = execute_sql(&executor, "DELETE FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            delete_result.rows,
+            vec![Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(2))])]
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT url FROM photos_seen")
+                .await
+                .unwrap()
+                .rows,
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_table_removes_it_from_catalog_and_disk() {
+        let executor = get_test_executor().await;
+        execute_sql(&executor, "CREATE DATABASE gaggle")
+            .await
+            .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE gaggle.photos_seen (url STRING PRIMARY KEY, hash UINT64 METRIC KEY)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT schema_name, table_name FROM system.tables"
+            )
+            .await
+            .unwrap()
+            .rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String("gaggle".into())),
+                DataInstance::Direct(DataInstanceRaw::String("photos_seen".into())),
+            ])]
+        );
+
+        execute_sql(&executor, "DROP TABLE gaggle.photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "SELECT * FROM system.tables")
+                .await
+                .unwrap()
+                .rows,
+            vec![]
+        );
+        assert!(!std::path::Path::new(&executor.config.data_directory)
+            .join("gaggle/photos_seen")
+            .exists());
+        assert_eq!(
+            execute_sql(&executor, "SELECT url FROM gaggle.photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Table `gaggle.photos_seen` does not exist".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "DROP TABLE gaggle.photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Table `gaggle.photos_seen` does not exist".into()
+            ))
+        );
+        execute_sql(&executor, "DROP TABLE IF EXISTS gaggle.photos_seen")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn truncate_removes_all_rows() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT64 METRIC KEY)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, hash) VALUES ('a.png', 11)",
+        )
+        .await
+        .unwrap();
+
+        execute_sql(&executor, "ANALYZE photos_seen").await.unwrap();
+        assert!(executor
+            .get_catalog_entry(DEFAULT_SCHEMA_NAME, "photos_seen")
+            .unwrap()
+            .statistics
+            .is_some());
+
+        execute_sql(&executor, "TRUNCATE TABLE photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "SELECT url FROM photos_seen")
+                .await
+                .unwrap()
+                .rows,
+            vec![]
+        );
+        assert_eq!(
+            Index::new(DEFAULT_SCHEMA_NAME, "photos_seen", &executor.config).get_data(),
+            vec![]
+        );
+        // Statistics of the removed rows are gone too
+        let entry = executor
+            .get_catalog_entry(DEFAULT_SCHEMA_NAME, "photos_seen")
+            .unwrap();
+        assert_eq!(entry.statistics, None);
+        assert_eq!(entry.statistic_ids, vec![]);
+        assert_eq!(
+            execute_sql(&executor, "SELECT column_name FROM system.statistics")
+                .await
+                .unwrap()
+                .rows,
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_database_removes_its_tables() {
+        let executor = get_test_executor().await;
+        execute_sql(&executor, "CREATE DATABASE gaggle")
+            .await
+            .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "CREATE DATABASE gaggle")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError("Database `gaggle` already exists".into()))
+        );
+        execute_sql(&executor, "CREATE DATABASE IF NOT EXISTS gaggle")
+            .await
+            .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE gaggle.photos_seen (url STRING PRIMARY KEY)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY)",
+        )
+        .await
+        .unwrap();
+
+        execute_sql(&executor, "DROP DATABASE gaggle")
+            .await
+            .unwrap();
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT schema_name, table_name FROM system.tables"
+            )
+            .await
+            .unwrap()
+            .rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String(DEFAULT_SCHEMA_NAME.into())),
+                DataInstance::Direct(DataInstanceRaw::String("photos_seen".into())),
+            ])]
+        );
+        assert!(!std::path::Path::new(&executor.config.data_directory)
+            .join("gaggle")
+            .exists());
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "CREATE TABLE gaggle.photos_seen (url STRING PRIMARY KEY)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError("Database `gaggle` does not exist".into()))
+        );
+        execute_sql(&executor, "DROP DATABASE IF EXISTS gaggle")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn system_schema_cannot_be_modified() {
+        let executor = get_test_executor().await;
+        let expected_error = ExecutorError::Validation(ValidationError(
+            "Database `system` is managed by Emdrive itself, so it cannot be modified".into(),
+        ));
+        for statement in [
+            "DROP DATABASE system",
+            "CREATE DATABASE system",
+            "DROP TABLE system.tables",
+            "TRUNCATE system.columns",
+            "DELETE FROM system.tables",
+            "CREATE TABLE system.photos_seen (url STRING PRIMARY KEY)",
+        ] {
+            assert_eq!(
+                execute_sql(&executor, statement).await.unwrap_err(),
+                expected_error
+            );
+        }
+        assert_eq!(
+            execute_sql(&executor, "DROP DATABASE public")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Database `public` is the default one, so it cannot be dropped".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn alter_table_rewrites_rows_and_catalog() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width UINT32)",
+        )
+        .await
+        .unwrap();
+        for (url, width) in [("a.png", 1280), ("b.png", 800)] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, width) VALUES ('{}', {})",
+                    url, width
+                ),
+            )
+            .await
+            .unwrap();
+        }
+
+        execute_sql(
+            &executor,
+            "ALTER TABLE photos_seen ADD COLUMN height NULLABLE(UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "ALTER TABLE photos_seen ADD COLUMN seen_count UINT16 DEFAULT 1",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "ALTER TABLE photos_seen ADD COLUMN hash UINT64")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `hash` is not nullable and has no DEFAULT, so it cannot be filled in existing rows of table `photos_seen`".into()
+            ))
+        );
+        execute_sql(&executor, "ALTER TABLE photos_seen DROP COLUMN width")
+            .await
+            .unwrap();
+        execute_sql(
+            &executor,
+            "ALTER TABLE photos_seen RENAME COLUMN seen_count TO times_seen",
+        )
+        .await
+        .unwrap();
+        execute_sql(&executor, "ALTER TABLE photos_seen RENAME TO photos")
+            .await
+            .unwrap();
+        // The table's directory can't be moved out of its database's directory
+        for new_table_name in ["/tmp/photos", "a/b", "a\\b"] {
+            assert_eq!(
+                execute_sql(
+                    &executor,
+                    &format!("ALTER TABLE photos RENAME TO \"{}\"", new_table_name)
+                )
+                .await
+                .unwrap_err(),
+                ExecutorError::Validation(ValidationError(format!(
+                    "Table name `{}` must not contain `.`, `/` or `\\`",
+                    new_table_name
+                )))
+            );
+        }
+
+        let select_result = execute_sql(&executor, "SELECT * FROM photos")
+            .await
+            .unwrap();
+        assert_eq!(
+            select_result.column_names,
+            vec!["url".to_string(), "height".into(), "times_seen".into()]
+        );
+        assert_eq!(
+            select_result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                    DataInstance::Null,
+                    DataInstance::Direct(DataInstanceRaw::UInt16(1)),
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Null,
+                    DataInstance::Direct(DataInstanceRaw::UInt16(1)),
+                ]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT table_name FROM system.tables")
+                .await
+                .unwrap()
+                .rows,
+            vec![Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                "photos".into()
+            ))])]
+        );
+        let mut column_names: Vec<DataInstance> =
+            execute_sql(&executor, "SELECT column_name FROM system.columns")
+                .await
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| row.0[0].clone())
+                .collect();
+        column_names.sort_by(|lhs, rhs| lhs.as_raw().cmp(&rhs.as_raw()));
+        assert_eq!(
+            column_names,
+            ["height", "times_seen", "url"]
+                .iter()
+                .map(|name| DataInstance::Direct(DataInstanceRaw::String(name.to_string())))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            execute_sql(&executor, "ALTER TABLE photos DROP COLUMN url")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `url` is the PRIMARY KEY of table `photos`, so it cannot be dropped".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn select_orders_and_paginates_rows() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width NULLABLE(UINT32))",
+        )
+        .await
+        .unwrap();
+        for (url, width) in [
+            ("a.png", "1280"),
+            ("b.png", "800"),
+            ("c.png", "NULL"),
+            ("d.png", "800"),
+            ("e.png", "640"),
+        ] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, width) VALUES ('{}', {})",
+                    url, width
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let select_urls = |clauses: &'static str| {
+            let executor = &executor;
+            async move {
+                execute_sql(
+                    executor,
+                    &format!("SELECT url FROM photos_seen {}", clauses),
+                )
+                .await
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| match &row.0[0] {
+                    DataInstance::Direct(DataInstanceRaw::String(url)) => url.clone(),
+                    other => panic!("Unexpected value {:?}", other),
+                })
+                .collect::<Vec<String>>()
+            }
+        };
+        assert_eq!(
+            select_urls("ORDER BY url DESC LIMIT 2 OFFSET 1").await,
+            vec!["d.png", "c.png"]
+        );
+        assert_eq!(
+            select_urls("WHERE width > 700 ORDER BY url LIMIT 2").await,
+            vec!["a.png", "b.png"]
+        );
+        assert_eq!(
+            select_urls("ORDER BY width").await,
+            vec!["e.png", "b.png", "d.png", "a.png", "c.png"]
+        );
+        assert_eq!(
+            select_urls("ORDER BY width DESC, url DESC").await,
+            vec!["c.png", "a.png", "d.png", "b.png", "e.png"]
+        );
+        assert_eq!(
+            select_urls("ORDER BY width DESC NULLS LAST LIMIT 3 OFFSET 1").await,
+            vec!["b.png", "d.png", "e.png"]
+        );
+        assert_eq!(
+            select_urls("ORDER BY width NULLS FIRST LIMIT 2").await,
+            vec!["c.png", "e.png"]
+        );
+        assert_eq!(select_urls("LIMIT 0").await, Vec::<String>::new());
+        assert_eq!(select_urls("OFFSET 4").await, vec!["e.png"]);
+        assert!(
+            execute_sql(&executor, "SELECT url FROM photos_seen ORDER BY height")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn select_aggregates_rows_by_groups() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, domain STRING, width NULLABLE(UINT32))",
+        )
+        .await
+        .unwrap();
+        for (url, domain, width) in [
+            ("a.png", "twixes.com", "1280"),
+            ("b.png", "twixes.com", "800"),
+            ("c.png", "example.com", "NULL"),
+            ("d.png", "example.com", "640"),
+            ("e.png", "other.org", "100"),
+        ] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, domain, width) VALUES ('{}', '{}', {})",
+                    url, domain, width
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let count = |count: u64| DataInstance::Direct(DataInstanceRaw::UInt64(count));
+        let width = |width: u32| DataInstance::Nullable(DataInstanceRaw::UInt32(width));
+        let domain = |domain: &str| DataInstance::Direct(DataInstanceRaw::String(domain.into()));
+
+        let result = execute_sql(
+            &executor,
+            "SELECT COUNT(*), COUNT(width), MIN(width), MAX(width), AVG(width) FROM photos_seen",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.column_names,
+            vec!["count", "count_2", "min", "max", "avg"]
+        );
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                count(5),
+                count(4),
+                width(100),
+                width(1280),
+                width(705)
+            ])]
+        );
+
+        let result = execute_sql(
+            &executor,
+            "SELECT COUNT(*), MAX(width) FROM photos_seen WHERE width > 5000",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, vec![Row(vec![count(0), DataInstance::Null])]);
+
+        let result = execute_sql(
+            &executor,
+            "SELECT domain, COUNT(*) FROM photos_seen GROUP BY domain HAVING COUNT(*) > 1 ORDER BY domain",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["domain", "count"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![domain("example.com"), count(2)]),
+                Row(vec![domain("twixes.com"), count(2)]),
+            ]
+        );
+
+        let result = execute_sql(
+            &executor,
+            "SELECT domain FROM photos_seen GROUP BY domain ORDER BY COUNT(*) DESC, MAX(width) LIMIT 2",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![domain("example.com")]),
+                Row(vec![domain("twixes.com")]),
+            ]
+        );
+
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT url, COUNT(*) FROM photos_seen GROUP BY domain"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError("Column `url` must appear in the GROUP BY clause or be used in an aggregate function".into()))
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT url FROM photos_seen WHERE COUNT(*) > 1")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Aggregate function `COUNT` cannot be used here".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT AVG(domain) FROM photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Argument 1 of function `AVG` must be of an unsigned integer type, instead found type STRING"
+                    .into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn select_computes_aliased_expressions() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT64 METRIC KEY, width UINT32)",
+        )
+        .await
+        .unwrap();
+        for (url, hash, width) in [
+            ("a.png", "0b11001111", "1280"),
+            ("b.png", "0b00001010", "800"),
+            ("c.png", "0xFF", "640"),
+        ] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, hash, width) VALUES ('{}', {}, {})",
+                    url, hash, width
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let url = |url: &str| DataInstance::Direct(DataInstanceRaw::String(url.into()));
+        let distance = |distance: u8| DataInstance::Direct(DataInstanceRaw::UInt8(distance));
+
+        let result = execute_sql(
+            &executor,
+            "SELECT url, hash @ 0b00001011 AS distance FROM photos_seen WHERE distance < 4",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["url", "distance"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![url("a.png"), distance(3)]),
+                Row(vec![url("b.png"), distance(1)]),
+            ]
+        );
+
+        let result = execute_sql(
+            &executor,
+            "SELECT url AS width, width > 700, hash @ 0 FROM photos_seen WHERE width > 700 ORDER BY width DESC",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["width", "?column?", "?column?_2"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    url("b.png"),
+                    DataInstance::Direct(DataInstanceRaw::Bool(true)),
+                    distance(2)
+                ]),
+                Row(vec![
+                    url("a.png"),
+                    DataInstance::Direct(DataInstanceRaw::Bool(true)),
+                    distance(6)
+                ]),
+            ]
+        );
+
+        assert_eq!(
+            execute_sql(&executor, "SELECT width @ 1 FROM photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "The `@` distance operator is only supported for METRIC KEY columns".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn select_calls_scalar_functions() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT64, caption NULLABLE(STRING))",
+        )
+        .await
+        .unwrap();
+        for (url, hash, caption) in [
+            ("https://twixes.com/a.png", "0b1011", "'Sunset'"),
+            ("https://example.com/bb.png", "0b11", "NULL"),
+        ] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, hash, caption) VALUES ('{}', {}, {})",
+                    url, hash, caption
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let result = execute_sql(
+            &executor,
+            "SELECT UPPER(SUBSTR(url, 9, 6)), POPCOUNT(BIT_AND(hash, 0b1001)), COALESCE(LOWER(caption), 'none'), \
+            ULID_TIME(ULID()) <= NOW() FROM photos_seen WHERE LENGTH(url) > 25",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.column_names,
+            vec!["upper", "popcount", "coalesce", "?column?"]
+        );
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String("EXAMPL".into())),
+                DataInstance::Direct(DataInstanceRaw::UInt8(1)),
+                DataInstance::Direct(DataInstanceRaw::String("none".into())),
+                DataInstance::Direct(DataInstanceRaw::Bool(true)),
+            ])]
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT DATE_TRUNC('fortnight', NOW()) FROM photos_seen"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "`fortnight` does not refer to a supported time field".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT LENGTH(hash) FROM photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Argument 1 of function `LENGTH` must be of type STRING, instead found type UINT64"
+                    .into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_takes_many_rows_or_a_select() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_archived (url STRING PRIMARY KEY, width UINT16)",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (width, url) VALUES (128000, 'a.png'), (800, 'b.png'), (640, 'c.png')",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, QueryResult::rows_affected(3).rows);
+        let result = execute_sql(
+            &executor,
+            "INSERT INTO photos_archived (url, width) SELECT url, width FROM photos_seen WHERE width < 1000",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, QueryResult::rows_affected(2).rows);
+        let result = execute_sql(&executor, "SELECT * FROM photos_archived")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt16(800)),
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("c.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt16(640)),
+                ]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_archived (url, width) SELECT url, width FROM photos_seen"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Problem at column `width`: Value UInt32(128000) is out of range for type UINT16"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_archived (url, width) SELECT url FROM photos_seen"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "2 columns were specified, but the SELECT returns 1 columns".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, width) VALUES ('d.png', 1), ('e.png')"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "2 columns were specified, but 1 values were provided".into()
+            ))
+        );
+        // Nothing is written if any row is invalid
+        let result = execute_sql(&executor, "SELECT url FROM photos_archived")
+            .await
+            .unwrap();
+        assert_eq!(result.rows.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn insert_fills_omitted_columns_from_defaults() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (id UUID PRIMARY KEY DEFAULT ULID(), url STRING, \
+            seen_at TIMESTAMP DEFAULT NOW(), width NULLABLE(UINT32), source STRING DEFAULT 'crawler')",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url) VALUES ('a.png'), ('b.png')",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (id, url, width, source) VALUES (DEFAULT, 'c.png', DEFAULT, 'user')",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(
+            &executor,
+            "SELECT COUNT(*), COUNT(width), MIN(source), MAX(source), MAX(seen_at) <= NOW() FROM photos_seen",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::UInt64(3)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(0)),
+                DataInstance::Nullable(DataInstanceRaw::String("crawler".into())),
+                DataInstance::Nullable(DataInstanceRaw::String("user".into())),
+                DataInstance::Direct(DataInstanceRaw::Bool(true)),
+            ])]
+        );
+        assert_eq!(
+            execute_sql(&executor, "INSERT INTO photos_seen (id) VALUES (DEFAULT)")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `url` must be provided a value, as it has no DEFAULT and is not nullable"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "INSERT INTO photos_seen (url) VALUES (DEFAULT)")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `url` must be provided a value, as it has no DEFAULT and is not nullable"
+                    .into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn insert_rejects_duplicates_or_resolves_conflicts() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, seen_count UINT32, caption NULLABLE(STRING))",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, seen_count, caption) VALUES ('a.png', 1, 'Sunset'), ('b.png', 1, NULL)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, seen_count) VALUES ('c.png', 1), ('a.png', 1)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value String(\"a.png\") of PRIMARY KEY column `url` already exists in table `photos_seen`"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, seen_count) VALUES ('c.png', 1), ('c.png', 2)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value String(\"c.png\") of PRIMARY KEY column `url` already exists in table `photos_seen`"
+                    .into()
+            ))
+        );
+        let result = execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, seen_count) VALUES ('a.png', 5), ('c.png', 1), ('c.png', 2) \
+            ON CONFLICT (url) DO NOTHING",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, QueryResult::rows_affected(1).rows);
+        let result = execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, seen_count, caption) VALUES ('a.png', 7, NULL), ('b.png', 7, 'Dawn'), \
+            ('d.png', 1, NULL) ON CONFLICT (url) DO UPDATE SET seen_count = excluded.seen_count, \
+            caption = COALESCE(excluded.caption, caption) WHERE url <> 'b.png'",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, QueryResult::rows_affected(2).rows);
+        let result = execute_sql(&executor, "SELECT * FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(7)),
+                    DataInstance::Nullable(DataInstanceRaw::String("Sunset".into())),
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1)),
+                    DataInstance::Null,
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("c.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1)),
+                    DataInstance::Null,
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("d.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1)),
+                    DataInstance::Null,
+                ]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, seen_count) VALUES ('a.png', 1) ON CONFLICT (seen_count) DO NOTHING"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "The ON CONFLICT target must be the PRIMARY KEY of table `photos_seen`, i.e. `(url)`"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, seen_count) VALUES ('e.png', 1), ('e.png', 2) \
+                ON CONFLICT (url) DO UPDATE SET seen_count = excluded.seen_count"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "ON CONFLICT DO UPDATE cannot affect the row with PRIMARY KEY value String(\"e.png\") a second time"
+                    .into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn returning_gives_back_affected_rows() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (id UUID PRIMARY KEY DEFAULT ULID(), url STRING, width UINT32)",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width) VALUES ('a.png', 800), ('b.png', 640) RETURNING id, url",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.column_names,
+            vec!["id".to_string(), "url".to_string()]
+        );
+        assert_eq!(result.rows.len(), 2);
+        let ids: Vec<DataInstance> = result.rows.iter().map(|row| row.0[0].clone()).collect();
+        assert!(matches!(
+            ids[0],
+            DataInstance::Direct(DataInstanceRaw::Uuid(_))
+        ));
+        assert_eq!(
+            result.rows[1].0[1],
+            DataInstance::Direct(DataInstanceRaw::String("b.png".into()))
+        );
+        let result = execute_sql(
+            &executor,
+            "UPDATE photos_seen SET width = 1024 WHERE url = 'a.png' RETURNING url, width AS new_width",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.column_names,
+            vec!["url".to_string(), "new_width".to_string()]
+        );
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                DataInstance::Direct(DataInstanceRaw::UInt32(1024)),
+            ])]
+        );
+        let result = execute_sql(
+            &executor,
+            "DELETE FROM photos_seen WHERE url = 'b.png' RETURNING *",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                ids[1].clone(),
+                DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                DataInstance::Direct(DataInstanceRaw::UInt32(640)),
+            ])]
+        );
+        let result = execute_sql(&executor, "DELETE FROM photos_seen WHERE url = 'c.png'")
+            .await
+            .unwrap();
+        assert_eq!(result.rows, QueryResult::rows_affected(0).rows);
+        assert_eq!(
+            execute_sql(&executor, "DELETE FROM photos_seen RETURNING COUNT(*)")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Aggregate function `COUNT` cannot be used here".into()
+            ))
+        );
+        // The failed statement didn't delete anything
+        let result = execute_sql(&executor, "SELECT url FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                "a.png".into()
+            ))])]
+        );
+    }
+
+    #[tokio::test]
+    async fn casts_and_coerces_literals() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT64, seen_at TIMESTAMP)",
+        )
+        .await
+        .unwrap();
+        for (url, hash, seen_at) in [
+            ("a.png", "300", "'2077-01-01T21:37'"),
+            ("b.png", "'7'", "'2021-03-04 05:06:07Z'"),
+        ] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, hash, seen_at) VALUES ('{}', {}, {})",
+                    url, hash, seen_at
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let result = execute_sql(
+            &executor,
+            "SELECT url, CAST(hash AS STRING), seen_at::STRING, 1::BOOL FROM photos_seen \
+            WHERE seen_at > '2050-01-01' AND hash IN ('300', 7)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["url", "hash", "seen_at", "bool"]);
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                DataInstance::Direct(DataInstanceRaw::String("300".into())),
+                DataInstance::Direct(DataInstanceRaw::String("2077-01-01T21:37:00Z".into())),
+                DataInstance::Direct(DataInstanceRaw::Bool(true)),
+            ])]
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT hash::UINT8 FROM photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Execution(ExecutionError(
+                "Value UInt64(300) is out of range for type UINT8".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "SELECT seen_at::UINT64 FROM photos_seen")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Type TIMESTAMP cannot be cast to type UINT64".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT url FROM photos_seen WHERE seen_at < 'yesterday'"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Value String(\"yesterday\") cannot be parsed as type TIMESTAMP".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn select_filters_with_comparisons_and_logic() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, width NULLABLE(UINT32))",
+        )
+        .await
+        .unwrap();
+        for (url, width) in [("a.png", "1280"), ("b.png", "800"), ("c.png", "NULL")] {
+            execute_sql(
+                &executor,
+                &format!(
+                    "INSERT INTO photos_seen (url, width) VALUES ('{}', {})",
+                    url, width
+                ),
+            )
+            .await
+            .unwrap();
+        }
+        let select_urls = |condition: &'static str| {
+            let executor = &executor;
+            async move {
+                execute_sql(
+                    executor,
+                    &format!("SELECT url FROM photos_seen WHERE {}", condition),
+                )
+                .await
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| match &row.0[0] {
+                    DataInstance::Direct(DataInstanceRaw::String(url)) => url.clone(),
+                    other => panic!("Unexpected value {:?}", other),
+                })
+                .collect::<Vec<String>>()
+            }
+        };
+        assert_eq!(
+            select_urls("width >= 800 AND width < 1000").await,
+            vec!["b.png"]
+        );
+        assert_eq!(
+            select_urls("width<>800 OR url='b.png'").await,
+            vec!["a.png", "b.png"]
+        );
+        assert_eq!(select_urls("NOT width > 1000").await, vec!["b.png"]);
+        assert_eq!(
+            // For c.png, NULL AND FALSE is FALSE, so the negation is TRUE
+            select_urls("NOT (width > 1000 AND url = 'a.png')").await,
+            vec!["b.png", "c.png"]
+        );
+        assert_eq!(
+            select_urls("url = 'c.png' OR width = 1").await,
+            vec!["c.png"]
+        );
+        assert_eq!(select_urls("width IS NULL").await, vec!["c.png"]);
+        assert_eq!(
+            select_urls("width IS NOT NULL AND width BETWEEN 800 AND 1000").await,
+            vec!["b.png"]
+        );
+        assert_eq!(select_urls("width NOT IN (800, 1920)").await, vec!["a.png"]);
+        assert_eq!(
+            select_urls("url LIKE '_.png' AND url NOT LIKE 'a%'").await,
+            vec!["b.png", "c.png"]
+        );
+    }
+
+    /// WHERE clause of a SELECT from `table`, ready to be planned.
+    fn parse_where(table: &TableDefinition, condition: &str) -> Expression {
+        match parse_single_statement(&format!("SELECT * FROM {} WHERE {}", table.name, condition)) {
+            Statement::Select(select) => {
+                select.where_clause.unwrap().coerce_literals(table).unwrap()
+            }
+            other => panic!("Unexpected statement: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn indexes_find_rows_and_enforce_uniqueness() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (id UUID PRIMARY KEY DEFAULT ULID(), url NULLABLE(STRING), width UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width) VALUES ('a.png', 800), ('b.png', 640), (NULL, 640)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE UNIQUE INDEX photos_by_url ON photos_seen (url)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE INDEX photos_by_width ON photos_seen (width)",
+        )
+        .await
+        .unwrap();
+        assert!(
+            does_table_file_exist(
+                &executor.config,
+                "public",
+                "photos_seen",
+                "photos_by_url.index"
+            )
+            .await
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "CREATE INDEX photos_by_url ON photos_seen (width)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Index `public.photos_by_url` already exists".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "CREATE UNIQUE INDEX photos_by_unique_width ON photos_seen (width)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value [UInt32(640)] of UNIQUE index `photos_by_unique_width` already exists in table `photos_seen`".into()
+            ))
+        );
+        // The executor picks the index for equality and range predicates on its columns
+        let table = executor
+            .get_table_definition("public", "photos_seen")
+            .unwrap();
+        let indexes = executor.get_indexes("public", "photos_seen");
+        let where_clause = parse_where(&table, "url = 'b.png'");
+        assert_eq!(
+            indexes::plan_access_path(&table, &indexes, Some(&where_clause)),
+            indexes::AccessPath::IndexRange {
+                index: indexes[0].clone(),
+                lower_bound: Bound::Included(vec![DataInstanceRaw::String("b.png".into())]),
+                upper_bound: Bound::Included(vec![DataInstanceRaw::String("b.png".into())]),
+            }
+        );
+        let where_clause = parse_where(&table, "700 > width AND url IS NOT NULL");
+        assert_eq!(
+            indexes::plan_access_path(&table, &indexes, Some(&where_clause)),
+            indexes::AccessPath::IndexRange {
+                index: indexes[1].clone(),
+                lower_bound: Bound::Unbounded,
+                upper_bound: Bound::Excluded(vec![DataInstanceRaw::UInt32(700)]),
+            }
+        );
+        let result = execute_sql(&executor, "SELECT url FROM photos_seen WHERE url = 'b.png'")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![DataInstance::Nullable(DataInstanceRaw::String(
+                "b.png".into()
+            ))])]
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT url FROM photos_seen WHERE width < 700 AND url IS NOT NULL",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![DataInstance::Nullable(DataInstanceRaw::String(
+                "b.png".into()
+            ))])]
+        );
+        // Writes keep the indexes up to date, and UNIQUE ones reject duplicates
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, width) VALUES ('a.png', 1024)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value [String(\"a.png\")] of UNIQUE index `photos_by_url` already exists in table `photos_seen`".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "UPDATE photos_seen SET url = 'a.png' WHERE url = 'b.png'"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value [String(\"a.png\")] of UNIQUE index `photos_by_url` already exists in table `photos_seen`".into()
+            ))
+        );
+        execute_sql(
+            &executor,
+            "UPDATE photos_seen SET url = 'c.png', width = 1024 WHERE url = 'b.png'",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width) VALUES ('b.png', 320)",
+        )
+        .await
+        .unwrap();
+        execute_sql(&executor, "DELETE FROM photos_seen WHERE url = 'a.png'")
+            .await
+            .unwrap();
+        let result = execute_sql(
+            &executor,
+            "SELECT url, width FROM photos_seen WHERE width >= 320 AND width <= 1024 ORDER BY width",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Nullable(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(320)),
+                ]),
+                Row(vec![
+                    DataInstance::Null,
+                    DataInstance::Direct(DataInstanceRaw::UInt32(640)),
+                ]),
+                Row(vec![
+                    DataInstance::Nullable(DataInstanceRaw::String("c.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(1024)),
+                ]),
+            ]
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT width FROM photos_seen WHERE url = 'a.png'",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, vec![]);
+        // Dropped indexes are no longer used nor maintained
+        execute_sql(&executor, "DROP INDEX photos_by_url")
+            .await
+            .unwrap();
+        assert!(
+            !does_table_file_exist(
+                &executor.config,
+                "public",
+                "photos_seen",
+                "photos_by_url.index"
+            )
+            .await
+        );
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width) VALUES ('c.png', 100)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(&executor, "DROP INDEX photos_by_url")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Index `public.photos_by_url` does not exist".into()
+            ))
+        );
+        execute_sql(&executor, "DROP INDEX IF EXISTS photos_by_url")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn constraints_are_enforced_and_recorded() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (
+                id UUID PRIMARY KEY DEFAULT ULID(),
+                url STRING UNIQUE,
+                width UINT32 CONSTRAINT photos_width_limit CHECK (width <= 8192),
+                height NULLABLE(UINT32),
+                CHECK ((height > 0 OR height IS NULL) AND width > 0)
+            )",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, width, height) VALUES ('a.png', 800, 600), ('b.png', 640, NULL)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, width) VALUES ('c.png', 10000)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Row violates CHECK constraint `photos_width_limit` of table `photos_seen`".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "UPDATE photos_seen SET width = 0 WHERE url = 'b.png'"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Row violates CHECK constraint `photos_seen_height_check` of table `photos_seen`"
+                    .into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, width) VALUES ('a.png', 1024)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value [String(\"a.png\")] violates UNIQUE constraint `photos_seen_url_key` of table `photos_seen`".into()
+            ))
+        );
+        // None of the failed statements changed anything
+        let result = execute_sql(&executor, "SELECT url, width FROM photos_seen ORDER BY url")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("a.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(800)),
+                ]),
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String("b.png".into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt32(640)),
+                ]),
+            ]
+        );
+        // Constraints are in the system tables, and they keep up with their columns
+        execute_sql(
+            &executor,
+            "ALTER TABLE photos_seen RENAME COLUMN width TO w",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(
+            &executor,
+            "SELECT constraint_name, kind, definition FROM system.constraints ORDER BY constraint_name",
+        )
+        .await
+        .unwrap();
+        let string = |value: &str| DataInstance::Direct(DataInstanceRaw::String(value.into()));
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![
+                    string("photos_seen_height_check"),
+                    string("CHECK"),
+                    string("CHECK (((height > 0) OR (height IS NULL)) AND (w > 0))"),
+                ]),
+                Row(vec![
+                    string("photos_seen_url_key"),
+                    string("UNIQUE"),
+                    string("UNIQUE (url)"),
+                ]),
+                Row(vec![
+                    string("photos_width_limit"),
+                    string("CHECK"),
+                    string("CHECK (w <= 8192)"),
+                ]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, w) VALUES ('c.png', 10000)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Row violates CHECK constraint `photos_width_limit` of table `photos_seen`".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "ALTER TABLE photos_seen DROP COLUMN w")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `w` is used by constraint `photos_width_limit` of table `photos_seen`, so it cannot be dropped".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(&executor, "DROP INDEX photos_seen_url_key")
+                .await
+                .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Index `public.photos_seen_url_key` enforces a constraint of table `photos_seen`, so it cannot be dropped".into()
+            ))
+        );
+        execute_sql(&executor, "DROP TABLE photos_seen")
+            .await
+            .unwrap();
+        let result = execute_sql(&executor, "SELECT * FROM system.constraints")
+            .await
+            .unwrap();
+        assert_eq!(result.rows, vec![]);
+    }
+
+    #[tokio::test]
+    async fn composite_primary_key_orders_rows_lexicographically() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (
+                url STRING,
+                domain STRING,
+                width UINT32,
+                PRIMARY KEY (domain, url)
+            )",
+        )
+        .await
+        .unwrap();
+        // Enough rows for the B+ tree to have inner nodes, inserted out of order
+        let values: Vec<String> = (0..300u32)
+            .map(|number| (number * 7) % 300)
+            .map(|number| {
+                format!(
+                    "('/{:03}.png', 'domain{}.com', {})",
+                    number,
+                    number % 3,
+                    number
+                )
+            })
+            .collect();
+        execute_sql(
+            &executor,
+            &format!(
+                "INSERT INTO photos_seen (url, domain, width) VALUES {}",
+                values.join(", ")
+            ),
+        )
+        .await
+        .unwrap();
+        let string = |value: String| DataInstance::Direct(DataInstanceRaw::String(value));
+        let key_of = |number: u32| {
+            Row(vec![
+                string(format!("domain{}.com", number % 3)),
+                string(format!("/{:03}.png", number)),
+            ])
+        };
+        let mut expected_numbers: Vec<u32> = (0..300).collect();
+        expected_numbers.sort_by_key(|number| (number % 3, *number));
+        let expected_rows: Vec<Row> = expected_numbers.into_iter().map(key_of).collect();
+        let result = execute_sql(&executor, "SELECT domain, url FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(result.rows, expected_rows);
+        // A condition on the leading key column narrows down the range of the B+ tree to read
+        let table = executor
+            .get_table_definition("public", "photos_seen")
+            .unwrap();
+        assert_eq!(
+            indexes::plan_access_path(
+                &table,
+                &[],
+                Some(&parse_where(
+                    &table,
+                    "domain = 'domain1.com' AND url < '/010.png'"
+                ))
+            ),
+            indexes::AccessPath::PrimaryKeyRange {
+                lower_bound: Bound::Included(vec![DataInstanceRaw::String("domain1.com".into())]),
+                upper_bound: Bound::Excluded(vec![
+                    DataInstanceRaw::String("domain1.com".into()),
+                    DataInstanceRaw::String("/010.png".into())
+                ]),
+            }
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT width FROM photos_seen WHERE domain = 'domain1.com' AND url < '/010.png' ORDER BY domain DESC, url DESC",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            [7, 4, 1]
+                .into_iter()
+                .map(|width| Row(vec![DataInstance::Direct(DataInstanceRaw::UInt32(width))]))
+                .collect::<Vec<Row>>()
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "INSERT INTO photos_seen (url, domain, width) VALUES ('/001.png', 'domain1.com', 1)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Constraint(ConstraintError(
+                "Value [String(\"domain1.com\"), String(\"/001.png\")] of PRIMARY KEY columns `domain`, `url` already exists in table `photos_seen`".into()
+            ))
+        );
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, domain, width) VALUES ('/001.png', 'domain1.com', 1024)
+            ON CONFLICT (domain, url) DO UPDATE SET width = excluded.width",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "UPDATE photos_seen SET url = '/999.png' WHERE width = 1024"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `url` is part of the PRIMARY KEY of table `photos_seen`, so it cannot be updated".into()
+            ))
+        );
+        execute_sql(
+            &executor,
+            "DELETE FROM photos_seen WHERE domain = 'domain0.com' OR domain = 'domain2.com'",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(&executor, "SELECT COUNT(*), MAX(width) FROM photos_seen")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![
+                DataInstance::Direct(DataInstanceRaw::UInt64(100)),
+                DataInstance::Nullable(DataInstanceRaw::UInt32(1024)),
+            ])]
+        );
+    }
+
+    /// Lines of the plan, with estimated and (with ANALYZE) actual row counts.
+    async fn explain(executor: &Executor, input: &str) -> Vec<(String, u64, Option<u64>)> {
+        let number_at = |row: &Row, index: usize| {
+            row.0
+                .get(index)
+                .map(|value| value.as_raw().unwrap().as_u128().unwrap() as u64)
+        };
+        execute_sql(executor, input)
+            .await
+            .unwrap()
+            .rows
+            .iter()
+            .map(|row| match &row.0[0] {
+                DataInstance::Direct(DataInstanceRaw::String(text)) => {
+                    (text.clone(), number_at(row, 1).unwrap(), number_at(row, 2))
+                }
+                other => panic!("Expected plan text, found {:?}", other),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn explain_shows_chosen_plan_with_row_counts() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, hash UINT8 METRIC KEY, width UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE INDEX photos_width_idx ON photos_seen (width)",
+        )
+        .await
+        .unwrap();
+        // Hamming distances from 0b00001011: 0, 1, 1, 4, 7, 1, 2 and 3
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, hash, width) VALUES
+                ('a.png', 0b00001011, 100), ('b.png', 0b00001010, 200), ('c.png', 0b00001001, 300),
+                ('d.png', 0b11111011, 400), ('e.png', 0b11110000, 500), ('f.png', 0b00000011, 600),
+                ('g.png', 0b01101011, 700), ('h.png', 0, 800)",
+        )
+        .await
+        .unwrap();
+        let line = |text: &str, estimated_rows: u64, actual_rows: Option<u64>| {
+            (text.to_string(), estimated_rows, actual_rows)
+        };
+
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT url, hash @ 0b00001011 AS distance FROM photos_seen
+                WHERE distance < 2 ORDER BY distance, url LIMIT 3"
+            )
+            .await,
+            vec![
+                line("Project (url, distance)", 3, Some(3)),
+                line("-> Limit 3", 3, Some(3)),
+                line("   -> Sort (hash @ 11, url), top 3", 3, Some(3)),
+                line("      -> Filter ((hash @ 11) < 2)", 4, Some(4)),
+                line(
+                    "         -> Metric Range on public.photos_seen ((hash @ 11) < 2)",
+                    4,
+                    Some(4)
+                ),
+            ]
+        );
+        // The metric index shows that no rows are this far, so the table isn't even read
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT url FROM photos_seen WHERE hash @ 11 > 7"
+            )
+            .await,
+            vec![
+                line("Project (url)", 0, Some(0)),
+                line("-> Filter ((hash @ 11) > 7)", 0, Some(0)),
+                line(
+                    "   -> Metric Range on public.photos_seen ((hash @ 11) > 7)",
+                    0,
+                    Some(0)
+                ),
+            ]
+        );
+        // Only rows at most as far as the 2nd nearest one have to be sorted - ties included
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT url FROM photos_seen ORDER BY hash @ 11 LIMIT 2"
+            )
+            .await,
+            vec![
+                line("Project (url)", 2, Some(2)),
+                line("-> Limit 2", 2, Some(2)),
+                line("   -> Sort (hash @ 11), top 2", 2, Some(2)),
+                line(
+                    "      -> Metric kNN on public.photos_seen (hash @ 11, k = 2)",
+                    4,
+                    Some(4)
+                ),
+            ]
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT url FROM photos_seen ORDER BY hash @ 11 LIMIT 2",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                    "a.png".into()
+                ))]),
+                Row(vec![DataInstance::Direct(DataInstanceRaw::String(
+                    "b.png".into()
+                ))]),
+            ]
+        );
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN SELECT url FROM photos_seen WHERE width >= 300 AND width < 500"
+            )
+            .await,
+            vec![
+                line("Project (url)", 1, None),
+                line("-> Filter ((width >= 300) AND (width < 500))", 1, None),
+                line(
+                    "   -> Index Seek using photos_width_idx on public.photos_seen (width >= 300 AND width < 500)",
+                    1,
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT width FROM photos_seen WHERE url = 'c.png'"
+            )
+            .await,
+            vec![
+                line("Project (width)", 1, Some(1)),
+                line("-> Filter (url = 'c.png')", 1, Some(1)),
+                line(
+                    "   -> Primary Key Seek on public.photos_seen (url = 'c.png')",
+                    1,
+                    Some(1)
+                ),
+            ]
+        );
+        // Rows come out of the B+ tree in PRIMARY KEY order already
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT url FROM photos_seen ORDER BY url DESC LIMIT 2 OFFSET 1"
+            )
+            .await,
+            vec![
+                line("Project (url)", 2, Some(2)),
+                line("-> Limit 2 OFFSET 1", 2, Some(2)),
+                line("   -> Seq Scan Backward on public.photos_seen", 8, Some(8)),
+            ]
+        );
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN SELECT hash @ 11 AS distance, COUNT(*) FROM photos_seen
+                GROUP BY hash @ 11 HAVING COUNT(*) > 1 ORDER BY COUNT(*) DESC"
+            )
+            .await,
+            vec![
+                line("Project (distance, count)", 3, None),
+                line("-> Sort (COUNT(*) DESC)", 3, None),
+                line("   -> Filter (COUNT(*) > 1)", 3, None),
+                line("      -> Aggregate (GROUP BY hash @ 11: COUNT(*))", 8, None),
+                line("         -> Seq Scan on public.photos_seen", 8, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn analyze_gathers_statistics_for_estimates() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE events (id UINT32 PRIMARY KEY, kind STRING, size NULLABLE(UINT16))",
+        )
+        .await
+        .unwrap();
+        let values: Vec<String> = (0..100)
+            .map(|id| match id % 5 {
+                0 => format!("({}, 'k{}', NULL)", id, id % 10),
+                _ => format!("({}, 'k{}', {})", id, id % 10, id * 10),
+            })
+            .collect();
+        execute_sql(
+            &executor,
+            &format!(
+                "INSERT INTO events (id, kind, size) VALUES {}",
+                values.join(", ")
+            ),
+        )
+        .await
+        .unwrap();
+        let line = |text: &str, estimated_rows: u64| (text.to_string(), estimated_rows, None);
+
+        // Without statistics, the row count is known, but the fraction of matching rows is assumed
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN SELECT id FROM events WHERE kind != 'k3'"
+            )
+            .await,
+            vec![
+                line("Project (id)", 100),
+                line("-> Filter (kind != 'k3')", 100),
+                line("   -> Seq Scan on public.events", 100),
+            ]
+        );
+        execute_sql(&executor, "ANALYZE events").await.unwrap();
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN SELECT id FROM events WHERE kind != 'k3'"
+            )
+            .await,
+            vec![
+                line("Project (id)", 90),
+                line("-> Filter (kind != 'k3')", 90),
+                line("   -> Seq Scan on public.events", 100),
+            ]
+        );
+        // Sizes are spread evenly from 10 to 990, with every 5th one NULL
+        assert_eq!(
+            explain(&executor, "EXPLAIN SELECT id FROM events WHERE size < 500").await,
+            vec![
+                line("Project (id)", 41),
+                line("-> Filter (size < 500)", 41),
+                line("   -> Seq Scan on public.events", 100),
+            ]
+        );
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN SELECT id FROM events WHERE size IS NULL"
+            )
+            .await,
+            vec![
+                line("Project (id)", 20),
+                line("-> Filter (size IS NULL)", 20),
+                line("   -> Seq Scan on public.events", 100),
+            ]
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT column_name, sampled_row_count, null_count, distinct_count, distance_counts
+            FROM system.statistics ORDER BY column_name",
+        )
+        .await
+        .unwrap();
+        let statistics_row = |column_name: &str, null_count: u64, distinct_count: u64| {
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String(column_name.into())),
+                DataInstance::Direct(DataInstanceRaw::UInt64(100)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(null_count)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(distinct_count)),
+                DataInstance::Null,
+            ])
+        };
+        assert_eq!(
+            result.rows,
+            vec![
+                statistics_row("id", 0, 100),
+                statistics_row("kind", 0, 10),
+                statistics_row("size", 20, 80),
+            ]
+        );
+
+        // The row count is kept up to date as rows change, statistics or not
+        execute_sql(&executor, "DELETE FROM events WHERE id >= 60")
+            .await
+            .unwrap();
+        assert_eq!(
+            explain(&executor, "EXPLAIN SELECT id FROM events").await,
+            vec![
+                line("Project (id)", 60),
+                line("-> Seq Scan on public.events", 60),
+            ]
+        );
+        // Statistics of changed columns are discarded, while analyzing again replaces them
+        execute_sql(&executor, "ALTER TABLE events DROP COLUMN size")
+            .await
+            .unwrap();
+        execute_sql(&executor, "ANALYZE").await.unwrap();
+        let result = execute_sql(
+            &executor,
+            "SELECT column_name, sampled_row_count FROM system.statistics ORDER BY column_name",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            ["id", "kind"]
+                .into_iter()
+                .map(|column_name| Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String(column_name.into())),
+                    DataInstance::Direct(DataInstanceRaw::UInt64(60)),
+                ]))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn select_joins_tables() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, domain STRING, hash UINT8 METRIC KEY, width UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE domains (domain STRING PRIMARY KEY, owner STRING)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, domain, hash, width) VALUES
+            ('a.com/1', 'a.com', 0b0001, 100), ('a.com/2', 'a.com', 0b0111, 200),
+            ('b.com/1', 'b.com', 0b1111, 300), ('b.com/2', 'b.com', 0b1000, 500),
+            ('c.com/1', 'c.com', 0b0011, 400)",
+        )
+        .await
+        .unwrap();
+        let domains: Vec<String> = (0..30)
+            .map(|number| format!("('filler{}.com', 'nobody')", number))
+            .chain([
+                "('a.com', 'alice')".to_string(),
+                "('b.com', 'bob')".to_string(),
+            ])
+            .collect();
+        execute_sql(
+            &executor,
+            &format!(
+                "INSERT INTO domains (domain, owner) VALUES {}",
+                domains.join(", ")
+            ),
+        )
+        .await
+        .unwrap();
+        let string = |value: &str| DataInstance::Direct(DataInstanceRaw::String(value.to_string()));
+
+        let result = execute_sql(
+            &executor,
+            "SELECT p.url, d.owner FROM photos_seen p JOIN domains d ON p.domain = d.domain ORDER BY url",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["url", "owner"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![string("a.com/1"), string("alice")]),
+                Row(vec![string("a.com/2"), string("alice")]),
+                Row(vec![string("b.com/1"), string("bob")]),
+                Row(vec![string("b.com/2"), string("bob")]),
+            ]
+        );
+        // Unqualified columns are resolved to the only table that has them
+        let result = execute_sql(
+            &executor,
+            "SELECT url, owner FROM photos_seen AS p LEFT JOIN domains AS d ON p.domain = d.domain
+            WHERE width >= 300 ORDER BY url",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![string("b.com/1"), string("bob")]),
+                Row(vec![string("b.com/2"), string("bob")]),
+                Row(vec![string("c.com/1"), DataInstance::Null]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT domain FROM photos_seen JOIN domains ON photos_seen.domain = domains.domain"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column reference `domain` is ambiguous, as more than one table has such a column"
+                    .into()
+            ))
+        );
+
+        let line = |text: &str, estimated_rows: u64, actual_rows: Option<u64>| {
+            (text.to_string(), estimated_rows, actual_rows)
+        };
+        // Conditions on a single table filter its rows before joining. There are few enough photos left
+        // for each of them to be joined by looking its domain up through the PRIMARY KEY
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT p.url, d.owner FROM photos_seen p JOIN domains d ON p.domain = d.domain
+                WHERE p.url = 'b.com/2'"
+            )
+            .await,
+            vec![
+                line("Project (url, owner)", 1, Some(1)),
+                line(
+                    "-> Index Nested Loop Join on public.domains d (p.domain = d.domain)",
+                    1,
+                    Some(1)
+                ),
+                line("   -> Filter (p.url = 'b.com/2')", 1, Some(1)),
+                line(
+                    "      -> Primary Key Seek on public.photos_seen p (url = 'b.com/2')",
+                    1,
+                    Some(1)
+                ),
+            ]
+        );
+        assert_eq!(
+            explain(
+                &executor,
+                "EXPLAIN ANALYZE SELECT p.url, d.owner FROM photos_seen p JOIN domains d ON p.domain = d.domain
+                WHERE d.owner != 'nobody'"
+            )
+            .await,
+            vec![
+                line("Project (url, owner)", 5, Some(4)),
+                line("-> Hash Join (p.domain = d.domain)", 5, Some(4)),
+                line("   -> Seq Scan on public.photos_seen p", 5, Some(5)),
+                line("   -> Filter (d.owner != 'nobody')", 32, Some(2)),
+                line("      -> Seq Scan on public.domains d", 32, Some(32)),
+            ]
+        );
+        // Each photo has one domain at most, so the nearest photos can be found before joining
+        let query = "SELECT p.url, d.owner, p.hash @ 0b0011 AS distance FROM photos_seen p
+            LEFT JOIN domains d ON p.domain = d.domain ORDER BY distance LIMIT 2";
+        assert_eq!(
+            explain(&executor, &format!("EXPLAIN ANALYZE {}", query)).await,
+            vec![
+                line("Project (url, owner, distance)", 2, Some(2)),
+                line("-> Limit 2", 2, Some(2)),
+                line(
+                    "   -> Index Nested Loop Left Join on public.domains d (p.domain = d.domain)",
+                    2,
+                    Some(2)
+                ),
+                line("      -> Sort (p.hash @ 3), top 2", 2, Some(2)),
+                line(
+                    "         -> Metric kNN on public.photos_seen p (hash @ 3, k = 2)",
+                    3,
+                    Some(3)
+                ),
+            ]
+        );
+        assert_eq!(
+            execute_sql(&executor, query).await.unwrap().rows,
+            vec![
+                Row(vec![
+                    string("c.com/1"),
+                    DataInstance::Null,
+                    DataInstance::Direct(DataInstanceRaw::UInt8(0))
+                ]),
+                Row(vec![
+                    string("a.com/1"),
+                    string("alice"),
+                    DataInstance::Direct(DataInstanceRaw::UInt8(1))
+                ]),
+            ]
+        );
+        // Without equality between the tables, every pair of rows is checked
+        let result = execute_sql(
+            &executor,
+            "SELECT p.url, d.domain FROM photos_seen p JOIN domains d ON p.width > 400 AND d.owner = 'bob'",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![string("b.com/2"), string("b.com")])]
+        );
+    }
+
+    #[tokio::test]
+    async fn select_runs_subqueries_and_with_queries() {
+        let executor = get_test_executor().await;
+        execute_sql(
+            &executor,
+            "CREATE TABLE photos_seen (url STRING PRIMARY KEY, domain STRING, hash UINT8 METRIC KEY, seen_at UINT32)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "CREATE TABLE domains (domain STRING PRIMARY KEY, owner STRING)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO photos_seen (url, domain, hash, seen_at) VALUES
+            ('a.com/1', 'a.com', 0b0001, 1), ('a.com/2', 'a.com', 0b0111, 2),
+            ('b.com/1', 'b.com', 0b1111, 3), ('b.com/2', 'b.com', 0b1000, 4),
+            ('c.com/1', 'c.com', 0b0011, 5)",
+        )
+        .await
+        .unwrap();
+        execute_sql(
+            &executor,
+            "INSERT INTO domains (domain, owner) VALUES ('a.com', 'alice'), ('b.com', 'bob')",
+        )
+        .await
+        .unwrap();
+        let string = |value: &str| DataInstance::Direct(DataInstanceRaw::String(value.to_string()));
+        let uint8 = |value: u8| DataInstance::Direct(DataInstanceRaw::UInt8(value));
+
+        // Photos similar to the latest photo from b.com, which is b.com/2
+        let result = execute_sql(
+            &executor,
+            "SELECT url, hash @ (SELECT hash FROM photos_seen WHERE domain = 'b.com' ORDER BY seen_at DESC LIMIT 1)
+            AS distance FROM photos_seen ORDER BY distance, url LIMIT 3",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["url", "distance"]);
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![string("b.com/2"), uint8(0)]),
+                Row(vec![string("a.com/1"), uint8(2)]),
+                Row(vec![string("b.com/1"), uint8(3)]),
+            ]
+        );
+        let result = execute_sql(
+            &executor,
+            "SELECT url FROM photos_seen WHERE domain IN (SELECT domain FROM domains WHERE owner = 'bob')
+            AND EXISTS (SELECT * FROM domains) AND NOT EXISTS (SELECT * FROM domains WHERE owner = 'carol')
+            ORDER BY url",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![Row(vec![string("b.com/1")]), Row(vec![string("b.com/2")])]
+        );
+        // A scalar subquery without rows is NULL
+        let result = execute_sql(
+            &executor,
+            "SELECT (SELECT url FROM photos_seen WHERE domain = 'z.com') FROM domains LIMIT 1",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.column_names, vec!["url"]);
+        assert_eq!(result.rows, vec![Row(vec![DataInstance::Null])]);
+
+        // The latest photo of each domain, along with the domain's owner if there is one
+        let query = "WITH latest AS (SELECT domain, MAX(seen_at) AS seen_at FROM photos_seen GROUP BY domain)
+            SELECT p.url, d.owner FROM photos_seen p
+            JOIN latest l ON l.domain = p.domain AND l.seen_at = p.seen_at
+            LEFT JOIN domains d ON d.domain = p.domain ORDER BY p.url";
+        let result = execute_sql(&executor, query).await.unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![string("a.com/2"), string("alice")]),
+                Row(vec![string("b.com/2"), string("bob")]),
+                Row(vec![string("c.com/1"), DataInstance::Null]),
+            ]
+        );
+        let line = |text: &str, estimated_rows: u64, actual_rows: Option<u64>| {
+            (text.to_string(), estimated_rows, actual_rows)
+        };
+        // Rows of the WITH query are paired up with the table's through a hash table
+        assert_eq!(
+            explain(&executor, &format!("EXPLAIN ANALYZE {}", query)).await,
+            vec![
+                line("Project (url, owner)", 0, Some(3)),
+                line(
+                    "-> Index Nested Loop Left Join on public.domains d (d.domain = p.domain)",
+                    0,
+                    Some(3)
+                ),
+                line("   -> Sort (p.url)", 0, Some(3)),
+                line(
+                    "      -> Hash Join ((l.domain = p.domain) AND (l.seen_at = p.seen_at))",
+                    0,
+                    Some(3)
+                ),
+                line("         -> Seq Scan on public.photos_seen p", 5, Some(5)),
+                line("         -> CTE Scan on latest l", 3, Some(3)),
+            ]
+        );
+        // WITH queries can read earlier ones, and subqueries can read WITH queries
+        let result = execute_sql(
+            &executor,
+            "WITH b AS (SELECT url, hash FROM photos_seen WHERE domain = 'b.com'),
+            nearest AS (SELECT url FROM b ORDER BY hash @ 0b1110 LIMIT 1)
+            SELECT url FROM photos_seen WHERE url IN (SELECT url FROM nearest)",
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows, vec![Row(vec![string("b.com/1")])]);
+
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT url FROM photos_seen WHERE hash = (SELECT hash FROM photos_seen)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Execution(ExecutionError(
+                "A subquery used as an expression returned more than one row".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "SELECT url FROM photos_seen WHERE domain IN (SELECT domain, owner FROM domains)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "A subquery used with IN must return a single column, instead it returns 2".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &executor,
+                "WITH nulls_only AS (SELECT NULL AS owner FROM domains) SELECT * FROM nulls_only"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Column `owner` of WITH query `nulls_only` cannot be the NULL literal".into()
+            ))
+        );
+
+        // Subqueries are executed before the queries enclosing them, so they cannot refer to those
+        for (query, column_reference) in [
+            (
+                "SELECT url FROM photos_seen p WHERE EXISTS (SELECT * FROM domains d WHERE d.domain = p.domain)",
+                "p.domain",
+            ),
+            (
+                "SELECT url FROM photos_seen WHERE EXISTS (SELECT * FROM domains WHERE owner = url)",
+                "url",
+            ),
+            (
+                "SELECT (SELECT owner FROM domains WHERE domains.domain = photos_seen.domain) FROM photos_seen",
+                "photos_seen.domain",
+            ),
+            (
+                "SELECT url FROM photos_seen p WHERE domain IN (SELECT domain FROM domains WHERE owner IN
+                (SELECT owner FROM domains WHERE owner = p.url))",
+                "p.url",
+            ),
+            (
+                "DELETE FROM photos_seen WHERE domain = (SELECT domain FROM domains WHERE owner = url)",
+                "url",
+            ),
+        ] {
+            assert_eq!(
+                execute_sql(&executor, query).await.unwrap_err(),
+                ExecutorError::Validation(ValidationError(format!(
+                    "Correlated subqueries are not supported, but `{}` refers to a table of an enclosing query",
+                    column_reference
+                )))
+            );
+        }
+
+        // Subqueries work in data-modifying statements too
+        execute_sql(
+            &executor,
+            "DELETE FROM photos_seen WHERE domain IN (SELECT domain FROM domains WHERE owner = 'alice')",
+        )
+        .await
+        .unwrap();
+        let result = execute_sql(&executor, "SELECT url FROM photos_seen ORDER BY url")
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                Row(vec![string("b.com/1")]),
+                Row(vec![string("b.com/2")]),
+                Row(vec![string("c.com/1")]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn catalog_is_loaded_back_after_restart() {
+        let executor = get_test_executor().await;
+        let string = |value: &str| DataInstance::Direct(DataInstanceRaw::String(value.into()));
+        for statement in [
+            "CREATE TABLE photos_seen (
+                id UUID PRIMARY KEY DEFAULT ULID(),
+                obsolete NULLABLE(UINT8),
+                url STRING UNIQUE,
+                width UINT32 CONSTRAINT photos_width_limit CHECK (width <= 8192),
+                \"Key\" NULLABLE(STRING) DEFAULT 'it''s',
+                hash UINT64 METRIC KEY,
+                CHECK (width > 0)
+            )",
+            "ALTER TABLE photos_seen ADD COLUMN seen_at TIMESTAMP DEFAULT NOW()",
+            "ALTER TABLE photos_seen DROP COLUMN obsolete",
+            "CREATE INDEX photos_by_width ON photos_seen (width, \"Key\")",
+            "INSERT INTO photos_seen (url, width, hash) VALUES ('a.png', 800, 11), ('b.png', 640, 7)",
+            "ANALYZE photos_seen",
+            "CREATE TABLE gaggle_members (name STRING, joined_at TIMESTAMP, PRIMARY KEY (joined_at, name))",
+        ] {
+            execute_sql(&executor, statement).await.unwrap();
+        }
+
+        let mut restarted_executor = Executor::new(&executor.config);
+        restarted_executor.bootstrap().await.unwrap();
+        // Timestamps are stored with a precision of seconds
+        let get_entries = |executor: &Executor| {
+            let mut entries = executor.tables.lock().clone();
+            for entry in &mut entries {
+                if let Some(statistics) = &mut entry.statistics {
+                    let statistics = Arc::make_mut(statistics);
+                    statistics.analyzed_at = OffsetDateTime::from_unix_timestamp(
+                        statistics.analyzed_at.unix_timestamp(),
+                    )
+                    .unwrap();
+                }
+            }
+            entries
+        };
+        assert_eq!(get_entries(&restarted_executor), get_entries(&executor));
+        assert_eq!(
+            execute_sql(
+                &restarted_executor,
+                "SELECT url, width, hash FROM photos_seen WHERE width = 640"
+            )
+            .await
+            .unwrap()
+            .rows,
+            vec![Row(vec![
+                string("b.png"),
+                DataInstance::Direct(DataInstanceRaw::UInt32(640)),
+                DataInstance::Direct(DataInstanceRaw::UInt64(7)),
+            ])]
+        );
+
+        // The table exists on disk and in the catalog already, so it's neither recreated nor truncated
+        assert_eq!(
+            execute_sql(
+                &restarted_executor,
+                "CREATE TABLE photos_seen (url STRING PRIMARY KEY)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Table `public.photos_seen` already exists".into()
+            ))
+        );
+        execute_sql(
+            &restarted_executor,
+            "CREATE TABLE IF NOT EXISTS photos_seen (url STRING PRIMARY KEY)",
+        )
+        .await
+        .unwrap();
+        // A table file left behind without a catalog record isn't overwritten either
+        write_table_file(
+            &restarted_executor.config,
+            DEFAULT_SCHEMA_NAME,
+            "orphaned",
+            TABLE_DATA_FILE_NAME,
+            construct_blank_table(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            execute_sql(
+                &restarted_executor,
+                "CREATE TABLE orphaned (url STRING PRIMARY KEY)"
+            )
+            .await
+            .unwrap_err(),
+            ExecutorError::Validation(ValidationError(
+                "Table `public.orphaned` already exists".into()
+            ))
+        );
+        assert_eq!(
+            execute_sql(
+                &restarted_executor,
+                "SELECT schema_name, table_name FROM system.tables"
+            )
+            .await
+            .unwrap()
+            .rows,
+            vec![
+                Row(vec![string("public"), string("photos_seen")]),
+                Row(vec![string("public"), string("gaggle_members")]),
+            ]
+        );
+        assert_eq!(
+            execute_sql(
+                &restarted_executor,
+                "SELECT url FROM photos_seen ORDER BY url"
+            )
+            .await
+            .unwrap()
+            .rows,
+            vec![Row(vec![string("a.png")]), Row(vec![string("b.png")])]
+        );
+    }
 }
@@ -0,0 +1,191 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::constructs::{
+    components::DataInstance, evaluation::compare_values, statements::OrderBy,
+};
+use crate::storage::Row;
+
+/// Compare two sort keys (values of ORDER BY expressions) item by item.
+/// Keys are expected to be validated beforehand, so values of incomparable types are treated as equal.
+pub fn compare_sort_keys(
+    order_by: &[OrderBy],
+    lhs: &[DataInstance],
+    rhs: &[DataInstance],
+) -> Ordering {
+    for (item, (lhs, rhs)) in order_by.iter().zip(lhs.iter().zip(rhs.iter())) {
+        let ordering = match (lhs.as_raw(), rhs.as_raw()) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if item.nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if item.nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(lhs), Some(rhs)) => {
+                let ordering = compare_values(lhs, rhs).unwrap_or(Ordering::Equal);
+                if item.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Row waiting in the top-N heap. The sequence number keeps the sort stable.
+struct HeapEntry<'o> {
+    order_by: &'o [OrderBy],
+    key: Vec<DataInstance>,
+    sequence: usize,
+    row: Row,
+}
+
+impl HeapEntry<'_> {
+    fn cmp_position(&self, other: &Self) -> Ordering {
+        compare_sort_keys(self.order_by, &self.key, &other.key)
+            .then(self.sequence.cmp(&other.sequence))
+    }
+}
+
+impl PartialEq for HeapEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp_position(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry<'_> {}
+
+impl PartialOrd for HeapEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_position(other)
+    }
+}
+
+/// Sort rows by their keys, stably. With `max_count` only that many first rows are kept,
+/// using a bounded max-heap, so that memory stays proportional to the count instead of the table size.
+pub fn sort_rows(
+    order_by: &[OrderBy],
+    keyed_rows: impl IntoIterator<Item = (Vec<DataInstance>, Row)>,
+    max_count: Option<usize>,
+) -> Vec<Row> {
+    match max_count {
+        None => {
+            let mut keyed_rows: Vec<(Vec<DataInstance>, Row)> = keyed_rows.into_iter().collect();
+            // `sort_by` is stable, so rows with equal keys stay in table order
+            keyed_rows.sort_by(|(lhs, _), (rhs, _)| compare_sort_keys(order_by, lhs, rhs));
+            keyed_rows.into_iter().map(|(_, row)| row).collect()
+        }
+        Some(0) => Vec::new(),
+        Some(max_count) => {
+            let mut heap = BinaryHeap::with_capacity(max_count + 1);
+            for (sequence, (key, row)) in keyed_rows.into_iter().enumerate() {
+                let entry = HeapEntry {
+                    order_by,
+                    key,
+                    sequence,
+                    row,
+                };
+                if heap.len() < max_count {
+                    heap.push(entry);
+                } else if let Some(mut largest) = heap.peek_mut() {
+                    if entry < *largest {
+                        *largest = entry;
+                    }
+                }
+            }
+            heap.into_sorted_vec()
+                .into_iter()
+                .map(|entry| entry.row)
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod order_tests {
+    use super::*;
+    use crate::constructs::components::{DataDefinition, DataInstanceRaw, Expression};
+    use pretty_assertions::assert_eq;
+
+    fn order_by(descending: bool, nulls_first: bool) -> Vec<OrderBy> {
+        vec![OrderBy {
+            expression: Expression::Atom(DataDefinition::Identifier("width".into())),
+            descending,
+            nulls_first,
+        }]
+    }
+
+    fn keyed_rows(widths: &[Option<u32>]) -> Vec<(Vec<DataInstance>, Row)> {
+        widths
+            .iter()
+            .enumerate()
+            .map(|(position, width)| {
+                let width = match width {
+                    Some(width) => DataInstance::Nullable(DataInstanceRaw::UInt32(*width)),
+                    None => DataInstance::Null,
+                };
+                (
+                    vec![width],
+                    Row(vec![DataInstance::Direct(DataInstanceRaw::UInt64(
+                        position as u64,
+                    ))]),
+                )
+            })
+            .collect()
+    }
+
+    fn positions(rows: Vec<Row>) -> Vec<u64> {
+        rows.into_iter()
+            .map(|row| match row.0[0] {
+                DataInstance::Direct(DataInstanceRaw::UInt64(position)) => position,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sort_rows_is_stable_and_places_nulls() {
+        let widths = [Some(800), None, Some(640), Some(800), None];
+        assert_eq!(
+            positions(sort_rows(
+                &order_by(false, false),
+                keyed_rows(&widths),
+                None
+            )),
+            vec![2, 0, 3, 1, 4]
+        );
+        assert_eq!(
+            positions(sort_rows(&order_by(true, true), keyed_rows(&widths), None)),
+            vec![1, 4, 0, 3, 2]
+        );
+        assert_eq!(
+            positions(sort_rows(&order_by(false, true), keyed_rows(&widths), None)),
+            vec![1, 4, 2, 0, 3]
+        );
+    }
+
+    #[test]
+    fn sort_rows_top_n_matches_full_sort() {
+        let widths: Vec<Option<u32>> = (0..50)
+            .map(|i| if i % 7 == 0 { None } else { Some(i * 37 % 11) })
+            .collect();
+        let order_by = order_by(true, false);
+        let full = positions(sort_rows(&order_by, keyed_rows(&widths), None));
+        for max_count in [0, 1, 5, 49, 50, 80] {
+            assert_eq!(
+                positions(sort_rows(&order_by, keyed_rows(&widths), Some(max_count))),
+                full.iter().take(max_count).copied().collect::<Vec<u64>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,1906 @@
+//! Query planning. A SELECT is first put together as a logical plan, i.e. the steps that SQL semantics
+//! call for, in their order. That is then turned into a physical plan: a tree of operators, which also
+//! decides how exactly rows are found and ordered, e.g. through which index, or whether sorting can be skipped.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+
+use futures::future::{BoxFuture, FutureExt};
+
+use crate::config;
+use crate::constructs::components::{
+    ColumnDefinition, DataDefinition, DataInstance, DataInstanceRaw, Expression, IndexDefinition,
+    TableDefinition,
+};
+use crate::constructs::evaluation::{compare_values, RowContext};
+use crate::constructs::statements::{JoinKind, OrderBy};
+use crate::storage::encoding::GlobalCount;
+use crate::storage::index::Index;
+use crate::storage::paging::Key;
+use crate::storage::Row;
+
+use super::aggregate::Aggregation;
+use super::indexes::{self, AccessPath};
+use super::order;
+use super::statistics::{ColumnStatistics, TableStatistics};
+
+/// Assumed fraction of rows for which a column is equal to a value.
+const EQUALITY_SELECTIVITY: f64 = 0.005;
+/// Assumed fraction of rows for which a column is on one side of a value.
+const RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+/// Assumed fraction of rows satisfying a condition of any other kind.
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+/// Assumed number of groups that rows are put into by GROUP BY.
+const DEFAULT_GROUP_COUNT: f64 = 200.0;
+/// Assumed cost of looking rows up by key in a B+ tree, relative to reading a row in a full scan.
+const INDEX_LOOKUP_COST: f64 = 10.0;
+
+/// A table that a query reads, along with what's known about it.
+#[derive(Debug)]
+pub struct TableSource {
+    pub schema: String,
+    pub table: TableDefinition,
+    /// Secondary indexes of the table.
+    pub indexes: Vec<IndexDefinition>,
+    /// Number of rows in the table, as kept in its meta page.
+    pub row_count: GlobalCount,
+    /// Statistics gathered by the latest ANALYZE of the table, if there was one.
+    pub statistics: Option<Arc<TableStatistics>>,
+    /// Name that the table's columns are qualified with in rows read from it, as in `p.url`, if the query
+    /// joins tables.
+    pub qualifier: Option<String>,
+}
+
+impl TableSource {
+    /// Expression in terms of the table's own columns, i.e. with qualifiers removed.
+    fn localize(&self, expression: &Expression) -> Expression {
+        let qualifier = match &self.qualifier {
+            Some(qualifier) => qualifier,
+            None => return expression.clone(),
+        };
+        expression.replace(&mut |subexpression| match subexpression {
+            Expression::Atom(DataDefinition::Identifier(name)) => name
+                .strip_prefix(qualifier.as_str())
+                .and_then(|name| name.strip_prefix('.'))
+                .map(|column_name| {
+                    Expression::Atom(DataDefinition::Identifier(column_name.to_string()))
+                }),
+            _ => None,
+        })
+    }
+
+    /// Name of the table's column that the expression just is, if it is one.
+    fn get_column_name(&self, expression: &Expression) -> Option<String> {
+        match self.localize(expression) {
+            Expression::Atom(DataDefinition::Identifier(column_name))
+                if self.table.get_column(&column_name).is_some() =>
+            {
+                Some(column_name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Names of columns of rows read from the table, as the rest of the query refers to them.
+    fn get_column_names(&self) -> Vec<String> {
+        let column_names = self.table.get_column_names();
+        match &self.qualifier {
+            Some(qualifier) => column_names
+                .into_iter()
+                .map(|column_name| format!("{}.{}", qualifier, column_name))
+                .collect(),
+            None => column_names,
+        }
+    }
+
+    /// Whether there's at most one row for any values of the columns, as they cover the PRIMARY KEY
+    /// or a UNIQUE index.
+    fn is_unique_by(&self, column_names: &[String]) -> bool {
+        let is_covered = |key_column_names: &[String]| {
+            !key_column_names.is_empty()
+                && key_column_names
+                    .iter()
+                    .all(|key_column_name| column_names.contains(key_column_name))
+        };
+        is_covered(&self.table.get_key_column_names())
+            || self
+                .indexes
+                .iter()
+                .any(|index| index.unique && is_covered(&index.column_names))
+    }
+
+    /// Name of the table for EXPLAIN, e.g. `public.photos_seen p`.
+    fn describe(&self) -> String {
+        match &self.qualifier {
+            Some(qualifier) if *qualifier != self.table.name => {
+                format!("{}.{} {}", self.schema, self.table.name, qualifier)
+            }
+            _ => format!("{}.{}", self.schema, self.table.name),
+        }
+    }
+}
+
+/// Rows of a WITH query, computed before the query that reads them.
+#[derive(Debug)]
+pub struct CommonTableSource {
+    /// Definition of the rows, named after the WITH query.
+    pub table: TableDefinition,
+    pub rows: Arc<Vec<Row>>,
+    /// Same as in `TableSource`.
+    pub qualifier: Option<String>,
+}
+
+impl CommonTableSource {
+    /// Names of columns of the rows, as the rest of the query refers to them.
+    fn get_column_names(&self) -> Vec<String> {
+        let column_names = self.table.get_column_names();
+        match &self.qualifier {
+            Some(qualifier) => column_names
+                .into_iter()
+                .map(|column_name| format!("{}.{}", qualifier, column_name))
+                .collect(),
+            None => column_names,
+        }
+    }
+
+    /// Name of the WITH query for EXPLAIN, e.g. `latest l`.
+    fn describe(&self) -> String {
+        match &self.qualifier {
+            Some(qualifier) if *qualifier != self.table.name => {
+                format!("{} {}", self.table.name, qualifier)
+            }
+            _ => self.table.name.clone(),
+        }
+    }
+}
+
+/// Step of a query as defined by SQL, with the input it works on.
+#[derive(Debug)]
+pub enum LogicalPlan {
+    /// All rows of a table.
+    Scan(Box<TableSource>),
+    /// All rows of a WITH query.
+    CommonTableScan(Box<CommonTableSource>),
+    /// Input rows satisfying the condition.
+    Filter {
+        input: Box<Self>,
+        condition: Expression,
+    },
+    /// Input rows aggregated into groups.
+    Aggregate {
+        input: Box<Self>,
+        aggregation: Box<Aggregation>,
+    },
+    /// Input rows in order.
+    Sort {
+        input: Box<Self>,
+        order_by: Vec<OrderBy>,
+    },
+    /// Window of input rows.
+    Limit {
+        input: Box<Self>,
+        offset: usize,
+        limit: Option<usize>,
+    },
+    /// Output values computed from input rows.
+    Project {
+        input: Box<Self>,
+        expressions: Vec<Expression>,
+        column_names: Vec<String>,
+    },
+    /// Pairs of left and right input rows satisfying the condition, made into single rows. LEFT joins also
+    /// pass on left rows without a pair, with NULLs in place of right values.
+    Join {
+        left: Box<Self>,
+        right: Box<Self>,
+        kind: JoinKind,
+        condition: Expression,
+    },
+}
+
+impl LogicalPlan {
+    /// Aggregation that rows coming out of this step have gone through, if any.
+    fn get_aggregation(&self) -> Option<&Aggregation> {
+        match self {
+            Self::Scan(_) | Self::CommonTableScan(_) | Self::Join { .. } => None,
+            Self::Aggregate { aggregation, .. } => Some(aggregation),
+            Self::Filter { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::Project { input, .. } => input.get_aggregation(),
+        }
+    }
+
+    /// SQL text of an expression over rows coming out of this step, in terms of the table's own columns
+    /// even if the rows have been aggregated.
+    fn describe_expression(&self, expression: &Expression) -> String {
+        match self.get_aggregation() {
+            Some(aggregation) => aggregation.restore(expression).to_string(),
+            None => expression.to_string(),
+        }
+    }
+
+    /// Qualifiers of tables whose columns are in rows coming out of this step.
+    fn get_qualifiers(&self) -> Vec<&str> {
+        match self {
+            Self::Scan(source) => source.qualifier.as_deref().into_iter().collect(),
+            Self::CommonTableScan(source) => source.qualifier.as_deref().into_iter().collect(),
+            Self::Join { left, right, .. } => {
+                [left.get_qualifiers(), right.get_qualifiers()].concat()
+            }
+            Self::Filter { input, .. }
+            | Self::Aggregate { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Limit { input, .. }
+            | Self::Project { input, .. } => input.get_qualifiers(),
+        }
+    }
+
+    /// Move conditions on joined rows as close to the tables they're about as possible, so that fewer rows
+    /// are read and joined.
+    pub fn push_down_conditions(self) -> Self {
+        match self {
+            Self::Filter { input, condition } => match *input {
+                join @ Self::Join { .. } => {
+                    let (join, rest) = join.absorb_conditions(
+                        get_conjuncts(&condition).into_iter().cloned().collect(),
+                    );
+                    match conjoin(rest) {
+                        Some(condition) => Self::Filter {
+                            input: Box::new(join),
+                            condition,
+                        },
+                        None => join,
+                    }
+                }
+                input => Self::Filter {
+                    input: Box::new(input.push_down_conditions()),
+                    condition,
+                },
+            },
+            join @ Self::Join { .. } => join.absorb_conditions(Vec::new()).0,
+            Self::Aggregate { input, aggregation } => Self::Aggregate {
+                input: Box::new(input.push_down_conditions()),
+                aggregation,
+            },
+            Self::Sort { input, order_by } => Self::Sort {
+                input: Box::new(input.push_down_conditions()),
+                order_by,
+            },
+            Self::Limit {
+                input,
+                offset,
+                limit,
+            } => Self::Limit {
+                input: Box::new(input.push_down_conditions()),
+                offset,
+                limit,
+            },
+            Self::Project {
+                input,
+                expressions,
+                column_names,
+            } => Self::Project {
+                input: Box::new(input.push_down_conditions()),
+                expressions,
+                column_names,
+            },
+            scan @ (Self::Scan(_) | Self::CommonTableScan(_)) => scan,
+        }
+    }
+
+    /// Put conditions on rows coming out of this step (or its join's own conditions) where they filter rows
+    /// earliest. Conditions that can't be evaluated within this step are given back.
+    fn absorb_conditions(self, conditions: Vec<Expression>) -> (Self, Vec<Expression>) {
+        let (left, right, kind, condition) = match self {
+            Self::Join {
+                left,
+                right,
+                kind,
+                condition,
+            } => (left, right, kind, condition),
+            Self::Filter { input, condition } => {
+                return match conjoin(conditions) {
+                    Some(conditions) => (
+                        Self::Filter {
+                            input,
+                            condition: Expression::And(Box::new(condition), Box::new(conditions)),
+                        },
+                        Vec::new(),
+                    ),
+                    None => (Self::Filter { input, condition }, Vec::new()),
+                }
+            }
+            other => {
+                return match conjoin(conditions) {
+                    Some(condition) => (
+                        Self::Filter {
+                            input: Box::new(other),
+                            condition,
+                        },
+                        Vec::new(),
+                    ),
+                    None => (other, Vec::new()),
+                }
+            }
+        };
+        let is_within = |expression: &Expression, qualifiers: &[&str]| {
+            get_referenced_qualifiers(expression)
+                .iter()
+                .all(|qualifier| qualifiers.contains(qualifier))
+        };
+        let left_qualifiers = left.get_qualifiers();
+        let right_qualifiers = right.get_qualifiers();
+        let mut left_conditions = Vec::new();
+        let mut right_conditions = Vec::new();
+        let mut join_conditions = Vec::new();
+        let mut rest = Vec::new();
+        // Conditions from above are on joined rows, so with a LEFT join they can't filter the right side
+        // beforehand - rows without a pair would then be passed on with NULLs instead of being filtered out
+        for condition in conditions {
+            if is_within(&condition, &left_qualifiers) {
+                left_conditions.push(condition);
+            } else if kind == JoinKind::Left {
+                rest.push(condition);
+            } else if is_within(&condition, &right_qualifiers) {
+                right_conditions.push(condition);
+            } else {
+                join_conditions.push(condition);
+            }
+        }
+        // The join's own conditions only decide which pairs are made, so those about the right side alone
+        // can always filter it beforehand, but with a LEFT join all left rows are passed on regardless
+        for conjunct in get_conjuncts(&condition) {
+            if !get_referenced_qualifiers(conjunct).is_empty()
+                && is_within(conjunct, &right_qualifiers)
+            {
+                right_conditions.push(conjunct.clone());
+            } else if kind == JoinKind::Inner && is_within(conjunct, &left_qualifiers) {
+                left_conditions.push(conjunct.clone());
+            } else {
+                join_conditions.push(conjunct.clone());
+            }
+        }
+        let (left, left_rest) = left.absorb_conditions(left_conditions);
+        match kind {
+            JoinKind::Inner => join_conditions.extend(left_rest),
+            JoinKind::Left => rest.extend(left_rest),
+        }
+        let (right, right_rest) = right.absorb_conditions(right_conditions);
+        join_conditions.extend(right_rest);
+        let join = Self::Join {
+            left: Box::new(left),
+            right: Box::new(right),
+            kind,
+            condition: conjoin(join_conditions).unwrap_or(Expression::Atom(DataDefinition::Const(
+                DataInstance::Direct(DataInstanceRaw::Bool(true)),
+            ))),
+        };
+        (join, rest)
+    }
+
+    /// Whether at most one row of this table read can satisfy the join condition together with any row
+    /// it's joined to, so that a join passes on each left row exactly once.
+    fn is_matched_uniquely(&self, condition: &Expression) -> bool {
+        let source = match self {
+            Self::Scan(source) => source,
+            Self::Filter { input, .. } => match &**input {
+                Self::Scan(source) => source,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        let qualifier = match &source.qualifier {
+            Some(qualifier) => qualifier,
+            None => return false,
+        };
+        let key_column_names: Vec<String> = get_join_keys(condition, qualifier)
+            .iter()
+            .filter_map(|(_, right_key)| source.get_column_name(right_key))
+            .collect();
+        source.is_unique_by(&key_column_names)
+    }
+
+    /// The table and filtering condition, if this step is just reading the table.
+    fn into_table_read(self) -> Result<(Box<TableSource>, Option<Expression>), Self> {
+        match self {
+            Self::Scan(source) => Ok((source, None)),
+            Self::Filter { input, condition } => match *input {
+                Self::Scan(source) => Ok((source, Some(condition))),
+                input => Err(Self::Filter {
+                    input: Box::new(input),
+                    condition,
+                }),
+            },
+            other => Err(other),
+        }
+    }
+}
+
+/// What a node of the physical plan does with rows.
+#[derive(Debug)]
+pub enum Operator {
+    /// Reading rows of the table along the access path, in PRIMARY KEY order or the reverse if `backward`.
+    Scan {
+        schema: String,
+        table: TableDefinition,
+        access_path: AccessPath,
+        backward: bool,
+    },
+    /// Passing on rows of a WITH query.
+    CommonTableScan(Arc<Vec<Row>>),
+    /// Passing on input rows satisfying the condition, up to `max_count` of them.
+    Filter {
+        condition: Expression,
+        max_count: Option<usize>,
+    },
+    /// Turning groups of input rows into single rows.
+    Aggregate(Aggregation),
+    /// Sorting input rows, keeping only `max_count` first ones.
+    Sort {
+        order_by: Vec<OrderBy>,
+        max_count: Option<usize>,
+    },
+    /// Skipping `offset` input rows, then passing on up to `limit` of them.
+    Limit { offset: usize, limit: Option<usize> },
+    /// Computing output values from each input row.
+    Project(Vec<Expression>),
+    /// Pairing left input rows with right input rows whose keys are equal, found through a hash table
+    /// of the latter. The condition is checked against each pair.
+    HashJoin {
+        kind: JoinKind,
+        /// Pairs of expressions over left and right rows, whose values have to be equal.
+        keys: Vec<(Expression, Expression)>,
+        condition: Expression,
+    },
+    /// Pairing each left input row with rows of the table looked up by key, through the PRIMARY KEY
+    /// or an index. The condition is checked against each pair.
+    IndexNestedLoopJoin {
+        kind: JoinKind,
+        schema: String,
+        table: TableDefinition,
+        indexes: Vec<IndexDefinition>,
+        /// Pairs of expressions over left rows and the table's columns, whose values have to be equal.
+        keys: Vec<(Expression, String)>,
+        /// Condition on the table's rows alone, which can narrow lookups down further.
+        lookup_condition: Option<Expression>,
+        condition: Expression,
+    },
+    /// Pairing each left input row with each right input row satisfying the condition.
+    NestedLoopJoin {
+        kind: JoinKind,
+        condition: Expression,
+    },
+}
+
+/// Node of the physical plan.
+#[derive(Debug)]
+pub struct PlanNode {
+    pub operator: Operator,
+    /// What the node does, for EXPLAIN.
+    pub description: String,
+    pub inputs: Vec<PlanNode>,
+    /// Names of columns of rows coming out of the node.
+    pub column_names: Vec<String>,
+    pub estimated_rows: f64,
+    /// How many rows actually came out of the node, once it's been executed.
+    pub actual_rows: Option<usize>,
+}
+
+impl PlanNode {
+    fn new(
+        operator: Operator,
+        description: String,
+        inputs: Vec<PlanNode>,
+        column_names: Vec<String>,
+        estimated_rows: f64,
+    ) -> Self {
+        PlanNode {
+            operator,
+            description,
+            inputs,
+            column_names,
+            estimated_rows,
+            actual_rows: None,
+        }
+    }
+
+    /// Node with a single input, producing rows of the same shape.
+    fn on_top_of(
+        input: PlanNode,
+        operator: Operator,
+        description: String,
+        estimated_rows: f64,
+    ) -> Self {
+        let column_names = input.column_names.clone();
+        Self::new(
+            operator,
+            description,
+            vec![input],
+            column_names,
+            estimated_rows,
+        )
+    }
+
+    /// All nodes of the tree, depth-first, along with their depths.
+    pub fn flatten(&self) -> Vec<(usize, &PlanNode)> {
+        let mut nodes = vec![(0, self)];
+        for input in &self.inputs {
+            nodes.extend(
+                input
+                    .flatten()
+                    .into_iter()
+                    .map(|(depth, node)| (depth + 1, node)),
+            );
+        }
+        nodes
+    }
+
+    /// Execute the plan, giving back resulting rows. Row counts of all nodes are recorded along the way.
+    pub fn execute<'p>(
+        &'p mut self,
+        config: &'p config::Config,
+    ) -> BoxFuture<'p, Result<Vec<Row>, String>> {
+        async move {
+            let mut input_rows = Vec::with_capacity(self.inputs.len());
+            for input in &mut self.inputs {
+                input_rows.push(input.execute(config).await?);
+            }
+            let input_column_names = self
+                .inputs
+                .first()
+                .map_or(&[][..], |input| input.column_names.as_slice());
+            let mut input_rows = input_rows.into_iter();
+            let rows = input_rows.next().unwrap_or_default();
+            let rows = match &self.operator {
+                Operator::Scan {
+                    schema,
+                    table,
+                    access_path,
+                    backward,
+                } => {
+                    let mut rows = indexes::read_rows(config, schema, table, access_path).await?;
+                    if *backward {
+                        rows.reverse();
+                    }
+                    rows
+                }
+                Operator::CommonTableScan(rows) => rows.as_ref().clone(),
+                Operator::Filter {
+                    condition,
+                    max_count,
+                } => {
+                    let mut matching_rows = Vec::new();
+                    for row in rows {
+                        if max_count.is_some_and(|max_count| matching_rows.len() >= max_count) {
+                            break;
+                        }
+                        if condition
+                            .is_satisfied_by(&RowContext::new(input_column_names, &row.0))?
+                        {
+                            matching_rows.push(row);
+                        }
+                    }
+                    matching_rows
+                }
+                Operator::Aggregate(aggregation) => {
+                    aggregation.aggregate(input_column_names, rows)?
+                }
+                Operator::Sort {
+                    order_by,
+                    max_count,
+                } => {
+                    let keyed_rows = rows
+                        .into_iter()
+                        .map(|row| {
+                            let context = RowContext::new(input_column_names, &row.0);
+                            let key = order_by
+                                .iter()
+                                .map(|item| item.expression.evaluate(&context))
+                                .collect::<Result<Vec<DataInstance>, String>>()?;
+                            Ok((key, row))
+                        })
+                        .collect::<Result<Vec<(Vec<DataInstance>, Row)>, String>>()?;
+                    order::sort_rows(order_by, keyed_rows, *max_count)
+                }
+                Operator::Limit { offset, limit } => rows
+                    .into_iter()
+                    .skip(*offset)
+                    .take(limit.unwrap_or(usize::MAX))
+                    .collect(),
+                Operator::Project(expressions) => rows
+                    .into_iter()
+                    .map(|row| {
+                        let context = RowContext::new(input_column_names, &row.0);
+                        Ok(Row(expressions
+                            .iter()
+                            .map(|expression| expression.evaluate(&context))
+                            .collect::<Result<Vec<DataInstance>, String>>()?))
+                    })
+                    .collect::<Result<Vec<Row>, String>>()?,
+                Operator::HashJoin {
+                    kind,
+                    keys,
+                    condition,
+                } => {
+                    let right_column_names = &self.inputs[1].column_names;
+                    let right_rows = input_rows.next().unwrap_or_default();
+                    let mut right_rows_by_key: HashMap<Vec<DataInstanceRaw>, Vec<&Row>> =
+                        HashMap::new();
+                    for right_row in &right_rows {
+                        let context = RowContext::new(right_column_names, &right_row.0);
+                        let key = evaluate_join_key(keys.iter().map(|(_, key)| key), &context)?;
+                        if let Some(key) = key {
+                            right_rows_by_key
+                                .entry(normalize_join_key(key))
+                                .or_default()
+                                .push(right_row);
+                        }
+                    }
+                    let mut joined_rows = Vec::new();
+                    for left_row in &rows {
+                        let context = RowContext::new(input_column_names, &left_row.0);
+                        let key = evaluate_join_key(keys.iter().map(|(key, _)| key), &context)?;
+                        let candidates = key
+                            .and_then(|key| right_rows_by_key.get(&normalize_join_key(key)))
+                            .map_or(&[][..], Vec::as_slice);
+                        join_candidates(
+                            *kind,
+                            condition,
+                            &self.column_names,
+                            left_row,
+                            candidates.iter().copied(),
+                            &mut joined_rows,
+                        )?;
+                    }
+                    joined_rows
+                }
+                Operator::IndexNestedLoopJoin {
+                    kind,
+                    schema,
+                    table,
+                    indexes,
+                    keys,
+                    lookup_condition,
+                    condition,
+                } => {
+                    let mut joined_rows = Vec::new();
+                    for left_row in &rows {
+                        let context = RowContext::new(input_column_names, &left_row.0);
+                        let key = evaluate_join_key(keys.iter().map(|(key, _)| key), &context)?;
+                        let right_rows = match key {
+                            Some(key) => {
+                                let lookup_condition = conjoin(
+                                    keys.iter()
+                                        .zip(key)
+                                        .map(|((_, column_name), value)| {
+                                            Expression::Equal(
+                                                Box::new(Expression::Atom(
+                                                    DataDefinition::Identifier(column_name.clone()),
+                                                )),
+                                                Box::new(Expression::Atom(DataDefinition::Const(
+                                                    DataInstance::Direct(value),
+                                                ))),
+                                            )
+                                        })
+                                        .chain(lookup_condition.clone())
+                                        .collect(),
+                                );
+                                let access_path = indexes::plan_access_path(
+                                    table,
+                                    indexes,
+                                    lookup_condition.as_ref(),
+                                );
+                                indexes::read_rows(config, schema, table, &access_path).await?
+                            }
+                            // NULL isn't equal to anything
+                            None => Vec::new(),
+                        };
+                        join_candidates(
+                            *kind,
+                            condition,
+                            &self.column_names,
+                            left_row,
+                            right_rows.iter(),
+                            &mut joined_rows,
+                        )?;
+                    }
+                    joined_rows
+                }
+                Operator::NestedLoopJoin { kind, condition } => {
+                    let right_rows = input_rows.next().unwrap_or_default();
+                    let mut joined_rows = Vec::new();
+                    for left_row in &rows {
+                        join_candidates(
+                            *kind,
+                            condition,
+                            &self.column_names,
+                            left_row,
+                            right_rows.iter(),
+                            &mut joined_rows,
+                        )?;
+                    }
+                    joined_rows
+                }
+            };
+            self.actual_rows = Some(rows.len());
+            Ok(rows)
+        }
+        .boxed()
+    }
+}
+
+/// METRIC KEY values of a table's rows, as held by the metric index.
+struct MetricValues {
+    column: ColumnDefinition,
+    /// How many rows there are with each value.
+    counts: HashMap<u128, usize>,
+    total: usize,
+}
+
+impl MetricValues {
+    fn load(config: &config::Config, schema: &str, table: &TableDefinition) -> Option<Self> {
+        let column = table.get_metric_key()?.clone();
+        let data = Index::new(schema, &table.name, config).get_data();
+        let mut counts = HashMap::new();
+        for value in &data {
+            *counts.entry(*value).or_insert(0) += 1;
+        }
+        Some(MetricValues {
+            column,
+            counts,
+            total: data.len(),
+        })
+    }
+
+    /// Apply `f` to the context of a row with the given METRIC KEY value (`None` standing for NULL).
+    fn with_row<T>(
+        &self,
+        value: Option<u128>,
+        f: impl FnOnce(&RowContext) -> Result<T, String>,
+    ) -> Option<T> {
+        let instance = match value {
+            Some(value) => {
+                let value = DataInstanceRaw::UInt128(value)
+                    .coerce(self.column.data_type.raw_type)
+                    .ok()?;
+                match self.column.data_type.is_nullable {
+                    true => DataInstance::Nullable(value),
+                    false => DataInstance::Direct(value),
+                }
+            }
+            None => DataInstance::Null,
+        };
+        f(&RowContext::new(
+            std::slice::from_ref(&self.column.name),
+            &[instance],
+        ))
+        .ok()
+    }
+
+    /// Values for which the condition holds. `None` if the index can't tell, because the condition depends
+    /// on something else than the METRIC KEY column, or holds for NULL, which isn't in the index.
+    fn find_matching(&self, condition: &Expression) -> Option<HashSet<u128>> {
+        if !depends_only_on(condition, &self.column.name)
+            || self.with_row(None, |row| condition.is_satisfied_by(row))?
+        {
+            return None;
+        }
+        let mut values = HashSet::new();
+        for value in self.counts.keys() {
+            if self.with_row(Some(*value), |row| condition.is_satisfied_by(row))? {
+                values.insert(*value);
+            }
+        }
+        Some(values)
+    }
+
+    /// Values of the rows first in ascending order of `expression` - as many as needed for `count` rows,
+    /// plus ties.
+    fn find_nearest(&self, expression: &Expression, count: usize) -> Option<HashSet<u128>> {
+        if !depends_only_on(expression, &self.column.name) {
+            return None;
+        }
+        let mut keyed_values = Vec::with_capacity(self.counts.len());
+        for (value, value_count) in &self.counts {
+            let key = self
+                .with_row(Some(*value), |row| expression.evaluate(row))?
+                .into_raw()?;
+            keyed_values.push((key, *value, *value_count));
+        }
+        keyed_values
+            .sort_by(|(lhs, ..), (rhs, ..)| compare_values(lhs, rhs).unwrap_or(Ordering::Equal));
+        let mut values = HashSet::new();
+        let mut row_count = 0;
+        let mut farthest_key = None;
+        for (key, value, value_count) in keyed_values {
+            if let Some(farthest_key) = &farthest_key {
+                if compare_values(&key, farthest_key) == Ok(Ordering::Greater) {
+                    break;
+                }
+            }
+            values.insert(value);
+            row_count += value_count;
+            if row_count >= count && farthest_key.is_none() {
+                farthest_key = Some(key);
+            }
+        }
+        Some(values)
+    }
+
+    /// Number of rows with any of the values.
+    fn count(&self, values: &HashSet<u128>) -> usize {
+        values
+            .iter()
+            .map(|value| self.counts.get(value).copied().unwrap_or(0))
+            .sum()
+    }
+}
+
+/// Whether the column is the only thing that the expression's value depends on - there are no other columns
+/// or function calls.
+fn depends_only_on(expression: &Expression, column_name: &str) -> bool {
+    fn visit(expression: &Expression, column_name: &str, is_column_found: &mut bool) -> bool {
+        match expression {
+            Expression::Atom(DataDefinition::Identifier(name)) => {
+                *is_column_found = true;
+                name == column_name
+            }
+            Expression::Atom(DataDefinition::FunctionCall(..)) => false,
+            _ => expression
+                .get_operands()
+                .into_iter()
+                .all(|operand| visit(operand, column_name, is_column_found)),
+        }
+    }
+    let mut is_column_found = false;
+    visit(expression, column_name, &mut is_column_found) && is_column_found
+}
+
+/// Conditions of the top-level AND chain.
+fn get_conjuncts(condition: &Expression) -> Vec<&Expression> {
+    match condition {
+        Expression::And(lhs, rhs) => [get_conjuncts(lhs), get_conjuncts(rhs)].concat(),
+        _ => vec![condition],
+    }
+}
+
+/// AND chain of the conditions, `None` if there are none.
+fn conjoin(conditions: Vec<Expression>) -> Option<Expression> {
+    conditions
+        .into_iter()
+        .reduce(|lhs, rhs| Expression::And(Box::new(lhs), Box::new(rhs)))
+}
+
+/// Qualifiers of columns that the expression refers to, e.g. `p` for `p.url`. An unqualified column is counted
+/// as an empty qualifier.
+pub fn get_referenced_qualifiers(expression: &Expression) -> HashSet<&str> {
+    match expression {
+        Expression::Atom(DataDefinition::Identifier(name)) => {
+            HashSet::from([name.split_once('.').map_or("", |(qualifier, _)| qualifier)])
+        }
+        _ => expression
+            .get_operands()
+            .into_iter()
+            .flat_map(get_referenced_qualifiers)
+            .collect(),
+    }
+}
+
+/// Pairs of expressions over rows of other tables and over rows of the table with the qualifier,
+/// which the join condition requires to be equal - as found in its AND chain.
+fn get_join_keys(condition: &Expression, qualifier: &str) -> Vec<(Expression, Expression)> {
+    // Whether the expression is over rows of the table (`true`) or of other tables (`false`)
+    let get_side = |expression: &Expression| {
+        let referenced_qualifiers = get_referenced_qualifiers(expression);
+        if referenced_qualifiers.is_empty() {
+            None
+        } else if referenced_qualifiers
+            .iter()
+            .all(|referenced| *referenced == qualifier)
+        {
+            Some(true)
+        } else if !referenced_qualifiers.contains(qualifier) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    get_conjuncts(condition)
+        .into_iter()
+        .filter_map(|conjunct| match conjunct {
+            Expression::Equal(lhs, rhs) => match (get_side(lhs), get_side(rhs)) {
+                (Some(false), Some(true)) => Some(((**lhs).clone(), (**rhs).clone())),
+                (Some(true), Some(false)) => Some(((**rhs).clone(), (**lhs).clone())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Values of join key expressions for a row. `None` if any of them is NULL, as then there can't be a pair.
+fn evaluate_join_key<'e>(
+    expressions: impl Iterator<Item = &'e Expression>,
+    context: &RowContext,
+) -> Result<Option<Vec<DataInstanceRaw>>, String> {
+    let mut key = Vec::new();
+    for expression in expressions {
+        match expression.evaluate(context)?.into_raw() {
+            Some(value) => key.push(value),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(key))
+}
+
+/// Join key values made comparable by hash, as integers of different types are equal if their values are.
+fn normalize_join_key(key: Vec<DataInstanceRaw>) -> Vec<DataInstanceRaw> {
+    key.into_iter()
+        .map(|value| match value.as_u128() {
+            Some(value) => DataInstanceRaw::UInt128(value),
+            None => value,
+        })
+        .collect()
+}
+
+/// Add rows made of the left row and each of the candidate right rows, if they satisfy the condition.
+/// With a LEFT join the left row is passed on with NULLs in place of right values if there are no such rows.
+fn join_candidates<'r>(
+    kind: JoinKind,
+    condition: &Expression,
+    column_names: &[String],
+    left_row: &Row,
+    candidates: impl Iterator<Item = &'r Row>,
+    joined_rows: &mut Vec<Row>,
+) -> Result<(), String> {
+    let mut is_paired = false;
+    for right_row in candidates {
+        let row = Row([left_row.0.as_slice(), right_row.0.as_slice()].concat());
+        if condition.is_satisfied_by(&RowContext::new(column_names, &row.0))? {
+            joined_rows.push(row);
+            is_paired = true;
+        }
+    }
+    if !is_paired && kind == JoinKind::Left {
+        let mut values = left_row.0.clone();
+        values.resize(column_names.len(), DataInstance::Null);
+        joined_rows.push(Row(values));
+    }
+    Ok(())
+}
+
+/// What estimates of row counts for reading a table are based on.
+struct TableEstimates<'s> {
+    row_count: f64,
+    statistics: Option<&'s TableStatistics>,
+    metric_values: Option<MetricValues>,
+    /// Columns whose values are unique by themselves.
+    unique_column_names: Vec<String>,
+}
+
+impl<'s> TableEstimates<'s> {
+    /// Estimates for rows that nothing is known about, other than how many there are.
+    fn unknown(row_count: f64) -> Self {
+        TableEstimates {
+            row_count,
+            statistics: None,
+            metric_values: None,
+            unique_column_names: Vec::new(),
+        }
+    }
+
+    /// Estimates for reading the table's rows that satisfy the condition. The metric index has to be read
+    /// whole, so that only happens if the condition has parts that the index can narrow down.
+    fn load(
+        config: &config::Config,
+        source: &'s TableSource,
+        condition: Option<&Expression>,
+    ) -> Self {
+        let metric_values = match (source.table.get_metric_key(), condition) {
+            (Some(metric_key), Some(condition))
+                if get_conjuncts(condition)
+                    .into_iter()
+                    .any(|conjunct| depends_only_on(conjunct, &metric_key.name)) =>
+            {
+                MetricValues::load(config, &source.schema, &source.table)
+            }
+            _ => None,
+        };
+        let mut unique_column_names: Vec<String> = source
+            .indexes
+            .iter()
+            .filter(|index| index.unique && index.column_names.len() == 1)
+            .map(|index| index.column_names[0].clone())
+            .collect();
+        if let [key_column_name] = source.table.get_key_column_names().as_slice() {
+            unique_column_names.push(key_column_name.to_string());
+        }
+        TableEstimates {
+            row_count: source.row_count as f64,
+            statistics: source.statistics.as_deref(),
+            metric_values,
+            unique_column_names,
+        }
+    }
+
+    /// Assumed fraction of rows satisfying the condition. Conditions on the METRIC KEY alone are checked
+    /// against the metric index, so their fraction is exact. Otherwise statistics are used, if the table
+    /// has been analyzed.
+    fn estimate_selectivity(&self, condition: &Expression) -> f64 {
+        if let Some(metric_values) = &self.metric_values {
+            if let Some(values) = metric_values.find_matching(condition) {
+                return metric_values.count(&values) as f64 / metric_values.total.max(1) as f64;
+            }
+        }
+        match condition {
+            Expression::And(lhs, rhs) => {
+                self.estimate_selectivity(lhs) * self.estimate_selectivity(rhs)
+            }
+            Expression::Or(lhs, rhs) => {
+                let lhs = self.estimate_selectivity(lhs);
+                let rhs = self.estimate_selectivity(rhs);
+                lhs + rhs - lhs * rhs
+            }
+            Expression::Not(operand) => 1.0 - self.estimate_selectivity(operand),
+            Expression::Equal(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_eq)
+                .unwrap_or_else(|| self.estimate_equality_selectivity(&[lhs, rhs])),
+            Expression::NotEqual(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_ne)
+                .unwrap_or_else(|| 1.0 - self.estimate_equality_selectivity(&[lhs, rhs])),
+            Expression::LessThan(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_lt)
+                .unwrap_or(RANGE_SELECTIVITY),
+            Expression::LessThanOrEqual(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_le)
+                .unwrap_or(RANGE_SELECTIVITY),
+            Expression::GreaterThan(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_gt)
+                .unwrap_or(RANGE_SELECTIVITY),
+            Expression::GreaterThanOrEqual(lhs, rhs) => self
+                .estimate_comparison_selectivity(lhs, rhs, Ordering::is_ge)
+                .unwrap_or(RANGE_SELECTIVITY),
+            Expression::Between(operand, low, high) => match (
+                self.estimate_comparison_selectivity(operand, high, Ordering::is_le),
+                self.estimate_comparison_selectivity(operand, low, Ordering::is_lt),
+            ) {
+                (Some(at_most_high), Some(below_low)) => (at_most_high - below_low).max(0.0),
+                _ => RANGE_SELECTIVITY * RANGE_SELECTIVITY,
+            },
+            Expression::IsNull(operand) => match &**operand {
+                Expression::Atom(DataDefinition::Identifier(column_name)) => self
+                    .get_column_statistics(column_name)
+                    .map_or(EQUALITY_SELECTIVITY, |(statistics, column)| {
+                        statistics.get_null_fraction(column)
+                    }),
+                _ => EQUALITY_SELECTIVITY,
+            },
+            Expression::Like(..) => EQUALITY_SELECTIVITY,
+            Expression::In(operand, items) => items
+                .iter()
+                .map(|item| {
+                    self.estimate_comparison_selectivity(operand, item, Ordering::is_eq)
+                        .unwrap_or_else(|| self.estimate_equality_selectivity(&[operand]))
+                })
+                .sum::<f64>()
+                .min(1.0),
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+
+    /// Assumed fraction of rows for which one of the operands is equal to a value. A unique column
+    /// is equal to a value in one row at most.
+    fn estimate_equality_selectivity(&self, operands: &[&Expression]) -> f64 {
+        if operands.iter().any(|operand| {
+            matches!(
+                operand,
+                Expression::Atom(DataDefinition::Identifier(column_name))
+                    if self.unique_column_names.contains(column_name)
+            )
+        }) {
+            1.0 / self.row_count.max(1.0)
+        } else {
+            EQUALITY_SELECTIVITY
+        }
+    }
+
+    fn get_column_statistics(
+        &self,
+        column_name: &str,
+    ) -> Option<(&'s TableStatistics, &'s ColumnStatistics)> {
+        let statistics = self.statistics?;
+        Some((statistics, statistics.get_column(column_name)?))
+    }
+
+    /// Fraction of rows for which comparing a column with a value gives an ordering accepted by `is_satisfied`,
+    /// according to statistics. `None` if there are no statistics of the column, or the value can't be
+    /// converted to the column's type.
+    fn estimate_column_selectivity(
+        &self,
+        column_name: &str,
+        value: &DataInstanceRaw,
+        is_satisfied: impl Fn(Ordering) -> bool,
+    ) -> Option<f64> {
+        let (statistics, column) = self.get_column_statistics(column_name)?;
+        let value = match column.histogram_bounds.first() {
+            Some(bound) => value.clone().coerce(bound.get_type()).ok()?,
+            None => value.clone(),
+        };
+        Some(statistics.estimate_comparison_selectivity(column, &value, is_satisfied))
+    }
+
+    /// Fraction of rows for which comparing the operands gives an ordering accepted by `is_satisfied`,
+    /// according to statistics. That's known for comparisons of a column, or of the METRIC KEY's distance
+    /// from a value, with a value.
+    fn estimate_comparison_selectivity(
+        &self,
+        lhs: &Expression,
+        rhs: &Expression,
+        is_satisfied: fn(Ordering) -> bool,
+    ) -> Option<f64> {
+        let (operand, value, is_flipped) = match (lhs, rhs) {
+            (operand, Expression::Atom(DataDefinition::Const(value))) => (operand, value, false),
+            (Expression::Atom(DataDefinition::Const(value)), operand) => (operand, value, true),
+            _ => return None,
+        };
+        let value = value.as_raw()?;
+        let is_satisfied = |ordering: Ordering| match is_flipped {
+            true => is_satisfied(ordering.reverse()),
+            false => is_satisfied(ordering),
+        };
+        match operand {
+            Expression::Atom(DataDefinition::Identifier(column_name)) => {
+                self.estimate_column_selectivity(column_name, value, is_satisfied)
+            }
+            Expression::Distance(distance_lhs, distance_rhs) => {
+                let column_name = match (&**distance_lhs, &**distance_rhs) {
+                    (
+                        Expression::Atom(DataDefinition::Identifier(column_name)),
+                        Expression::Atom(DataDefinition::Const(_)),
+                    )
+                    | (
+                        Expression::Atom(DataDefinition::Const(_)),
+                        Expression::Atom(DataDefinition::Identifier(column_name)),
+                    ) => column_name,
+                    _ => return None,
+                };
+                let (statistics, column) = self.get_column_statistics(column_name)?;
+                statistics.estimate_distance_selectivity(column, value.as_u128()?, is_satisfied)
+            }
+            _ => None,
+        }
+    }
+
+    /// Assumed number of rows within a B+ tree range over the given key columns.
+    fn estimate_key_range_rows(
+        &self,
+        column_names: &[String],
+        lower_bound: &Bound<Key>,
+        upper_bound: &Bound<Key>,
+        is_unique: bool,
+    ) -> f64 {
+        let (equal_values, lower_bound, upper_bound) = split_key_range(lower_bound, upper_bound);
+        if is_unique && equal_values.len() == column_names.len() {
+            return self.row_count.min(1.0);
+        }
+        let mut rows = self.row_count;
+        for (column_name, value) in column_names.iter().zip(equal_values) {
+            rows *= self
+                .estimate_column_selectivity(column_name, value, Ordering::is_eq)
+                .unwrap_or(EQUALITY_SELECTIVITY);
+        }
+        let column_name = match column_names.get(equal_values.len()) {
+            Some(column_name) => column_name,
+            None => return rows,
+        };
+        let estimate_bound =
+            |bound: Bound<&DataInstanceRaw>, is_satisfied: fn(Ordering) -> bool| match bound {
+                Bound::Included(value) | Bound::Excluded(value) => Some(
+                    self.estimate_column_selectivity(column_name, value, |ordering| {
+                        is_satisfied(ordering)
+                            || (ordering.is_eq() && matches!(bound, Bound::Included(_)))
+                    })
+                    .unwrap_or(RANGE_SELECTIVITY),
+                ),
+                Bound::Unbounded => None,
+            };
+        match (
+            estimate_bound(lower_bound, Ordering::is_gt),
+            estimate_bound(upper_bound, Ordering::is_lt),
+        ) {
+            // Rows that are both above the lower bound and below the upper one
+            (Some(above_lower), Some(below_upper)) => match self.get_column_statistics(column_name)
+            {
+                Some((statistics, column)) => {
+                    rows * (above_lower + below_upper - 1.0 + statistics.get_null_fraction(column))
+                        .max(0.0)
+                }
+                None => rows * above_lower * below_upper,
+            },
+            (Some(selectivity), None) | (None, Some(selectivity)) => rows * selectivity,
+            (None, None) => rows,
+        }
+    }
+}
+
+/// Split a B+ tree range, as built by `indexes::plan_access_path`, into values that the leading key columns
+/// are equal to, and bounds of the key column after those.
+fn split_key_range<'k>(
+    lower_bound: &'k Bound<Key>,
+    upper_bound: &'k Bound<Key>,
+) -> (
+    &'k [DataInstanceRaw],
+    Bound<&'k DataInstanceRaw>,
+    Bound<&'k DataInstanceRaw>,
+) {
+    let key_of = |bound: &'k Bound<Key>| match bound {
+        Bound::Included(key) | Bound::Excluded(key) => Some(key.as_slice()),
+        Bound::Unbounded => None,
+    };
+    let (equal_values, mut equal_count) = match (key_of(lower_bound), key_of(upper_bound)) {
+        (Some(lower_key), Some(upper_key)) => (
+            lower_key,
+            lower_key
+                .iter()
+                .zip(upper_key)
+                .take_while(|(lower_value, upper_value)| lower_value == upper_value)
+                .count(),
+        ),
+        _ => (&[][..], 0),
+    };
+    // An excluded bound can't be just the equality prefix, so then its last value is a range one after all
+    for bound in [lower_bound, upper_bound] {
+        if matches!(bound, Bound::Excluded(key) if key.len() == equal_count) {
+            equal_count -= 1;
+        }
+    }
+    let range_bound = |bound: &'k Bound<Key>| match bound {
+        Bound::Included(key) if key.len() > equal_count => Bound::Included(&key[equal_count]),
+        Bound::Excluded(key) if key.len() > equal_count => Bound::Excluded(&key[equal_count]),
+        _ => Bound::Unbounded,
+    };
+    (
+        &equal_values[..equal_count],
+        range_bound(lower_bound),
+        range_bound(upper_bound),
+    )
+}
+
+/// Conditions that a B+ tree range over the given key columns stands for, e.g. `domain = 'a' AND url < 'b'`.
+fn describe_key_range(
+    column_names: &[String],
+    lower_bound: &Bound<Key>,
+    upper_bound: &Bound<Key>,
+) -> String {
+    let (equal_values, lower_bound, upper_bound) = split_key_range(lower_bound, upper_bound);
+    let mut conditions: Vec<String> = column_names
+        .iter()
+        .zip(equal_values)
+        .map(|(column_name, value)| format!("{} = {}", column_name, value))
+        .collect();
+    if let Some(column_name) = column_names.get(equal_values.len()) {
+        match lower_bound {
+            Bound::Included(value) => conditions.push(format!("{} >= {}", column_name, value)),
+            Bound::Excluded(value) => conditions.push(format!("{} > {}", column_name, value)),
+            Bound::Unbounded => (),
+        }
+        match upper_bound {
+            Bound::Included(value) => conditions.push(format!("{} <= {}", column_name, value)),
+            Bound::Excluded(value) => conditions.push(format!("{} < {}", column_name, value)),
+            Bound::Unbounded => (),
+        }
+    }
+    conditions.join(" AND ")
+}
+
+/// Direction in which the table's B+ tree has to be walked for rows to come out in the given order
+/// (`true` meaning backward), if that's the order of the PRIMARY KEY or its first columns, or there's no order.
+fn get_key_order_direction(table: &TableDefinition, order_by: &[OrderBy]) -> Option<bool> {
+    let first_item = match order_by.first() {
+        Some(first_item) => first_item,
+        None => return Some(false),
+    };
+    let key_column_names = table.get_key_column_names();
+    let is_key_prefix = order_by.len() <= key_column_names.len()
+        && order_by
+            .iter()
+            .zip(&key_column_names)
+            .all(|(item, key_column_name)| {
+                item.descending == first_item.descending
+                    && matches!(
+                        &item.expression,
+                        Expression::Atom(DataDefinition::Identifier(column_name))
+                            if column_name == key_column_name
+                    )
+            });
+    is_key_prefix.then_some(first_item.descending)
+}
+
+/// Description of reading a table, e.g. `Index Seek using photos_width_idx on public.photos (width > 100)`.
+fn describe_scan(
+    kind: &str,
+    backward: bool,
+    index_name: Option<&str>,
+    source: &TableSource,
+    details: &str,
+) -> String {
+    let mut description = kind.to_string();
+    if backward {
+        description.push_str(" Backward");
+    }
+    if let Some(index_name) = index_name {
+        description.push_str(&format!(" using {}", index_name));
+    }
+    description.push_str(&format!(" on {}", source.describe()));
+    if !details.is_empty() {
+        description.push_str(&format!(" ({})", details));
+    }
+    description
+}
+
+/// Turns logical plans into physical ones.
+pub struct Planner<'p> {
+    config: &'p config::Config,
+}
+
+impl<'p> Planner<'p> {
+    pub fn new(config: &'p config::Config) -> Self {
+        Planner { config }
+    }
+
+    pub fn plan(&self, logical_plan: LogicalPlan) -> PlanNode {
+        match logical_plan {
+            LogicalPlan::Scan(source) => self.plan_scan(*source, None, false, None),
+            LogicalPlan::CommonTableScan(source) => PlanNode::new(
+                Operator::CommonTableScan(source.rows.clone()),
+                format!("CTE Scan on {}", source.describe()),
+                Vec::new(),
+                source.get_column_names(),
+                source.rows.len() as f64,
+            ),
+            LogicalPlan::Filter { input, condition } => match (*input).into_table_read() {
+                Ok((source, None)) => self.plan_scan(*source, Some(condition), false, None),
+                Ok((source, Some(inner_condition))) => self.plan_scan(
+                    *source,
+                    Some(Expression::And(
+                        Box::new(inner_condition),
+                        Box::new(condition),
+                    )),
+                    false,
+                    None,
+                ),
+                Err(input) => {
+                    let description = format!("Filter ({})", input.describe_expression(&condition));
+                    let input = self.plan(input);
+                    let estimated_rows = input.estimated_rows
+                        * TableEstimates::unknown(input.estimated_rows)
+                            .estimate_selectivity(&condition);
+                    PlanNode::on_top_of(
+                        input,
+                        Operator::Filter {
+                            condition,
+                            max_count: None,
+                        },
+                        description,
+                        estimated_rows,
+                    )
+                }
+            },
+            LogicalPlan::Aggregate { input, aggregation } => {
+                let input = self.plan(*input);
+                let estimated_rows = match aggregation.is_grouped() {
+                    true => input.estimated_rows.min(DEFAULT_GROUP_COUNT),
+                    false => 1.0,
+                };
+                let details = aggregation.to_string();
+                let description = match details.is_empty() {
+                    true => "Aggregate".to_string(),
+                    false => format!("Aggregate ({})", details),
+                };
+                let column_names = aggregation.table.get_column_names();
+                PlanNode::new(
+                    Operator::Aggregate(*aggregation),
+                    description,
+                    vec![input],
+                    column_names,
+                    estimated_rows,
+                )
+            }
+            LogicalPlan::Sort { input, order_by } => self.plan_sort(*input, order_by, None),
+            LogicalPlan::Limit {
+                input,
+                offset,
+                limit,
+            } => {
+                let max_count = limit.map(|limit| offset.saturating_add(limit));
+                let input = match *input {
+                    LogicalPlan::Sort { input, order_by } => {
+                        self.plan_sort(*input, order_by, max_count)
+                    }
+                    input => self.plan_sort(input, Vec::new(), max_count),
+                };
+                let mut description = match limit {
+                    Some(limit) => format!("Limit {}", limit),
+                    None => "Limit ALL".to_string(),
+                };
+                if offset > 0 {
+                    description.push_str(&format!(" OFFSET {}", offset));
+                }
+                let estimated_rows = (input.estimated_rows - offset as f64)
+                    .max(0.0)
+                    .min(limit.map_or(f64::INFINITY, |limit| limit as f64));
+                PlanNode::on_top_of(
+                    input,
+                    Operator::Limit { offset, limit },
+                    description,
+                    estimated_rows,
+                )
+            }
+            LogicalPlan::Join {
+                left,
+                right,
+                kind,
+                condition,
+            } => {
+                let left = self.plan(*left);
+                self.plan_join(left, *right, kind, condition)
+            }
+            LogicalPlan::Project {
+                input,
+                expressions,
+                column_names,
+            } => {
+                let input = self.plan(*input);
+                let description = format!("Project ({})", column_names.join(", "));
+                let estimated_rows = input.estimated_rows;
+                PlanNode::new(
+                    Operator::Project(expressions),
+                    description,
+                    vec![input],
+                    column_names,
+                    estimated_rows,
+                )
+            }
+        }
+    }
+
+    /// Plan getting input rows in the given order (any order if there are no ORDER BY items), of which only
+    /// `max_count` first ones may be needed.
+    fn plan_sort(
+        &self,
+        input: LogicalPlan,
+        order_by: Vec<OrderBy>,
+        max_count: Option<usize>,
+    ) -> PlanNode {
+        let mut description = format!(
+            "Sort ({})",
+            order_by
+                .iter()
+                .map(|item| {
+                    let mut text = input.describe_expression(&item.expression);
+                    if item.descending {
+                        text.push_str(" DESC");
+                    }
+                    if item.nulls_first != item.descending {
+                        text.push_str(match item.nulls_first {
+                            true => " NULLS FIRST",
+                            false => " NULLS LAST",
+                        });
+                    }
+                    text
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+        if let Some(max_count) = max_count {
+            description.push_str(&format!(", top {}", max_count));
+        }
+        let input = match input.into_table_read() {
+            Ok((source, condition)) => {
+                let local_order_by: Vec<OrderBy> = order_by
+                    .iter()
+                    .map(|item| OrderBy {
+                        expression: source.localize(&item.expression),
+                        ..item.clone()
+                    })
+                    .collect();
+                // The B+ tree is ordered by the primary key, so in that case (or without any ORDER BY)
+                // no sorting is needed - the tree only has to be walked in the right direction
+                if let Some(backward) = get_key_order_direction(&source.table, &local_order_by) {
+                    return self.plan_scan(*source, condition, backward, max_count);
+                }
+                let metric_knn = match (&condition, max_count) {
+                    (None, Some(max_count)) => {
+                        self.plan_metric_knn(&source, &local_order_by[0], max_count)
+                    }
+                    _ => None,
+                };
+                match metric_knn {
+                    Some(metric_knn) => metric_knn,
+                    None => self.plan_scan(*source, condition, false, None),
+                }
+            }
+            // A LEFT join to rows matched uniquely passes on each left row once, in order, so sorting and
+            // limiting left rows beforehand gives the same result - possibly without reading all of them
+            Err(LogicalPlan::Join {
+                left,
+                right,
+                kind: JoinKind::Left,
+                condition,
+            }) if right.is_matched_uniquely(&condition)
+                && order_by.iter().all(|item| {
+                    let left_qualifiers = left.get_qualifiers();
+                    get_referenced_qualifiers(&item.expression)
+                        .iter()
+                        .all(|qualifier| left_qualifiers.contains(qualifier))
+                }) =>
+            {
+                let left = self.plan_sort(*left, order_by, max_count);
+                return self.plan_join(left, *right, JoinKind::Left, condition);
+            }
+            Err(input) if order_by.is_empty() => return self.plan(input),
+            Err(input) => self.plan(input),
+        };
+        let estimated_rows = max_count.map_or(input.estimated_rows, |max_count| {
+            input.estimated_rows.min(max_count as f64)
+        });
+        PlanNode::on_top_of(
+            input,
+            Operator::Sort {
+                order_by,
+                max_count,
+            },
+            description,
+            estimated_rows,
+        )
+    }
+
+    /// Plan reading rows of the table which satisfy the condition, in PRIMARY KEY order or the reverse if
+    /// `backward`, of which only `max_count` first ones may be needed.
+    fn plan_scan(
+        &self,
+        source: TableSource,
+        condition: Option<Expression>,
+        backward: bool,
+        max_count: Option<usize>,
+    ) -> PlanNode {
+        // Only the condition itself refers to columns the way the rest of the query does
+        let local_condition = condition
+            .as_ref()
+            .map(|condition| source.localize(condition));
+        let estimates = TableEstimates::load(self.config, &source, local_condition.as_ref());
+        let row_count = estimates.row_count;
+        let table = &source.table;
+        let mut access_path =
+            indexes::plan_access_path(table, &source.indexes, local_condition.as_ref());
+        let (mut estimated_rows, mut description) = match &access_path {
+            AccessPath::PrimaryKeyRange {
+                lower_bound,
+                upper_bound,
+            } => {
+                let key_column_names = table.get_key_column_names();
+                (
+                    estimates.estimate_key_range_rows(
+                        &key_column_names,
+                        lower_bound,
+                        upper_bound,
+                        true,
+                    ),
+                    describe_scan(
+                        "Primary Key Seek",
+                        backward,
+                        None,
+                        &source,
+                        &describe_key_range(&key_column_names, lower_bound, upper_bound),
+                    ),
+                )
+            }
+            AccessPath::IndexRange {
+                index,
+                lower_bound,
+                upper_bound,
+            } => (
+                estimates.estimate_key_range_rows(
+                    &index.column_names,
+                    lower_bound,
+                    upper_bound,
+                    index.unique,
+                ),
+                describe_scan(
+                    "Index Seek",
+                    backward,
+                    Some(&index.name),
+                    &source,
+                    &describe_key_range(&index.column_names, lower_bound, upper_bound),
+                ),
+            ),
+            AccessPath::FullScan | AccessPath::MetricValues { .. } => (
+                row_count,
+                describe_scan("Seq Scan", backward, None, &source, ""),
+            ),
+        };
+        if let (Some(metric_values), Some(condition)) = (&estimates.metric_values, &local_condition)
+        {
+            let mut metric_conditions = Vec::new();
+            let mut matching_values: Option<HashSet<u128>> = None;
+            for conjunct in get_conjuncts(condition) {
+                if let Some(values) = metric_values.find_matching(conjunct) {
+                    metric_conditions.push(conjunct.to_string());
+                    matching_values = Some(match matching_values {
+                        Some(matching_values) => &matching_values & &values,
+                        None => values,
+                    });
+                }
+            }
+            // The metric index doesn't point to rows, so it only beats reading the whole table if no rows match
+            if let Some(values) = matching_values
+                .filter(|values| values.is_empty() || access_path == AccessPath::FullScan)
+            {
+                estimated_rows = metric_values.count(&values) as f64;
+                description = describe_scan(
+                    "Metric Range",
+                    backward,
+                    None,
+                    &source,
+                    &metric_conditions.join(" AND "),
+                );
+                access_path = AccessPath::MetricValues {
+                    values,
+                    include_nulls: false,
+                };
+            }
+        }
+        let column_names = source.get_column_names();
+        let estimated_selectivity = local_condition
+            .as_ref()
+            .map(|condition| estimates.estimate_selectivity(condition));
+        let scan = PlanNode::new(
+            Operator::Scan {
+                schema: source.schema,
+                table: source.table,
+                access_path,
+                backward,
+            },
+            description,
+            Vec::new(),
+            column_names,
+            estimated_rows,
+        );
+        match (condition, estimated_selectivity) {
+            (Some(condition), Some(estimated_selectivity)) => {
+                let estimated_rows = scan.estimated_rows.min(row_count * estimated_selectivity);
+                let description = format!("Filter ({})", condition);
+                PlanNode::on_top_of(
+                    scan,
+                    Operator::Filter {
+                        condition,
+                        max_count,
+                    },
+                    description,
+                    estimated_rows,
+                )
+            }
+            _ => scan,
+        }
+    }
+
+    /// Plan reading just the rows nearest by METRIC KEY distance, if that's what the first ORDER BY item
+    /// sorts by, using the metric index to find out how near they have to be.
+    fn plan_metric_knn(
+        &self,
+        source: &TableSource,
+        first_item: &OrderBy,
+        max_count: usize,
+    ) -> Option<PlanNode> {
+        let (schema, table) = (source.schema.as_str(), &source.table);
+        if first_item.descending || !matches!(first_item.expression, Expression::Distance(..)) {
+            return None;
+        }
+        let metric_values = MetricValues::load(self.config, schema, table)?;
+        let values = metric_values.find_nearest(&first_item.expression, max_count)?;
+        let estimated_rows = metric_values.count(&values) as f64;
+        Some(PlanNode::new(
+            Operator::Scan {
+                schema: schema.to_string(),
+                table: table.clone(),
+                access_path: AccessPath::MetricValues {
+                    values,
+                    include_nulls: true,
+                },
+                backward: false,
+            },
+            describe_scan(
+                "Metric kNN",
+                false,
+                None,
+                source,
+                &format!("{}, k = {}", first_item.expression, max_count),
+            ),
+            Vec::new(),
+            source.get_column_names(),
+            estimated_rows,
+        ))
+    }
+
+    /// Plan joining rows of the right side to the left input rows. If the right side is reading a table,
+    /// its rows are looked up by key for each left row if that's cheap enough, otherwise right rows are
+    /// read once and paired up through a hash table.
+    fn plan_join(
+        &self,
+        left: PlanNode,
+        right: LogicalPlan,
+        kind: JoinKind,
+        condition: Expression,
+    ) -> PlanNode {
+        let join_name = match kind {
+            JoinKind::Inner => "Join",
+            JoinKind::Left => "Left Join",
+        };
+        let (source, right_condition) = match right.into_table_read() {
+            Ok(read) => read,
+            Err(right) => {
+                let keys = match right.get_qualifiers().as_slice() {
+                    [qualifier] => get_join_keys(&condition, qualifier),
+                    _ => Vec::new(),
+                };
+                let right = self.plan(right);
+                if keys.is_empty() {
+                    return self.plan_nested_loop_join(left, right, kind, condition, join_name);
+                }
+                return self.plan_hash_join(left, right, kind, keys, condition, join_name);
+            }
+        };
+        let keys = get_join_keys(&condition, source.qualifier.as_deref().unwrap_or_default());
+        let key_column_names: Vec<String> = keys
+            .iter()
+            .filter_map(|(_, right_key)| source.get_column_name(right_key))
+            .collect();
+        let local_right_condition = right_condition
+            .as_ref()
+            .map(|right_condition| source.localize(right_condition));
+        let estimates = TableEstimates::load(self.config, &source, local_right_condition.as_ref());
+        let right_selectivity = local_right_condition
+            .as_ref()
+            .map_or(1.0, |condition| estimates.estimate_selectivity(condition));
+        let right_rows = estimates.row_count * right_selectivity;
+        // How many right rows there are for each left row
+        let pair_count = if keys.is_empty() {
+            right_rows * DEFAULT_SELECTIVITY
+        } else if source.is_unique_by(&key_column_names) {
+            right_selectivity
+        } else {
+            match key_column_names
+                .first()
+                .and_then(|column_name| estimates.get_column_statistics(column_name))
+            {
+                Some((_, column)) => right_rows / column.distinct_count.max(1) as f64,
+                None => right_rows * EQUALITY_SELECTIVITY,
+            }
+        };
+        let mut estimated_rows = left.estimated_rows * pair_count;
+        if kind == JoinKind::Left {
+            estimated_rows = estimated_rows.max(left.estimated_rows);
+        }
+        let is_looked_up_by_key = key_column_names.iter().any(|column_name| {
+            source.table.get_key_column_names().first() == Some(column_name)
+                || source
+                    .indexes
+                    .iter()
+                    .any(|index| index.column_names.first() == Some(column_name))
+        });
+        let is_lookup_cheaper = left.estimated_rows * INDEX_LOOKUP_COST < estimates.row_count;
+        let column_names = [left.column_names.clone(), source.get_column_names()].concat();
+        if is_looked_up_by_key && is_lookup_cheaper {
+            let condition = conjoin(
+                [Some(condition), right_condition]
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            )
+            .expect("there's the join condition at least");
+            let description = format!(
+                "Index Nested Loop {} on {} ({})",
+                join_name,
+                source.describe(),
+                condition
+            );
+            let keys = keys
+                .into_iter()
+                .filter_map(|(left_key, right_key)| {
+                    Some((left_key, source.get_column_name(&right_key)?))
+                })
+                .collect();
+            let source = *source;
+            return PlanNode::new(
+                Operator::IndexNestedLoopJoin {
+                    kind,
+                    schema: source.schema,
+                    table: source.table,
+                    indexes: source.indexes,
+                    keys,
+                    lookup_condition: local_right_condition,
+                    condition,
+                },
+                description,
+                vec![left],
+                column_names,
+                estimated_rows,
+            );
+        }
+        let right = self.plan_scan(*source, right_condition, false, None);
+        if keys.is_empty() {
+            return self.plan_nested_loop_join(left, right, kind, condition, join_name);
+        }
+        let description = format!("Hash {} ({})", join_name, condition);
+        PlanNode::new(
+            Operator::HashJoin {
+                kind,
+                keys,
+                condition,
+            },
+            description,
+            vec![left, right],
+            column_names,
+            estimated_rows,
+        )
+    }
+
+    /// Plan pairing left input rows with right input rows whose keys are equal, without knowing more
+    /// about the right side than its estimated row count.
+    fn plan_hash_join(
+        &self,
+        left: PlanNode,
+        right: PlanNode,
+        kind: JoinKind,
+        keys: Vec<(Expression, Expression)>,
+        condition: Expression,
+        join_name: &str,
+    ) -> PlanNode {
+        let mut estimated_rows = left.estimated_rows * right.estimated_rows * EQUALITY_SELECTIVITY;
+        if kind == JoinKind::Left {
+            estimated_rows = estimated_rows.max(left.estimated_rows);
+        }
+        let description = format!("Hash {} ({})", join_name, condition);
+        let column_names = [left.column_names.clone(), right.column_names.clone()].concat();
+        PlanNode::new(
+            Operator::HashJoin {
+                kind,
+                keys,
+                condition,
+            },
+            description,
+            vec![left, right],
+            column_names,
+            estimated_rows,
+        )
+    }
+
+    /// Plan pairing each left input row with each right input row.
+    fn plan_nested_loop_join(
+        &self,
+        left: PlanNode,
+        right: PlanNode,
+        kind: JoinKind,
+        condition: Expression,
+        join_name: &str,
+    ) -> PlanNode {
+        let mut estimated_rows = left.estimated_rows
+            * right.estimated_rows
+            * TableEstimates::unknown(right.estimated_rows).estimate_selectivity(&condition);
+        if kind == JoinKind::Left {
+            estimated_rows = estimated_rows.max(left.estimated_rows);
+        }
+        let description = format!("Nested Loop {} ({})", join_name, condition);
+        let column_names = [left.column_names.clone(), right.column_names.clone()].concat();
+        PlanNode::new(
+            Operator::NestedLoopJoin { kind, condition },
+            description,
+            vec![left, right],
+            column_names,
+            estimated_rows,
+        )
+    }
+}
@@ -1,35 +1,245 @@
+use std::cmp::Ordering;
+use std::ops::Bound;
+
 use crate::{
     config,
-    constructs::components::TableDefinition,
-    storage::{filesystem::seek_read_decode_page, paging::Page, Row},
+    constructs::components::{DataInstanceRaw, TableDefinition},
+    storage::{
+        encoding::{GlobalCount, PageIndex},
+        filesystem::{seek_read_decode_page, TABLE_DATA_FILE_NAME},
+        paging::{Key, Page},
+        Row,
+    },
 };
 
-pub async fn read_all_rows(
+async fn read_root_page_index(
     config: &config::Config,
     schema: &str,
     table_definition: &TableDefinition,
-) -> Result<Vec<Row>, String> {
-    let meta = seek_read_decode_page(config, schema, table_definition, 0)
-        .await
-        .unwrap();
-    match meta {
+    file_name: &str,
+) -> Result<PageIndex, String> {
+    match seek_read_decode_page(config, schema, table_definition, file_name, 0).await? {
         Page::Meta {
             b_tree_root_page_index,
             ..
-        } => {
-            let data =
-                seek_read_decode_page(config, schema, table_definition, b_tree_root_page_index)
-                    .await
-                    .unwrap();
-            match data {
-                Page::BTreeLeaf { rows, .. } => Ok(rows),
-                _ => Err("Invalid page type 1".to_string()),
+        } => Ok(b_tree_root_page_index),
+        _ => Err("Invalid page type 0".to_string()),
+    }
+}
+
+/// Number of rows in the table, as kept in its meta page. Cheap, as only a single page is read.
+pub async fn read_row_count(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+) -> Result<GlobalCount, String> {
+    match seek_read_decode_page(config, schema, table_definition, TABLE_DATA_FILE_NAME, 0).await? {
+        Page::Meta { row_count, .. } => Ok(row_count),
+        _ => Err("Invalid page type 0".to_string()),
+    }
+}
+
+/// Values of the row's key columns, which the B+ tree is ordered by.
+pub fn get_row_key(row: &Row, key_indexes: &[usize]) -> Key {
+    key_indexes
+        .iter()
+        .map(|key_index| {
+            row.0[*key_index]
+                .as_raw()
+                .expect("A key value cannot be NULL")
+                .clone()
+        })
+        .collect()
+}
+
+/// Compare the beginning of a key with a bound, which may cover fewer columns than the key.
+fn compare_key_prefix(key: &[DataInstanceRaw], bound: &[DataInstanceRaw]) -> Ordering {
+    key[..bound.len().min(key.len())].cmp(bound)
+}
+
+/// Whether the key lies past the lower bound.
+fn is_above_lower_bound(key: &[DataInstanceRaw], lower_bound: &Bound<Key>) -> bool {
+    match lower_bound {
+        Bound::Included(bound) => compare_key_prefix(key, bound) != Ordering::Less,
+        Bound::Excluded(bound) => compare_key_prefix(key, bound) == Ordering::Greater,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Whether the key lies before the upper bound.
+fn is_below_upper_bound(key: &[DataInstanceRaw], upper_bound: &Bound<Key>) -> bool {
+    match upper_bound {
+        Bound::Included(bound) => compare_key_prefix(key, bound) != Ordering::Greater,
+        Bound::Excluded(bound) => compare_key_prefix(key, bound) == Ordering::Less,
+        Bound::Unbounded => true,
+    }
+}
+
+/// Find the index of the B+ tree leaf where keys satisfying the lower bound begin.
+async fn find_lower_bound_leaf_page_index(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    file_name: &str,
+    lower_bound: &Bound<Key>,
+) -> Result<PageIndex, String> {
+    let mut page_index = read_root_page_index(config, schema, table_definition, file_name).await?;
+    loop {
+        match seek_read_decode_page(config, schema, table_definition, file_name, page_index).await?
+        {
+            Page::BTreeNode {
+                primary_keys,
+                child_page_indexes,
+            } => {
+                // Separator key N is the lowest key found in child N+1, so children before the first separator
+                // that satisfies the bound can be skipped
+                page_index = child_page_indexes
+                    [primary_keys.partition_point(|key| !is_above_lower_bound(key, lower_bound))]
             }
+            Page::BTreeLeaf { .. } => return Ok(page_index),
+            _ => return Err(format!("Invalid page type at B+ tree page {}", page_index)),
         }
-        _ => Err("Invalid page type 0".to_string()),
     }
 }
 
+/// Find the index of the leftmost B+ tree leaf, where an in-order scan of the table begins.
+pub async fn find_first_leaf_page_index(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    file_name: &str,
+) -> Result<PageIndex, String> {
+    find_lower_bound_leaf_page_index(
+        config,
+        schema,
+        table_definition,
+        file_name,
+        &Bound::Unbounded,
+    )
+    .await
+}
+
+/// Find rows with the given primary keys, in the order of the keys, with `None` for keys not in the table.
+/// Keys are looked up in ascending order, so the meta page is read once and the B+ tree is descended
+/// once per leaf that they fall on, rather than once per key.
+pub async fn find_rows(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    primary_keys: &[Key],
+) -> Result<Vec<Option<Row>>, String> {
+    let key_indexes = table_definition.get_key_indexes();
+    let root_page_index =
+        read_root_page_index(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    let mut key_order: Vec<usize> = (0..primary_keys.len()).collect();
+    key_order.sort_by(|a, b| primary_keys[*a].cmp(&primary_keys[*b]));
+    let mut found_rows = vec![None; primary_keys.len()];
+    // Rows of the current leaf, along with the lowest key of the leaf after it, if there's any
+    let mut current_leaf: Option<(Vec<Row>, Option<Key>)> = None;
+    for key_position in key_order {
+        let primary_key = &primary_keys[key_position];
+        let is_on_current_leaf = matches!(
+            &current_leaf,
+            Some((_, upper_bound)) if upper_bound.as_ref().is_none_or(|upper_bound| primary_key < upper_bound)
+        );
+        if !is_on_current_leaf {
+            let mut page_index = root_page_index;
+            let mut upper_bound = None;
+            loop {
+                match seek_read_decode_page(
+                    config,
+                    schema,
+                    table_definition,
+                    TABLE_DATA_FILE_NAME,
+                    page_index,
+                )
+                .await?
+                {
+                    Page::BTreeNode {
+                        mut primary_keys,
+                        child_page_indexes,
+                    } => {
+                        // Separator key N is the lowest key found in child N+1
+                        let child_position = primary_keys.partition_point(|key| key <= primary_key);
+                        page_index = child_page_indexes[child_position];
+                        if child_position < primary_keys.len() {
+                            upper_bound = Some(primary_keys.swap_remove(child_position));
+                        }
+                    }
+                    Page::BTreeLeaf { rows, .. } => {
+                        current_leaf = Some((rows, upper_bound));
+                        break;
+                    }
+                    _ => return Err(format!("Invalid page type at B+ tree page {}", page_index)),
+                }
+            }
+        }
+        let (rows, _) = current_leaf.as_ref().expect("The key's leaf has been read");
+        found_rows[key_position] = rows
+            .binary_search_by(|row| get_row_key(row, &key_indexes).cmp(primary_key))
+            .ok()
+            .map(|row_position| rows[row_position].clone());
+    }
+    Ok(found_rows)
+}
+
+/// Read rows of a B+ tree file in key order, from the lower bound to the upper one. Bounds may be
+/// prefixes of keys, e.g. bounds covering just the first column of an index find all entries with
+/// values of that column in the range, regardless of values of further columns.
+pub async fn read_rows_in_range(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    file_name: &str,
+    lower_bound: &Bound<Key>,
+    upper_bound: &Bound<Key>,
+) -> Result<Vec<Row>, String> {
+    let key_indexes = table_definition.get_key_indexes();
+    let mut rows_in_range = Vec::new();
+    let mut page_index =
+        find_lower_bound_leaf_page_index(config, schema, table_definition, file_name, lower_bound)
+            .await?;
+    // Leaves are linked, with 0 marking the last one
+    while page_index != 0 {
+        match seek_read_decode_page(config, schema, table_definition, file_name, page_index).await?
+        {
+            Page::BTreeLeaf {
+                next_leaf_page_index,
+                rows,
+            } => {
+                for row in rows {
+                    let key = get_row_key(&row, &key_indexes);
+                    if !is_below_upper_bound(&key, upper_bound) {
+                        return Ok(rows_in_range);
+                    }
+                    if is_above_lower_bound(&key, lower_bound) {
+                        rows_in_range.push(row);
+                    }
+                }
+                page_index = next_leaf_page_index;
+            }
+            _ => return Err(format!("Invalid page type at B+ tree leaf {}", page_index)),
+        }
+    }
+    Ok(rows_in_range)
+}
+
+pub async fn read_all_rows(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+) -> Result<Vec<Row>, String> {
+    read_rows_in_range(
+        config,
+        schema,
+        table_definition,
+        TABLE_DATA_FILE_NAME,
+        &Bound::Unbounded,
+        &Bound::Unbounded,
+    )
+    .await
+}
+
 #[cfg(test)]
 mod read_tests {
     use crate::{
@@ -51,6 +261,7 @@ mod read_tests {
                         is_nullable: false,
                     },
                     primary_key: true,
+                    metric_key: false,
                     default: None,
                 },
                 ColumnDefinition {
@@ -60,6 +271,7 @@ mod read_tests {
                         is_nullable: false,
                     },
                     primary_key: false,
+                    metric_key: false,
                     default: None,
                 },
             ],
@@ -75,9 +287,15 @@ mod read_tests {
         let schema = "test";
         let data = construct_blank_table();
         let test_table = get_test_table();
-        write_table_file(&config, schema, &test_table.name, data)
-            .await
-            .unwrap();
+        write_table_file(
+            &config,
+            schema,
+            &test_table.name,
+            TABLE_DATA_FILE_NAME,
+            data,
+        )
+        .await
+        .unwrap();
         let rows = read_all_rows(&config, schema, &test_table).await.unwrap();
         assert_eq!(rows.len(), 0);
     }
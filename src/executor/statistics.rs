@@ -0,0 +1,464 @@
+//! Statistics of table data, gathered by ANALYZE from a sample of the table's pages. They let the planner
+//! estimate how many rows a condition matches, instead of assuming a fixed fraction.
+
+use std::cmp::Ordering;
+
+use rand::seq::index::sample;
+use rand::{thread_rng, Rng};
+use time::OffsetDateTime;
+
+use crate::config;
+use crate::constructs::components::{DataInstanceRaw, TableDefinition};
+use crate::storage::encoding::GlobalCount;
+use crate::storage::filesystem::{count_table_pages, seek_read_decode_page, TABLE_DATA_FILE_NAME};
+use crate::storage::paging::Page;
+use crate::storage::Row;
+
+use super::read::read_row_count;
+
+/// How many pages of a table are read at most to gather its statistics.
+const SAMPLE_PAGE_COUNT: usize = 100;
+/// How many buckets value histograms have at most.
+const HISTOGRAM_BUCKET_COUNT: usize = 20;
+/// How many pairs of sampled METRIC KEY values the distance distribution is based on at most.
+const DISTANCE_SAMPLE_PAIR_COUNT: usize = 10_000;
+/// Histogram bounds that are strings get cut to this many characters, so that statistics stay small.
+const MAX_BOUND_STRING_LENGTH: usize = 64;
+
+/// Statistics of a single column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStatistics {
+    pub column_name: String,
+    /// How many sampled rows have NULL in the column.
+    pub null_count: usize,
+    /// Estimated number of distinct non-NULL values in the whole table.
+    pub distinct_count: GlobalCount,
+    /// Bounds of an equi-depth histogram of sampled non-NULL values, i.e. about the same number of values
+    /// falls between each two consecutive bounds. The first bound is the lowest value, the last one the highest.
+    pub histogram_bounds: Vec<DataInstanceRaw>,
+    /// For the METRIC KEY column: how many pairs of sampled values are at each distance, from 0 up to
+    /// the number of bits of the column's type.
+    pub distance_counts: Option<Vec<GlobalCount>>,
+}
+
+/// Statistics of a table, as of the latest ANALYZE.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStatistics {
+    /// Number of rows in the table when it was analyzed.
+    pub row_count: GlobalCount,
+    /// Number of rows that the statistics are based on.
+    pub sampled_row_count: usize,
+    /// Statistics of each column, in the same order as the table's columns.
+    pub columns: Vec<ColumnStatistics>,
+    pub analyzed_at: OffsetDateTime,
+}
+
+impl TableStatistics {
+    /// Compute statistics of a table with `row_count` rows, based on a sample of them.
+    pub fn compute(table: &TableDefinition, row_count: GlobalCount, sampled_rows: &[Row]) -> Self {
+        let is_whole_table_sampled = sampled_rows.len() as GlobalCount >= row_count;
+        let columns = table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(column_index, column)| {
+                let mut values: Vec<&DataInstanceRaw> = sampled_rows
+                    .iter()
+                    .filter_map(|row| row.0[column_index].as_raw())
+                    .collect();
+                values.sort_unstable();
+                let null_count = sampled_rows.len() - values.len();
+                let distinct_count = match is_whole_table_sampled {
+                    true => count_distinct(&values).0 as GlobalCount,
+                    false => estimate_distinct_count(&values, sampled_rows.len(), row_count),
+                };
+                let distance_counts = match column.metric_key {
+                    true => column
+                        .data_type
+                        .raw_type
+                        .as_unsigned_bits()
+                        .map(|bits| count_distances(&values, bits)),
+                    false => None,
+                };
+                ColumnStatistics {
+                    column_name: column.name.clone(),
+                    null_count,
+                    distinct_count,
+                    histogram_bounds: get_histogram_bounds(&values),
+                    distance_counts,
+                }
+            })
+            .collect();
+        TableStatistics {
+            row_count,
+            sampled_row_count: sampled_rows.len(),
+            columns,
+            analyzed_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    pub fn get_column(&self, column_name: &str) -> Option<&ColumnStatistics> {
+        self.columns
+            .iter()
+            .find(|column| column.column_name == column_name)
+    }
+
+    /// Fraction of rows with NULL in the column.
+    pub fn get_null_fraction(&self, column: &ColumnStatistics) -> f64 {
+        column.null_count as f64 / self.sampled_row_count.max(1) as f64
+    }
+
+    /// Estimated fraction of rows for which comparing the column's value with `value` gives an ordering
+    /// accepted by `is_satisfied`. NULLs are never accepted.
+    pub fn estimate_comparison_selectivity(
+        &self,
+        column: &ColumnStatistics,
+        value: &DataInstanceRaw,
+        is_satisfied: impl Fn(Ordering) -> bool,
+    ) -> f64 {
+        let non_null_fraction = 1.0 - self.get_null_fraction(column);
+        let bounds = &column.histogram_bounds;
+        let (lowest, highest) = match (bounds.first(), bounds.last()) {
+            (Some(lowest), Some(highest)) => (lowest, highest),
+            _ => return 0.0,
+        };
+        // Fractions of non-NULL values that are less than, equal to, and greater than `value`
+        let equal = match value < lowest || value > highest {
+            true => 0.0,
+            false => 1.0 / column.distinct_count.max(1) as f64,
+        };
+        let less = match value.cmp(lowest) {
+            Ordering::Less | Ordering::Equal => 0.0,
+            Ordering::Greater if value > highest => 1.0,
+            Ordering::Greater => {
+                let bucket_count = (bounds.len() - 1) as f64;
+                let position = bounds.partition_point(|bound| bound < value);
+                let within_bucket =
+                    interpolate(&bounds[position - 1], &bounds[position], value).unwrap_or(0.5);
+                ((position - 1) as f64 + within_bucket) / bucket_count
+            }
+        }
+        .min(1.0 - equal);
+        let greater = (1.0 - less - equal).max(0.0);
+        let mut fraction = 0.0;
+        for (ordering, ordering_fraction) in [
+            (Ordering::Less, less),
+            (Ordering::Equal, equal),
+            (Ordering::Greater, greater),
+        ] {
+            if is_satisfied(ordering) {
+                fraction += ordering_fraction;
+            }
+        }
+        fraction * non_null_fraction
+    }
+
+    /// Estimated fraction of rows for which comparing the distance of the METRIC KEY from a value with
+    /// `distance` gives an ordering accepted by `is_satisfied`, based on distances between sampled values.
+    pub fn estimate_distance_selectivity(
+        &self,
+        column: &ColumnStatistics,
+        distance: u128,
+        is_satisfied: impl Fn(Ordering) -> bool,
+    ) -> Option<f64> {
+        let distance_counts = column.distance_counts.as_ref()?;
+        let total: GlobalCount = distance_counts.iter().sum();
+        if total == 0 {
+            return Some(0.0);
+        }
+        let matching: GlobalCount = distance_counts
+            .iter()
+            .enumerate()
+            .filter(|(pair_distance, _)| is_satisfied((*pair_distance as u128).cmp(&distance)))
+            .map(|(_, count)| count)
+            .sum();
+        Some(matching as f64 / total as f64 * (1.0 - self.get_null_fraction(column)))
+    }
+}
+
+/// Gather statistics of the table from a random sample of its pages, or all of them if there aren't many.
+pub async fn analyze_table(
+    config: &config::Config,
+    schema: &str,
+    table: &TableDefinition,
+) -> Result<TableStatistics, String> {
+    let row_count = read_row_count(config, schema, table).await?;
+    let page_count = count_table_pages(config, schema, &table.name, TABLE_DATA_FILE_NAME)
+        .await
+        .map_err(|error| error.to_string())? as usize;
+    // Page 0 is the meta page, so data pages start at 1
+    let mut page_indexes: Vec<usize> = match page_count - 1 <= SAMPLE_PAGE_COUNT {
+        true => (1..page_count).collect(),
+        false => sample(&mut thread_rng(), page_count - 1, SAMPLE_PAGE_COUNT)
+            .into_iter()
+            .map(|position| position + 1)
+            .collect(),
+    };
+    page_indexes.sort_unstable();
+    let mut sampled_rows = Vec::new();
+    for page_index in page_indexes {
+        // Only leaves hold rows - nodes and free pages are skipped
+        if let Page::BTreeLeaf { rows, .. } = seek_read_decode_page(
+            config,
+            schema,
+            table,
+            TABLE_DATA_FILE_NAME,
+            page_index as u32,
+        )
+        .await?
+        {
+            sampled_rows.extend(rows);
+        }
+    }
+    Ok(TableStatistics::compute(table, row_count, &sampled_rows))
+}
+
+/// Number of distinct values among sorted ones, and number of those that occur just once.
+fn count_distinct(sorted_values: &[&DataInstanceRaw]) -> (usize, usize) {
+    let mut distinct_count = 0;
+    let mut singleton_count = 0;
+    let mut position = 0;
+    while position < sorted_values.len() {
+        let run_length = sorted_values[position..]
+            .iter()
+            .take_while(|value| **value == sorted_values[position])
+            .count();
+        distinct_count += 1;
+        if run_length == 1 {
+            singleton_count += 1;
+        }
+        position += run_length;
+    }
+    (distinct_count, singleton_count)
+}
+
+/// Estimate the number of distinct values in the whole table from those in a sample of its rows,
+/// with the Haas-Stokes estimator: values seen just once in the sample hint at many more unseen ones.
+fn estimate_distinct_count(
+    sorted_values: &[&DataInstanceRaw],
+    sampled_row_count: usize,
+    row_count: GlobalCount,
+) -> GlobalCount {
+    let (distinct_count, singleton_count) = count_distinct(sorted_values);
+    if distinct_count == 0 {
+        return 0;
+    }
+    let sampled = sampled_row_count as f64;
+    let estimate = sampled * distinct_count as f64
+        / (sampled - singleton_count as f64 + singleton_count as f64 * sampled / row_count as f64);
+    let non_null_row_count = row_count as f64 * sorted_values.len() as f64 / sampled;
+    estimate.clamp(
+        distinct_count as f64,
+        non_null_row_count.max(distinct_count as f64),
+    ) as GlobalCount
+}
+
+/// Bounds of an equi-depth histogram of sorted values.
+fn get_histogram_bounds(sorted_values: &[&DataInstanceRaw]) -> Vec<DataInstanceRaw> {
+    if sorted_values.is_empty() {
+        return Vec::new();
+    }
+    let bucket_count = HISTOGRAM_BUCKET_COUNT.min(sorted_values.len() - 1);
+    (0..=bucket_count)
+        .map(|bound_index| {
+            let position = match bucket_count {
+                0 => 0,
+                _ => bound_index * (sorted_values.len() - 1) / bucket_count,
+            };
+            match sorted_values[position] {
+                DataInstanceRaw::String(value)
+                    if value.chars().count() > MAX_BOUND_STRING_LENGTH =>
+                {
+                    DataInstanceRaw::String(value.chars().take(MAX_BOUND_STRING_LENGTH).collect())
+                }
+                value => value.clone(),
+            }
+        })
+        .collect()
+}
+
+/// How many pairs of values are at each (Hamming) distance, for all pairs, or random ones if there are many.
+fn count_distances(values: &[&DataInstanceRaw], bits: u8) -> Vec<GlobalCount> {
+    let values: Vec<u128> = values.iter().filter_map(|value| value.as_u128()).collect();
+    let mut distance_counts = vec![0; usize::from(bits) + 1];
+    let mut count_pair = |lhs: u128, rhs: u128| {
+        distance_counts[(lhs ^ rhs).count_ones() as usize] += 1;
+    };
+    let pair_count = values.len() * values.len().saturating_sub(1) / 2;
+    if pair_count <= DISTANCE_SAMPLE_PAIR_COUNT {
+        for (position, lhs) in values.iter().enumerate() {
+            for rhs in &values[position + 1..] {
+                count_pair(*lhs, *rhs);
+            }
+        }
+    } else {
+        let mut rng = thread_rng();
+        for _ in 0..DISTANCE_SAMPLE_PAIR_COUNT {
+            let lhs_position = rng.gen_range(0..values.len());
+            // Skipping over the first value, so that a value is never paired with itself
+            let rhs_position = (lhs_position + rng.gen_range(1..values.len())) % values.len();
+            count_pair(values[lhs_position], values[rhs_position]);
+        }
+    }
+    distance_counts
+}
+
+/// Position of `value` between `lower` and `upper` as a fraction, if the values are of a numeric nature.
+fn interpolate(
+    lower: &DataInstanceRaw,
+    upper: &DataInstanceRaw,
+    value: &DataInstanceRaw,
+) -> Option<f64> {
+    let as_number = |value: &DataInstanceRaw| match value {
+        DataInstanceRaw::Timestamp(timestamp) => Some(timestamp.unix_timestamp() as f64),
+        DataInstanceRaw::Uuid(uuid) => Some(uuid.as_u128() as f64),
+        _ => value.as_u128().map(|value| value as f64),
+    };
+    let (lower, upper, value) = (as_number(lower)?, as_number(upper)?, as_number(value)?);
+    match upper > lower {
+        true => Some(((value - lower) / (upper - lower)).clamp(0.0, 1.0)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod statistics_tests {
+    use super::*;
+    use crate::constructs::components::{ColumnDefinition, DataInstance, DataType, DataTypeRaw};
+    use pretty_assertions::assert_eq;
+
+    fn get_test_table() -> TableDefinition {
+        TableDefinition::new(
+            "photos_seen".into(),
+            vec![
+                ColumnDefinition {
+                    name: "url".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::String,
+                        is_nullable: false,
+                    },
+                    primary_key: true,
+                    metric_key: false,
+                    default: None,
+                },
+                ColumnDefinition {
+                    name: "hash".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::UInt8,
+                        is_nullable: false,
+                    },
+                    primary_key: false,
+                    metric_key: true,
+                    default: None,
+                },
+                ColumnDefinition {
+                    name: "width".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::UInt16,
+                        is_nullable: true,
+                    },
+                    primary_key: false,
+                    metric_key: false,
+                    default: None,
+                },
+            ],
+        )
+    }
+
+    fn get_test_rows() -> Vec<Row> {
+        (0..100u16)
+            .map(|number| {
+                Row(vec![
+                    DataInstance::Direct(DataInstanceRaw::String(format!("photo-{:03}", number))),
+                    DataInstance::Direct(DataInstanceRaw::UInt8(match number % 2 {
+                        0 => 0b0000_0000,
+                        _ => 0b0000_0011,
+                    })),
+                    match number % 4 {
+                        0 => DataInstance::Null,
+                        _ => DataInstance::Nullable(DataInstanceRaw::UInt16(number * 10)),
+                    },
+                ])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn statistics_describe_whole_table() {
+        let statistics = TableStatistics::compute(&get_test_table(), 100, &get_test_rows());
+        assert_eq!(statistics.sampled_row_count, 100);
+        let url = statistics.get_column("url").unwrap();
+        assert_eq!(url.null_count, 0);
+        assert_eq!(url.distinct_count, 100);
+        assert_eq!(url.histogram_bounds.len(), HISTOGRAM_BUCKET_COUNT + 1);
+        assert_eq!(
+            url.histogram_bounds.first(),
+            Some(&DataInstanceRaw::String("photo-000".into()))
+        );
+        assert_eq!(
+            url.histogram_bounds.last(),
+            Some(&DataInstanceRaw::String("photo-099".into()))
+        );
+        let hash = statistics.get_column("hash").unwrap();
+        assert_eq!(hash.distinct_count, 2);
+        // 50 values of each kind: pairs of equal values are at distance 0, other pairs at distance 2
+        let mut expected_distance_counts = vec![0; 9];
+        expected_distance_counts[0] = 2 * 50 * 49 / 2;
+        expected_distance_counts[2] = 50 * 50;
+        assert_eq!(hash.distance_counts, Some(expected_distance_counts));
+        let width = statistics.get_column("width").unwrap();
+        assert_eq!(width.null_count, 25);
+        assert_eq!(width.distinct_count, 75);
+        assert_eq!(width.distance_counts, None);
+    }
+
+    #[test]
+    fn statistics_estimate_selectivity() {
+        let statistics = TableStatistics::compute(&get_test_table(), 100, &get_test_rows());
+        let width = statistics.get_column("width").unwrap();
+        assert_eq!(statistics.get_null_fraction(width), 0.25);
+        let is_less = |value: u16| {
+            statistics.estimate_comparison_selectivity(
+                width,
+                &DataInstanceRaw::UInt16(value),
+                Ordering::is_lt,
+            )
+        };
+        assert_eq!(is_less(0), 0.0);
+        assert_eq!(is_less(2000), 0.75);
+        // Widths are spread evenly from 10 to 990, so about half of non-NULL ones are below 500
+        assert!((is_less(500) - 0.375).abs() < 0.02);
+        let equal = statistics.estimate_comparison_selectivity(
+            width,
+            &DataInstanceRaw::UInt16(500),
+            Ordering::is_eq,
+        );
+        assert_eq!(equal, 0.75 / 75.0);
+        let hash = statistics.get_column("hash").unwrap();
+        let is_within = |distance: u128| {
+            statistics
+                .estimate_distance_selectivity(hash, distance, Ordering::is_le)
+                .unwrap()
+        };
+        assert!((is_within(1) - 2450.0 / 4950.0).abs() < 1e-9);
+        assert_eq!(is_within(2), 1.0);
+    }
+
+    #[test]
+    fn distinct_count_is_extrapolated_from_sample() {
+        let values: Vec<DataInstanceRaw> = (0..100u8).map(DataInstanceRaw::UInt8).collect();
+        let value_references: Vec<&DataInstanceRaw> = values.iter().collect();
+        // All sampled values are unique, so the whole table probably is too
+        assert_eq!(
+            estimate_distinct_count(&value_references, 100, 10_000),
+            10_000
+        );
+        let values: Vec<DataInstanceRaw> = (0..100u8)
+            .map(|number| DataInstanceRaw::UInt8(number % 10))
+            .collect();
+        let mut value_references: Vec<&DataInstanceRaw> = values.iter().collect();
+        value_references.sort_unstable();
+        // Every sampled value repeats, so there probably aren't many more
+        assert_eq!(estimate_distinct_count(&value_references, 100, 10_000), 10);
+    }
+}
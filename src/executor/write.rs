@@ -1,32 +1,40 @@
+use std::collections::BTreeMap;
 use std::io;
+use std::mem;
 
 use crate::config;
+use crate::storage::encoding::{Encodable, GlobalCount, LocalCount, PageIndex};
 use crate::storage::filesystem::{
-    does_table_file_exist, seek_read_decode_page, seek_write_page, write_table_file,
+    count_table_pages, does_table_file_exist, seek_read_decode_page, seek_write_page,
+    write_table_file, TABLE_DATA_FILE_NAME,
 };
-use crate::storage::paging::{construct_blank_table, Page};
-use crate::storage::system::SYSTEM_SCHEMA_NAME;
+use crate::storage::paging::{construct_blank_table, Key, Page, PAGE_SIZE};
 use crate::{constructs::components::TableDefinition, storage::Row};
 use tracing::*;
 
+use super::read::{find_first_leaf_page_index, get_row_key};
+
 pub async fn ensure_table_file_exists(
     config: &config::Config,
+    schema: &str,
     table_definition: &TableDefinition,
 ) -> io::Result<()> {
-    if !does_table_file_exist(&config, SYSTEM_SCHEMA_NAME, &table_definition.name).await {
+    if !does_table_file_exist(config, schema, &table_definition.name, TABLE_DATA_FILE_NAME).await {
         let blank_table_blob = construct_blank_table();
         match write_table_file(
-            &config,
-            SYSTEM_SCHEMA_NAME,
+            config,
+            schema,
             &table_definition.name,
+            TABLE_DATA_FILE_NAME,
             blank_table_blob,
         )
         .await
         {
-            Ok(_) => debug!("Initialized system table `{}`", table_definition.name),
+            Ok(_) => debug!("Initialized table `{}.{}`", schema, table_definition.name),
             Err(error) => {
                 trace!(
-                    "Failed to initialize system table `{}`: {}",
+                    "Failed to initialize table `{}.{}`: {}",
+                    schema,
                     table_definition.name,
                     error
                 );
@@ -37,40 +45,1240 @@ pub async fn ensure_table_file_exists(
     Ok(())
 }
 
+/// Pages that are filled less than this many bytes get merged with or take data from a sibling.
+const MIN_PAGE_FILL: usize = PAGE_SIZE / 2;
+
+/// A B+ tree node that was passed through while descending to a leaf.
+struct PathStep {
+    page_index: PageIndex,
+    primary_keys: Vec<Key>,
+    child_page_indexes: Vec<PageIndex>,
+    /// Position of the child that the descent continued into.
+    child_position: usize,
+}
+
+impl PathStep {
+    fn to_page(&self) -> Page {
+        Page::BTreeNode {
+            primary_keys: self.primary_keys.clone(),
+            child_page_indexes: self.child_page_indexes.clone(),
+        }
+    }
+}
+
+/// Handle for structural modifications of a B+ tree file, keeping track of the meta page.
+/// That's the table's data file, or the file of one of its indexes.
+/// The meta page must be saved once modifications are done, as insertions and deletions don't do that themselves.
+struct BTree<'t> {
+    config: &'t config::Config,
+    schema: &'t str,
+    /// Shape of the rows stored in the tree.
+    table_definition: &'t TableDefinition,
+    file_name: &'t str,
+    key_indexes: Vec<usize>,
+    layout_version: u8,
+    root_page_index: PageIndex,
+    first_free_page_index: PageIndex,
+    page_count: PageIndex,
+    row_count: GlobalCount,
+}
+
+impl<'t> BTree<'t> {
+    async fn open(
+        config: &'t config::Config,
+        schema: &'t str,
+        table_definition: &'t TableDefinition,
+        file_name: &'t str,
+    ) -> Result<BTree<'t>, String> {
+        match seek_read_decode_page(config, schema, table_definition, file_name, 0).await? {
+            Page::Meta {
+                layout_version,
+                b_tree_root_page_index,
+                first_free_page_index,
+                row_count,
+            } => Ok(BTree {
+                config,
+                schema,
+                table_definition,
+                file_name,
+                key_indexes: table_definition.get_key_indexes(),
+                layout_version,
+                root_page_index: b_tree_root_page_index,
+                first_free_page_index,
+                page_count: count_table_pages(config, schema, &table_definition.name, file_name)
+                    .await
+                    .map_err(|error| error.to_string())?,
+                row_count,
+            }),
+            _ => Err(format!(
+                "Found a non-meta page at the beginning of table {}.{}'s file {}",
+                schema, table_definition.name, file_name
+            )),
+        }
+    }
+
+    async fn read_page(&self, page_index: PageIndex) -> Result<Page, String> {
+        seek_read_decode_page(
+            self.config,
+            self.schema,
+            self.table_definition,
+            self.file_name,
+            page_index,
+        )
+        .await
+    }
+
+    async fn read_leaf(&self, page_index: PageIndex) -> Result<(PageIndex, Vec<Row>), String> {
+        match self.read_page(page_index).await? {
+            Page::BTreeLeaf {
+                next_leaf_page_index,
+                rows,
+            } => Ok((next_leaf_page_index, rows)),
+            _ => Err(format!("Expected a B+ tree leaf at page {}", page_index)),
+        }
+    }
+
+    async fn read_node(&self, page_index: PageIndex) -> Result<(Vec<Key>, Vec<PageIndex>), String> {
+        match self.read_page(page_index).await? {
+            Page::BTreeNode {
+                primary_keys,
+                child_page_indexes,
+            } => Ok((primary_keys, child_page_indexes)),
+            _ => Err(format!("Expected a B+ tree node at page {}", page_index)),
+        }
+    }
+
+    async fn write_page(&self, page_index: PageIndex, page: Page) -> Result<(), String> {
+        seek_write_page(
+            self.config,
+            self.schema,
+            &self.table_definition.name,
+            self.file_name,
+            page_index,
+            page.into(),
+        )
+        .await
+        .map_err(|error| error.to_string())
+    }
+
+    async fn save_meta(&self) -> Result<(), String> {
+        self.write_page(
+            0,
+            Page::Meta {
+                layout_version: self.layout_version,
+                b_tree_root_page_index: self.root_page_index,
+                first_free_page_index: self.first_free_page_index,
+                row_count: self.row_count,
+            },
+        )
+        .await
+    }
+
+    /// Get a page for new data, reusing a free one if possible. The meta page must be saved afterwards.
+    async fn allocate_page(&mut self) -> Result<PageIndex, String> {
+        if self.first_free_page_index == 0 {
+            self.page_count += 1;
+            return Ok(self.page_count - 1);
+        }
+        let page_index = self.first_free_page_index;
+        match self.read_page(page_index).await? {
+            Page::Free {
+                next_free_page_index,
+            } => self.first_free_page_index = next_free_page_index,
+            _ => return Err(format!("Expected a free page at page {}", page_index)),
+        }
+        Ok(page_index)
+    }
+
+    /// Return a page to the free list. The meta page must be saved afterwards.
+    async fn free_page(&mut self, page_index: PageIndex) -> Result<(), String> {
+        self.write_page(
+            page_index,
+            Page::Free {
+                next_free_page_index: self.first_free_page_index,
+            },
+        )
+        .await?;
+        self.first_free_page_index = page_index;
+        Ok(())
+    }
+
+    fn get_primary_key(&self, row: &Row) -> Key {
+        get_row_key(row, &self.key_indexes)
+    }
+
+    /// Descend from the root to the leaf where `primary_key` belongs.
+    /// Returns the nodes passed through on the way and the leaf's page index.
+    async fn find_leaf(&self, primary_key: &Key) -> Result<(Vec<PathStep>, PageIndex), String> {
+        let mut path = Vec::new();
+        let mut page_index = self.root_page_index;
+        loop {
+            match self.read_page(page_index).await? {
+                Page::BTreeNode {
+                    primary_keys,
+                    child_page_indexes,
+                } => {
+                    // Separator key N is the lowest key found in child N+1
+                    let child_position = primary_keys.partition_point(|key| key <= primary_key);
+                    let child_page_index = child_page_indexes[child_position];
+                    path.push(PathStep {
+                        page_index,
+                        primary_keys,
+                        child_page_indexes,
+                        child_position,
+                    });
+                    page_index = child_page_index;
+                }
+                Page::BTreeLeaf { .. } => return Ok((path, page_index)),
+                _ => return Err(format!("Invalid page type at B+ tree page {}", page_index)),
+            }
+        }
+    }
+
+    async fn insert(&mut self, row: Row) -> Result<(), String> {
+        if !(Page::BTreeLeaf {
+            next_leaf_page_index: 0,
+            rows: vec![row.clone()],
+        })
+        .fits()
+        {
+            return Err(format!(
+                "Row is too large to be stored, as it takes up {} B",
+                row.encoded_size()
+            ));
+        }
+        let primary_key = self.get_primary_key(&row);
+        let (mut path, leaf_page_index) = self.find_leaf(&primary_key).await?;
+        let (next_leaf_page_index, mut rows) = self.read_leaf(leaf_page_index).await?;
+        let position = rows.partition_point(|row| self.get_primary_key(row) <= primary_key);
+        if position > 0 && self.get_primary_key(&rows[position - 1]) == primary_key {
+            return Err(format!(
+                "Row with PRIMARY KEY value {:?} already exists",
+                primary_key
+            ));
+        }
+        rows.insert(position, row);
+        self.row_count += 1;
+        let leaf = Page::BTreeLeaf {
+            next_leaf_page_index,
+            rows,
+        };
+        if leaf.fits() {
+            return self.write_page(leaf_page_index, leaf).await;
+        }
+        // The leaf overflowed, so we split it in two and propagate the split upwards
+        let rows = match leaf {
+            Page::BTreeLeaf { rows, .. } => rows,
+            _ => unreachable!(),
+        };
+        let (left_rows, right_rows) = split_rows(rows)?;
+        let right_page_index = self.allocate_page().await?;
+        let mut separator = self.get_primary_key(&right_rows[0]);
+        self.write_page(
+            right_page_index,
+            Page::BTreeLeaf {
+                next_leaf_page_index,
+                rows: right_rows,
+            },
+        )
+        .await?;
+        self.write_page(
+            leaf_page_index,
+            Page::BTreeLeaf {
+                next_leaf_page_index: right_page_index,
+                rows: left_rows,
+            },
+        )
+        .await?;
+        let mut left_page_index = leaf_page_index;
+        let mut right_page_index = right_page_index;
+        loop {
+            match path.pop() {
+                Some(mut parent) => {
+                    parent.primary_keys.insert(parent.child_position, separator);
+                    parent
+                        .child_page_indexes
+                        .insert(parent.child_position + 1, right_page_index);
+                    let node = parent.to_page();
+                    if node.fits() {
+                        self.write_page(parent.page_index, node).await?;
+                        break;
+                    }
+                    // The node overflowed too - its middle key moves up to the grandparent
+                    let middle = find_node_split_position(&parent.primary_keys)?;
+                    let right_primary_keys = parent.primary_keys.split_off(middle + 1);
+                    separator = parent.primary_keys.pop().unwrap();
+                    let right_child_page_indexes = parent.child_page_indexes.split_off(middle + 1);
+                    right_page_index = self.allocate_page().await?;
+                    self.write_page(
+                        right_page_index,
+                        Page::BTreeNode {
+                            primary_keys: right_primary_keys,
+                            child_page_indexes: right_child_page_indexes,
+                        },
+                    )
+                    .await?;
+                    self.write_page(parent.page_index, parent.to_page()).await?;
+                    left_page_index = parent.page_index;
+                }
+                None => {
+                    // The root split, so the tree grows by a level
+                    let new_root_page_index = self.allocate_page().await?;
+                    self.write_page(
+                        new_root_page_index,
+                        Page::BTreeNode {
+                            primary_keys: vec![separator],
+                            child_page_indexes: vec![left_page_index, right_page_index],
+                        },
+                    )
+                    .await?;
+                    self.root_page_index = new_root_page_index;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete(&mut self, primary_key: &Key) -> Result<Option<Row>, String> {
+        let (path, leaf_page_index) = self.find_leaf(primary_key).await?;
+        let (next_leaf_page_index, mut rows) = self.read_leaf(leaf_page_index).await?;
+        let removed_row = match rows
+            .iter()
+            .position(|row| self.get_primary_key(row) == *primary_key)
+        {
+            Some(position) => rows.remove(position),
+            None => return Ok(None),
+        };
+        self.row_count -= 1;
+        let leaf = Page::BTreeLeaf {
+            next_leaf_page_index,
+            rows,
+        };
+        self.write_leaf(path, leaf_page_index, leaf).await?;
+        Ok(Some(removed_row))
+    }
+
+    /// Merge the leaf where `primary_key` belongs with a sibling or redistribute rows between them if the leaf
+    /// is underfull, e.g. after its rows were rewritten in place to be smaller, or taken out of it.
+    async fn rebalance_leaf(&mut self, primary_key: &Key) -> Result<(), String> {
+        let (path, leaf_page_index) = self.find_leaf(primary_key).await?;
+        let (next_leaf_page_index, rows) = self.read_leaf(leaf_page_index).await?;
+        let leaf = Page::BTreeLeaf {
+            next_leaf_page_index,
+            rows,
+        };
+        if leaf.encoded_size() < MIN_PAGE_FILL {
+            self.write_leaf(path, leaf_page_index, leaf).await?;
+        }
+        Ok(())
+    }
+
+    /// Write back a leaf that may have shrunk, merging it with or redistributing it with a sibling if it became
+    /// underfull. `path` leads from the root to the leaf's parent.
+    async fn write_leaf(
+        &mut self,
+        mut path: Vec<PathStep>,
+        leaf_page_index: PageIndex,
+        leaf: Page,
+    ) -> Result<(), String> {
+        let next_leaf_page_index = match &leaf {
+            Page::BTreeLeaf {
+                next_leaf_page_index,
+                ..
+            } => *next_leaf_page_index,
+            _ => {
+                return Err(format!(
+                    "Invalid page type at B+ tree leaf {}",
+                    leaf_page_index
+                ))
+            }
+        };
+        // The root leaf may be arbitrarily small, and other leaves are fine as long as they're filled enough
+        let mut parent = match path.pop() {
+            Some(parent) if leaf.encoded_size() < MIN_PAGE_FILL => parent,
+            _ => return self.write_page(leaf_page_index, leaf).await,
+        };
+        // Rebalance the leaf with a sibling - the left one if there is one, otherwise the right one
+        let left_position = parent.child_position.saturating_sub(1);
+        let left_page_index = parent.child_page_indexes[left_position];
+        let right_page_index = parent.child_page_indexes[left_position + 1];
+        let ((_, left_rows), (right_next_leaf_page_index, right_rows)) =
+            if left_page_index == leaf_page_index {
+                (
+                    (right_page_index, leaf.into_rows()),
+                    self.read_leaf(right_page_index).await?,
+                )
+            } else {
+                (
+                    self.read_leaf(left_page_index).await?,
+                    (next_leaf_page_index, leaf.into_rows()),
+                )
+            };
+        let mut combined_rows = left_rows;
+        combined_rows.extend(right_rows);
+        let merged_leaf = Page::BTreeLeaf {
+            next_leaf_page_index: right_next_leaf_page_index,
+            rows: combined_rows,
+        };
+        if !merged_leaf.fits() {
+            // Siblings together are too big for a single page, so we redistribute rows between them evenly
+            let (left_rows, right_rows) = split_rows(merged_leaf.into_rows())?;
+            parent.primary_keys[left_position] = self.get_primary_key(&right_rows[0]);
+            self.write_page(
+                left_page_index,
+                Page::BTreeLeaf {
+                    next_leaf_page_index: right_page_index,
+                    rows: left_rows,
+                },
+            )
+            .await?;
+            self.write_page(
+                right_page_index,
+                Page::BTreeLeaf {
+                    next_leaf_page_index: right_next_leaf_page_index,
+                    rows: right_rows,
+                },
+            )
+            .await?;
+            return self.write_page(parent.page_index, parent.to_page()).await;
+        }
+        // Siblings fit into a single page, so we merge the right one into the left one
+        self.write_page(left_page_index, merged_leaf).await?;
+        self.free_page(right_page_index).await?;
+        parent.primary_keys.remove(left_position);
+        parent.child_page_indexes.remove(left_position + 1);
+        self.rebalance_node(parent, path).await
+    }
+
+    /// Write back a node that just lost a child, merging it with or redistributing it with a sibling if it became
+    /// underfull, which may propagate up the tree. `path` leads from the root to the node's parent.
+    async fn rebalance_node(
+        &mut self,
+        mut node: PathStep,
+        mut path: Vec<PathStep>,
+    ) -> Result<(), String> {
+        loop {
+            let mut parent = match path.pop() {
+                Some(parent) if node.to_page().encoded_size() < MIN_PAGE_FILL => parent,
+                Some(_) => return self.write_page(node.page_index, node.to_page()).await,
+                None if node.child_page_indexes.len() == 1 => {
+                    // The root is left with a single child, so that child becomes the root and the tree shrinks
+                    self.root_page_index = node.child_page_indexes[0];
+                    return self.free_page(node.page_index).await;
+                }
+                None => return self.write_page(node.page_index, node.to_page()).await,
+            };
+            let left_position = parent.child_position.saturating_sub(1);
+            let left_page_index = parent.child_page_indexes[left_position];
+            let right_page_index = parent.child_page_indexes[left_position + 1];
+            let (
+                (mut primary_keys, mut child_page_indexes),
+                (right_primary_keys, right_child_page_indexes),
+            ) = if left_page_index == node.page_index {
+                (
+                    (node.primary_keys, node.child_page_indexes),
+                    self.read_node(right_page_index).await?,
+                )
+            } else {
+                (
+                    self.read_node(left_page_index).await?,
+                    (node.primary_keys, node.child_page_indexes),
+                )
+            };
+            // The parent's separator moves down between the siblings' keys
+            primary_keys.push(parent.primary_keys[left_position].clone());
+            primary_keys.extend(right_primary_keys);
+            child_page_indexes.extend(right_child_page_indexes);
+            let merged_node = Page::BTreeNode {
+                primary_keys,
+                child_page_indexes,
+            };
+            if !merged_node.fits() {
+                // Redistribute, with the middle key moving back up to the parent as the new separator
+                let (mut primary_keys, mut child_page_indexes) = match merged_node {
+                    Page::BTreeNode {
+                        primary_keys,
+                        child_page_indexes,
+                    } => (primary_keys, child_page_indexes),
+                    _ => unreachable!(),
+                };
+                let middle = find_node_split_position(&primary_keys)?;
+                let right_primary_keys = primary_keys.split_off(middle + 1);
+                parent.primary_keys[left_position] = primary_keys.pop().unwrap();
+                let right_child_page_indexes = child_page_indexes.split_off(middle + 1);
+                self.write_page(
+                    left_page_index,
+                    Page::BTreeNode {
+                        primary_keys,
+                        child_page_indexes,
+                    },
+                )
+                .await?;
+                self.write_page(
+                    right_page_index,
+                    Page::BTreeNode {
+                        primary_keys: right_primary_keys,
+                        child_page_indexes: right_child_page_indexes,
+                    },
+                )
+                .await?;
+                return self.write_page(parent.page_index, parent.to_page()).await;
+            }
+            self.write_page(left_page_index, merged_node).await?;
+            self.free_page(right_page_index).await?;
+            parent.primary_keys.remove(left_position);
+            parent.child_page_indexes.remove(left_position + 1);
+            node = parent;
+        }
+    }
+}
+
+/// Position at which to split a page's items, given their encoded sizes, so that both resulting pages carry
+/// about the same number of bytes, rather than the same number of items, as sizes vary. Positions are tried
+/// from the byte midpoint outwards until both pages fit, each taking up `base_size` bytes besides the items.
+/// With `is_item_at_position_moved_up`, the item at the position goes to neither page, as is the case for
+/// a node's middle key, which moves up to the parent.
+fn find_split_position(
+    item_sizes: &[usize],
+    base_size: usize,
+    is_item_at_position_moved_up: bool,
+) -> Result<usize, String> {
+    let mut offsets = Vec::with_capacity(item_sizes.len() + 1);
+    offsets.push(0);
+    for item_size in item_sizes {
+        offsets.push(offsets[offsets.len() - 1] + item_size);
+    }
+    let total_size = offsets[item_sizes.len()];
+    let right_start_offset = usize::from(is_item_at_position_moved_up);
+    let get_page_sizes = |position: usize| {
+        (
+            base_size + offsets[position],
+            base_size + total_size - offsets[position + right_start_offset],
+        )
+    };
+    // Both leaves must get a row, while a node is fine with a single child
+    let mut positions: Vec<usize> = (1 - right_start_offset..item_sizes.len()).collect();
+    positions.sort_by_key(|position| {
+        let (left_size, right_size) = get_page_sizes(*position);
+        left_size.abs_diff(right_size)
+    });
+    positions
+        .into_iter()
+        .find(|position| {
+            let (left_size, right_size) = get_page_sizes(*position);
+            left_size <= PAGE_SIZE && right_size <= PAGE_SIZE
+        })
+        .ok_or_else(|| "Contents of a B+ tree page cannot be split into two pages".to_string())
+}
+
+/// Split rows of an overflowing leaf into two leaves' worth of about the same size.
+fn split_rows(mut rows: Vec<Row>) -> Result<(Vec<Row>, Vec<Row>), String> {
+    let empty_leaf = Page::BTreeLeaf {
+        next_leaf_page_index: 0,
+        rows: Vec::new(),
+    };
+    // Besides its own size, each row takes up the size of its address
+    let row_sizes: Vec<usize> = rows
+        .iter()
+        .map(|row| mem::size_of::<LocalCount>() + row.encoded_size())
+        .collect();
+    let position = find_split_position(&row_sizes, empty_leaf.encoded_size(), false)?;
+    let right_rows = rows.split_off(position);
+    Ok((rows, right_rows))
+}
+
+/// Position of the key of an overflowing node that moves up to the parent, so that the nodes to its left and
+/// right are of about the same size.
+fn find_node_split_position(primary_keys: &[Key]) -> Result<usize, String> {
+    // Each key comes with the child to its right, while the leftmost child is part of the base size
+    let key_sizes: Vec<usize> = primary_keys
+        .iter()
+        .map(|key| {
+            key.iter()
+                .map(|key_value| key_value.encoded_size())
+                .sum::<usize>()
+                + mem::size_of::<PageIndex>()
+        })
+        .collect();
+    let base_size = Page::BTreeNode {
+        primary_keys: Vec::new(),
+        child_page_indexes: vec![0],
+    }
+    .encoded_size();
+    find_split_position(&key_sizes, base_size, true)
+}
+
+/// Insert a row into the table, in primary key order.
 pub async fn b_tree_insert(
     config: &config::Config,
     schema: &str,
     table_definition: &TableDefinition,
     row: Row,
 ) -> Result<(), String> {
-    let b_tree_root_page_index =
-        match seek_read_decode_page(config, schema, table_definition, 0).await? {
-            Page::Meta {
-                b_tree_root_page_index,
-                ..
-            } => b_tree_root_page_index,
-            _ => panic!(
-                "Found a non-meta page at the beginning of table {}.{}'s data file ",
-                schema, table_definition.name
-            ),
-        };
-    let mut b_tree_root_page =
-        seek_read_decode_page(config, schema, table_definition, b_tree_root_page_index).await?;
-    match b_tree_root_page {
-        Page::BTreeLeaf { ref mut rows, .. } => rows.push(row),
-        _ => panic!(
-            "Found a non-B-tree page at `b_tree_root_page_index` of table {}.{}'s data file",
-            schema, table_definition.name
-        ),
-    };
-    seek_write_page(
+    let mut tree = BTree::open(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    tree.insert(row).await?;
+    tree.save_meta().await
+}
+
+/// Insert rows into the table as one batch, so that the meta page is only read once.
+pub async fn b_tree_insert_rows(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    rows: Vec<Row>,
+) -> Result<(), String> {
+    let mut tree = BTree::open(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    for row in rows {
+        tree.insert(row).await?;
+    }
+    tree.save_meta().await
+}
+
+/// Replace existing rows with new versions, given as pairs of old and new versions, as one batch.
+/// Rows are rewritten in place on their leaves, with each affected leaf written once. Only rows whose
+/// primary key changed, or whose leaf would overflow with the new versions, are deleted and inserted anew.
+/// Leaves that shrank below the minimum fill are then rebalanced, same as after deletions.
+pub async fn b_tree_rewrite_rows(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    changes: Vec<(Row, Row)>,
+) -> Result<(), String> {
+    let mut tree = BTree::open(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    let mut changes_by_leaf: BTreeMap<PageIndex, Vec<(Key, Row)>> = BTreeMap::new();
+    let mut rows_to_move = Vec::new();
+    for (old_row, new_row) in changes {
+        let primary_key = tree.get_primary_key(&old_row);
+        if tree.get_primary_key(&new_row) != primary_key {
+            rows_to_move.push((primary_key, new_row));
+            continue;
+        }
+        let (_, leaf_page_index) = tree.find_leaf(&primary_key).await?;
+        changes_by_leaf
+            .entry(leaf_page_index)
+            .or_default()
+            .push((primary_key, new_row));
+    }
+    // Any key of a shrunk leaf leads back to it, or to where its rows went if it got merged in the meantime
+    let mut shrunk_leaf_keys = Vec::new();
+    for (leaf_page_index, leaf_changes) in changes_by_leaf {
+        let (next_leaf_page_index, mut rows) = tree.read_leaf(leaf_page_index).await?;
+        let (mut old_size, mut new_size) = (0, 0);
+        for (primary_key, new_row) in &leaf_changes {
+            let position = rows
+                .iter()
+                .position(|row| tree.get_primary_key(row) == *primary_key)
+                .ok_or_else(|| {
+                    format!(
+                        "Row with PRIMARY KEY value {:?} to replace does not exist",
+                        primary_key
+                    )
+                })?;
+            old_size += rows[position].encoded_size();
+            new_size += new_row.encoded_size();
+            rows[position] = new_row.clone();
+        }
+        let leaf = Page::BTreeLeaf {
+            next_leaf_page_index,
+            rows,
+        };
+        if leaf.fits() {
+            tree.write_page(leaf_page_index, leaf).await?;
+            if new_size < old_size {
+                shrunk_leaf_keys.push(leaf_changes[0].0.clone());
+            }
+        } else {
+            // The leaf is left as it was, and its changed rows go through the tree's own splitting instead
+            rows_to_move.extend(leaf_changes);
+        }
+    }
+    for primary_key in &shrunk_leaf_keys {
+        tree.rebalance_leaf(primary_key).await?;
+    }
+    // All deletions come first, so that rows may swap primary keys
+    for (primary_key, _) in &rows_to_move {
+        tree.delete(primary_key).await?;
+    }
+    for (_, new_row) in rows_to_move {
+        tree.insert(new_row).await?;
+    }
+    tree.save_meta().await
+}
+
+/// Replace all of the table's data with `rows`, e.g. after the table's row format has changed.
+/// The table file is written anew, so the rows are encoded according to `table_definition`.
+pub async fn b_tree_rebuild(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    rows: Vec<Row>,
+) -> Result<(), String> {
+    write_table_file(
         config,
         schema,
         &table_definition.name,
-        b_tree_root_page_index,
-        b_tree_root_page.into(),
+        TABLE_DATA_FILE_NAME,
+        construct_blank_table(),
     )
     .await
-    .unwrap();
-    Ok(())
+    .map_err(|error| error.to_string())?;
+    b_tree_insert_rows(config, schema, table_definition, rows).await
+}
+
+/// Apply a batch of changes to a B+ tree file other than the table's data file, e.g. that of an index,
+/// whose rows are shaped like `table_definition`. Rows with the given keys are deleted first, and only then
+/// are new rows inserted, so that a row may be replaced with another one of the same key.
+pub async fn b_tree_file_apply_changes(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    file_name: &str,
+    keys_to_delete: Vec<Key>,
+    rows_to_insert: Vec<Row>,
+) -> Result<(), String> {
+    let mut tree = BTree::open(config, schema, table_definition, file_name).await?;
+    for key in keys_to_delete {
+        tree.delete(&key).await?;
+    }
+    for row in rows_to_insert {
+        tree.insert(row).await?;
+    }
+    tree.save_meta().await
+}
+
+/// Delete the row with the given primary key from the table, returning it if it existed.
+pub async fn b_tree_delete(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    primary_key: &Key,
+) -> Result<Option<Row>, String> {
+    let mut tree = BTree::open(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    let removed_row = tree.delete(primary_key).await?;
+    tree.save_meta().await?;
+    Ok(removed_row)
+}
+
+/// Rewrite rows in place, leaf by leaf. `rewrite_row` returns the new version of a row, or `None` if the row
+/// is to be left as is. Only leaves that actually changed are written back. If a leaf would overflow with
+/// the new versions, its changed rows are deleted and inserted anew afterwards instead, so that the leaf is
+/// split properly, and leaves that shrank below the minimum fill are rebalanced, same as after deletions.
+/// Returns the changed rows as pairs of old and new versions.
+pub async fn b_tree_update_rows<F>(
+    config: &config::Config,
+    schema: &str,
+    table_definition: &TableDefinition,
+    mut rewrite_row: F,
+) -> Result<Vec<(Row, Row)>, String>
+where
+    F: FnMut(&Row) -> Result<Option<Row>, String>,
+{
+    let mut tree = BTree::open(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    let mut changes = Vec::new();
+    let mut rows_to_move = Vec::new();
+    let mut shrunk_leaf_keys = Vec::new();
+    let mut page_index =
+        find_first_leaf_page_index(config, schema, table_definition, TABLE_DATA_FILE_NAME).await?;
+    while page_index != 0 {
+        let (next_leaf_page_index, rows) = tree.read_leaf(page_index).await?;
+        let mut leaf_changes = Vec::new();
+        let mut new_rows = Vec::with_capacity(rows.len());
+        let (mut old_size, mut new_size) = (0, 0);
+        for row in rows {
+            match rewrite_row(&row)? {
+                Some(new_row) => {
+                    old_size += row.encoded_size();
+                    new_size += new_row.encoded_size();
+                    leaf_changes.push((row, new_row.clone()));
+                    new_rows.push(new_row);
+                }
+                None => new_rows.push(row),
+            }
+        }
+        if !leaf_changes.is_empty() {
+            let leaf = Page::BTreeLeaf {
+                next_leaf_page_index,
+                rows: new_rows,
+            };
+            if leaf.fits() {
+                tree.write_page(page_index, leaf).await?;
+                if new_size < old_size {
+                    shrunk_leaf_keys.push(tree.get_primary_key(&leaf_changes[0].0));
+                }
+            } else {
+                rows_to_move.extend(
+                    leaf_changes
+                        .iter()
+                        .map(|(old_row, new_row)| (tree.get_primary_key(old_row), new_row.clone())),
+                );
+            }
+            changes.extend(leaf_changes);
+        }
+        page_index = next_leaf_page_index;
+    }
+    // The tree's structure only changes once all leaves have been walked through
+    for primary_key in &shrunk_leaf_keys {
+        tree.rebalance_leaf(primary_key).await?;
+    }
+    for (primary_key, _) in &rows_to_move {
+        tree.delete(primary_key).await?;
+    }
+    for (_, new_row) in rows_to_move {
+        tree.insert(new_row).await?;
+    }
+    if !changes.is_empty() {
+        tree.save_meta().await?;
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod b_tree_tests {
+    use super::*;
+    use crate::{
+        constructs::components::{
+            ColumnDefinition, DataInstance, DataInstanceRaw, DataType, DataTypeRaw,
+        },
+        executor::read::{find_rows, read_all_rows, read_row_count},
+    };
+    use pretty_assertions::assert_eq;
+    use rand::distributions::Alphanumeric;
+    use rand::seq::SliceRandom;
+    use rand::{thread_rng, Rng};
+
+    fn get_test_table() -> TableDefinition {
+        let table_name: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+        TableDefinition::new(
+            table_name,
+            vec![
+                ColumnDefinition {
+                    name: "url".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::String,
+                        is_nullable: false,
+                    },
+                    primary_key: true,
+                    metric_key: false,
+                    default: None,
+                },
+                ColumnDefinition {
+                    name: "width".into(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::UInt32,
+                        is_nullable: false,
+                    },
+                    primary_key: false,
+                    metric_key: false,
+                    default: None,
+                },
+            ],
+        )
+    }
+
+    /// Long keys, so that only a few fit in a page and the tree grows multiple levels quickly.
+    fn get_test_row(number: u32) -> Row {
+        Row(vec![
+            DataInstance::Direct(DataInstanceRaw::String(format!(
+                "{:05}{}",
+                number,
+                "x".repeat(1000)
+            ))),
+            DataInstance::Direct(DataInstanceRaw::UInt32(number)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn insert_and_delete_keep_rows_ordered_through_splits_and_merges() {
+        let config = config::Config {
+            data_directory: env!("TMPDIR").to_string(),
+            ..Default::default()
+        };
+        let schema = "test";
+        let test_table = get_test_table();
+        ensure_table_file_exists(&config, schema, &test_table)
+            .await
+            .unwrap();
+        let mut numbers: Vec<u32> = (0..400).collect();
+        numbers.shuffle(&mut thread_rng());
+        for number in &numbers {
+            b_tree_insert(&config, schema, &test_table, get_test_row(*number))
+                .await
+                .unwrap();
+        }
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert!(matches!(
+            tree.read_page(tree.root_page_index).await.unwrap(),
+            Page::BTreeNode { .. }
+        ));
+        assert_eq!(tree.row_count, 400);
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            (0..400).map(get_test_row).collect::<Vec<_>>()
+        );
+
+        numbers.shuffle(&mut thread_rng());
+        let (numbers_deleted, numbers_kept) = numbers.split_at(350);
+        for number in numbers_deleted {
+            let deleted_row = b_tree_delete(
+                &config,
+                schema,
+                &test_table,
+                &vec![get_test_row(*number).0[0].as_raw().unwrap().clone()],
+            )
+            .await
+            .unwrap();
+            assert_eq!(deleted_row, Some(get_test_row(*number)));
+        }
+        assert_eq!(
+            read_row_count(&config, schema, &test_table).await.unwrap(),
+            50
+        );
+        let mut numbers_kept = numbers_kept.to_vec();
+        numbers_kept.sort_unstable();
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            numbers_kept
+                .iter()
+                .copied()
+                .map(get_test_row)
+                .collect::<Vec<_>>()
+        );
+
+        // Freed pages get reused before the file grows
+        let mut tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert_ne!(tree.first_free_page_index, 0);
+        for number in numbers_deleted {
+            let page_count_before = tree.page_count;
+            tree.insert(get_test_row(*number)).await.unwrap();
+            // The file may only grow once there are no free pages left
+            assert!(tree.page_count == page_count_before || tree.first_free_page_index == 0);
+        }
+        tree.save_meta().await.unwrap();
+        assert_eq!(
+            read_row_count(&config, schema, &test_table).await.unwrap(),
+            400
+        );
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            (0..400).map(get_test_row).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn overflowing_node_splits_at_byte_midpoint() {
+        let get_node = |primary_keys: &[Key]| Page::BTreeNode {
+            primary_keys: primary_keys.to_vec(),
+            child_page_indexes: vec![0; primary_keys.len() + 1],
+        };
+        let primary_keys: Vec<Key> = [2700, 2700, 2700]
+            .into_iter()
+            .chain([10; 20])
+            .map(|length| vec![DataInstanceRaw::String("k".repeat(length))])
+            .collect();
+        assert!(!get_node(&primary_keys).fits());
+        // Splitting by key count would leave the left node with all the long keys, overflowing it
+        assert!(!get_node(&primary_keys[..primary_keys.len() / 2]).fits());
+        let middle = find_node_split_position(&primary_keys).unwrap();
+        assert_eq!(middle, 1);
+        assert!(get_node(&primary_keys[..middle]).fits());
+        assert!(get_node(&primary_keys[middle + 1..]).fits());
+    }
+
+    #[tokio::test]
+    async fn find_rows_looks_up_keys_across_leaves_in_any_order() {
+        let config = config::Config {
+            data_directory: env!("TMPDIR").to_string(),
+            ..Default::default()
+        };
+        let schema = "test";
+        let test_table = get_test_table();
+        ensure_table_file_exists(&config, schema, &test_table)
+            .await
+            .unwrap();
+        // Only even numbers are in the table, so odd ones fall into gaps between rows and between leaves
+        b_tree_insert_rows(
+            &config,
+            schema,
+            &test_table,
+            (0..200).map(|number| get_test_row(number * 2)).collect(),
+        )
+        .await
+        .unwrap();
+        let mut numbers: Vec<u32> = (0..420).chain([10, 10]).collect();
+        numbers.shuffle(&mut thread_rng());
+        let primary_keys: Vec<Key> = numbers
+            .iter()
+            .map(|number| vec![get_test_row(*number).0[0].as_raw().unwrap().clone()])
+            .collect();
+        assert_eq!(
+            find_rows(&config, schema, &test_table, &primary_keys)
+                .await
+                .unwrap(),
+            numbers
+                .iter()
+                .map(|number| (number % 2 == 0 && *number < 400).then(|| get_test_row(*number)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn deleting_everything_shrinks_tree_back_to_a_single_leaf() {
+        let config = config::Config {
+            data_directory: env!("TMPDIR").to_string(),
+            ..Default::default()
+        };
+        let schema = "test";
+        let test_table = get_test_table();
+        ensure_table_file_exists(&config, schema, &test_table)
+            .await
+            .unwrap();
+        for number in 0..100 {
+            b_tree_insert(&config, schema, &test_table, get_test_row(number))
+                .await
+                .unwrap();
+        }
+        for number in 0..100 {
+            b_tree_delete(
+                &config,
+                schema,
+                &test_table,
+                &vec![get_test_row(number).0[0].as_raw().unwrap().clone()],
+            )
+            .await
+            .unwrap();
+        }
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert_eq!(
+            tree.read_page(tree.root_page_index).await.unwrap(),
+            Page::BTreeLeaf {
+                next_leaf_page_index: 0,
+                rows: vec![]
+            }
+        );
+        assert_eq!(
+            b_tree_delete(
+                &config,
+                schema,
+                &test_table,
+                &vec![get_test_row(0).0[0].as_raw().unwrap().clone()]
+            )
+            .await
+            .unwrap(),
+            None
+        );
+    }
+
+    async fn get_leaf_page_index(tree: &BTree<'_>, number: u32) -> PageIndex {
+        let primary_key = tree.get_primary_key(&get_test_row(number));
+        tree.find_leaf(&primary_key).await.unwrap().1
+    }
+
+    /// Encoded sizes of the tree's leaves, in order.
+    async fn get_leaf_sizes(tree: &BTree<'_>) -> Vec<usize> {
+        let mut leaf_sizes = Vec::new();
+        let mut page_index = find_first_leaf_page_index(
+            tree.config,
+            tree.schema,
+            tree.table_definition,
+            TABLE_DATA_FILE_NAME,
+        )
+        .await
+        .unwrap();
+        while page_index != 0 {
+            let (next_leaf_page_index, rows) = tree.read_leaf(page_index).await.unwrap();
+            leaf_sizes.push(
+                Page::BTreeLeaf {
+                    next_leaf_page_index,
+                    rows,
+                }
+                .encoded_size(),
+            );
+            page_index = next_leaf_page_index;
+        }
+        leaf_sizes
+    }
+
+    #[tokio::test]
+    async fn rewriting_rows_keeps_them_on_their_leaves_unless_their_key_changes() {
+        let config = config::Config {
+            data_directory: env!("TMPDIR").to_string(),
+            ..Default::default()
+        };
+        let schema = "test";
+        let test_table = get_test_table();
+        ensure_table_file_exists(&config, schema, &test_table)
+            .await
+            .unwrap();
+        b_tree_insert_rows(
+            &config,
+            schema,
+            &test_table,
+            (0..100).map(get_test_row).collect(),
+        )
+        .await
+        .unwrap();
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        let mut leaf_page_indexes_before = Vec::new();
+        for number in 0..100 {
+            leaf_page_indexes_before.push(get_leaf_page_index(&tree, number).await);
+        }
+        let page_count_before = tree.page_count;
+
+        let with_width = |mut row: Row, width: u32| {
+            row.0[1] = DataInstance::Direct(DataInstanceRaw::UInt32(width));
+            row
+        };
+        let changes: Vec<(Row, Row)> = (0..100)
+            .step_by(3)
+            .map(|number| {
+                (
+                    get_test_row(number),
+                    with_width(get_test_row(number), number * 2),
+                )
+            })
+            .collect();
+        b_tree_rewrite_rows(&config, schema, &test_table, changes)
+            .await
+            .unwrap();
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert_eq!(tree.page_count, page_count_before);
+        assert_eq!(tree.row_count, 100);
+        for number in 0..100 {
+            assert_eq!(
+                get_leaf_page_index(&tree, number).await,
+                leaf_page_indexes_before[number as usize]
+            );
+        }
+        let with_new_widths = |number: u32| match number % 3 {
+            0 if number < 100 => with_width(get_test_row(number), number * 2),
+            _ => get_test_row(number),
+        };
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            (0..100).map(with_new_widths).collect::<Vec<_>>()
+        );
+
+        // Row 1 gets the primary key of a new row 1000, so it has to move to another leaf
+        b_tree_rewrite_rows(
+            &config,
+            schema,
+            &test_table,
+            vec![(get_test_row(1), get_test_row(1000))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            read_row_count(&config, schema, &test_table).await.unwrap(),
+            100
+        );
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            (0..100)
+                .filter(|number| *number != 1)
+                .chain([1000])
+                .map(with_new_widths)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn rewriting_rows_smaller_rebalances_leaves_like_deleting() {
+        let config = config::Config {
+            data_directory: env!("TMPDIR").to_string(),
+            ..Default::default()
+        };
+        let schema = "test";
+        let mut test_table = get_test_table();
+        test_table.columns[1] = ColumnDefinition {
+            name: "caption".into(),
+            data_type: DataType {
+                raw_type: DataTypeRaw::String,
+                is_nullable: false,
+            },
+            primary_key: false,
+            metric_key: false,
+            default: None,
+        };
+        let get_row = |number: u32, caption_length: usize| {
+            Row(vec![
+                DataInstance::Direct(DataInstanceRaw::String(format!("{:05}", number))),
+                DataInstance::Direct(DataInstanceRaw::String("x".repeat(caption_length))),
+            ])
+        };
+        ensure_table_file_exists(&config, schema, &test_table)
+            .await
+            .unwrap();
+        b_tree_insert_rows(
+            &config,
+            schema,
+            &test_table,
+            (0..200).map(|number| get_row(number, 500)).collect(),
+        )
+        .await
+        .unwrap();
+
+        // The first half of rows shrinks in place, which would leave a trail of nearly empty leaves
+        b_tree_rewrite_rows(
+            &config,
+            schema,
+            &test_table,
+            (0..100)
+                .map(|number| (get_row(number, 500), get_row(number, 0)))
+                .collect(),
+        )
+        .await
+        .unwrap();
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert_eq!(tree.row_count, 200);
+        // Splits leave leaves about half full, while shrunk ones got merged together
+        let leaf_sizes = get_leaf_sizes(&tree).await;
+        assert!(leaf_sizes.len() > 1);
+        assert!(leaf_sizes[0] >= MIN_PAGE_FILL);
+        assert!(leaf_sizes.iter().all(|size| *size >= MIN_PAGE_FILL / 2));
+
+        // The second half shrinks through a rewrite of all rows
+        b_tree_update_rows(&config, schema, &test_table, |row| {
+            Ok(match &row.0[1] {
+                DataInstance::Direct(DataInstanceRaw::String(caption)) if !caption.is_empty() => {
+                    Some(Row(vec![row.0[0].clone(), get_row(0, 0).0[1].clone()]))
+                }
+                _ => None,
+            })
+        })
+        .await
+        .unwrap();
+        let tree = BTree::open(&config, schema, &test_table, TABLE_DATA_FILE_NAME)
+            .await
+            .unwrap();
+        assert_eq!(tree.row_count, 200);
+        // All rows fit into a single leaf now, which is the root
+        assert!(matches!(
+            tree.read_page(tree.root_page_index).await.unwrap(),
+            Page::BTreeLeaf { .. }
+        ));
+        assert_eq!(get_leaf_sizes(&tree).await.len(), 1);
+        assert_eq!(
+            read_all_rows(&config, schema, &test_table).await.unwrap(),
+            (0..200)
+                .map(|number| get_row(number, 0))
+                .collect::<Vec<_>>()
+        );
+    }
 }
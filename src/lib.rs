@@ -26,7 +26,9 @@ impl Instance {
         let executor_tx = executor.prepare_channel();
         let (executor_join_result, _) = tokio::join!(
             tokio::spawn(async move {
-                executor.start().await;
+                if let Err(error) = executor.start().await {
+                    error!("‼️ Encountered executor error: {}", error);
+                }
             }),
             server::start_server(&self.config, executor_tx),
         );
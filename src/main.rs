@@ -5,6 +5,7 @@ use human_panic::setup_panic;
 use tracing::*;
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
+#[allow(deprecated)] // human-panic's `setup_panic!` still refers to `PanicInfo`
 fn main() {
     setup_panic!(Metadata {
         name: "Emdrive".into(),
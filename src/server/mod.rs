@@ -1,7 +1,7 @@
 use crate::config;
 use crate::constructs::components::Validatable;
-use crate::executor::{ExecutorPayload, QueryResult};
-use crate::sql::parse_statement;
+use crate::executor::{ExecutorError, ExecutorPayload, ExecutorResult};
+use crate::sql::{parse_statements, SyntaxErrorInInput};
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use serde::{ser::SerializeMap, Serialize, Serializer};
@@ -29,38 +29,97 @@ impl Serialize for ServerError {
     }
 }
 
+/// Response to a single statement: its result or its error.
+struct StatementResponse(ExecutorResult);
+
+impl Serialize for StatementResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.0 {
+            Ok(query_result) => query_result.serialize(serializer),
+            Err(executor_error) => executor_error.serialize(serializer),
+        }
+    }
+}
+
+/// Response to an internal failure, which isn't the fault of the request.
+fn get_server_error_response(message: &str) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        serde_json::to_string(&ServerError(message.into())).unwrap(),
+    )
+}
+
+/// Status code of the response to a statement that the executor failed to carry out.
+fn get_error_status_code(executor_error: &ExecutorError) -> StatusCode {
+    match executor_error {
+        ExecutorError::Validation(_) => StatusCode::BAD_REQUEST,
+        ExecutorError::Execution(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        ExecutorError::Constraint(_) => StatusCode::CONFLICT,
+    }
+}
+
+/// Execute the semicolon-separated statements of the body in order, stopping at the first one that fails.
+/// Nothing is executed if any statement is syntactically or semantically invalid. With a single statement
+/// the response is its result or error, otherwise it's a list of those - ending with the error if there
+/// was one.
 async fn process_post(
     executor_tx: mpsc::Sender<ExecutorPayload>,
     body: &str,
 ) -> (StatusCode, String) {
-    let statement = parse_statement(&body);
-    if let Err(parsing_error) = statement {
-        return (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&parsing_error).unwrap(),
-        );
+    let statements = match parse_statements(body) {
+        Ok(statements) => statements,
+        Err(parsing_error) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                serde_json::to_string(&SyntaxErrorInInput(&parsing_error, body)).unwrap(),
+            )
+        }
+    };
+    for statement in &statements {
+        if let Err(validation_error) = statement.validate() {
+            return (
+                StatusCode::BAD_REQUEST,
+                serde_json::to_string(&validation_error).unwrap(),
+            );
+        }
     }
-    let statement = statement.unwrap();
-    if let Err(validation_error) = statement.validate() {
-        return (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&validation_error).unwrap(),
-        );
+    let is_single_statement = statements.len() == 1;
+    let mut status_code = StatusCode::OK;
+    let mut responses = Vec::with_capacity(statements.len());
+    for statement in statements {
+        let (resp_tx, resp_rx) = oneshot::channel::<ExecutorResult>();
+        // If there was an error on `send` or on receiving, that means that the executor has disconnected
+        // for some reason
+        if executor_tx.send((statement, resp_tx)).await.is_err() {
+            return get_server_error_response("The query executor has disengaged.");
+        }
+        let Ok(result) = resp_rx.await else {
+            return get_server_error_response("The query executor has disengaged.");
+        };
+        let is_error = result.is_err();
+        if let Err(executor_error) = &result {
+            status_code = get_error_status_code(executor_error);
+        }
+        responses.push(StatementResponse(result));
+        if is_error {
+            break;
+        }
     }
-    let (resp_tx, resp_rx) = oneshot::channel::<QueryResult>();
-    if let Err(_) = executor_tx.send((statement, resp_tx)).await {
-        // If there was an error on `send`, that means that the receiver has disconnected for some reason
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            serde_json::to_string(&ServerError("The query executor has disengaged.".into()))
-                .unwrap(),
-        );
+    let response_string = match (is_single_statement, status_code) {
+        (true, StatusCode::OK) => serde_json::to_string_pretty(&responses[0]),
+        (true, _) => serde_json::to_string(&responses[0]),
+        (false, _) => serde_json::to_string_pretty(&responses),
+    };
+    match response_string {
+        Ok(response_string) => (status_code, response_string),
+        Err(serialization_error) => get_server_error_response(&format!(
+            "The statement result could not be serialized: {}.",
+            serialization_error
+        )),
     }
-    let query_result = resp_rx.await;
-    (
-        StatusCode::OK,
-        serde_json::to_string_pretty(&query_result.unwrap()).unwrap(),
-    )
 }
 
 async fn process_get(
@@ -168,3 +227,246 @@ pub async fn start_server(config: &config::Config, executor_tx: mpsc::Sender<Exe
         debug!("⏹ Server no longer listening");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::Executor;
+    use pretty_assertions::assert_eq;
+    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+    /// Start an executor with a fresh data directory, returning the channel to send statements on.
+    fn start_test_executor() -> mpsc::Sender<ExecutorPayload> {
+        let data_directory_name: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(10)
+            .map(char::from)
+            .collect();
+        let mut executor = Executor::new(&config::Config {
+            data_directory: format!("{}/{}", env!("TMPDIR"), data_directory_name),
+            ..Default::default()
+        });
+        let executor_tx = executor.prepare_channel();
+        tokio::spawn(async move { executor.start().await.unwrap() });
+        executor_tx
+    }
+
+    #[tokio::test]
+    async fn post_returns_results_of_multiple_statements() {
+        let executor_tx = start_test_executor();
+
+        let (status_code, response_string) = process_post(
+            executor_tx,
+            "CREATE TABLE photos (url STRING PRIMARY KEY, hash UINT128 METRIC KEY);
+            INSERT INTO photos (url, hash) VALUES ('a.png', 7);
+            SELECT url, hash, CAST('340282366920938463463374607431768211455' AS UINT128) AS max_hash FROM photos;",
+        )
+        .await;
+
+        assert_eq!(status_code, StatusCode::OK);
+        // UINT128 values don't fit into JSON values of serde, so the response mustn't go through them
+        assert_eq!(
+            response_string,
+            r#"[
+  [],
+  [
+    {
+      "rows_affected": 1
+    }
+  ],
+  [
+    {
+      "url": "a.png",
+      "hash": 7,
+      "max_hash": 340282366920938463463374607431768211455
+    }
+  ]
+]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn post_returns_values_of_all_unaliased_columns() {
+        let executor_tx = start_test_executor();
+
+        let (status_code, response_string) = process_post(
+            executor_tx,
+            "CREATE TABLE photos (url STRING PRIMARY KEY, caption STRING);
+            INSERT INTO photos (url, caption) VALUES ('a.png', 'Sunset');
+            SELECT substr(url, 1, 1), substr(caption, 1, 1), url = 'a.png', caption = 'Dawn' FROM photos;",
+        )
+        .await;
+
+        assert_eq!(status_code, StatusCode::OK);
+        assert_eq!(
+            response_string,
+            r#"[
+  [],
+  [
+    {
+      "rows_affected": 1
+    }
+  ],
+  [
+    {
+      "substr": "a",
+      "substr_2": "S",
+      "?column?": true,
+      "?column?_2": false
+    }
+  ]
+]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn post_rejects_constants_that_cannot_be_cast_as_bad_request() {
+        let executor_tx = start_test_executor();
+
+        let (status_code, response_string) = process_post(
+            executor_tx.clone(),
+            "CREATE TABLE photos (url STRING PRIMARY KEY, width UINT32);
+            INSERT INTO photos (url, width) VALUES ('a.png', 1280);
+            SELECT url, UPPER('x')::UINT8 FROM photos;",
+        )
+        .await;
+
+        assert_eq!(status_code, StatusCode::BAD_REQUEST);
+        let response: serde_json::Value = serde_json::from_str(&response_string).unwrap();
+        assert_eq!(
+            response[2],
+            serde_json::json!({
+                "type": "validation",
+                "message": "Value String(\"X\") cannot be parsed as type UINT8"
+            })
+        );
+
+        // A stored value that doesn't fit isn't the fault of the statement alone
+        let (status_code, response_string) =
+            process_post(executor_tx, "SELECT width::UINT8 FROM photos").await;
+
+        assert_eq!(status_code, StatusCode::INTERNAL_SERVER_ERROR);
+        let response: serde_json::Value = serde_json::from_str(&response_string).unwrap();
+        assert_eq!(
+            response,
+            serde_json::json!({
+                "type": "execution",
+                "message": "Value UInt32(1280) is out of range for type UINT8"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn post_positions_unterminated_string_quoted_identifier_and_block_comment() {
+        // Parsing fails before anything is executed, so there's no need for an executor
+        let post_unparsable = |body: &'static str| async move {
+            let (executor_tx, _executor_rx) = mpsc::channel(1);
+            let (status_code, response_string) = process_post(executor_tx, body).await;
+            assert_eq!(status_code, StatusCode::BAD_REQUEST);
+            serde_json::from_str::<serde_json::Value>(&response_string).unwrap()
+        };
+
+        assert_eq!(
+            post_unparsable("SELECT url FROM photos\nWHERE url = 'a.png AND hash = 7").await,
+            serde_json::json!({
+                "type": "syntax",
+                "message": "Unterminated string literal.",
+                "line": 2,
+                "column": 13,
+                "excerpt": "WHERE url = 'a.png AND hash = 7\n            ^"
+            })
+        );
+        assert_eq!(
+            post_unparsable("SELECT \"url FROM photos").await,
+            serde_json::json!({
+                "type": "syntax",
+                "message": "Unterminated quoted identifier.",
+                "line": 1,
+                "column": 8,
+                "excerpt": "SELECT \"url FROM photos\n       ^"
+            })
+        );
+        assert_eq!(
+            post_unparsable("SELECT url -- the URL\n  /* and the rest\nFROM photos").await,
+            serde_json::json!({
+                "type": "syntax",
+                "message": "Unterminated block comment.",
+                "line": 2,
+                "column": 3,
+                "excerpt": "  /* and the rest\n  ^^"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn post_rejects_table_and_database_names_that_are_paths() {
+        let executor_tx = start_test_executor();
+        let outside_path = format!(
+            "{}/outside_{}",
+            env!("TMPDIR"),
+            thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(char::from)
+                .collect::<String>()
+        );
+
+        for (statement, table_name) in [
+            (
+                format!("CREATE TABLE \"{}\" (url STRING PRIMARY KEY)", outside_path),
+                outside_path.as_str(),
+            ),
+            (
+                "CREATE TABLE \"a/b\" (url STRING PRIMARY KEY)".into(),
+                "a/b",
+            ),
+            (
+                "CREATE TABLE \"a\\b\" (url STRING PRIMARY KEY)".into(),
+                "a\\b",
+            ),
+            (
+                format!("DROP TABLE \"{}\"", outside_path),
+                outside_path.as_str(),
+            ),
+            ("TRUNCATE \"a/b\"".into(), "a/b"),
+            (
+                format!("ALTER TABLE photos RENAME TO \"{}\"", outside_path),
+                outside_path.as_str(),
+            ),
+        ] {
+            let (status_code, response_string) =
+                process_post(executor_tx.clone(), &statement).await;
+            assert_eq!(status_code, StatusCode::BAD_REQUEST, "{}", statement);
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&response_string).unwrap(),
+                serde_json::json!({
+                    "type": "validation",
+                    "message": format!("Table name `{}` must not contain `.`, `/` or `\\`", table_name)
+                })
+            );
+        }
+
+        // The database a table is in becomes a directory too
+        for statement in [
+            format!(
+                "CREATE TABLE \"{}\".victim (url STRING PRIMARY KEY)",
+                outside_path
+            ),
+            format!("DROP TABLE \"{}\".victim", outside_path),
+            format!("TRUNCATE \"{}\".victim", outside_path),
+            format!("SELECT * FROM \"{}\".victim", outside_path),
+        ] {
+            let (status_code, response_string) =
+                process_post(executor_tx.clone(), &statement).await;
+            assert_eq!(status_code, StatusCode::BAD_REQUEST, "{}", statement);
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&response_string).unwrap(),
+                serde_json::json!({
+                    "type": "validation",
+                    "message": format!("Database name `{}` must not contain `.`, `/` or `\\`", outside_path)
+                })
+            );
+        }
+        assert!(!std::path::Path::new(&outside_path).exists());
+    }
+}
@@ -1,22 +1,84 @@
 use serde::{ser::SerializeMap, Serialize, Serializer};
 use thiserror::Error;
 
+use super::tokenizer::Span;
+
 #[derive(Error, Debug, PartialEq, Eq)]
 #[error("SyntaxError: {0}")]
-pub struct SyntaxError(pub String);
+pub struct SyntaxError(pub String, pub Option<Span>);
 
-impl Serialize for SyntaxError {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+impl SyntaxError {
+    /// Position the error at the given span, unless it's positioned already.
+    pub fn or_at(self, span: Span) -> Self {
+        SyntaxError(self.0, self.1.or(Some(span)))
+    }
+
+    /// Line of input that the error is positioned at, with carets under the erroneous part.
+    fn get_excerpt(&self, input: &str) -> Option<String> {
+        let span = self.1?;
+        let line = input.lines().nth(span.line - 1).unwrap_or_default();
+        // Keep tabs so that carets line up regardless of tab width
+        let indentation: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|character| if character == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined_length = input[span.offset..span.offset + span.length]
+            .lines()
+            .next()
+            .map_or(0, |underlined| underlined.chars().count());
+        Some(format!(
+            "{}\n{}{}",
+            line,
+            indentation,
+            "^".repeat(underlined_length.max(1))
+        ))
+    }
+
+    fn serialize_with_excerpt<S>(
+        &self,
+        serializer: S,
+        excerpt: Option<String>,
+    ) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut map = serializer.serialize_map(Some(2))?;
+        let mut map = serializer.serialize_map(None)?;
         map.serialize_entry("type", "syntax")?;
         map.serialize_entry("message", &self.0)?;
+        if let Some(span) = &self.1 {
+            map.serialize_entry("line", &span.line)?;
+            map.serialize_entry("column", &span.column)?;
+        }
+        if let Some(excerpt) = &excerpt {
+            map.serialize_entry("excerpt", excerpt)?;
+        }
         map.end()
     }
 }
 
+impl Serialize for SyntaxError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.serialize_with_excerpt(serializer, None)
+    }
+}
+
+/// Syntax error along with the input it was found in, which makes for an excerpt in the serialized error.
+pub struct SyntaxErrorInInput<'e>(pub &'e SyntaxError, pub &'e str);
+
+impl Serialize for SyntaxErrorInInput<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0
+            .serialize_with_excerpt(serializer, self.0.get_excerpt(self.1))
+    }
+}
+
 #[derive(Error, Debug, PartialEq, Eq)]
 #[error("ValidationError: {0}")]
 pub struct ValidationError(pub String);
@@ -32,3 +94,46 @@ impl Serialize for ValidationError {
         map.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::parse_statements;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn syntax_error_serializes_with_position_and_excerpt() {
+        const INPUT: &str = "SELECT url\nFROM photos_seen\nWHERE url = 'x' LIMT 5";
+
+        let error = parse_statements(INPUT).unwrap_err();
+
+        assert_eq!(
+            serde_json::to_value(SyntaxErrorInInput(&error, INPUT)).unwrap(),
+            serde_json::json!({
+                "type": "syntax",
+                "message": "Expected end of statement, instead found arbitrary `LIMT` at line 3.",
+                "line": 3,
+                "column": 17,
+                "excerpt": "WHERE url = 'x' LIMT 5\n                ^^^^"
+            })
+        );
+    }
+
+    #[test]
+    fn syntax_error_at_end_of_statement_is_positioned_at_separator() {
+        const INPUT: &str = "SELECT url FROM\n\tphotos_seen WHERE;\nSELECT 1";
+
+        let error = parse_statements(INPUT).unwrap_err();
+
+        assert_eq!(
+            serde_json::to_value(SyntaxErrorInInput(&error, INPUT)).unwrap(),
+            serde_json::json!({
+                "type": "syntax",
+                "message": "Expected WHERE conditions, instead found end of statement.",
+                "line": 2,
+                "column": 19,
+                "excerpt": "\tphotos_seen WHERE;\n\t                 ^"
+            })
+        );
+    }
+}
@@ -47,14 +47,6 @@ pub fn detect<'t, P, M>(
     }
 }
 
-pub fn expect_identity(tokens: &[Token]) -> ExpectResult<Vec<Token>> {
-    Ok(ExpectOk {
-        rest: &[][..],
-        tokens_consumed_count: tokens.len(),
-        outcome: tokens.to_vec(),
-    })
-}
-
 pub fn expect_token_value<'t>(
     tokens: &'t [Token],
     expected_token_value: &TokenValue,
@@ -71,10 +63,13 @@ pub fn expect_token_value<'t>(
             outcome: (),
         })
     } else {
-        Err(SyntaxError(format!(
-            "Expected {}, instead found {}.",
-            expected_token_value, found_token
-        )))
+        Err(SyntaxError(
+            format!(
+                "Expected {}, instead found {}.",
+                expected_token_value, found_token
+            ),
+            Some(found_token.span),
+        ))
     }
 }
 
@@ -100,10 +95,10 @@ pub fn expect_end_of_statement(tokens: &[Token]) -> ExpectResult<'_, ()> {
             tokens_consumed_count: 0,
             outcome: (),
         }),
-        Some(wrong_token) => Err(SyntaxError(format!(
-            "Expected end of statement, instead found {}.",
-            wrong_token
-        ))),
+        Some(wrong_token) => Err(SyntaxError(
+            format!("Expected end of statement, instead found {}.", wrong_token),
+            Some(wrong_token.span),
+        )),
     }
 }
 
@@ -117,10 +112,13 @@ pub fn expect_next_token<'t>(
             tokens_consumed_count: 1,
             outcome: found_token,
         }),
-        None => Err(SyntaxError(format!(
-            "Expected {}, instead found end of statement.",
-            expectation_description
-        ))),
+        None => Err(SyntaxError(
+            format!(
+                "Expected {}, instead found end of statement.",
+                expectation_description
+            ),
+            None,
+        )),
     }
 }
 
@@ -183,7 +181,7 @@ pub fn expect_enclosure(
     tokens: &[Token],
     opening: Delimiter,
     closing: Delimiter,
-) -> ExpectResult<&[Token]> {
+) -> ExpectResult<'_, &[Token]> {
     let ExpectOk { rest, .. } = expect_token_value(
         tokens,
         &TokenValue::Delimiting(Delimiter::ParenthesisOpening),
@@ -211,10 +209,13 @@ pub fn expect_enclosure(
             tokens_consumed_count: enclosure_size + 2, // +2 to account for parentheses
             outcome: &rest[..enclosure_size],
         }),
-        None => Err(SyntaxError(format!(
-            "Expected to find a matching {} for {}, instead found end of statement.",
-            closing, tokens[0]
-        ))),
+        None => Err(SyntaxError(
+            format!(
+                "Expected to find a matching {} for {}, instead found end of statement.",
+                closing, tokens[0]
+            ),
+            Some(tokens[0].span),
+        )),
     }
 }
 
@@ -234,35 +235,54 @@ pub fn expect_enclosed_comma_separated<'t, O>(
     )?;
     // Disallow empty enclosures
     if enclosure_tokens.is_empty() {
-        return Err(SyntaxError(format!(
-            "Found an enclosure delimited by {} and {} as expected, but it's empty.",
-            tokens[0],
-            tokens[tokens_consumed_count - 1],
-        )));
+        return Err(SyntaxError(
+            format!(
+                "Found an enclosure delimited by {} and {} as expected, but it's empty.",
+                tokens[0],
+                tokens[tokens_consumed_count - 1],
+            ),
+            Some(tokens[0].span),
+        ));
     }
     let mut elements = Vec::<O>::new();
     let mut previous_separator_offset: usize = 0;
+    // Separators within nested parentheses belong to elements, e.g. to function calls
+    let mut current_enclosure_depth: usize = 0;
     for (current_index, current_token) in enclosure_tokens.iter().enumerate() {
-        if current_token.value == SEPARATOR {
+        match current_token.value {
+            TokenValue::Delimiting(Delimiter::ParenthesisOpening) => current_enclosure_depth += 1,
+            TokenValue::Delimiting(Delimiter::ParenthesisClosing) => current_enclosure_depth -= 1,
+            _ => (),
+        }
+        if current_enclosure_depth == 0 && current_token.value == SEPARATOR {
+            // An element that ends early is positioned at the separator
             if previous_separator_offset == current_index {
-                expect_element(&enclosure_tokens[..previous_separator_offset])?;
+                expect_element(&enclosure_tokens[..previous_separator_offset])
+                    .map_err(|error| error.or_at(current_token.span))?;
             }
-            elements.push(consume_all(
-                &enclosure_tokens[previous_separator_offset..current_index],
-                expect_element,
-            )?);
+            elements.push(
+                consume_all(
+                    &enclosure_tokens[previous_separator_offset..current_index],
+                    expect_element,
+                )
+                .map_err(|error| error.or_at(current_token.span))?,
+            );
             previous_separator_offset = current_index + 1;
         }
     }
     // If the final vector is empty, it means there was a trailing separator, which is generally disallowed in SQL
     let final_element_tokens = &enclosure_tokens[previous_separator_offset..];
     if final_element_tokens.is_empty() {
-        return Err(SyntaxError(format!(
-            "Found disallowed trailing {}.",
-            &SEPARATOR
-        )));
+        return Err(SyntaxError(
+            format!("Found disallowed trailing {}.", &SEPARATOR),
+            Some(enclosure_tokens[previous_separator_offset - 1].span),
+        ));
     }
-    elements.push(expect_element(&enclosure_tokens[previous_separator_offset..])?.outcome);
+    elements.push(
+        expect_element(&enclosure_tokens[previous_separator_offset..])
+            .map_err(|error| error.or_at(tokens[tokens_consumed_count - 1].span))?
+            .outcome,
+    );
     Ok(ExpectOk {
         rest,
         tokens_consumed_count,
@@ -282,15 +302,24 @@ mod expect_token_sequence_tests {
                 &[
                     Token {
                         value: TokenValue::Const(Keyword::If),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Not),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Exists),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     }
                 ],
                 &[
@@ -314,15 +343,24 @@ mod expect_token_sequence_tests {
                 &[
                     Token {
                         value: TokenValue::Const(Keyword::If),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Not),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Arbitrary("xyz".to_string()),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     }
                 ],
                 &[
@@ -332,7 +370,11 @@ mod expect_token_sequence_tests {
                 ]
             ),
             Err(SyntaxError(
-                "Expected keyword `EXISTS`, instead found arbitrary `xyz` at line 1.".to_string()
+                "Expected keyword `EXISTS`, instead found arbitrary `xyz` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -343,7 +385,10 @@ mod expect_token_sequence_tests {
             expect_token_values_sequence(
                 &[Token {
                     value: TokenValue::Const(Keyword::If),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }],
                 &[
                     TokenValue::Const(Keyword::If),
@@ -352,7 +397,8 @@ mod expect_token_sequence_tests {
                 ]
             ),
             Err(SyntaxError(
-                "Expected keyword `NOT`, instead found end of statement.".to_string()
+                "Expected keyword `NOT`, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -369,7 +415,8 @@ mod expect_token_sequence_tests {
                 ]
             ),
             Err(SyntaxError(
-                "Expected keyword `IF`, instead found end of statement.".to_string()
+                "Expected keyword `IF`, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -387,11 +434,17 @@ mod expect_token_single_tests {
                 &[
                     Token {
                         value: TokenValue::Const(Keyword::Primary),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Arbitrary("foo".to_string()),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     }
                 ],
                 &TokenValue::Const(Keyword::Primary)
@@ -399,7 +452,10 @@ mod expect_token_single_tests {
             Ok(ExpectOk {
                 rest: &[Token {
                     value: TokenValue::Arbitrary("foo".to_string()),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }][..],
                 tokens_consumed_count: 1,
                 outcome: ()
@@ -413,12 +469,19 @@ mod expect_token_single_tests {
             expect_token_value(
                 &[Token {
                     value: TokenValue::Const(Keyword::Create),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }],
                 &TokenValue::Const(Keyword::Primary)
             ),
             Err(SyntaxError(
-                "Expected keyword `PRIMARY`, instead found keyword `CREATE` at line 1.".to_string()
+                "Expected keyword `PRIMARY`, instead found keyword `CREATE` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -428,7 +491,8 @@ mod expect_token_single_tests {
         assert_eq!(
             expect_token_value(&[], &TokenValue::Const(Keyword::Primary)),
             Err(SyntaxError(
-                "Expected keyword `PRIMARY`, instead found end of statement.".to_string()
+                "Expected keyword `PRIMARY`, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -448,35 +512,59 @@ mod expect_enclosure_tests {
                 &[
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Arbitrary("foo".to_string()),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Nullable),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Type(DataTypeRaw::UInt64),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Values),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                 ],
                 Delimiter::ParenthesisOpening,
@@ -485,29 +573,47 @@ mod expect_enclosure_tests {
             Ok(ExpectOk {
                 rest: &[Token {
                     value: TokenValue::Const(Keyword::Values),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }][..],
                 tokens_consumed_count: 7,
                 outcome: &[
                     Token {
                         value: TokenValue::Arbitrary("foo".to_string()),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Const(Keyword::Nullable),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Type(DataTypeRaw::UInt64),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                     Token {
                         value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                        line_number: 1
+                        span: Span {
+                            line: 1,
+                            ..Span::default()
+                        }
                     },
                 ][..]
             })
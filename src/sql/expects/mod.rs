@@ -6,7 +6,7 @@ use crate::sql::errors::*;
 use crate::sql::tokenizer::*;
 
 pub use generic::*;
-pub use semantic::*;
+pub use semantic::{expect_data_definition, expect_expression, expect_identifier};
 pub use statements::*;
 
 #[derive(Debug, PartialEq, Eq)]
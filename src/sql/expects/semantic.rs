@@ -2,11 +2,12 @@ use crate::constructs::components::{
     DataDefinition, DataInstance, DataInstanceRaw, DataType, DataTypeRaw, Expression,
 };
 use crate::constructs::functions::Function;
+use crate::constructs::statements::SelectStatement;
 use crate::sql::errors::*;
-use crate::sql::expects::{generic::*, ExpectOk, ExpectResult};
+use crate::sql::expects::{generic::*, statements::expect_query, ExpectOk, ExpectResult};
 use crate::sql::tokenizer::*;
 
-pub fn expect_identifier(tokens: &[Token]) -> ExpectResult<String> {
+pub fn expect_identifier(tokens: &[Token]) -> ExpectResult<'_, String> {
     let ExpectOk {
         outcome: found_token,
         ..
@@ -20,14 +21,75 @@ pub fn expect_identifier(tokens: &[Token]) -> ExpectResult<String> {
             tokens_consumed_count: 1,
             outcome: value.to_owned(),
         }),
-        wrong_token => Err(SyntaxError(format!(
-            "Expected an identifier, instead found {}.",
-            wrong_token
-        ))),
+        wrong_token => Err(SyntaxError(
+            format!("Expected an identifier, instead found {}.", wrong_token),
+            Some(wrong_token.span),
+        )),
     }
 }
 
-pub fn expect_data_type_raw(tokens: &[Token]) -> ExpectResult<DataTypeRaw> {
+/// Expect a table name, optionally qualified with the database (schema) it lives in, as in `gaggle.photos_seen`.
+/// The outcome is a pair of the database name, if specified, and the table name.
+pub fn expect_table_name(tokens: &[Token]) -> ExpectResult<'_, (Option<String>, String)> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: identifier,
+    } = expect_identifier(tokens)?;
+    let qualified_name = match identifier.split_once('.') {
+        None => (None, identifier),
+        Some((schema_name, table_name))
+            if !schema_name.is_empty() && !table_name.is_empty() && !table_name.contains('.') =>
+        {
+            (Some(schema_name.to_string()), table_name.to_string())
+        }
+        Some(_) => {
+            return Err(SyntaxError(
+                format!("Expected a table name, instead found {}.", tokens[0]),
+                Some(tokens[0].span),
+            ))
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: qualified_name,
+    })
+}
+
+/// Expect a non-negative integer literal, such as the count of LIMIT.
+pub fn expect_count(tokens: &[Token]) -> ExpectResult<'_, u64> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: found_token,
+    } = expect_next_token(tokens, &"a non-negative integer")?;
+    match &found_token.value {
+        TokenValue::Arbitrary(candidate) => match candidate.parse::<u64>() {
+            Ok(count) => Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: count,
+            }),
+            Err(_) => Err(SyntaxError(
+                format!(
+                    "Expected a non-negative integer, instead found {}.",
+                    found_token
+                ),
+                Some(found_token.span),
+            )),
+        },
+        _ => Err(SyntaxError(
+            format!(
+                "Expected a non-negative integer, instead found {}.",
+                found_token
+            ),
+            Some(found_token.span),
+        )),
+    }
+}
+
+pub fn expect_data_type_raw(tokens: &[Token]) -> ExpectResult<'_, DataTypeRaw> {
     let ExpectOk {
         outcome: found_token,
         ..
@@ -41,18 +103,15 @@ pub fn expect_data_type_raw(tokens: &[Token]) -> ExpectResult<DataTypeRaw> {
             tokens_consumed_count: 1,
             outcome: *found_data_type,
         }),
-        wrong_token => Err(SyntaxError(format!(
-            "Expected a data type, instead found {}.",
-            wrong_token
-        ))),
+        wrong_token => Err(SyntaxError(
+            format!("Expected a data type, instead found {}.", wrong_token),
+            Some(wrong_token.span),
+        )),
     }
 }
 
-pub fn expect_data_type(tokens: &[Token]) -> ExpectResult<DataType> {
-    let is_nullable = matches!(
-        expect_token_value(tokens, &TokenValue::Const(Keyword::Nullable)),
-        Ok(_)
-    );
+pub fn expect_data_type(tokens: &[Token]) -> ExpectResult<'_, DataType> {
+    let is_nullable = expect_token_value(tokens, &TokenValue::Const(Keyword::Nullable)).is_ok();
     let ExpectOk {
         rest,
         tokens_consumed_count,
@@ -77,7 +136,19 @@ pub fn expect_data_type(tokens: &[Token]) -> ExpectResult<DataType> {
     })
 }
 
-pub fn expect_data_instance(tokens: &[Token]) -> ExpectResult<DataInstance> {
+/// Parse an integer literal, which can be binary with prefix `0b` or hexadecimal with prefix `0x`.
+fn parse_integer_literal(candidate: &str) -> Result<u32, std::num::ParseIntError> {
+    let lowercase_candidate = candidate.to_lowercase();
+    if let Some(digits) = lowercase_candidate.strip_prefix("0b") {
+        u32::from_str_radix(digits, 2)
+    } else if let Some(digits) = lowercase_candidate.strip_prefix("0x") {
+        u32::from_str_radix(digits, 16)
+    } else {
+        candidate.parse::<u32>()
+    }
+}
+
+pub fn expect_data_instance(tokens: &[Token]) -> ExpectResult<'_, DataInstance> {
     let ExpectOk {
         rest,
         tokens_consumed_count,
@@ -103,26 +174,125 @@ pub fn expect_data_instance(tokens: &[Token]) -> ExpectResult<DataInstance> {
         Token {
             value: TokenValue::Arbitrary(found_number_candidate),
             ..
-        } => match found_number_candidate.parse::<u32>() {
+        } => match parse_integer_literal(found_number_candidate) {
             // UInt32 is the default integer type
             Ok(found_number) => Ok(ExpectOk {
                 rest,
                 tokens_consumed_count,
                 outcome: DataInstance::Direct(DataInstanceRaw::UInt32(found_number)),
             }),
-            Err(_) => Err(SyntaxError(format!(
-                "Expected a value, instead found {}.",
-                found_number_candidate
-            ))),
+            Err(_) => Err(SyntaxError(
+                format!(
+                    "Expected a value, instead found {}.",
+                    found_number_candidate
+                ),
+                Some(found_token.span),
+            )),
         },
-        wrong_token => Err(SyntaxError(format!(
-            "Expected a value, instead found {}.",
-            wrong_token
-        ))),
+        wrong_token => Err(SyntaxError(
+            format!("Expected a value, instead found {}.", wrong_token),
+            Some(wrong_token.span),
+        )),
     }
 }
 
-pub fn expect_function_call(tokens: &[Token]) -> ExpectResult<Function> {
+/// Expect the inside of `CAST(operand AS type)`.
+fn expect_cast_inside(tokens: &[Token]) -> ExpectResult<'_, Expression> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_operand,
+        outcome: operand,
+    } = expect_expression(tokens)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::As))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_data_type,
+        outcome: data_type,
+    } = expect_data_type_raw(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_operand + 1 + tokens_consumed_count_data_type, // +1 to account for AS
+        outcome: Expression::Cast(Box::new(operand), data_type),
+    })
+}
+
+/// Expect arguments of EXTRACT in the standard form `field FROM timestamp`. The field is passed on
+/// as a string argument, same as the one of DATE_TRUNC.
+fn expect_extract_arguments(tokens: &[Token]) -> ExpectResult<'_, Vec<Expression>> {
+    let ExpectOk {
+        rest,
+        outcome: found_token,
+        ..
+    } = expect_next_token(tokens, &"a time field")?;
+    let field = match &found_token.value {
+        TokenValue::Arbitrary(field) | TokenValue::String(field) => field.to_lowercase(),
+        _ => {
+            return Err(SyntaxError(
+                format!("Expected a time field, instead found {}.", found_token),
+                Some(found_token.span),
+            ))
+        }
+    };
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::From))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: timestamp,
+    } = expect_expression(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 2 + tokens_consumed_count, // +2 to account for the field and FROM
+        outcome: vec![
+            Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                DataInstanceRaw::String(field),
+            ))),
+            timestamp,
+        ],
+    })
+}
+
+/// Expect the arguments of a function call, enclosed in parentheses. `*` is only allowed as in `COUNT(*)`,
+/// and is represented by no arguments.
+fn expect_function_arguments(
+    tokens: &[Token],
+    function: Function,
+) -> ExpectResult<'_, Vec<Expression>> {
+    let ExpectOk { rest, .. } = expect_token_value(
+        tokens,
+        &TokenValue::Delimiting(Delimiter::ParenthesisOpening),
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: arguments,
+    } = match rest.first().map(|token| &token.value) {
+        Some(TokenValue::Delimiting(Delimiter::ParenthesisClosing))
+            if function != Function::Count =>
+        {
+            ExpectOk {
+                rest,
+                tokens_consumed_count: 0,
+                outcome: Vec::new(),
+            }
+        }
+        Some(TokenValue::Const(Keyword::Asterisk)) if function == Function::Count => ExpectOk {
+            rest: &rest[1..],
+            tokens_consumed_count: 1,
+            outcome: Vec::new(),
+        },
+        _ if function == Function::Extract => expect_extract_arguments(rest)?,
+        _ => expect_expression_list(rest)?,
+    };
+    let ExpectOk { rest, .. } =
+        expect_token_value(rest, &TokenValue::Delimiting(Delimiter::ParenthesisClosing))?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count + 2, // +2 to account for parentheses
+        outcome: arguments,
+    })
+}
+
+pub fn expect_function_call(tokens: &[Token]) -> ExpectResult<'_, (Function, Vec<Expression>)> {
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_call,
@@ -135,40 +305,34 @@ pub fn expect_function_call(tokens: &[Token]) -> ExpectResult<Function> {
         } => {
             let ExpectOk {
                 rest,
-                tokens_consumed_count: tokens_consumed_count_parentheses,
-                outcome: _,
-            } = expect_token_values_sequence(
-                rest,
-                &[
-                    TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                ],
-            )?;
+                tokens_consumed_count: tokens_consumed_count_arguments,
+                outcome: arguments,
+            } = expect_function_arguments(rest, *found_function)?;
             Ok(ExpectOk {
                 rest,
-                tokens_consumed_count: tokens_consumed_count_call
-                    + tokens_consumed_count_parentheses,
-                outcome: found_function.to_owned(),
+                tokens_consumed_count: tokens_consumed_count_call + tokens_consumed_count_arguments,
+                outcome: (*found_function, arguments),
             })
         }
-        wrong_token => Err(SyntaxError(format!(
-            "Expected a function name, instead found {}.",
-            wrong_token
-        ))),
+        wrong_token => Err(SyntaxError(
+            format!("Expected a function name, instead found {}.", wrong_token),
+            Some(wrong_token.span),
+        )),
     }
 }
 
-pub fn expect_data_definition(tokens: &[Token]) -> ExpectResult<DataDefinition> {
-    if let Ok(ExpectOk {
-        rest,
-        tokens_consumed_count,
-        outcome: found_function,
-    }) = expect_function_call(tokens)
-    {
+pub fn expect_data_definition(tokens: &[Token]) -> ExpectResult<'_, DataDefinition> {
+    // A function name can't be anything else, so errors in the call itself are reported as such
+    if let Some(TokenValue::Function(_)) = tokens.first().map(|token| &token.value) {
+        let ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: (function, arguments),
+        } = expect_function_call(tokens)?;
         return Ok(ExpectOk {
             rest,
             tokens_consumed_count,
-            outcome: DataDefinition::FunctionCall(found_function),
+            outcome: DataDefinition::FunctionCall(function, arguments),
         });
     }
     if let Ok(ExpectOk {
@@ -207,58 +371,621 @@ pub fn expect_data_definition(tokens: &[Token]) -> ExpectResult<DataDefinition>
             outcome: DataDefinition::Identifier(identifier),
         });
     }
-    Err(SyntaxError(format!(
-        "Expected a function call, a constant value or an identifier, instead found {:?}.",
-        tokens.first()
-    )))
+    const EXPECTED_ATOM: &str = "a function call, a constant value or an identifier";
+    Err(match tokens.first() {
+        Some(wrong_token) => SyntaxError(
+            format!("Expected {}, instead found {}.", EXPECTED_ATOM, wrong_token),
+            Some(wrong_token.span),
+        ),
+        None => SyntaxError(
+            format!(
+                "Expected {}, instead found end of statement.",
+                EXPECTED_ATOM
+            ),
+            None,
+        ),
+    })
 }
 
-pub fn expect_expression(tokens: &[Token]) -> ExpectResult<Expression> {
-    let ExpectOk {
-        rest: rest_atom,
-        tokens_consumed_count: tokens_consumed_count_lhs,
-        outcome: lhs_raw,
-    } = expect_data_definition(tokens)?;
-    let lhs = Expression::Atom(lhs_raw);
-    let ExpectOk {
-        rest,
-        tokens_consumed_count: tokens_consumed_count_operator_and_rhs,
-        outcome: operator_and_rhs,
-    } = detect(
-        rest_atom,
-        |tokens| expect_next_token(tokens, &"an operator"),
-        expect_data_definition,
-        &"the right-hand side of the expression",
-    )?;
-    match operator_and_rhs {
-        Some((
+/// Operators placed between two operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Distance,
+}
+
+impl BinaryOperator {
+    fn from_token_value(token_value: &TokenValue) -> Option<Self> {
+        match token_value {
+            TokenValue::Const(Keyword::Or) => Some(Self::Or),
+            TokenValue::Const(Keyword::And) => Some(Self::And),
+            TokenValue::Delimiting(Delimiter::Equal) => Some(Self::Equal),
+            TokenValue::Delimiting(Delimiter::NotEqual) => Some(Self::NotEqual),
+            TokenValue::Delimiting(Delimiter::LessThan) => Some(Self::LessThan),
+            TokenValue::Delimiting(Delimiter::LessThanOrEqual) => Some(Self::LessThanOrEqual),
+            TokenValue::Delimiting(Delimiter::GreaterThan) => Some(Self::GreaterThan),
+            TokenValue::Delimiting(Delimiter::GreaterThanOrEqual) => Some(Self::GreaterThanOrEqual),
+            TokenValue::Delimiting(Delimiter::Distance) => Some(Self::Distance),
+            _ => None,
+        }
+    }
+
+    /// Left and right binding power of the operator. The higher the power, the tighter the operator binds,
+    /// e.g. `a OR b AND c` is `a OR (b AND c)`. Right power being higher makes the operator left-associative.
+    fn get_binding_power(&self) -> (u8, u8) {
+        match self {
+            Self::Or => (1, 2),
+            Self::And => (3, 4),
+            // NOT sits between AND and comparisons, see NOT_BINDING_POWER
+            // Distance is computed before comparing, so that `hash @ 11 < 4` works
+            Self::Distance => (9, 10),
+            _ => COMPARISON_BINDING_POWER,
+        }
+    }
+
+    fn apply(&self, lhs: Expression, rhs: Expression) -> Expression {
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+        match self {
+            Self::Or => Expression::Or(lhs, rhs),
+            Self::And => Expression::And(lhs, rhs),
+            Self::Equal => Expression::Equal(lhs, rhs),
+            Self::NotEqual => Expression::NotEqual(lhs, rhs),
+            Self::LessThan => Expression::LessThan(lhs, rhs),
+            Self::LessThanOrEqual => Expression::LessThanOrEqual(lhs, rhs),
+            Self::GreaterThan => Expression::GreaterThan(lhs, rhs),
+            Self::GreaterThanOrEqual => Expression::GreaterThanOrEqual(lhs, rhs),
+            Self::Distance => Expression::Distance(lhs, rhs),
+        }
+    }
+}
+
+/// Binding power of comparisons, which is shared by predicates such as IN or LIKE.
+const COMPARISON_BINDING_POWER: (u8, u8) = (7, 8);
+
+/// Predicates following their first operand, e.g. `IS NULL` or `BETWEEN 1 AND 2`. Their NOT forms are represented
+/// as the predicate wrapped in NOT, which is equivalent in three-valued logic.
+enum Predicate {
+    IsNull,
+    In(Vec<Expression>),
+    InSubquery(Box<SelectStatement>),
+    Between(Expression, Expression),
+    Like(Expression, char),
+}
+
+impl Predicate {
+    fn apply(self, operand: Expression) -> Expression {
+        let operand = Box::new(operand);
+        match self {
+            Self::IsNull => Expression::IsNull(operand),
+            Self::In(items) => Expression::In(operand, items),
+            Self::InSubquery(query) => Expression::InSubquery(operand, query),
+            Self::Between(low, high) => Expression::Between(operand, Box::new(low), Box::new(high)),
+            Self::Like(pattern, escape) => Expression::Like(operand, Box::new(pattern), escape),
+        }
+    }
+}
+
+/// Default LIKE escape character, same as in Postgres.
+const DEFAULT_LIKE_ESCAPE: char = '\\';
+
+/// Expect the operand of a predicate, which must not swallow the AND of BETWEEN.
+fn expect_predicate_operand(tokens: &[Token]) -> ExpectResult<'_, Expression> {
+    expect_expression_with_binding_power(tokens, COMPARISON_BINDING_POWER.1)
+}
+
+fn expect_expression_list(tokens: &[Token]) -> ExpectResult<'_, Vec<Expression>> {
+    expect_comma_separated(tokens, expect_expression)
+}
+
+/// Whether the tokens start with a parenthesized query, i.e. `(SELECT ...` or `(WITH ...`.
+fn is_subquery_ahead(tokens: &[Token]) -> bool {
+    matches!(
+        tokens,
+        [
             Token {
-                value: TokenValue::Delimiting(Delimiter::Equal),
+                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
                 ..
             },
-            rhs_raw,
-        )) => Ok(ExpectOk {
-            rest,
-            tokens_consumed_count: tokens_consumed_count_lhs
-                + tokens_consumed_count_operator_and_rhs,
-            outcome: Expression::Equal(Box::new(lhs), Box::new(Expression::Atom(rhs_raw))),
-        }),
-        Some((
             Token {
-                value: unexpected_token,
+                value: TokenValue::Const(Keyword::Select | Keyword::With),
                 ..
             },
-            _,
-        )) => Err(SyntaxError(format!(
-            "Expected {}, instead found {}.",
-            Delimiter::Equal,
-            unexpected_token
-        ))),
-        None => Ok(ExpectOk {
+            ..
+        ]
+    )
+}
+
+/// Expect a parenthesized query: `(<query>)`.
+fn expect_subquery(tokens: &[Token]) -> ExpectResult<'_, SelectStatement> {
+    expect_enclosed(
+        tokens,
+        expect_query,
+        Delimiter::ParenthesisOpening,
+        Delimiter::ParenthesisClosing,
+    )
+}
+
+fn expect_like_escape(tokens: &[Token]) -> ExpectResult<'_, char> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: found_token,
+    } = expect_next_token(tokens, &"an escape character")?;
+    if let TokenValue::String(escape) = &found_token.value {
+        let mut escape_characters = escape.chars();
+        if let (Some(escape), None) = (escape_characters.next(), escape_characters.next()) {
+            return Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: escape,
+            });
+        }
+    }
+    Err(SyntaxError(
+        format!(
+            "Expected a single-character string, instead found {}.",
+            found_token
+        ),
+        Some(found_token.span),
+    ))
+}
+
+/// Detect a predicate following an operand. The outcome is `None` if what follows is not a predicate,
+/// and whether the predicate is negated otherwise.
+fn detect_predicate(tokens: &[Token]) -> ExpectResult<'_, Option<(bool, Predicate)>> {
+    let not_found = Ok(ExpectOk {
+        rest: tokens,
+        tokens_consumed_count: 0,
+        outcome: None,
+    });
+    // IS [NOT] NULL
+    if let Ok(ExpectOk { rest, .. }) = expect_token_value(tokens, &TokenValue::Const(Keyword::Is)) {
+        let is_negated = expect_token_value(rest, &TokenValue::Const(Keyword::Not)).is_ok();
+        let ExpectOk { rest, .. } = expect_token_value(
+            &rest[usize::from(is_negated)..],
+            &TokenValue::Const(Keyword::Null),
+        )?;
+        return Ok(ExpectOk {
             rest,
-            tokens_consumed_count: tokens_consumed_count_lhs,
-            outcome: lhs,
-        }),
+            tokens_consumed_count: 2 + usize::from(is_negated), // IS + [NOT] + NULL
+            outcome: Some((is_negated, Predicate::IsNull)),
+        });
+    }
+    // [NOT] IN/BETWEEN/LIKE
+    let is_negated = expect_token_value(tokens, &TokenValue::Const(Keyword::Not)).is_ok();
+    let rest = &tokens[usize::from(is_negated)..];
+    let keyword = match rest.first() {
+        Some(Token {
+            value: TokenValue::Const(keyword @ (Keyword::In | Keyword::Between | Keyword::Like)),
+            ..
+        }) => keyword,
+        _ => return not_found,
+    };
+    let tokens_consumed_count_keywords = usize::from(is_negated) + 1;
+    let rest = &rest[1..];
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: predicate,
+    } = match keyword {
+        Keyword::In if is_subquery_ahead(rest) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: query,
+            } = expect_subquery(rest)?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: Predicate::InSubquery(Box::new(query)),
+            }
+        }
+        Keyword::In => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: items,
+            } = expect_enclosed(
+                rest,
+                expect_expression_list,
+                Delimiter::ParenthesisOpening,
+                Delimiter::ParenthesisClosing,
+            )?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: Predicate::In(items),
+            }
+        }
+        Keyword::Between => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_low,
+                outcome: low,
+            } = expect_predicate_operand(rest)?;
+            let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::And))?;
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_high,
+                outcome: high,
+            } = expect_predicate_operand(rest)?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_low + 1 + tokens_consumed_count_high, // +1 for AND
+                outcome: Predicate::Between(low, high),
+            }
+        }
+        _ => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_pattern,
+                outcome: pattern,
+            } = expect_predicate_operand(rest)?;
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_escape,
+                outcome: maybe_escape,
+            } = detect(
+                rest,
+                |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Escape)),
+                expect_like_escape,
+                &"an escape character",
+            )?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_pattern + tokens_consumed_count_escape,
+                outcome: Predicate::Like(
+                    pattern,
+                    maybe_escape.map_or(DEFAULT_LIKE_ESCAPE, |(_, escape)| escape),
+                ),
+            }
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_keywords + tokens_consumed_count,
+        outcome: Some((is_negated, predicate)),
+    })
+}
+
+/// Binding power of prefix operator NOT, so that `NOT a = b` is `NOT (a = b)`, but `NOT a AND b` is `(NOT a) AND b`.
+const NOT_BINDING_POWER: u8 = 5;
+
+/// Expect an operand of a binary operator: a NOT-prefixed expression, a parenthesized expression, a CAST,
+/// a subquery, EXISTS or an atom.
+fn expect_operand(tokens: &[Token]) -> ExpectResult<'_, Expression> {
+    if is_subquery_ahead(tokens) {
+        let ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: query,
+        } = expect_subquery(tokens)?;
+        return Ok(ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: Expression::Subquery(Box::new(query)),
+        });
+    }
+    match tokens.first().map(|token| &token.value) {
+        Some(TokenValue::Const(Keyword::Exists)) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: query,
+            } = expect_subquery(&tokens[1..])?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for EXISTS
+                outcome: Expression::Exists(Box::new(query)),
+            })
+        }
+        Some(TokenValue::Const(Keyword::Cast)) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: cast,
+            } = expect_enclosed(
+                &tokens[1..],
+                expect_cast_inside,
+                Delimiter::ParenthesisOpening,
+                Delimiter::ParenthesisClosing,
+            )?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for CAST
+                outcome: cast,
+            })
+        }
+        Some(TokenValue::Const(Keyword::Not)) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: operand,
+            } = expect_expression_with_binding_power(&tokens[1..], NOT_BINDING_POWER)?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for NOT
+                outcome: Expression::Not(Box::new(operand)),
+            })
+        }
+        Some(TokenValue::Delimiting(Delimiter::ParenthesisOpening)) => expect_enclosed(
+            tokens,
+            expect_expression,
+            Delimiter::ParenthesisOpening,
+            Delimiter::ParenthesisClosing,
+        ),
+        _ => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: atom,
+            } = expect_data_definition(tokens)?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: Expression::Atom(atom),
+            })
+        }
+    }
+}
+
+/// Precedence climbing (Pratt parsing): only operators binding tighter than `min_binding_power` are consumed here,
+/// the rest is left for the caller.
+fn expect_expression_with_binding_power(
+    tokens: &[Token],
+    min_binding_power: u8,
+) -> ExpectResult<'_, Expression> {
+    let ExpectOk {
+        mut rest,
+        mut tokens_consumed_count,
+        outcome: mut lhs,
+    } = expect_operand(tokens)?;
+    loop {
+        // Postfix `::type` binds tighter than any other operator, so it always applies to the operand at hand
+        if let Some(TokenValue::Delimiting(Delimiter::TypeCast)) =
+            rest.first().map(|token| &token.value)
+        {
+            let ExpectOk {
+                rest: rest_after_data_type,
+                tokens_consumed_count: tokens_consumed_count_data_type,
+                outcome: data_type,
+            } = expect_data_type_raw(&rest[1..])?;
+            rest = rest_after_data_type;
+            tokens_consumed_count += 1 + tokens_consumed_count_data_type; // +1 to account for `::`
+            lhs = Expression::Cast(Box::new(lhs), data_type);
+            continue;
+        }
+        let operator = match rest
+            .first()
+            .and_then(|token| BinaryOperator::from_token_value(&token.value))
+        {
+            Some(operator) => operator,
+            None => {
+                // Predicates bind like comparisons
+                if COMPARISON_BINDING_POWER.0 < min_binding_power {
+                    break;
+                }
+                match detect_predicate(rest)? {
+                    ExpectOk {
+                        rest: rest_after_predicate,
+                        tokens_consumed_count: tokens_consumed_count_predicate,
+                        outcome: Some((is_negated, predicate)),
+                    } => {
+                        rest = rest_after_predicate;
+                        tokens_consumed_count += tokens_consumed_count_predicate;
+                        lhs = predicate.apply(lhs);
+                        if is_negated {
+                            lhs = Expression::Not(Box::new(lhs));
+                        }
+                        continue;
+                    }
+                    _ => break,
+                }
+            }
+        };
+        let (left_binding_power, right_binding_power) = operator.get_binding_power();
+        if left_binding_power < min_binding_power {
+            break;
+        }
+        expect_next_token(&rest[1..], &"the right-hand side of the expression")?;
+        let ExpectOk {
+            rest: rest_after_rhs,
+            tokens_consumed_count: tokens_consumed_count_rhs,
+            outcome: rhs,
+        } = expect_expression_with_binding_power(&rest[1..], right_binding_power)?;
+        rest = rest_after_rhs;
+        tokens_consumed_count += 1 + tokens_consumed_count_rhs; // +1 to account for the operator
+        lhs = operator.apply(lhs, rhs);
+    }
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: lhs,
+    })
+}
+
+pub fn expect_expression(tokens: &[Token]) -> ExpectResult<'_, Expression> {
+    expect_expression_with_binding_power(tokens, 0)
+}
+
+#[cfg(test)]
+mod expect_expression_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn parse(input: &str) -> Expression {
+        let tokens = tokenize(input).unwrap();
+        let ExpectOk { rest, outcome, .. } = expect_expression(&tokens).unwrap();
+        assert_eq!(rest, &[][..]);
+        outcome
+    }
+
+    fn identifier(name: &str) -> Box<Expression> {
+        Box::new(Expression::Atom(DataDefinition::Identifier(
+            name.to_string(),
+        )))
+    }
+
+    fn number(value: u32) -> Box<Expression> {
+        Box::new(Expression::Atom(DataDefinition::Const(
+            DataInstance::Direct(DataInstanceRaw::UInt32(value)),
+        )))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse("a = 1 OR b < 2 AND c >= 3"),
+            Expression::Or(
+                Box::new(Expression::Equal(identifier("a"), number(1))),
+                Box::new(Expression::And(
+                    Box::new(Expression::LessThan(identifier("b"), number(2))),
+                    Box::new(Expression::GreaterThanOrEqual(identifier("c"), number(3)))
+                ))
+            )
+        )
+    }
+
+    #[test]
+    fn not_binds_looser_than_comparison_but_tighter_than_and() {
+        assert_eq!(
+            parse("NOT a <> 1 AND b"),
+            Expression::And(
+                Box::new(Expression::Not(Box::new(Expression::NotEqual(
+                    identifier("a"),
+                    number(1)
+                )))),
+                identifier("b")
+            )
+        )
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(
+            parse("(a OR b) AND c"),
+            Expression::And(
+                Box::new(Expression::Or(identifier("a"), identifier("b"))),
+                identifier("c")
+            )
+        )
+    }
+
+    #[test]
+    fn operators_are_left_associative() {
+        assert_eq!(
+            parse("a AND b AND c"),
+            Expression::And(
+                Box::new(Expression::And(identifier("a"), identifier("b"))),
+                identifier("c")
+            )
+        )
+    }
+
+    fn string(value: &str) -> Box<Expression> {
+        Box::new(Expression::Atom(DataDefinition::Const(
+            DataInstance::Direct(DataInstanceRaw::String(value.to_string())),
+        )))
+    }
+
+    #[test]
+    fn predicates_bind_like_comparisons() {
+        assert_eq!(
+            parse("a IS NOT NULL AND b NOT IN (1, 2) OR c BETWEEN 1 AND 2 AND d LIKE 'x!%%' ESCAPE '!'"),
+            Expression::Or(
+                Box::new(Expression::And(
+                    Box::new(Expression::Not(Box::new(Expression::IsNull(identifier("a"))))),
+                    Box::new(Expression::Not(Box::new(Expression::In(
+                        identifier("b"),
+                        vec![*number(1), *number(2)]
+                    ))))
+                )),
+                Box::new(Expression::And(
+                    Box::new(Expression::Between(identifier("c"), number(1), number(2))),
+                    Box::new(Expression::Like(identifier("d"), string("x!%%"), '!'))
+                ))
+            )
+        )
+    }
+
+    #[test]
+    fn not_prefix_applies_to_whole_predicate() {
+        assert_eq!(
+            parse("NOT a LIKE 'x%'"),
+            Expression::Not(Box::new(Expression::Like(
+                identifier("a"),
+                string("x%"),
+                '\\'
+            )))
+        )
+    }
+
+    #[test]
+    fn type_cast_binds_tightest() {
+        assert_eq!(
+            parse("CAST(a AS UINT8) < b::UINT8 AND NOT c::BOOL"),
+            Expression::And(
+                Box::new(Expression::LessThan(
+                    Box::new(Expression::Cast(identifier("a"), DataTypeRaw::UInt8)),
+                    Box::new(Expression::Cast(identifier("b"), DataTypeRaw::UInt8))
+                )),
+                Box::new(Expression::Not(Box::new(Expression::Cast(
+                    identifier("c"),
+                    DataTypeRaw::Bool
+                ))))
+            )
+        )
+    }
+
+    #[test]
+    fn returns_error_if_cast_lacks_type() {
+        assert_eq!(
+            expect_expression(&tokenize("CAST(a AS b)").unwrap()),
+            Err(SyntaxError(
+                "Expected a data type, instead found arbitrary `b` at line 1.".to_string(),
+                Some(Span {
+                    offset: 10,
+                    length: 1,
+                    line: 1,
+                    column: 11
+                })
+            ))
+        )
+    }
+
+    #[test]
+    fn returns_error_if_is_not_followed_by_null() {
+        assert_eq!(
+            expect_expression(&tokenize("a IS 1").unwrap()),
+            Err(SyntaxError(
+                "Expected keyword `NULL`, instead found arbitrary `1` at line 1.".to_string(),
+                Some(Span {
+                    offset: 5,
+                    length: 1,
+                    line: 1,
+                    column: 6
+                })
+            ))
+        )
+    }
+
+    #[test]
+    fn returns_error_if_rhs_missing() {
+        assert_eq!(
+            expect_expression(&tokenize("a <=").unwrap()),
+            Err(SyntaxError(
+                "Expected the right-hand side of the expression, instead found end of statement."
+                    .to_string(),
+                None
+            ))
+        )
     }
 }
 
@@ -272,7 +999,10 @@ mod expect_identifier_tests {
         assert_eq!(
             expect_identifier(&[Token {
                 value: TokenValue::Arbitrary("foo".to_string()),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Ok(ExpectOk {
                 rest: &[][..],
@@ -287,10 +1017,17 @@ mod expect_identifier_tests {
         assert_eq!(
             expect_identifier(&[Token {
                 value: TokenValue::Const(Keyword::Create),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Err(SyntaxError(
-                "Expected an identifier, instead found keyword `CREATE` at line 1.".to_string()
+                "Expected an identifier, instead found keyword `CREATE` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -300,7 +1037,71 @@ mod expect_identifier_tests {
         assert_eq!(
             expect_identifier(&[]),
             Err(SyntaxError(
-                "Expected an identifier, instead found end of statement.".to_string()
+                "Expected an identifier, instead found end of statement.".to_string(),
+                None
+            ))
+        )
+    }
+}
+
+#[cfg(test)]
+mod expect_table_name_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn returns_ok_unqualified() {
+        assert_eq!(
+            expect_table_name(&[Token {
+                value: TokenValue::Arbitrary("photos_seen".to_string()),
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
+            }]),
+            Ok(ExpectOk {
+                rest: &[][..],
+                tokens_consumed_count: 1,
+                outcome: (None, "photos_seen".to_string())
+            })
+        )
+    }
+
+    #[test]
+    fn returns_ok_qualified() {
+        assert_eq!(
+            expect_table_name(&[Token {
+                value: TokenValue::Arbitrary("gaggle.photos_seen".to_string()),
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
+            }]),
+            Ok(ExpectOk {
+                rest: &[][..],
+                tokens_consumed_count: 1,
+                outcome: (Some("gaggle".to_string()), "photos_seen".to_string())
+            })
+        )
+    }
+
+    #[test]
+    fn returns_error_if_too_many_parts() {
+        assert_eq!(
+            expect_table_name(&[Token {
+                value: TokenValue::Arbitrary("gaggle.photos.seen".to_string()),
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
+            }]),
+            Err(SyntaxError(
+                "Expected a table name, instead found arbitrary `gaggle.photos.seen` at line 1."
+                    .to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -316,7 +1117,10 @@ mod expect_data_type_wrapped_tests {
         assert_eq!(
             expect_data_type(&[Token {
                 value: TokenValue::Type(DataTypeRaw::UInt64),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Ok(ExpectOk {
                 rest: &[][..],
@@ -335,19 +1139,31 @@ mod expect_data_type_wrapped_tests {
             expect_data_type(&[
                 Token {
                     value: TokenValue::Const(Keyword::Nullable),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Type(DataTypeRaw::Timestamp),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }
             ]),
             Ok(ExpectOk {
@@ -367,23 +1183,39 @@ mod expect_data_type_wrapped_tests {
             expect_data_type(&[
                 Token {
                     value: TokenValue::Const(Keyword::Nullable),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Type(DataTypeRaw::Timestamp),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::Comma),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }
             ]),
             Err(SyntaxError(
-                "Expected closing parenthesis `)`, instead found comma `,` at line 1.".to_string()
+                "Expected closing parenthesis `)`, instead found comma `,` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -393,10 +1225,17 @@ mod expect_data_type_wrapped_tests {
         assert_eq!(
             expect_data_type(&[Token {
                 value: TokenValue::Arbitrary("foo".to_string()),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Err(SyntaxError(
-                "Expected a data type, instead found arbitrary `foo` at line 1.".to_string()
+                "Expected a data type, instead found arbitrary `foo` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -406,7 +1245,8 @@ mod expect_data_type_wrapped_tests {
         assert_eq!(
             expect_data_type(&[]),
             Err(SyntaxError(
-                "Expected a data type, instead found end of statement.".to_string()
+                "Expected a data type, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -417,19 +1257,32 @@ mod expect_data_type_wrapped_tests {
             expect_data_type(&[
                 Token {
                     value: TokenValue::Const(Keyword::Nullable),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Arbitrary("bar".to_string()),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }
             ]),
             Err(SyntaxError(
-                "Expected a data type, instead found arbitrary `bar` at line 1.".to_string()
+                "Expected a data type, instead found arbitrary `bar` at line 1.".to_string(),
+                Some(Span {
+                    line: 1,
+                    ..Span::default()
+                })
             ))
         )
     }
@@ -440,15 +1293,22 @@ mod expect_data_type_wrapped_tests {
             expect_data_type(&[
                 Token {
                     value: TokenValue::Const(Keyword::Nullable),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }
             ]),
             Err(SyntaxError(
-                "Expected a data type, instead found end of statement.".to_string()
+                "Expected a data type, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -464,7 +1324,10 @@ mod expect_data_instance_tests {
         assert_eq!(
             expect_data_instance(&[Token {
                 value: TokenValue::String("foo".to_string()),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             },]),
             Ok(ExpectOk {
                 rest: &[][..],
@@ -479,7 +1342,10 @@ mod expect_data_instance_tests {
         assert_eq!(
             expect_data_instance(&[Token {
                 value: TokenValue::Arbitrary("1227".to_string()),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Ok(ExpectOk {
                 rest: &[][..],
@@ -501,34 +1367,106 @@ mod expect_function_call_tests {
             expect_function_call(&[
                 Token {
                     value: TokenValue::Function(Function::Ulid),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 },
                 Token {
                     value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                    line_number: 1
+                    span: Span {
+                        line: 1,
+                        ..Span::default()
+                    }
                 }
             ]),
             Ok(ExpectOk {
                 rest: &[][..],
                 tokens_consumed_count: 3,
-                outcome: Function::Ulid
+                outcome: (Function::Ulid, vec![])
             })
         )
     }
 
+    #[test]
+    fn returns_ok_with_args_or_asterisk() {
+        let tokens = tokenize("COUNT(*) MAX(width)").unwrap();
+        let ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome,
+        } = expect_function_call(&tokens).unwrap();
+        assert_eq!(
+            (tokens_consumed_count, outcome),
+            (4, (Function::Count, vec![]))
+        );
+        assert_eq!(
+            expect_function_call(rest).map(|ok| ok.outcome),
+            Ok((
+                Function::Max,
+                vec![Expression::Atom(DataDefinition::Identifier(
+                    "width".to_string()
+                ))]
+            ))
+        );
+    }
+
+    #[test]
+    fn returns_ok_with_extract_from() {
+        assert_eq!(
+            expect_function_call(&tokenize("EXTRACT(YEAR FROM seen_at)").unwrap())
+                .map(|ok| (ok.tokens_consumed_count, ok.outcome)),
+            Ok((
+                6,
+                (
+                    Function::Extract,
+                    vec![
+                        Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                            DataInstanceRaw::String("year".to_string())
+                        ))),
+                        Expression::Atom(DataDefinition::Identifier("seen_at".to_string()))
+                    ]
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn returns_error_with_asterisk_outside_count() {
+        assert_eq!(
+            expect_function_call(&tokenize("MAX(*)").unwrap()).map(|ok| ok.outcome),
+            Err(SyntaxError(
+                "Expected a function call, a constant value or an identifier, instead found keyword `*` at line 1.".to_string(),
+                Some(Span {
+                    offset: 4,
+                    length: 1,
+                    line: 1,
+                    column: 5
+                })
+            ))
+        );
+    }
+
     #[test]
     fn returns_error_if_no_opening_parenthesis() {
         assert_eq!(
             expect_function_call(&[Token {
                 value: TokenValue::Function(Function::Ulid),
-                line_number: 1
+                span: Span {
+                    line: 1,
+                    ..Span::default()
+                }
             }]),
             Err(SyntaxError(
-                "Expected opening parenthesis `(`, instead found end of statement.".to_string()
+                "Expected opening parenthesis `(`, instead found end of statement.".to_string(),
+                None
             ))
         )
     }
@@ -0,0 +1,139 @@
+use crate::constructs::statements::{AlterTableAction, AlterTableStatement};
+use crate::sql::errors::*;
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+use super::expect_column_definition;
+
+/// Skip the optional COLUMN keyword, returning the number of tokens consumed.
+fn skip_column_keyword(tokens: &[Token]) -> usize {
+    match expect_token_value(tokens, &TokenValue::Const(Keyword::Column)) {
+        Ok(ExpectOk {
+            tokens_consumed_count,
+            ..
+        }) => tokens_consumed_count,
+        Err(_) => 0,
+    }
+}
+
+/// Expect the part of RENAME [COLUMN] following RENAME, i.e. either `TO <table>` or `[COLUMN] <column> TO <column>`.
+fn expect_rename(tokens: &[Token]) -> ExpectResult<'_, AlterTableAction> {
+    if let Ok(ExpectOk { rest, .. }) = expect_token_value(tokens, &TokenValue::Const(Keyword::To)) {
+        let ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: new_table_name,
+        } = expect_identifier(rest)?;
+        return Ok(ExpectOk {
+            rest,
+            tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for TO
+            outcome: AlterTableAction::RenameTo(new_table_name),
+        });
+    }
+    let tokens_consumed_count_column_keyword = skip_column_keyword(tokens);
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_column_name,
+        outcome: column_name,
+    } = expect_identifier(&tokens[tokens_consumed_count_column_keyword..])?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::To))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_new_column_name,
+        outcome: new_column_name,
+    } = expect_identifier(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 // +1 to account for TO
+            + tokens_consumed_count_column_keyword
+            + tokens_consumed_count_column_name
+            + tokens_consumed_count_new_column_name,
+        outcome: AlterTableAction::RenameColumn {
+            column_name,
+            new_column_name,
+        },
+    })
+}
+
+fn expect_alter_table_action(tokens: &[Token]) -> ExpectResult<'_, AlterTableAction> {
+    let ExpectOk {
+        rest,
+        outcome: found_token,
+        ..
+    } = expect_next_token(tokens, &EXPECTED_ACTION)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: action,
+    } = match found_token.value {
+        // ADD [COLUMN]
+        TokenValue::Const(Keyword::Add) => {
+            let tokens_consumed_count_column_keyword = skip_column_keyword(rest);
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: column,
+            } = expect_column_definition(&rest[tokens_consumed_count_column_keyword..])?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_column_keyword + tokens_consumed_count,
+                outcome: AlterTableAction::AddColumn(column),
+            }
+        }
+        // DROP [COLUMN]
+        TokenValue::Const(Keyword::Drop) => {
+            let tokens_consumed_count_column_keyword = skip_column_keyword(rest);
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: column_name,
+            } = expect_identifier(&rest[tokens_consumed_count_column_keyword..])?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_column_keyword + tokens_consumed_count,
+                outcome: AlterTableAction::DropColumn(column_name),
+            }
+        }
+        // RENAME
+        TokenValue::Const(Keyword::Rename) => expect_rename(rest)?,
+        _ => {
+            return Err(SyntaxError(
+                format!(
+                    "Expected {}, instead found {}.",
+                    EXPECTED_ACTION, found_token
+                ),
+                Some(found_token.span),
+            ))
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for the action keyword
+        outcome: action,
+    })
+}
+
+const EXPECTED_ACTION: &str = "keyword `ADD`, `DROP` or `RENAME`";
+
+/// Conjure an AlterTableStatement from tokens following ALTER TABLE.
+pub fn expect_alter_table<'t>(tokens: &'t [Token]) -> ExpectResult<'t, AlterTableStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(tokens)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_action,
+        outcome: action,
+    } = expect_alter_table_action(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name + tokens_consumed_count_action,
+        outcome: AlterTableStatement {
+            schema_name,
+            table_name,
+            action,
+        },
+    })
+}
@@ -0,0 +1,31 @@
+use crate::constructs::statements::AnalyzeStatement;
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+/// Conjure an AnalyzeStatement from tokens following ANALYZE.
+pub fn expect_analyze<'t>(tokens: &'t [Token]) -> ExpectResult<'t, AnalyzeStatement> {
+    // Without a table name, all tables get analyzed
+    if expect_end_of_statement(tokens).is_ok() {
+        return Ok(ExpectOk {
+            rest: tokens,
+            tokens_consumed_count: 0,
+            outcome: AnalyzeStatement {
+                schema_name: None,
+                table_name: None,
+            },
+        });
+    }
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(tokens)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: AnalyzeStatement {
+            schema_name,
+            table_name: Some(table_name),
+        },
+    })
+}
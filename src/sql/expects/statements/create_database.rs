@@ -0,0 +1,39 @@
+use crate::constructs::statements::CreateDatabaseStatement;
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+/// Conjure a CreateDatabaseStatement from tokens following CREATE DATABASE.
+pub fn expect_create_database<'t>(
+    tokens: &'t [Token],
+) -> ExpectResult<'t, CreateDatabaseStatement> {
+    let (if_not_exists, rest, tokens_consumed_count_if_not_exists) =
+        match expect_token_values_sequence(
+            tokens,
+            &[
+                TokenValue::Const(Keyword::If),
+                TokenValue::Const(Keyword::Not),
+                TokenValue::Const(Keyword::Exists),
+            ],
+        ) {
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                ..
+            }) => (true, rest, tokens_consumed_count),
+            Err(_) => (false, tokens, 0),
+        };
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_database_name,
+        outcome: database_name,
+    } = expect_identifier(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_not_exists
+            + tokens_consumed_count_database_name,
+        outcome: CreateDatabaseStatement {
+            database_name,
+            if_not_exists,
+        },
+    })
+}
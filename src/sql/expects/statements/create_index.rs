@@ -0,0 +1,90 @@
+use crate::constructs::{components::IndexDefinition, statements::CreateIndexStatement};
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+/// Conjure a CreateIndexStatement from tokens following CREATE INDEX or CREATE UNIQUE INDEX.
+fn expect_index_definition(
+    tokens: &[Token],
+    unique: bool,
+) -> ExpectResult<'_, CreateIndexStatement> {
+    let (if_not_exists, rest, tokens_consumed_count_if_not_exists) =
+        match expect_token_values_sequence(
+            tokens,
+            &[
+                TokenValue::Const(Keyword::If),
+                TokenValue::Const(Keyword::Not),
+                TokenValue::Const(Keyword::Exists),
+            ],
+        ) {
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                ..
+            }) => (true, rest, tokens_consumed_count),
+            Err(_) => (false, tokens, 0),
+        };
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_name,
+        outcome: name,
+    } = expect_identifier(rest)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_on,
+        ..
+    } = expect_token_value(rest, &TokenValue::Const(Keyword::On))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(rest)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_column_names,
+        outcome: column_names,
+    } = expect_enclosed_comma_separated(rest, expect_identifier)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_not_exists
+            + tokens_consumed_count_name
+            + tokens_consumed_count_on
+            + tokens_consumed_count_table_name
+            + tokens_consumed_count_column_names,
+        outcome: CreateIndexStatement {
+            schema_name,
+            table_name,
+            index: IndexDefinition {
+                name,
+                column_names,
+                unique,
+            },
+            if_not_exists,
+        },
+    })
+}
+
+/// Conjure a CreateIndexStatement from tokens following CREATE INDEX.
+pub fn expect_create_index<'t>(tokens: &'t [Token]) -> ExpectResult<'t, CreateIndexStatement> {
+    expect_index_definition(tokens, false)
+}
+
+/// Conjure a CreateIndexStatement from tokens following CREATE UNIQUE.
+pub fn expect_create_unique_index<'t>(
+    tokens: &'t [Token],
+) -> ExpectResult<'t, CreateIndexStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_index,
+        ..
+    } = expect_token_value(tokens, &TokenValue::Const(Keyword::Index))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome,
+    } = expect_index_definition(rest, true)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_index + tokens_consumed_count,
+        outcome,
+    })
+}
@@ -1,10 +1,20 @@
 use crate::constructs::{
-    components::{ColumnDefinition, TableDefinition},
+    components::{ColumnDefinition, ConstraintDefinition, ConstraintKind, TableDefinition},
     statements::CreateTableStatement,
 };
+use crate::sql::errors::*;
 use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
 use crate::sql::tokenizer::*;
 
+/// Constraint whose name is yet to be generated if it wasn't given with CONSTRAINT.
+type MaybeNamedConstraint = (Option<String>, ConstraintKind);
+
+/// Element of a table definition: a column along with constraints declared next to it, or a table constraint.
+enum TableElement {
+    Column(ColumnDefinition, Vec<MaybeNamedConstraint>),
+    Constraint(MaybeNamedConstraint),
+}
+
 pub fn expect_column_definition<'t>(tokens: &'t [Token]) -> ExpectResult<'t, ColumnDefinition> {
     let ExpectOk {
         rest,
@@ -26,6 +36,16 @@ pub fn expect_column_definition<'t>(tokens: &'t [Token]) -> ExpectResult<'t, Col
         |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Key)),
         &TokenValue::Const(Keyword::Key),
     )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_metric_key,
+        outcome: metric_key_option,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Metric)),
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Key)),
+        &TokenValue::Const(Keyword::Key),
+    )?;
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_default,
@@ -42,31 +62,318 @@ pub fn expect_column_definition<'t>(tokens: &'t [Token]) -> ExpectResult<'t, Col
         tokens_consumed_count: tokens_consumed_count_name
             + tokens_consumed_count_data_type
             + tokens_consumed_count_primary_key
+            + tokens_consumed_count_metric_key
             + tokens_consumed_count_default,
         outcome: ColumnDefinition {
             name,
             data_type,
             primary_key: primary_key_option.is_some(),
-            default: maybe_default.and_then(|(_, default)| Some(default)),
+            metric_key: metric_key_option.is_some(),
+            default: maybe_default.map(|(_, default)| default),
         },
     })
 }
 
-pub fn expect_table_definition<'t>(tokens: &'t [Token]) -> ExpectResult<'t, TableDefinition> {
+const EXPECTED_CONSTRAINT_KIND: &str = "keyword `UNIQUE` or `CHECK`";
+const EXPECTED_TABLE_CONSTRAINT_KIND: &str = "keyword `PRIMARY`, `UNIQUE` or `CHECK`";
+
+/// Expect the optional `CONSTRAINT name` prefix of a constraint.
+fn expect_constraint_name(tokens: &[Token]) -> ExpectResult<'_, Option<String>> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: maybe_name,
+    } = detect(
+        tokens,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Constraint)),
+        expect_identifier,
+        &"constraint name",
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: maybe_name.map(|(_, name)| name),
+    })
+}
+
+/// Expect `CHECK (condition)`.
+fn expect_check(tokens: &[Token]) -> ExpectResult<'_, ConstraintKind> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_check,
+        ..
+    } = expect_token_value(tokens, &TokenValue::Const(Keyword::Check))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_condition,
+        outcome: condition,
+    } = expect_enclosed(
+        rest,
+        expect_expression,
+        Delimiter::ParenthesisOpening,
+        Delimiter::ParenthesisClosing,
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_check + tokens_consumed_count_condition,
+        outcome: ConstraintKind::Check(condition),
+    })
+}
+
+/// Expect a table constraint, i.e. `[CONSTRAINT name] PRIMARY KEY (column, ...)`, `[CONSTRAINT name] UNIQUE (column, ...)`
+/// or `[CONSTRAINT name] CHECK (condition)`.
+fn expect_table_constraint(tokens: &[Token]) -> ExpectResult<'_, MaybeNamedConstraint> {
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_name,
         outcome: name,
-    } = expect_identifier(tokens)?;
+    } = expect_constraint_name(tokens)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_kind,
+        outcome: kind,
+    } = match expect_next_token(rest, &EXPECTED_TABLE_CONSTRAINT_KIND)?.outcome {
+        Token {
+            value: TokenValue::Const(Keyword::Primary),
+            ..
+        } => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_key,
+                ..
+            } = expect_token_value(&rest[1..], &TokenValue::Const(Keyword::Key))?;
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: column_names,
+            } = expect_enclosed_comma_separated(rest, expect_identifier)?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count + tokens_consumed_count_key + 1,
+                outcome: ConstraintKind::PrimaryKey(column_names),
+            }
+        }
+        Token {
+            value: TokenValue::Const(Keyword::Unique),
+            ..
+        } => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: column_names,
+            } = expect_enclosed_comma_separated(&rest[1..], expect_identifier)?;
+            ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count + 1,
+                outcome: ConstraintKind::Unique(column_names),
+            }
+        }
+        Token {
+            value: TokenValue::Const(Keyword::Check),
+            ..
+        } => expect_check(rest)?,
+        wrong_token => {
+            return Err(SyntaxError(
+                format!(
+                    "Expected {}, instead found {}.",
+                    EXPECTED_TABLE_CONSTRAINT_KIND, wrong_token
+                ),
+                Some(wrong_token.span),
+            ))
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_name + tokens_consumed_count_kind,
+        outcome: (name, kind),
+    })
+}
+
+/// Expect a table constraint without its name, as kept in the catalog.
+pub fn expect_constraint_kind(tokens: &[Token]) -> ExpectResult<'_, ConstraintKind> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: (_, kind),
+    } = expect_table_constraint(tokens)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: kind,
+    })
+}
+
+/// Expect a column definition followed by any number of column constraints, i.e. `NOT NULL`,
+/// `[CONSTRAINT name] UNIQUE` or `[CONSTRAINT name] CHECK (condition)`.
+fn expect_column_with_constraints(tokens: &[Token]) -> ExpectResult<'_, TableElement> {
+    let ExpectOk {
+        mut rest,
+        tokens_consumed_count: mut tokens_consumed_total_count,
+        outcome: column,
+    } = expect_column_definition(tokens)?;
+    let mut constraints = Vec::new();
+    while !rest.is_empty() {
+        if let Ok(ExpectOk {
+            rest: rest_after_not_null,
+            tokens_consumed_count,
+            ..
+        }) = expect_token_values_sequence(
+            rest,
+            &[
+                TokenValue::Const(Keyword::Not),
+                TokenValue::Const(Keyword::Null),
+            ],
+        ) {
+            if column.data_type.is_nullable {
+                return Err(SyntaxError(
+                    format!(
+                        "Column `{}` cannot be both NULLABLE and NOT NULL.",
+                        column.name
+                    ),
+                    Some(rest[0].span),
+                ));
+            }
+            rest = rest_after_not_null;
+            tokens_consumed_total_count += tokens_consumed_count;
+            continue;
+        }
+        let ExpectOk {
+            rest: rest_after_name,
+            tokens_consumed_count: tokens_consumed_count_name,
+            outcome: name,
+        } = expect_constraint_name(rest)?;
+        let ExpectOk {
+            rest: rest_after_kind,
+            tokens_consumed_count: tokens_consumed_count_kind,
+            outcome: kind,
+        } = match expect_next_token(rest_after_name, &EXPECTED_CONSTRAINT_KIND)?.outcome {
+            Token {
+                value: TokenValue::Const(Keyword::Unique),
+                ..
+            } => ExpectOk {
+                rest: &rest_after_name[1..],
+                tokens_consumed_count: 1,
+                outcome: ConstraintKind::Unique(vec![column.name.clone()]),
+            },
+            Token {
+                value: TokenValue::Const(Keyword::Check),
+                ..
+            } => expect_check(rest_after_name)?,
+            wrong_token => {
+                return Err(SyntaxError(
+                    format!(
+                        "Expected {}, instead found {}.",
+                        EXPECTED_CONSTRAINT_KIND, wrong_token
+                    ),
+                    Some(wrong_token.span),
+                ))
+            }
+        };
+        constraints.push((name, kind));
+        rest = rest_after_kind;
+        tokens_consumed_total_count += tokens_consumed_count_name + tokens_consumed_count_kind;
+    }
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_total_count,
+        outcome: TableElement::Column(column, constraints),
+    })
+}
+
+/// Expect a column or a table constraint.
+fn expect_table_element(tokens: &[Token]) -> ExpectResult<'_, TableElement> {
+    match tokens.first().map(|token| &token.value) {
+        Some(TokenValue::Const(
+            Keyword::Constraint | Keyword::Primary | Keyword::Unique | Keyword::Check,
+        )) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome,
+            } = expect_table_constraint(tokens)?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: TableElement::Constraint(outcome),
+            })
+        }
+        _ => expect_column_with_constraints(tokens),
+    }
+}
+
+/// Name constraints that weren't given one, same as Postgres does it, e.g. `photos_url_key` for UNIQUE (url)
+/// or `photos_width_check` for a CHECK involving `width` first. Clashing names get a number appended.
+fn name_constraints(
+    table_name: &str,
+    constraints: Vec<MaybeNamedConstraint>,
+) -> Vec<ConstraintDefinition> {
+    let mut taken_names: Vec<String> = constraints
+        .iter()
+        .filter_map(|(name, _)| name.clone())
+        .collect();
+    constraints
+        .into_iter()
+        .map(|(name, kind)| {
+            let name = name.unwrap_or_else(|| {
+                let unnamed = ConstraintDefinition {
+                    name: String::new(),
+                    kind: kind.clone(),
+                };
+                let base_name = match (&kind, unnamed.get_column_names().first()) {
+                    (ConstraintKind::PrimaryKey(_), _) => format!("{}_pkey", table_name),
+                    (ConstraintKind::Unique(column_names), _) => {
+                        format!("{}_{}_key", table_name, column_names.join("_"))
+                    }
+                    (ConstraintKind::Check(_), Some(column_name)) => {
+                        format!("{}_{}_check", table_name, column_name)
+                    }
+                    (ConstraintKind::Check(_), None) => format!("{}_check", table_name),
+                };
+                let mut name = base_name.clone();
+                let mut suffix = 0;
+                while taken_names.contains(&name) {
+                    suffix += 1;
+                    name = format!("{}{}", base_name, suffix);
+                }
+                taken_names.push(name.clone());
+                name
+            });
+            ConstraintDefinition { name, kind }
+        })
+        .collect()
+}
+
+/// Expect a table definition, whose name may be qualified with a database name.
+pub fn expect_table_definition<'t>(
+    tokens: &'t [Token],
+) -> ExpectResult<'t, (Option<String>, TableDefinition)> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_name,
+        outcome: (schema_name, name),
+    } = expect_table_name(tokens)?;
     let ExpectOk {
         rest,
-        tokens_consumed_count: tokens_consumed_count_columns,
-        outcome: columns,
-    } = expect_enclosed_comma_separated(rest, expect_column_definition)?;
+        tokens_consumed_count: tokens_consumed_count_elements,
+        outcome: elements,
+    } = expect_enclosed_comma_separated(rest, expect_table_element)?;
+    let mut columns = Vec::new();
+    let mut constraints = Vec::new();
+    for element in elements {
+        match element {
+            TableElement::Column(column, column_constraints) => {
+                columns.push(column);
+                constraints.extend(column_constraints);
+            }
+            TableElement::Constraint(constraint) => constraints.push(constraint),
+        }
+    }
+    let mut table = TableDefinition::new(name, columns);
+    table.constraints = name_constraints(&table.name, constraints);
     Ok(ExpectOk {
         rest,
-        tokens_consumed_count: tokens_consumed_count_name + tokens_consumed_count_columns,
-        outcome: TableDefinition::new(name, columns),
+        tokens_consumed_count: tokens_consumed_count_name + tokens_consumed_count_elements,
+        outcome: (schema_name, table),
     })
 }
 
@@ -89,7 +396,7 @@ pub fn expect_create_table<'t>(tokens: &'t [Token]) -> ExpectResult<'t, CreateTa
             Err(_) => (false, tokens, 0),
         };
     let ExpectOk {
-        outcome: table,
+        outcome: (schema_name, table),
         rest,
         tokens_consumed_count: tokens_consumed_count_table_definition,
     } = expect_table_definition(rest)?;
@@ -98,6 +405,7 @@ pub fn expect_create_table<'t>(tokens: &'t [Token]) -> ExpectResult<'t, CreateTa
         tokens_consumed_count: tokens_consumed_count_table_definition
             + tokens_consumed_count_if_not_exists,
         outcome: CreateTableStatement {
+            schema_name,
             table,
             if_not_exists,
         },
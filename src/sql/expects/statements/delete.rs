@@ -0,0 +1,43 @@
+use crate::constructs::statements::DeleteStatement;
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+use super::detect_returning;
+
+/// Conjure a DeleteStatement from tokens following DELETE.
+pub fn expect_delete<'t>(tokens: &'t [Token]) -> ExpectResult<'t, DeleteStatement> {
+    let ExpectOk { rest, .. } = expect_token_value(tokens, &TokenValue::Const(Keyword::From))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(rest)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_where_clause,
+        outcome: maybe_where_clause,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Where)),
+        expect_expression,
+        &"WHERE conditions",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_returning,
+        outcome: returning,
+    } = detect_returning(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 // +1 to account for FROM
+            + tokens_consumed_count_table_name
+            + tokens_consumed_count_where_clause
+            + tokens_consumed_count_returning,
+        outcome: DeleteStatement {
+            schema_name,
+            table_name,
+            where_clause: maybe_where_clause.map(|(_, where_clause)| where_clause),
+            returning,
+        },
+    })
+}
@@ -0,0 +1,101 @@
+use crate::constructs::statements::{
+    DropDatabaseStatement, DropIndexStatement, DropTableStatement,
+};
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+/// Detect the optional IF EXISTS clause of DROP statements.
+fn detect_if_exists(tokens: &[Token]) -> ExpectResult<'_, bool> {
+    match expect_token_values_sequence(
+        tokens,
+        &[
+            TokenValue::Const(Keyword::If),
+            TokenValue::Const(Keyword::Exists),
+        ],
+    ) {
+        Ok(ExpectOk {
+            rest,
+            tokens_consumed_count,
+            ..
+        }) => Ok(ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: true,
+        }),
+        Err(_) => Ok(ExpectOk {
+            rest: tokens,
+            tokens_consumed_count: 0,
+            outcome: false,
+        }),
+    }
+}
+
+/// Conjure a DropTableStatement from tokens following DROP TABLE.
+pub fn expect_drop_table<'t>(tokens: &'t [Token]) -> ExpectResult<'t, DropTableStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists,
+        outcome: if_exists,
+    } = detect_if_exists(tokens)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists + tokens_consumed_count_table_name,
+        outcome: DropTableStatement {
+            schema_name,
+            table_name,
+            if_exists,
+        },
+    })
+}
+
+/// Conjure a DropIndexStatement from tokens following DROP INDEX.
+pub fn expect_drop_index<'t>(tokens: &'t [Token]) -> ExpectResult<'t, DropIndexStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists,
+        outcome: if_exists,
+    } = detect_if_exists(tokens)?;
+    // Indexes live in the database of their table, so their names are qualified like table names
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_index_name,
+        outcome: (schema_name, index_name),
+    } = expect_table_name(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists + tokens_consumed_count_index_name,
+        outcome: DropIndexStatement {
+            schema_name,
+            index_name,
+            if_exists,
+        },
+    })
+}
+
+/// Conjure a DropDatabaseStatement from tokens following DROP DATABASE.
+pub fn expect_drop_database<'t>(tokens: &'t [Token]) -> ExpectResult<'t, DropDatabaseStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists,
+        outcome: if_exists,
+    } = detect_if_exists(tokens)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_database_name,
+        outcome: database_name,
+    } = expect_identifier(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_if_exists
+            + tokens_consumed_count_database_name,
+        outcome: DropDatabaseStatement {
+            database_name,
+            if_exists,
+        },
+    })
+}
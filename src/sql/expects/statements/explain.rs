@@ -0,0 +1,30 @@
+use crate::constructs::statements::ExplainStatement;
+use crate::sql::expects::{generic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+use super::expect_query;
+
+/// Conjure an ExplainStatement from tokens following EXPLAIN.
+pub fn expect_explain<'t>(tokens: &'t [Token]) -> ExpectResult<'t, ExplainStatement> {
+    let tokens_consumed_count_analyze =
+        match expect_token_value(tokens, &TokenValue::Const(Keyword::Analyze)) {
+            Ok(ExpectOk {
+                tokens_consumed_count,
+                ..
+            }) => tokens_consumed_count,
+            Err(_) => 0,
+        };
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_select,
+        outcome: select,
+    } = expect_query(&tokens[tokens_consumed_count_analyze..])?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_analyze + tokens_consumed_count_select,
+        outcome: ExplainStatement {
+            analyze: tokens_consumed_count_analyze > 0,
+            select,
+        },
+    })
+}
@@ -1,6 +1,148 @@
-use crate::constructs::statements::InsertStatement;
+use crate::constructs::statements::{
+    ConflictAction, InsertSource, InsertStatement, InsertValue, OnConflict,
+};
 use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
-use crate::sql::tokenizer::*;
+use crate::sql::{tokenizer::*, SyntaxError};
+
+use super::{detect_returning, expect_assignment, expect_query};
+
+/// Expect an item of VALUES: a constant or keyword `DEFAULT`.
+fn expect_insert_value(tokens: &[Token]) -> ExpectResult<'_, InsertValue> {
+    if let Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        ..
+    }) = expect_token_value(tokens, &TokenValue::Const(Keyword::Default))
+    {
+        return Ok(ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: InsertValue::Default,
+        });
+    }
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: value,
+    } = expect_data_instance(tokens)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: InsertValue::Value(value),
+    })
+}
+
+/// Expect one parenthesized row of VALUES.
+fn expect_values_row(tokens: &[Token]) -> ExpectResult<'_, Vec<InsertValue>> {
+    expect_enclosed_comma_separated(tokens, expect_insert_value)
+}
+
+/// Expect the source of inserted rows: `VALUES (...), (...)` or a SELECT statement.
+fn expect_insert_source(tokens: &[Token]) -> ExpectResult<'_, InsertSource> {
+    let ExpectOk {
+        rest,
+        outcome: found_token,
+        ..
+    } = expect_next_token(tokens, &"keyword `VALUES`, `SELECT` or `WITH`")?;
+    match found_token.value {
+        TokenValue::Const(Keyword::Values) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: rows,
+            } = expect_comma_separated(rest, expect_values_row)?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for VALUES
+                outcome: InsertSource::Values(rows),
+            })
+        }
+        TokenValue::Const(Keyword::Select | Keyword::With) => {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: select,
+            } = expect_query(tokens)?;
+            Ok(ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: InsertSource::Select(Box::new(select)),
+            })
+        }
+        _ => Err(SyntaxError(
+            format!(
+                "Expected keyword `VALUES`, `SELECT` or `WITH`, instead found {}.",
+                found_token
+            ),
+            Some(found_token.span),
+        )),
+    }
+}
+
+/// Expect what follows `ON CONFLICT`: `(<columns>) DO NOTHING` or `(<columns>) DO UPDATE SET ... [WHERE ...]`.
+fn expect_on_conflict(tokens: &[Token]) -> ExpectResult<'_, OnConflict> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_column_names,
+        outcome: column_names,
+    } = expect_enclosed_comma_separated(tokens, expect_identifier)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::Do))?;
+    let ExpectOk {
+        rest,
+        outcome: found_token,
+        ..
+    } = expect_next_token(rest, &"keyword `NOTHING` or `UPDATE`")?;
+    let (rest, tokens_consumed_count_action, action) = match found_token.value {
+        TokenValue::Const(Keyword::Nothing) => (rest, 0, ConflictAction::DoNothing),
+        TokenValue::Const(Keyword::Update) => {
+            let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::Set))?;
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_assignments,
+                outcome: assignments,
+            } = expect_comma_separated(rest, expect_assignment)?;
+            let ExpectOk {
+                rest,
+                tokens_consumed_count: tokens_consumed_count_where_clause,
+                outcome: maybe_where_clause,
+            } = detect(
+                rest,
+                |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Where)),
+                expect_expression,
+                &"WHERE conditions",
+            )?;
+            (
+                rest,
+                1 // +1 to account for SET
+                    + tokens_consumed_count_assignments
+                    + tokens_consumed_count_where_clause,
+                ConflictAction::DoUpdate {
+                    assignments,
+                    where_clause: maybe_where_clause.map(|(_, where_clause)| where_clause),
+                },
+            )
+        }
+        _ => {
+            return Err(SyntaxError(
+                format!(
+                    "Expected keyword `NOTHING` or `UPDATE`, instead found {}.",
+                    found_token
+                ),
+                Some(found_token.span),
+            ))
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 2 // +2 to account for DO and NOTHING/UPDATE
+            + tokens_consumed_count_column_names
+            + tokens_consumed_count_action,
+        outcome: OnConflict {
+            column_names,
+            action,
+        },
+    })
+}
 
 /// Conjure an InsertStatement from tokens following INSERT.
 pub fn expect_insert<'t>(tokens: &'t [Token]) -> ExpectResult<'t, InsertStatement> {
@@ -8,28 +150,55 @@ pub fn expect_insert<'t>(tokens: &'t [Token]) -> ExpectResult<'t, InsertStatemen
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_table_name,
-        outcome: table_name,
-    } = expect_identifier(rest)?;
+        outcome: (schema_name, table_name),
+    } = expect_table_name(rest)?;
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_column_names,
         outcome: column_names,
     } = expect_enclosed_comma_separated(rest, expect_identifier)?;
-    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::Values))?;
     let ExpectOk {
         rest,
-        tokens_consumed_count: tokens_consumed_count_values,
-        outcome: values,
-    } = expect_enclosed_comma_separated(rest, expect_data_instance)?;
+        tokens_consumed_count: tokens_consumed_count_source,
+        outcome: source,
+    } = expect_insert_source(rest)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_on_conflict,
+        outcome: maybe_on_conflict,
+    } = detect(
+        rest,
+        |tokens| {
+            expect_token_values_sequence(
+                tokens,
+                &[
+                    TokenValue::Const(Keyword::On),
+                    TokenValue::Const(Keyword::Conflict),
+                ],
+            )
+        },
+        expect_on_conflict,
+        &"ON CONFLICT clause",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_returning,
+        outcome: returning,
+    } = detect_returning(rest)?;
     Ok(ExpectOk {
         rest,
-        tokens_consumed_count: 2 // +2 to account for INTO + VALUES
+        tokens_consumed_count: 1 // +1 to account for INTO
             + tokens_consumed_count_table_name
-            + tokens_consumed_count_column_names + tokens_consumed_count_values,
+            + tokens_consumed_count_column_names + tokens_consumed_count_source
+            + tokens_consumed_count_on_conflict
+            + tokens_consumed_count_returning,
         outcome: InsertStatement {
+            schema_name,
             table_name,
             column_names,
-            values,
+            source,
+            on_conflict: maybe_on_conflict.map(|(_, on_conflict)| on_conflict),
+            returning,
         },
     })
 }
@@ -1,7 +1,25 @@
+mod alter_table;
+mod analyze;
+mod create_database;
+mod create_index;
 mod create_table;
+mod delete;
+mod drop;
+mod explain;
 mod insert;
 mod select;
+mod truncate;
+mod update;
 
+pub use alter_table::*;
+pub use analyze::*;
+pub use create_database::*;
+pub use create_index::*;
 pub use create_table::*;
+pub use delete::*;
+pub use drop::*;
+pub use explain::*;
 pub use insert::*;
 pub use select::*;
+pub use truncate::*;
+pub use update::*;
@@ -1,34 +1,239 @@
-use crate::constructs::statements::{SelectColumn, SelectStatement};
+use crate::constructs::statements::{
+    CommonTableExpression, Join, JoinKind, OrderBy, SelectColumn, SelectStatement, TableReference,
+};
 use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
 use crate::sql::{tokenizer::*, SyntaxError};
 
 pub fn expect_select_column<'t>(tokens: &'t [Token]) -> ExpectResult<'t, SelectColumn> {
-    let ExpectOk {
-        outcome: found_token,
+    if let Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
         ..
-    } = expect_next_token(tokens, &"a SELECT column")?;
-    match found_token {
-        Token {
-            value: TokenValue::Arbitrary(value),
+    }) = expect_token_value(tokens, &TokenValue::Const(Keyword::Asterisk))
+    {
+        return Ok(ExpectOk {
+            rest,
+            tokens_consumed_count,
+            outcome: SelectColumn::All,
+        });
+    }
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_expression,
+        outcome: expression,
+    } = expect_expression(tokens)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_alias,
+        outcome: maybe_alias,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::As)),
+        expect_identifier,
+        &"an alias",
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_expression + tokens_consumed_count_alias,
+        outcome: SelectColumn::Expression {
+            expression,
+            alias: maybe_alias.map(|(_, alias)| alias),
+        },
+    })
+}
+
+/// Detect the RETURNING clause of a data-modifying statement, whose columns are like those of SELECT.
+pub fn detect_returning<'t>(tokens: &'t [Token]) -> ExpectResult<'t, Vec<SelectColumn>> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: maybe_returning,
+    } = detect(
+        tokens,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Returning)),
+        |tokens| expect_comma_separated(tokens, expect_select_column),
+        &"RETURNING columns",
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: maybe_returning.map_or_else(Vec::new, |(_, returning)| returning),
+    })
+}
+
+/// Expect an ORDER BY item: `expression [ASC|DESC] [NULLS FIRST|LAST]`.
+pub fn expect_order_by<'t>(tokens: &'t [Token]) -> ExpectResult<'t, OrderBy> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_expression,
+        outcome: expression,
+    } = expect_expression(tokens)?;
+    let (descending, tokens_consumed_count_direction) = match rest.first() {
+        Some(Token {
+            value: TokenValue::Const(Keyword::Asc),
             ..
-        } => Ok(ExpectOk {
-            rest: &tokens[1..],
-            tokens_consumed_count: 1,
-            outcome: SelectColumn::Identifier(value.to_owned()),
-        }),
-        Token {
-            value: TokenValue::Const(Keyword::Asterisk),
+        }) => (false, 1),
+        Some(Token {
+            value: TokenValue::Const(Keyword::Desc),
             ..
-        } => Ok(ExpectOk {
-            rest: &tokens[1..],
-            tokens_consumed_count: 1,
-            outcome: SelectColumn::All,
+        }) => (true, 1),
+        _ => (false, 0),
+    };
+    let rest = &rest[tokens_consumed_count_direction..];
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_nulls,
+        outcome: maybe_nulls,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Nulls)),
+        |tokens| {
+            let ExpectOk {
+                rest,
+                tokens_consumed_count,
+                outcome: found_token,
+            } = expect_next_token(tokens, &"keyword `FIRST` or `LAST`")?;
+            match found_token.value {
+                TokenValue::Const(Keyword::First) => Ok(ExpectOk {
+                    rest,
+                    tokens_consumed_count,
+                    outcome: true,
+                }),
+                TokenValue::Const(Keyword::Last) => Ok(ExpectOk {
+                    rest,
+                    tokens_consumed_count,
+                    outcome: false,
+                }),
+                _ => Err(SyntaxError(
+                    format!(
+                        "Expected keyword `FIRST` or `LAST`, instead found {}.",
+                        found_token
+                    ),
+                    Some(found_token.span),
+                )),
+            }
+        },
+        &"keyword `FIRST` or `LAST`",
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_expression
+            + tokens_consumed_count_direction
+            + tokens_consumed_count_nulls,
+        outcome: OrderBy {
+            expression,
+            descending,
+            nulls_first: maybe_nulls.map_or(descending, |(_, nulls_first)| nulls_first),
+        },
+    })
+}
+
+/// Expect a table to read from, optionally aliased: `table [[AS] alias]`.
+pub fn expect_table_reference<'t>(tokens: &'t [Token]) -> ExpectResult<'t, TableReference> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(tokens)?;
+    let (alias, tokens_consumed_count_alias) = match rest {
+        [Token {
+            value: TokenValue::Const(Keyword::As),
+            ..
+        }, ..] => (Some(expect_identifier(&rest[1..])?.outcome), 2),
+        [Token {
+            value: TokenValue::Arbitrary(alias),
+            ..
+        }, ..] => (Some(alias.to_owned()), 1),
+        _ => (None, 0),
+    };
+    Ok(ExpectOk {
+        rest: &rest[tokens_consumed_count_alias..],
+        tokens_consumed_count: tokens_consumed_count_table_name + tokens_consumed_count_alias,
+        outcome: TableReference {
+            schema_name,
+            table_name,
+            alias,
+        },
+    })
+}
+
+/// Detect a join: `[INNER] JOIN <table> ON <condition>` or `LEFT [OUTER] JOIN <table> ON <condition>`.
+pub fn detect_join<'t>(tokens: &'t [Token]) -> ExpectResult<'t, Option<Join>> {
+    let (kind, keyword_sequence): (JoinKind, &[TokenValue]) = match tokens {
+        [Token {
+            value: TokenValue::Const(Keyword::Join),
+            ..
+        }, ..] => (JoinKind::Inner, &[TokenValue::Const(Keyword::Join)]),
+        [Token {
+            value: TokenValue::Const(Keyword::Inner),
+            ..
+        }, ..] => (
+            JoinKind::Inner,
+            &[
+                TokenValue::Const(Keyword::Inner),
+                TokenValue::Const(Keyword::Join),
+            ],
+        ),
+        [Token {
+            value: TokenValue::Const(Keyword::Left),
+            ..
+        }, Token {
+            value: TokenValue::Const(Keyword::Outer),
+            ..
+        }, ..] => (
+            JoinKind::Left,
+            &[
+                TokenValue::Const(Keyword::Left),
+                TokenValue::Const(Keyword::Outer),
+                TokenValue::Const(Keyword::Join),
+            ],
+        ),
+        [Token {
+            value: TokenValue::Const(Keyword::Left),
+            ..
+        }, ..] => (
+            JoinKind::Left,
+            &[
+                TokenValue::Const(Keyword::Left),
+                TokenValue::Const(Keyword::Join),
+            ],
+        ),
+        _ => {
+            return Ok(ExpectOk {
+                rest: tokens,
+                tokens_consumed_count: 0,
+                outcome: None,
+            })
+        }
+    };
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_keywords,
+        ..
+    } = expect_token_values_sequence(tokens, keyword_sequence)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table,
+        outcome: table,
+    } = expect_table_reference(rest)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::On))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_condition,
+        outcome: condition,
+    } = expect_expression(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_keywords
+            + tokens_consumed_count_table
+            + 1 // +1 to account for ON
+            + tokens_consumed_count_condition,
+        outcome: Some(Join {
+            kind,
+            table,
+            condition,
         }),
-        wrong_token => Err(SyntaxError(format!(
-            "Expected a SELECT column, instead found {}.",
-            wrong_token
-        ))),
-    }
+    })
 }
 
 /// Conjure an SelectStatement from tokens following SELECT.
@@ -40,10 +245,22 @@ pub fn expect_select<'t>(tokens: &'t [Token]) -> ExpectResult<'t, SelectStatemen
     } = expect_comma_separated(tokens, expect_select_column)?;
     let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::From))?;
     let ExpectOk {
-        rest,
-        tokens_consumed_count: tokens_consumed_count_table_name,
-        outcome: table_name,
-    } = expect_identifier(rest)?;
+        mut rest,
+        tokens_consumed_count: tokens_consumed_count_source,
+        outcome: source,
+    } = expect_table_reference(rest)?;
+    let mut tokens_consumed_count_joins = 0;
+    let mut joins = Vec::new();
+    while let ExpectOk {
+        rest: rest_after_join,
+        tokens_consumed_count,
+        outcome: Some(join),
+    } = detect_join(rest)?
+    {
+        rest = rest_after_join;
+        tokens_consumed_count_joins += tokens_consumed_count;
+        joins.push(join);
+    }
     let ExpectOk {
         rest,
         tokens_consumed_count: tokens_consumed_count_where_clause,
@@ -54,15 +271,177 @@ pub fn expect_select<'t>(tokens: &'t [Token]) -> ExpectResult<'t, SelectStatemen
         expect_expression,
         &"WHERE conditions",
     )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_group_by,
+        outcome: maybe_group_by,
+    } = detect(
+        rest,
+        |tokens| {
+            expect_token_values_sequence(
+                tokens,
+                &[
+                    TokenValue::Const(Keyword::Group),
+                    TokenValue::Const(Keyword::By),
+                ],
+            )
+        },
+        |tokens| expect_comma_separated(tokens, expect_expression),
+        &"GROUP BY expressions",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_having,
+        outcome: maybe_having,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Having)),
+        expect_expression,
+        &"HAVING conditions",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_order_by,
+        outcome: maybe_order_by,
+    } = detect(
+        rest,
+        |tokens| {
+            expect_token_values_sequence(
+                tokens,
+                &[
+                    TokenValue::Const(Keyword::Order),
+                    TokenValue::Const(Keyword::By),
+                ],
+            )
+        },
+        |tokens| expect_comma_separated(tokens, expect_order_by),
+        &"ORDER BY items",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_limit,
+        outcome: maybe_limit,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Limit)),
+        expect_count,
+        &"LIMIT count",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_offset,
+        outcome: maybe_offset,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Offset)),
+        expect_count,
+        &"OFFSET count",
+    )?;
     Ok(ExpectOk {
         rest,
         tokens_consumed_count: 1 // +1 to account for FROM
             + tokens_consumed_columns
-            + tokens_consumed_count_table_name + tokens_consumed_count_where_clause,
+            + tokens_consumed_count_source
+            + tokens_consumed_count_joins
+            + tokens_consumed_count_where_clause
+            + tokens_consumed_count_group_by
+            + tokens_consumed_count_having
+            + tokens_consumed_count_order_by
+            + tokens_consumed_count_limit
+            + tokens_consumed_count_offset,
         outcome: SelectStatement {
+            with: Vec::new(),
             columns,
-            source: table_name,
-            where_clause: maybe_where_clause.and_then(|(_, where_clause)| Some(where_clause)),
+            source,
+            joins,
+            where_clause: maybe_where_clause.map(|(_, where_clause)| where_clause),
+            group_by: maybe_group_by.map_or_else(Vec::new, |(_, group_by)| group_by),
+            having: maybe_having.map(|(_, having)| having),
+            order_by: maybe_order_by.map_or_else(Vec::new, |(_, order_by)| order_by),
+            limit: maybe_limit.map(|(_, limit)| limit),
+            offset: maybe_offset.map(|(_, offset)| offset),
         },
     })
 }
+
+/// Expect a common table expression: `name AS (<query>)`.
+fn expect_common_table_expression<'t>(
+    tokens: &'t [Token],
+) -> ExpectResult<'t, CommonTableExpression> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_name,
+        outcome: name,
+    } = expect_identifier(tokens)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::As))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_query,
+        outcome: query,
+    } = expect_enclosed(
+        rest,
+        expect_query,
+        Delimiter::ParenthesisOpening,
+        Delimiter::ParenthesisClosing,
+    )?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_name
+            + 1 // +1 to account for AS
+            + tokens_consumed_count_query,
+        outcome: CommonTableExpression { name, query },
+    })
+}
+
+/// Conjure a SelectStatement from tokens following WITH: `name AS (<query>) [, ...] SELECT ...`.
+pub fn expect_with<'t>(tokens: &'t [Token]) -> ExpectResult<'t, SelectStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_with,
+        outcome: with,
+    } = expect_comma_separated(tokens, expect_common_table_expression)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::Select))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_select,
+        outcome: select,
+    } = expect_select(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_with
+            + 1 // +1 to account for SELECT
+            + tokens_consumed_count_select,
+        outcome: SelectStatement { with, ..select },
+    })
+}
+
+/// Expect a query, i.e. a SELECT statement, optionally preceded by WITH.
+pub fn expect_query<'t>(tokens: &'t [Token]) -> ExpectResult<'t, SelectStatement> {
+    let ExpectOk {
+        rest,
+        outcome: found_token,
+        ..
+    } = expect_next_token(tokens, &"keyword `SELECT` or `WITH`")?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count,
+        outcome: select,
+    } = match found_token.value {
+        TokenValue::Const(Keyword::Select) => expect_select(rest)?,
+        TokenValue::Const(Keyword::With) => expect_with(rest)?,
+        _ => {
+            return Err(SyntaxError(
+                format!(
+                    "Expected keyword `SELECT` or `WITH`, instead found {}.",
+                    found_token
+                ),
+                Some(found_token.span),
+            ))
+        }
+    };
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 + tokens_consumed_count, // +1 to account for SELECT or WITH
+        outcome: select,
+    })
+}
@@ -0,0 +1,30 @@
+use crate::constructs::statements::TruncateStatement;
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+/// Conjure a TruncateStatement from tokens following TRUNCATE.
+pub fn expect_truncate<'t>(tokens: &'t [Token]) -> ExpectResult<'t, TruncateStatement> {
+    // Keyword TABLE is optional here, as there's nothing else to truncate
+    let tokens_consumed_count_table_keyword =
+        match expect_token_value(tokens, &TokenValue::Const(Keyword::Table)) {
+            Ok(ExpectOk {
+                tokens_consumed_count,
+                ..
+            }) => tokens_consumed_count,
+            Err(_) => 0,
+        };
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(&tokens[tokens_consumed_count_table_keyword..])?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_keyword
+            + tokens_consumed_count_table_name,
+        outcome: TruncateStatement {
+            schema_name,
+            table_name,
+        },
+    })
+}
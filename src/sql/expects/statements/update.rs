@@ -0,0 +1,72 @@
+use crate::constructs::statements::{Assignment, UpdateStatement};
+use crate::sql::expects::{generic::*, semantic::*, ExpectOk, ExpectResult};
+use crate::sql::tokenizer::*;
+
+use super::detect_returning;
+
+pub fn expect_assignment<'t>(tokens: &'t [Token]) -> ExpectResult<'t, Assignment> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_column_name,
+        outcome: column_name,
+    } = expect_identifier(tokens)?;
+    let ExpectOk { rest, .. } =
+        expect_token_value(rest, &TokenValue::Delimiting(Delimiter::Equal))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_value,
+        outcome: value,
+    } = expect_expression(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 // +1 to account for the equality sign
+            + tokens_consumed_count_column_name
+            + tokens_consumed_count_value,
+        outcome: Assignment { column_name, value },
+    })
+}
+
+/// Conjure an UpdateStatement from tokens following UPDATE.
+pub fn expect_update<'t>(tokens: &'t [Token]) -> ExpectResult<'t, UpdateStatement> {
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_table_name,
+        outcome: (schema_name, table_name),
+    } = expect_table_name(tokens)?;
+    let ExpectOk { rest, .. } = expect_token_value(rest, &TokenValue::Const(Keyword::Set))?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_assignments,
+        outcome: assignments,
+    } = expect_comma_separated(rest, expect_assignment)?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_where_clause,
+        outcome: maybe_where_clause,
+    } = detect(
+        rest,
+        |tokens| expect_token_value(tokens, &TokenValue::Const(Keyword::Where)),
+        expect_expression,
+        &"WHERE conditions",
+    )?;
+    let ExpectOk {
+        rest,
+        tokens_consumed_count: tokens_consumed_count_returning,
+        outcome: returning,
+    } = detect_returning(rest)?;
+    Ok(ExpectOk {
+        rest,
+        tokens_consumed_count: 1 // +1 to account for SET
+            + tokens_consumed_count_table_name
+            + tokens_consumed_count_assignments
+            + tokens_consumed_count_where_clause
+            + tokens_consumed_count_returning,
+        outcome: UpdateStatement {
+            schema_name,
+            table_name,
+            assignments,
+            where_clause: maybe_where_clause.map(|(_, where_clause)| where_clause),
+            returning,
+        },
+    })
+}
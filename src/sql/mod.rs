@@ -4,4 +4,8 @@ mod parser;
 mod tokenizer;
 
 pub use errors::*;
-pub use parser::{parse_statement, Statement};
+pub use parser::{
+    parse_constraint_kind, parse_data_definition, parse_expressions, parse_identifiers,
+    parse_statements, Statement,
+};
+pub use tokenizer::{quote_identifier, quote_identifiers};
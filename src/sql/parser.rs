@@ -1,20 +1,86 @@
 use super::expects::*;
 use super::tokenizer::*;
-use crate::constructs::components::Validatable;
-use crate::constructs::statements::SelectStatement;
-use crate::constructs::statements::{CreateTableStatement, InsertStatement};
+use crate::constructs::components::{ConstraintKind, DataDefinition, Expression, Validatable};
+use crate::constructs::statements::{
+    AlterTableStatement, AnalyzeStatement, CreateDatabaseStatement, CreateIndexStatement,
+    CreateTableStatement, DeleteStatement, DropDatabaseStatement, DropIndexStatement,
+    DropTableStatement, ExplainStatement, InsertStatement, SelectStatement, TruncateStatement,
+    UpdateStatement,
+};
 use crate::sql::errors::*;
 
-pub fn parse_statement(input: &str) -> Result<Statement, SyntaxError> {
-    let tokens = tokenize_statement(input);
+fn is_statement_separator(token: &Token) -> bool {
+    token.value == TokenValue::Delimiting(Delimiter::StatementSeparator)
+}
+
+/// Parse input of statements separated by semicolons, in order. Empty statements, such as the one after
+/// the final semicolon, are skipped - but there has to be at least one non-empty statement.
+pub fn parse_statements(input: &str) -> Result<Vec<Statement>, SyntaxError> {
+    let tokens = tokenize(input)?;
+    let end_of_input = Span::get_end_of(input);
+    let statements = tokens
+        .split_inclusive(is_statement_separator)
+        .map(|tokens| split_statement_end(tokens, end_of_input))
+        .filter(|(tokens, _)| !tokens.is_empty())
+        .map(|(tokens, end)| parse_tokens(tokens).map_err(|error| error.or_at(end)))
+        .collect::<Result<Vec<Statement>, SyntaxError>>()?;
+    if statements.is_empty() {
+        // This fails, as there's no statement start
+        parse_tokens(&[]).map_err(|error| error.or_at(end_of_input))?;
+    }
+    Ok(statements)
+}
+
+/// Split off the separator ending the statement, if there's one. Returned alongside the statement's tokens
+/// is where the statement ends: at the separator, or at the end of input otherwise.
+fn split_statement_end(tokens: &[Token], end_of_input: Span) -> (&[Token], Span) {
+    match tokens.split_last() {
+        Some((last_token, tokens)) if is_statement_separator(last_token) => {
+            (tokens, last_token.span)
+        }
+        _ => (tokens, end_of_input),
+    }
+}
+
+/// Parse input consisting of a single construct other than a statement, e.g. a definition kept in the catalog.
+fn parse_construct<O>(
+    input: &str,
+    expect_construct: for<'t> fn(&'t [Token]) -> ExpectResult<'t, O>,
+) -> Result<O, SyntaxError> {
+    let tokens = tokenize(input)?;
+    consume_all(&tokens, expect_construct).map_err(|error| error.or_at(Span::get_end_of(input)))
+}
+
+/// Parse a DEFAULT definition, e.g. `'unknown'` or `NOW()`.
+pub fn parse_data_definition(input: &str) -> Result<DataDefinition, SyntaxError> {
+    parse_construct(input, expect_data_definition)
+}
+
+/// Parse comma-separated expressions, e.g. `1, 'two', '2077-01-01T00:00:00Z'::TIMESTAMP`.
+pub fn parse_expressions(input: &str) -> Result<Vec<Expression>, SyntaxError> {
+    parse_construct(input, |tokens| {
+        expect_comma_separated(tokens, expect_expression)
+    })
+}
+
+/// Parse comma-separated identifiers, e.g. `url, "Key"`.
+pub fn parse_identifiers(input: &str) -> Result<Vec<String>, SyntaxError> {
+    parse_construct(input, |tokens| {
+        expect_comma_separated(tokens, expect_identifier)
+    })
+}
+
+/// Parse the definition of a table constraint without its name, e.g. `PRIMARY KEY (url)` or `CHECK (width > 0)`.
+pub fn parse_constraint_kind(input: &str) -> Result<ConstraintKind, SyntaxError> {
+    parse_construct(input, expect_constraint_kind)
+}
+
+fn parse_tokens(tokens: &[Token]) -> Result<Statement, SyntaxError> {
     let ExpectOk {
         rest,
         outcome: found_token_first,
         ..
-    } = expect_next_token(
-        &tokens,
-        &format!("{} or {}", Keyword::Create, Keyword::Insert),
-    )?;
+    } = expect_next_token(tokens, &EXPECTED_STATEMENT_START)?;
     match found_token_first {
         // CREATE
         Token {
@@ -25,7 +91,7 @@ pub fn parse_statement(input: &str) -> Result<Statement, SyntaxError> {
                 rest,
                 outcome: found_token_second,
                 ..
-            } = expect_next_token(rest, &Keyword::Table.to_string())?;
+            } = expect_next_token(rest, &EXPECTED_OBJECT_KIND)?;
             match found_token_second {
                 // CREATE TABLE
                 Token {
@@ -35,14 +101,91 @@ pub fn parse_statement(input: &str) -> Result<Statement, SyntaxError> {
                     rest,
                     expect_create_table,
                 )?)),
+                // CREATE DATABASE
+                Token {
+                    value: TokenValue::Const(Keyword::Database),
+                    ..
+                } => Ok(Statement::CreateDatabase(consume_all(
+                    rest,
+                    expect_create_database,
+                )?)),
+                // CREATE INDEX
+                Token {
+                    value: TokenValue::Const(Keyword::Index),
+                    ..
+                } => Ok(Statement::CreateIndex(consume_all(
+                    rest,
+                    expect_create_index,
+                )?)),
+                // CREATE UNIQUE INDEX
+                Token {
+                    value: TokenValue::Const(Keyword::Unique),
+                    ..
+                } => Ok(Statement::CreateIndex(consume_all(
+                    rest,
+                    expect_create_unique_index,
+                )?)),
                 // CREATE ???
-                wrong_token => Err(SyntaxError(format!(
-                    "Expected {}, instead found {}.",
-                    Keyword::Table,
-                    wrong_token
-                ))),
+                wrong_token => Err(SyntaxError(
+                    format!(
+                        "Expected {}, instead found {}.",
+                        EXPECTED_OBJECT_KIND, wrong_token
+                    ),
+                    Some(wrong_token.span),
+                )),
             }
         }
+        // DROP
+        Token {
+            value: TokenValue::Const(Keyword::Drop),
+            ..
+        } => {
+            let ExpectOk {
+                rest,
+                outcome: found_token_second,
+                ..
+            } = expect_next_token(rest, &EXPECTED_OBJECT_KIND)?;
+            match found_token_second {
+                // DROP TABLE
+                Token {
+                    value: TokenValue::Const(Keyword::Table),
+                    ..
+                } => Ok(Statement::DropTable(consume_all(rest, expect_drop_table)?)),
+                // DROP DATABASE
+                Token {
+                    value: TokenValue::Const(Keyword::Database),
+                    ..
+                } => Ok(Statement::DropDatabase(consume_all(
+                    rest,
+                    expect_drop_database,
+                )?)),
+                // DROP INDEX
+                Token {
+                    value: TokenValue::Const(Keyword::Index),
+                    ..
+                } => Ok(Statement::DropIndex(consume_all(rest, expect_drop_index)?)),
+                // DROP ???
+                wrong_token => Err(SyntaxError(
+                    format!(
+                        "Expected {}, instead found {}.",
+                        EXPECTED_OBJECT_KIND, wrong_token
+                    ),
+                    Some(wrong_token.span),
+                )),
+            }
+        }
+        // ALTER TABLE
+        Token {
+            value: TokenValue::Const(Keyword::Alter),
+            ..
+        } => {
+            let ExpectOk { rest, .. } =
+                expect_token_value(rest, &TokenValue::Const(Keyword::Table))?;
+            Ok(Statement::AlterTable(consume_all(
+                rest,
+                expect_alter_table,
+            )?))
+        }
         // INSERT
         Token {
             value: TokenValue::Const(Keyword::Insert),
@@ -53,21 +196,67 @@ pub fn parse_statement(input: &str) -> Result<Statement, SyntaxError> {
             value: TokenValue::Const(Keyword::Select),
             ..
         } => Ok(Statement::Select(consume_all(rest, expect_select)?)),
+        // WITH
+        Token {
+            value: TokenValue::Const(Keyword::With),
+            ..
+        } => Ok(Statement::Select(consume_all(rest, expect_with)?)),
+        // UPDATE
+        Token {
+            value: TokenValue::Const(Keyword::Update),
+            ..
+        } => Ok(Statement::Update(consume_all(rest, expect_update)?)),
+        // DELETE
+        Token {
+            value: TokenValue::Const(Keyword::Delete),
+            ..
+        } => Ok(Statement::Delete(consume_all(rest, expect_delete)?)),
+        // TRUNCATE
+        Token {
+            value: TokenValue::Const(Keyword::Truncate),
+            ..
+        } => Ok(Statement::Truncate(consume_all(rest, expect_truncate)?)),
+        // EXPLAIN
+        Token {
+            value: TokenValue::Const(Keyword::Explain),
+            ..
+        } => Ok(Statement::Explain(consume_all(rest, expect_explain)?)),
+        // ANALYZE
+        Token {
+            value: TokenValue::Const(Keyword::Analyze),
+            ..
+        } => Ok(Statement::Analyze(consume_all(rest, expect_analyze)?)),
         // Something else
-        wrong_token => Err(SyntaxError(format!(
-            "Expected {} or {}, instead found {}.",
-            Keyword::Create,
-            Keyword::Insert,
-            wrong_token
-        ))),
+        wrong_token => Err(SyntaxError(
+            format!(
+                "Expected {}, instead found {}.",
+                EXPECTED_STATEMENT_START, wrong_token
+            ),
+            Some(wrong_token.span),
+        )),
     }
 }
 
+const EXPECTED_STATEMENT_START: &str =
+    "keyword `CREATE`, `DROP`, `ALTER`, `INSERT`, `SELECT`, `WITH`, `UPDATE`, `DELETE`, `TRUNCATE`, `EXPLAIN` or `ANALYZE`";
+const EXPECTED_OBJECT_KIND: &str = "keyword `TABLE`, `DATABASE` or `INDEX`";
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Statement {
     CreateTable(CreateTableStatement),
     Insert(InsertStatement),
     Select(SelectStatement),
+    Update(UpdateStatement),
+    Delete(DeleteStatement),
+    Truncate(TruncateStatement),
+    DropTable(DropTableStatement),
+    AlterTable(AlterTableStatement),
+    CreateDatabase(CreateDatabaseStatement),
+    DropDatabase(DropDatabaseStatement),
+    CreateIndex(CreateIndexStatement),
+    DropIndex(DropIndexStatement),
+    Explain(ExplainStatement),
+    Analyze(AnalyzeStatement),
 }
 
 impl Validatable for Statement {
@@ -76,6 +265,17 @@ impl Validatable for Statement {
             Statement::CreateTable(create_table) => create_table.validate(),
             Statement::Insert(insert) => insert.validate(),
             Statement::Select(select) => select.validate(),
+            Statement::Update(update) => update.validate(),
+            Statement::Delete(delete) => delete.validate(),
+            Statement::Truncate(truncate) => truncate.validate(),
+            Statement::DropTable(drop_table) => drop_table.validate(),
+            Statement::AlterTable(alter_table) => alter_table.validate(),
+            Statement::CreateDatabase(create_database) => create_database.validate(),
+            Statement::DropDatabase(drop_database) => drop_database.validate(),
+            Statement::CreateIndex(create_index) => create_index.validate(),
+            Statement::DropIndex(drop_index) => drop_index.validate(),
+            Statement::Explain(explain) => explain.validate(),
+            Statement::Analyze(analyze) => analyze.validate(),
         }
     }
 }
@@ -84,16 +284,26 @@ impl Validatable for Statement {
 mod tests {
     use crate::constructs::{
         components::{
-            ColumnDefinition, DataDefinition, DataInstance, DataInstanceRaw, DataType, DataTypeRaw,
-            Expression, TableDefinition,
+            ColumnDefinition, ConstraintDefinition, ConstraintKind, DataDefinition, DataInstance,
+            DataInstanceRaw, DataType, DataTypeRaw, Expression, IndexDefinition, TableDefinition,
         },
         functions::Function,
-        statements::SelectColumn,
+        statements::{
+            AlterTableAction, Assignment, CommonTableExpression, ConflictAction, InsertSource,
+            InsertValue, Join, JoinKind, OnConflict, OrderBy, SelectColumn, TableReference,
+        },
     };
 
     use super::*;
     use pretty_assertions::assert_eq;
 
+    /// Parse input of a single statement, which may be ended by a statement separator.
+    fn parse_statement(input: &str) -> Result<Statement, SyntaxError> {
+        let tokens = tokenize(input)?;
+        let (tokens, end) = split_statement_end(&tokens, Span::get_end_of(input));
+        parse_tokens(tokens).map_err(|error| error.or_at(end))
+    }
+
     #[test]
     fn parsing_works_with_create_table() {
         const STATEMENT: &str = "CREATE TABLE IF NOT EXISTS test (
@@ -108,6 +318,7 @@ mod tests {
         assert_eq!(
             detected_statement,
             Statement::CreateTable(CreateTableStatement {
+                schema_name: None,
                 table: TableDefinition::new(
                     "test".to_string(),
                     vec![
@@ -118,6 +329,7 @@ mod tests {
                                 is_nullable: false
                             },
                             primary_key: true,
+                            metric_key: false,
                             default: None,
                         },
                         ColumnDefinition {
@@ -127,6 +339,7 @@ mod tests {
                                 is_nullable: true
                             },
                             primary_key: false,
+                            metric_key: false,
                             default: None,
                         },
                         ColumnDefinition {
@@ -136,6 +349,7 @@ mod tests {
                                 is_nullable: false
                             },
                             primary_key: false,
+                            metric_key: false,
                             default: Some(DataDefinition::Const(DataInstance::Direct(
                                 // TODO: Infer number size from context
                                 DataInstanceRaw::UInt32(666)
@@ -148,7 +362,8 @@ mod tests {
                                 is_nullable: false
                             },
                             primary_key: false,
-                            default: Some(DataDefinition::FunctionCall(Function::Now)),
+                            metric_key: false,
+                            default: Some(DataDefinition::FunctionCall(Function::Now, vec![])),
                         },
                     ]
                 ),
@@ -157,6 +372,154 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parsing_works_with_create_table_constraints() {
+        const STATEMENT: &str = "CREATE TABLE photos_seen (
+            id UUID PRIMARY KEY,
+            url STRING NOT NULL UNIQUE,
+            width UINT32 CHECK (width > 0) CONSTRAINT photos_width_limit CHECK (width <= 8192),
+            height UINT32,
+            CONSTRAINT photos_size_key UNIQUE (width, height),
+            CHECK (height IN (480, 720, 1080))
+        );";
+
+        let table = match parse_statement(STATEMENT).unwrap() {
+            Statement::CreateTable(create_table) => create_table.table,
+            other => panic!("Expected CREATE TABLE, found {:?}", other),
+        };
+
+        assert_eq!(
+            table.get_column_names(),
+            vec!["id", "url", "width", "height"]
+        );
+        assert!(!table.columns[1].data_type.is_nullable);
+        let identifier =
+            |name: &str| Box::new(Expression::Atom(DataDefinition::Identifier(name.into())));
+        let number = |value: u32| {
+            Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                DataInstanceRaw::UInt32(value),
+            )))
+        };
+        assert_eq!(
+            table.constraints,
+            vec![
+                ConstraintDefinition {
+                    name: "photos_seen_url_key".into(),
+                    kind: ConstraintKind::Unique(vec!["url".into()]),
+                },
+                ConstraintDefinition {
+                    name: "photos_seen_width_check".into(),
+                    kind: ConstraintKind::Check(Expression::GreaterThan(
+                        identifier("width"),
+                        Box::new(number(0))
+                    )),
+                },
+                ConstraintDefinition {
+                    name: "photos_width_limit".into(),
+                    kind: ConstraintKind::Check(Expression::LessThanOrEqual(
+                        identifier("width"),
+                        Box::new(number(8192))
+                    )),
+                },
+                ConstraintDefinition {
+                    name: "photos_size_key".into(),
+                    kind: ConstraintKind::Unique(vec!["width".into(), "height".into()]),
+                },
+                ConstraintDefinition {
+                    name: "photos_seen_height_check".into(),
+                    kind: ConstraintKind::Check(Expression::In(
+                        identifier("height"),
+                        vec![number(480), number(720), number(1080)]
+                    )),
+                },
+            ]
+        );
+        assert_eq!(table.validate(), Ok(()));
+        assert_eq!(
+            table.constraints[4].to_string(),
+            "CHECK (height IN (480, 720, 1080))"
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_composite_primary_key() {
+        let table = match parse_statement(
+            "CREATE TABLE photos_seen (url STRING, domain STRING, width UINT32, PRIMARY KEY (domain, url))",
+        )
+        .unwrap()
+        {
+            Statement::CreateTable(create_table) => create_table.table,
+            other => panic!("Expected CREATE TABLE, found {:?}", other),
+        };
+
+        assert_eq!(
+            table.constraints,
+            vec![ConstraintDefinition {
+                name: "photos_seen_pkey".into(),
+                kind: ConstraintKind::PrimaryKey(vec!["domain".into(), "url".into()]),
+            }]
+        );
+        assert_eq!(table.validate(), Ok(()));
+        assert_eq!(table.get_key_indexes(), vec![1, 0]);
+        assert_eq!(table.get_key_column_names(), vec!["domain", "url"]);
+    }
+
+    #[test]
+    fn parsing_and_validation_fail_with_invalid_constraints() {
+        assert_eq!(
+            parse_statement("CREATE TABLE t (id UUID PRIMARY KEY, x NULLABLE(STRING) NOT NULL)"),
+            Err(SyntaxError(
+                "Column `x` cannot be both NULLABLE and NOT NULL.".to_string(),
+                Some(Span {
+                    offset: 56,
+                    length: 3,
+                    line: 1,
+                    column: 57
+                })
+            ))
+        );
+        let validation_error = |input: &str| match parse_statement(input).unwrap() {
+            Statement::CreateTable(create_table) => create_table.validate().unwrap_err(),
+            other => panic!("Expected CREATE TABLE, found {:?}", other),
+        };
+        assert_eq!(
+            validation_error("CREATE TABLE t (id UUID PRIMARY KEY, x UINT8, CHECK (y > 1))"),
+            ValidationError(
+                "Problem at constraint `t_y_check`: Column `y` does not exist in table definition"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validation_error("CREATE TABLE t (id UUID PRIMARY KEY, x STRING CHECK (x))"),
+            ValidationError(
+                "Problem at constraint `t_x_check`: A condition must be of type BOOL, instead found type STRING"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validation_error("CREATE TABLE t (a UUID PRIMARY KEY, b UINT8, PRIMARY KEY (a, b))"),
+            ValidationError(
+                "A table cannot have both a PRIMARY KEY column and a PRIMARY KEY constraint"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validation_error("CREATE TABLE t (a UUID, b NULLABLE(UINT8), PRIMARY KEY (a, b))"),
+            ValidationError(
+                "Problem at constraint `t_pkey`: Column `b` is nullable, so it cannot be in the PRIMARY KEY"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validation_error(
+                "CREATE TABLE t (id UUID PRIMARY KEY, x UINT8 CONSTRAINT c UNIQUE CONSTRAINT c CHECK (x > 1))"
+            ),
+            ValidationError(
+                "There is more than one constraint with name `c` in table definition".to_string()
+            )
+        );
+    }
+
     #[test]
     fn parsing_works_with_insert() {
         const STATEMENT: &str = "INSERT INTO xyz (foo, bar)
@@ -167,12 +530,121 @@ mod tests {
         assert_eq!(
             detected_statement,
             Statement::Insert(InsertStatement {
+                schema_name: None,
                 table_name: "xyz".to_string(),
                 column_names: vec!["foo".to_string(), "bar".to_string(),],
-                values: vec![
-                    DataInstance::Direct(DataInstanceRaw::UInt32(1815)),
-                    DataInstance::Direct(DataInstanceRaw::String("Waterloo".into())),
-                ]
+                source: InsertSource::Values(vec![vec![
+                    InsertValue::Value(DataInstance::Direct(DataInstanceRaw::UInt32(1815))),
+                    InsertValue::Value(DataInstance::Direct(DataInstanceRaw::String(
+                        "Waterloo".into()
+                    ))),
+                ]]),
+                on_conflict: None,
+                returning: vec![],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_multi_row_insert() {
+        const STATEMENT: &str = "INSERT INTO xyz (foo) VALUES (1815), (DEFAULT);";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Insert(InsertStatement {
+                schema_name: None,
+                table_name: "xyz".to_string(),
+                column_names: vec!["foo".to_string()],
+                source: InsertSource::Values(vec![
+                    vec![InsertValue::Value(DataInstance::Direct(
+                        DataInstanceRaw::UInt32(1815)
+                    ))],
+                    vec![InsertValue::Default],
+                ]),
+                on_conflict: None,
+                returning: vec![],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_insert_on_conflict() {
+        const STATEMENT: &str = "INSERT INTO xyz (foo, bar) VALUES (1815, 'Waterloo')
+        ON CONFLICT (foo) DO UPDATE SET bar = excluded.bar;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Insert(InsertStatement {
+                schema_name: None,
+                table_name: "xyz".to_string(),
+                column_names: vec!["foo".to_string(), "bar".to_string()],
+                source: InsertSource::Values(vec![vec![
+                    InsertValue::Value(DataInstance::Direct(DataInstanceRaw::UInt32(1815))),
+                    InsertValue::Value(DataInstance::Direct(DataInstanceRaw::String(
+                        "Waterloo".into()
+                    ))),
+                ]]),
+                on_conflict: Some(OnConflict {
+                    column_names: vec!["foo".to_string()],
+                    action: ConflictAction::DoUpdate {
+                        assignments: vec![Assignment {
+                            column_name: "bar".to_string(),
+                            value: Expression::Atom(DataDefinition::Identifier(
+                                "excluded.bar".to_string()
+                            )),
+                        }],
+                        where_clause: None,
+                    },
+                }),
+                returning: vec![],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_insert_select() {
+        const STATEMENT: &str = "INSERT INTO xyz (foo) SELECT bar FROM abc WHERE bar > 1;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Insert(InsertStatement {
+                schema_name: None,
+                table_name: "xyz".to_string(),
+                column_names: vec!["foo".to_string()],
+                source: InsertSource::Select(Box::new(SelectStatement {
+                    with: Vec::new(),
+                    columns: vec![SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier("bar".to_string())),
+                        alias: None,
+                    }],
+                    source: TableReference {
+                        schema_name: None,
+                        table_name: "abc".to_string(),
+                        alias: None,
+                    },
+                    joins: Vec::new(),
+                    where_clause: Some(Expression::GreaterThan(
+                        Box::new(Expression::Atom(DataDefinition::Identifier(
+                            "bar".to_string()
+                        ))),
+                        Box::new(Expression::Atom(DataDefinition::Const(
+                            DataInstance::Direct(DataInstanceRaw::UInt32(1))
+                        )))
+                    )),
+                    group_by: vec![],
+                    having: None,
+                    order_by: vec![],
+                    limit: None,
+                    offset: None,
+                })),
+                on_conflict: None,
+                returning: vec![],
             })
         )
     }
@@ -186,11 +658,20 @@ mod tests {
         assert_eq!(
             detected_statement,
             Statement::Select(SelectStatement {
+                with: Vec::new(),
                 columns: vec![
                     SelectColumn::All,
-                    SelectColumn::Identifier("foo".to_string()),
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier("foo".to_string())),
+                        alias: None,
+                    },
                 ],
-                source: "xyz".to_string(),
+                source: TableReference {
+                    schema_name: None,
+                    table_name: "xyz".to_string(),
+                    alias: None,
+                },
+                joins: Vec::new(),
                 where_clause: Some(Expression::Equal(
                     Box::new(Expression::Atom(DataDefinition::Identifier(
                         "foo".to_string()
@@ -198,8 +679,865 @@ mod tests {
                     Box::new(Expression::Atom(DataDefinition::Const(
                         DataInstance::Direct(DataInstanceRaw::String("bar".into()))
                     )))
-                ))
+                )),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_select_order_by_limit_offset() {
+        const STATEMENT: &str =
+            "SELECT url FROM photos_seen ORDER BY width DESC, url ASC NULLS FIRST, seen_at LIMIT 10 OFFSET 20;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Select(SelectStatement {
+                with: Vec::new(),
+                columns: vec![SelectColumn::Expression {
+                    expression: Expression::Atom(DataDefinition::Identifier("url".to_string())),
+                    alias: None,
+                }],
+                source: TableReference {
+                    schema_name: None,
+                    table_name: "photos_seen".to_string(),
+                    alias: None,
+                },
+                joins: Vec::new(),
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![
+                    OrderBy {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "width".to_string()
+                        )),
+                        descending: true,
+                        nulls_first: true,
+                    },
+                    OrderBy {
+                        expression: Expression::Atom(DataDefinition::Identifier("url".to_string())),
+                        descending: false,
+                        nulls_first: true,
+                    },
+                    OrderBy {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "seen_at".to_string()
+                        )),
+                        descending: false,
+                        nulls_first: false,
+                    },
+                ],
+                limit: Some(10),
+                offset: Some(20),
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_select_group_by_having() {
+        const STATEMENT: &str =
+            "SELECT domain, COUNT(*) AS seen_count FROM photos_seen GROUP BY domain HAVING MAX(width) > 1000;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Select(SelectStatement {
+                with: Vec::new(),
+                columns: vec![
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "domain".to_string()
+                        )),
+                        alias: None,
+                    },
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::FunctionCall(
+                            Function::Count,
+                            vec![]
+                        )),
+                        alias: Some("seen_count".to_string()),
+                    },
+                ],
+                source: TableReference {
+                    schema_name: None,
+                    table_name: "photos_seen".to_string(),
+                    alias: None,
+                },
+                joins: Vec::new(),
+                where_clause: None,
+                group_by: vec![Expression::Atom(DataDefinition::Identifier(
+                    "domain".to_string()
+                ))],
+                having: Some(Expression::GreaterThan(
+                    Box::new(Expression::Atom(DataDefinition::FunctionCall(
+                        Function::Max,
+                        vec![Expression::Atom(DataDefinition::Identifier(
+                            "width".to_string()
+                        ))]
+                    ))),
+                    Box::new(Expression::Atom(DataDefinition::Const(
+                        DataInstance::Direct(DataInstanceRaw::UInt32(1000))
+                    )))
+                )),
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_select_distance_alias() {
+        const STATEMENT: &str =
+            "SELECT url, hash @ 0b00001011 AS distance FROM photos_seen WHERE distance < 4;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Select(SelectStatement {
+                with: Vec::new(),
+                columns: vec![
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier("url".to_string())),
+                        alias: None,
+                    },
+                    SelectColumn::Expression {
+                        expression: Expression::Distance(
+                            Box::new(Expression::Atom(DataDefinition::Identifier(
+                                "hash".to_string()
+                            ))),
+                            Box::new(Expression::Atom(DataDefinition::Const(
+                                DataInstance::Direct(DataInstanceRaw::UInt32(11))
+                            )))
+                        ),
+                        alias: Some("distance".to_string()),
+                    },
+                ],
+                source: TableReference {
+                    schema_name: None,
+                    table_name: "photos_seen".to_string(),
+                    alias: None,
+                },
+                joins: Vec::new(),
+                where_clause: Some(Expression::LessThan(
+                    Box::new(Expression::Atom(DataDefinition::Identifier(
+                        "distance".to_string()
+                    ))),
+                    Box::new(Expression::Atom(DataDefinition::Const(
+                        DataInstance::Direct(DataInstanceRaw::UInt32(4))
+                    )))
+                )),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_rejects_negative_limit() {
+        assert_eq!(
+            parse_statement("SELECT * FROM photos_seen LIMIT -1;"),
+            Err(SyntaxError(
+                "Expected a non-negative integer, instead found arbitrary `-1` at line 1."
+                    .to_string(),
+                Some(Span {
+                    offset: 32,
+                    length: 2,
+                    line: 1,
+                    column: 33
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_update() {
+        const STATEMENT: &str = "UPDATE photos_seen SET seen_at = NOW(), width = 1280
+        WHERE url = 'https://twixes.com/a.png';";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Update(UpdateStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                assignments: vec![
+                    Assignment {
+                        column_name: "seen_at".to_string(),
+                        value: Expression::Atom(DataDefinition::FunctionCall(
+                            Function::Now,
+                            vec![]
+                        )),
+                    },
+                    Assignment {
+                        column_name: "width".to_string(),
+                        value: Expression::Atom(DataDefinition::Const(DataInstance::Direct(
+                            DataInstanceRaw::UInt32(1280)
+                        ))),
+                    },
+                ],
+                where_clause: Some(Expression::Equal(
+                    Box::new(Expression::Atom(DataDefinition::Identifier(
+                        "url".to_string()
+                    ))),
+                    Box::new(Expression::Atom(DataDefinition::Const(
+                        DataInstance::Direct(DataInstanceRaw::String(
+                            "https://twixes.com/a.png".into()
+                        ))
+                    )))
+                )),
+                returning: vec![],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_delete() {
+        const STATEMENT: &str =
+            "DELETE FROM gaggle.photos_seen WHERE url = 'https://twixes.com/a.png';";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Delete(DeleteStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: "photos_seen".to_string(),
+                where_clause: Some(Expression::Equal(
+                    Box::new(Expression::Atom(DataDefinition::Identifier(
+                        "url".to_string()
+                    ))),
+                    Box::new(Expression::Atom(DataDefinition::Const(
+                        DataInstance::Direct(DataInstanceRaw::String(
+                            "https://twixes.com/a.png".into()
+                        ))
+                    )))
+                )),
+                returning: vec![],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_delete_returning() {
+        const STATEMENT: &str = "DELETE FROM photos_seen RETURNING *, width AS w;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Delete(DeleteStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                where_clause: None,
+                returning: vec![
+                    SelectColumn::All,
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "width".to_string()
+                        )),
+                        alias: Some("w".to_string()),
+                    },
+                ],
+            })
+        )
+    }
+
+    #[test]
+    fn parsing_fails_with_unknown_statement() {
+        assert_eq!(
+            parse_statement("DESTROY TABLE xyz;"),
+            Err(SyntaxError(
+                "Expected keyword `CREATE`, `DROP`, `ALTER`, `INSERT`, `SELECT`, `WITH`, `UPDATE`, `DELETE`, `TRUNCATE`, `EXPLAIN` or `ANALYZE`, instead found arbitrary `DESTROY` at line 1.".to_string(),
+                Some(Span { offset: 0, length: 7, line: 1, column: 1 })
+            ))
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_create_index() {
+        assert_eq!(
+            parse_statement(
+                "CREATE UNIQUE INDEX IF NOT EXISTS photos_by_url ON gaggle.photos_seen (url);"
+            ),
+            Ok(Statement::CreateIndex(CreateIndexStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: "photos_seen".to_string(),
+                index: IndexDefinition {
+                    name: "photos_by_url".to_string(),
+                    column_names: vec!["url".to_string()],
+                    unique: true,
+                },
+                if_not_exists: true,
+            }))
+        );
+        assert_eq!(
+            parse_statement("CREATE INDEX photos_by_size ON photos_seen (width, height)"),
+            Ok(Statement::CreateIndex(CreateIndexStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                index: IndexDefinition {
+                    name: "photos_by_size".to_string(),
+                    column_names: vec!["width".to_string(), "height".to_string()],
+                    unique: false,
+                },
+                if_not_exists: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_drop_index() {
+        assert_eq!(
+            parse_statement("DROP INDEX IF EXISTS gaggle.photos_by_url;"),
+            Ok(Statement::DropIndex(DropIndexStatement {
+                schema_name: Some("gaggle".to_string()),
+                index_name: "photos_by_url".to_string(),
+                if_exists: true,
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_explain() {
+        let select = || SelectStatement {
+            with: Vec::new(),
+            columns: vec![SelectColumn::All],
+            source: TableReference {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                alias: None,
+            },
+            joins: Vec::new(),
+            where_clause: None,
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: Some(10),
+            offset: None,
+        };
+        assert_eq!(
+            parse_statement("EXPLAIN SELECT * FROM photos_seen LIMIT 10"),
+            Ok(Statement::Explain(ExplainStatement {
+                analyze: false,
+                select: select(),
+            }))
+        );
+        assert_eq!(
+            parse_statement("EXPLAIN ANALYZE SELECT * FROM photos_seen LIMIT 10;"),
+            Ok(Statement::Explain(ExplainStatement {
+                analyze: true,
+                select: select(),
+            }))
+        );
+        assert_eq!(
+            parse_statement("EXPLAIN DELETE FROM photos_seen"),
+            Err(SyntaxError(
+                "Expected keyword `SELECT` or `WITH`, instead found keyword `DELETE` at line 1."
+                    .to_string(),
+                Some(Span {
+                    offset: 8,
+                    length: 6,
+                    line: 1,
+                    column: 9
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_select_joins() {
+        const STATEMENT: &str = "SELECT p.url, d.owner FROM gaggle.photos_seen AS p
+            LEFT OUTER JOIN domains d ON p.domain = d.domain
+            JOIN owners ON owners.name = d.owner AND owners.active
+            WHERE p.width > 100;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement,
+            Statement::Select(SelectStatement {
+                with: Vec::new(),
+                columns: vec![
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "p.url".to_string()
+                        )),
+                        alias: None,
+                    },
+                    SelectColumn::Expression {
+                        expression: Expression::Atom(DataDefinition::Identifier(
+                            "d.owner".to_string()
+                        )),
+                        alias: None,
+                    },
+                ],
+                source: TableReference {
+                    schema_name: Some("gaggle".to_string()),
+                    table_name: "photos_seen".to_string(),
+                    alias: Some("p".to_string()),
+                },
+                joins: vec![
+                    Join {
+                        kind: JoinKind::Left,
+                        table: TableReference {
+                            schema_name: None,
+                            table_name: "domains".to_string(),
+                            alias: Some("d".to_string()),
+                        },
+                        condition: Expression::Equal(
+                            Box::new(Expression::Atom(DataDefinition::Identifier(
+                                "p.domain".to_string()
+                            ))),
+                            Box::new(Expression::Atom(DataDefinition::Identifier(
+                                "d.domain".to_string()
+                            ))),
+                        ),
+                    },
+                    Join {
+                        kind: JoinKind::Inner,
+                        table: TableReference {
+                            schema_name: None,
+                            table_name: "owners".to_string(),
+                            alias: None,
+                        },
+                        condition: Expression::And(
+                            Box::new(Expression::Equal(
+                                Box::new(Expression::Atom(DataDefinition::Identifier(
+                                    "owners.name".to_string()
+                                ))),
+                                Box::new(Expression::Atom(DataDefinition::Identifier(
+                                    "d.owner".to_string()
+                                ))),
+                            )),
+                            Box::new(Expression::Atom(DataDefinition::Identifier(
+                                "owners.active".to_string()
+                            ))),
+                        ),
+                    },
+                ],
+                where_clause: Some(Expression::GreaterThan(
+                    Box::new(Expression::Atom(DataDefinition::Identifier(
+                        "p.width".to_string()
+                    ))),
+                    Box::new(Expression::Atom(DataDefinition::Const(
+                        DataInstance::Direct(DataInstanceRaw::UInt32(100))
+                    ))),
+                )),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             })
         )
     }
+
+    #[test]
+    fn validation_fails_with_select_join_of_same_name_twice() {
+        const STATEMENT: &str =
+            "SELECT * FROM photos_seen JOIN photos_seen ON photos_seen.url = photos_seen.url;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement.validate(),
+            Err(ValidationError(
+                "Table name `photos_seen` is specified more than once. Use an alias to tell the tables apart."
+                    .to_string()
+            ))
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_subqueries_and_with() {
+        const STATEMENT: &str =
+            "WITH latest AS (SELECT hash FROM photos_seen WHERE domain = 'x' LIMIT 1)
+            SELECT url FROM photos_seen
+            WHERE domain IN (SELECT domain FROM domains) AND NOT EXISTS (SELECT * FROM hidden)
+            ORDER BY hash @ (SELECT hash FROM latest) LIMIT 5;";
+        let identifier = |name: &str| {
+            Box::new(Expression::Atom(DataDefinition::Identifier(
+                name.to_string(),
+            )))
+        };
+        let select_from = |table_name: &str, columns: Vec<SelectColumn>| SelectStatement {
+            with: Vec::new(),
+            columns,
+            source: TableReference {
+                schema_name: None,
+                table_name: table_name.to_string(),
+                alias: None,
+            },
+            joins: Vec::new(),
+            where_clause: None,
+            group_by: Vec::new(),
+            having: None,
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        };
+        let column = |name: &str| SelectColumn::Expression {
+            expression: *identifier(name),
+            alias: None,
+        };
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        let expected_statement = Statement::Select(SelectStatement {
+            with: vec![CommonTableExpression {
+                name: "latest".to_string(),
+                query: SelectStatement {
+                    where_clause: Some(Expression::Equal(
+                        identifier("domain"),
+                        Box::new(Expression::Atom(DataDefinition::Const(
+                            DataInstance::Direct(DataInstanceRaw::String("x".to_string())),
+                        ))),
+                    )),
+                    limit: Some(1),
+                    ..select_from("photos_seen", vec![column("hash")])
+                },
+            }],
+            where_clause: Some(Expression::And(
+                Box::new(Expression::InSubquery(
+                    identifier("domain"),
+                    Box::new(select_from("domains", vec![column("domain")])),
+                )),
+                Box::new(Expression::Not(Box::new(Expression::Exists(Box::new(
+                    select_from("hidden", vec![SelectColumn::All]),
+                ))))),
+            )),
+            order_by: vec![OrderBy {
+                expression: Expression::Distance(
+                    identifier("hash"),
+                    Box::new(Expression::Subquery(Box::new(select_from(
+                        "latest",
+                        vec![column("hash")],
+                    )))),
+                ),
+                descending: false,
+                nulls_first: false,
+            }],
+            limit: Some(5),
+            ..select_from("photos_seen", vec![column("url")])
+        });
+        assert_eq!(detected_statement, expected_statement);
+        // SQL text of the statement parses back into the same statement
+        let Statement::Select(select) = &expected_statement else {
+            unreachable!()
+        };
+        assert_eq!(
+            parse_statement(&format!("{};", select)),
+            Ok(expected_statement)
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_comments_and_quoted_identifiers() {
+        const STATEMENT: &str = r#"-- Reserved words and mixed case need quotes
+            SELECT "key" AS "Key Name", /* inline */ "p"."default"
+            FROM "Settings" AS p WHERE "key" <> 'it''s';"#;
+        let identifier = |name: &str| {
+            Box::new(Expression::Atom(DataDefinition::Identifier(
+                name.to_string(),
+            )))
+        };
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        let expected_statement = Statement::Select(SelectStatement {
+            with: Vec::new(),
+            columns: vec![
+                SelectColumn::Expression {
+                    expression: *identifier("key"),
+                    alias: Some("Key Name".to_string()),
+                },
+                SelectColumn::Expression {
+                    expression: *identifier("p.default"),
+                    alias: None,
+                },
+            ],
+            source: TableReference {
+                schema_name: None,
+                table_name: "Settings".to_string(),
+                alias: Some("p".to_string()),
+            },
+            joins: Vec::new(),
+            where_clause: Some(Expression::NotEqual(
+                identifier("key"),
+                Box::new(Expression::Atom(DataDefinition::Const(
+                    DataInstance::Direct(DataInstanceRaw::String("it's".to_string())),
+                ))),
+            )),
+            group_by: Vec::new(),
+            having: None,
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        });
+        assert_eq!(detected_statement, expected_statement);
+        // Identifiers are quoted in SQL text of the statement wherever needed
+        let Statement::Select(select) = &expected_statement else {
+            unreachable!()
+        };
+        assert_eq!(
+            parse_statement(&format!("{};", select)),
+            Ok(expected_statement)
+        );
+    }
+
+    #[test]
+    fn validation_fails_with_with_query_of_same_name_twice() {
+        const STATEMENT: &str =
+            "WITH a AS (SELECT * FROM photos_seen), a AS (SELECT * FROM domains) SELECT * FROM a;";
+
+        let detected_statement = parse_statement(STATEMENT).unwrap();
+
+        assert_eq!(
+            detected_statement.validate(),
+            Err(ValidationError(
+                "WITH query name `a` is specified more than once".to_string()
+            ))
+        )
+    }
+
+    #[test]
+    fn parsing_works_with_analyze() {
+        assert_eq!(
+            parse_statement("ANALYZE;"),
+            Ok(Statement::Analyze(AnalyzeStatement {
+                schema_name: None,
+                table_name: None,
+            }))
+        );
+        assert_eq!(
+            parse_statement("ANALYZE gaggle.photos_seen"),
+            Ok(Statement::Analyze(AnalyzeStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: Some("photos_seen".to_string()),
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_drop_table() {
+        assert_eq!(
+            parse_statement("DROP TABLE IF EXISTS gaggle.photos_seen;"),
+            Ok(Statement::DropTable(DropTableStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: "photos_seen".to_string(),
+                if_exists: true,
+            }))
+        );
+        assert_eq!(
+            parse_statement("DROP TABLE photos_seen"),
+            Ok(Statement::DropTable(DropTableStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                if_exists: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_truncate() {
+        assert_eq!(
+            parse_statement("TRUNCATE TABLE photos_seen;"),
+            Ok(Statement::Truncate(TruncateStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+            }))
+        );
+        assert_eq!(
+            parse_statement("TRUNCATE gaggle.photos_seen"),
+            Ok(Statement::Truncate(TruncateStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: "photos_seen".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_create_and_drop_database() {
+        assert_eq!(
+            parse_statement("CREATE DATABASE IF NOT EXISTS gaggle;"),
+            Ok(Statement::CreateDatabase(CreateDatabaseStatement {
+                database_name: "gaggle".to_string(),
+                if_not_exists: true,
+            }))
+        );
+        assert_eq!(
+            parse_statement("DROP DATABASE gaggle;"),
+            Ok(Statement::DropDatabase(DropDatabaseStatement {
+                database_name: "gaggle".to_string(),
+                if_exists: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_alter_table() {
+        assert_eq!(
+            parse_statement(
+                "ALTER TABLE photos_seen ADD COLUMN height NULLABLE(UINT32) DEFAULT 0;"
+            ),
+            Ok(Statement::AlterTable(AlterTableStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                action: AlterTableAction::AddColumn(ColumnDefinition {
+                    name: "height".to_string(),
+                    data_type: DataType {
+                        raw_type: DataTypeRaw::UInt32,
+                        is_nullable: true
+                    },
+                    primary_key: false,
+                    metric_key: false,
+                    default: Some(DataDefinition::Const(DataInstance::Direct(
+                        DataInstanceRaw::UInt32(0)
+                    ))),
+                }),
+            }))
+        );
+        assert_eq!(
+            parse_statement("ALTER TABLE gaggle.photos_seen DROP height"),
+            Ok(Statement::AlterTable(AlterTableStatement {
+                schema_name: Some("gaggle".to_string()),
+                table_name: "photos_seen".to_string(),
+                action: AlterTableAction::DropColumn("height".to_string()),
+            }))
+        );
+        assert_eq!(
+            parse_statement("ALTER TABLE photos_seen RENAME COLUMN height TO h"),
+            Ok(Statement::AlterTable(AlterTableStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                action: AlterTableAction::RenameColumn {
+                    column_name: "height".to_string(),
+                    new_column_name: "h".to_string(),
+                },
+            }))
+        );
+        assert_eq!(
+            parse_statement("ALTER TABLE photos_seen RENAME TO photos"),
+            Ok(Statement::AlterTable(AlterTableStatement {
+                schema_name: None,
+                table_name: "photos_seen".to_string(),
+                action: AlterTableAction::RenameTo("photos".to_string()),
+            }))
+        );
+        assert_eq!(
+            parse_statement("ALTER TABLE photos_seen TRUNCATE"),
+            Err(SyntaxError(
+                "Expected keyword `ADD`, `DROP` or `RENAME`, instead found keyword `TRUNCATE` at line 1.".to_string(),
+                Some(Span { offset: 24, length: 8, line: 1, column: 25 })
+            ))
+        );
+    }
+
+    #[test]
+    fn parsing_works_with_multiple_statements() {
+        const STATEMENTS: &str = "CREATE DATABASE IF NOT EXISTS gaggle; ANALYZE;
+            TRUNCATE gaggle.photos_seen;;
+            DELETE FROM gaggle.photos_seen WHERE url = 'a;b';";
+
+        assert_eq!(
+            parse_statements(STATEMENTS),
+            Ok(vec![
+                Statement::CreateDatabase(CreateDatabaseStatement {
+                    database_name: "gaggle".to_string(),
+                    if_not_exists: true,
+                }),
+                Statement::Analyze(AnalyzeStatement {
+                    schema_name: None,
+                    table_name: None,
+                }),
+                Statement::Truncate(TruncateStatement {
+                    schema_name: Some("gaggle".to_string()),
+                    table_name: "photos_seen".to_string(),
+                }),
+                Statement::Delete(DeleteStatement {
+                    schema_name: Some("gaggle".to_string()),
+                    table_name: "photos_seen".to_string(),
+                    where_clause: Some(Expression::Equal(
+                        Box::new(Expression::Atom(DataDefinition::Identifier(
+                            "url".to_string()
+                        ))),
+                        Box::new(Expression::Atom(DataDefinition::Const(
+                            DataInstance::Direct(DataInstanceRaw::String("a;b".to_string()))
+                        ))),
+                    )),
+                    returning: Vec::new(),
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn parsing_fails_with_invalid_or_missing_statements() {
+        assert_eq!(
+            parse_statements("ANALYZE; ANALYZE TABLE;"),
+            Err(SyntaxError(
+                "Expected an identifier, instead found keyword `TABLE` at line 1.".to_string(),
+                Some(Span {
+                    offset: 17,
+                    length: 5,
+                    line: 1,
+                    column: 18
+                })
+            ))
+        );
+        assert_eq!(
+            parse_statements(" ; "),
+            Err(SyntaxError(
+                format!(
+                    "Expected {}, instead found end of statement.",
+                    EXPECTED_STATEMENT_START
+                ),
+                Some(Span {
+                    offset: 3,
+                    length: 0,
+                    line: 1,
+                    column: 4
+                })
+            ))
+        );
+        // A single statement can't be followed by another one
+        assert_eq!(
+            parse_statement("TRUNCATE a; TRUNCATE b;"),
+            Err(SyntaxError(
+                "Expected end of statement, instead found statement separator `;` at line 1."
+                    .to_string(),
+                Some(Span {
+                    offset: 10,
+                    length: 1,
+                    line: 1,
+                    column: 11
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn parsing_fails_with_unknown_object_kind() {
+        assert_eq!(
+            parse_statement("DROP VIEW xyz;"),
+            Err(SyntaxError(
+                "Expected keyword `TABLE`, `DATABASE` or `INDEX`, instead found arbitrary `VIEW` at line 1.".to_string(),
+                Some(Span { offset: 5, length: 4, line: 1, column: 6 })
+            ))
+        )
+    }
 }
@@ -1,5 +1,7 @@
-use std::str::FromStr;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
 
+use super::errors::SyntaxError;
 use crate::constructs::{components::DataTypeRaw, functions::Function};
 use std::fmt::{self, Debug};
 
@@ -9,13 +11,23 @@ pub enum Delimiter {
     ParenthesisOpening,
     ParenthesisClosing,
     Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Distance,
+    TypeCast,
+    StatementSeparator,
 }
 
 impl Delimiter {
     /// Delimiting characters that affect statement meaning. Each one is a Delimiter variant.
-    const MEANINGFUL_CHARS: &'static [char] = &[',', '(', ')', '='];
-    const STATEMENT_SEPARATOR: char = ';';
+    const MEANINGFUL_CHARS: &'static [char] = &[',', '(', ')', '@', ';'];
+    /// Characters that operators are made of. An operator can span multiple such characters, e.g. `<=`.
+    const OPERATOR_CHARS: &'static [char] = &['=', '<', '>', '!', ':'];
     const STRING_MARKER: char = '\'';
+    const IDENTIFIER_MARKER: char = '"';
     const ESCAPE_CHARACTER: char = '\\';
 }
 
@@ -29,6 +41,14 @@ impl fmt::Display for Delimiter {
                 Self::ParenthesisOpening => "opening parenthesis `(`",
                 Self::ParenthesisClosing => "closing parenthesis `)`",
                 Self::Equal => "equality sign `=`",
+                Self::NotEqual => "inequality sign `!=`",
+                Self::LessThan => "less-than sign `<`",
+                Self::LessThanOrEqual => "less-than-or-equal sign `<=`",
+                Self::GreaterThan => "greater-than sign `>`",
+                Self::GreaterThanOrEqual => "greater-than-or-equal sign `>=`",
+                Self::Distance => "distance operator `@`",
+                Self::TypeCast => "type cast operator `::`",
+                Self::StatementSeparator => "statement separator `;`",
             }
         )
     }
@@ -43,6 +63,14 @@ impl FromStr for Delimiter {
             "(" => Ok(Self::ParenthesisOpening),
             ")" => Ok(Self::ParenthesisClosing),
             "=" => Ok(Self::Equal),
+            "!=" | "<>" => Ok(Self::NotEqual),
+            "<" => Ok(Self::LessThan),
+            "<=" => Ok(Self::LessThanOrEqual),
+            ">" => Ok(Self::GreaterThan),
+            ">=" => Ok(Self::GreaterThanOrEqual),
+            "@" => Ok(Self::Distance),
+            "::" => Ok(Self::TypeCast),
+            ";" => Ok(Self::StatementSeparator),
             _ => Err(format!(
                 "`{}` does not refer to a meaningful delimiter",
                 candidate
@@ -61,14 +89,60 @@ pub enum Keyword {
     Insert,
     Into,
     Values,
+    Update,
+    Set,
+    Delete,
+    Drop,
+    Truncate,
+    Database,
+    Alter,
+    Add,
+    Column,
+    Rename,
+    To,
     Table,
     If,
     Not,
+    And,
+    Or,
+    Is,
+    In,
+    Between,
+    Like,
+    Escape,
+    Order,
+    By,
+    Asc,
+    Desc,
+    Nulls,
+    First,
+    Last,
+    Limit,
+    Offset,
+    Group,
+    Having,
+    Cast,
+    Returning,
+    On,
+    Conflict,
+    Do,
+    Nothing,
     Exists,
     Nullable,
     Primary,
     Metric,
     Key,
+    Index,
+    Unique,
+    Constraint,
+    Check,
+    Explain,
+    Analyze,
+    Join,
+    Inner,
+    Left,
+    Outer,
+    With,
     Null,
     Default,
     Asterisk,
@@ -88,14 +162,60 @@ impl fmt::Display for Keyword {
                 Keyword::Insert => "INSERT",
                 Keyword::Into => "INTO",
                 Keyword::Values => "VALUES",
+                Keyword::Update => "UPDATE",
+                Keyword::Set => "SET",
+                Keyword::Delete => "DELETE",
+                Keyword::Drop => "DROP",
+                Keyword::Truncate => "TRUNCATE",
+                Keyword::Database => "DATABASE",
+                Keyword::Alter => "ALTER",
+                Keyword::Add => "ADD",
+                Keyword::Column => "COLUMN",
+                Keyword::Rename => "RENAME",
+                Keyword::To => "TO",
                 Keyword::Table => "TABLE",
                 Keyword::If => "IF",
                 Keyword::Not => "NOT",
+                Keyword::And => "AND",
+                Keyword::Or => "OR",
+                Keyword::Is => "IS",
+                Keyword::In => "IN",
+                Keyword::Between => "BETWEEN",
+                Keyword::Like => "LIKE",
+                Keyword::Escape => "ESCAPE",
+                Keyword::Order => "ORDER",
+                Keyword::By => "BY",
+                Keyword::Asc => "ASC",
+                Keyword::Desc => "DESC",
+                Keyword::Nulls => "NULLS",
+                Keyword::First => "FIRST",
+                Keyword::Last => "LAST",
+                Keyword::Limit => "LIMIT",
+                Keyword::Offset => "OFFSET",
+                Keyword::Group => "GROUP",
+                Keyword::Having => "HAVING",
+                Keyword::Cast => "CAST",
+                Keyword::Returning => "RETURNING",
+                Keyword::On => "ON",
+                Keyword::Conflict => "CONFLICT",
+                Keyword::Do => "DO",
+                Keyword::Nothing => "NOTHING",
                 Keyword::Exists => "EXISTS",
                 Keyword::Nullable => "NULLABLE",
                 Keyword::Primary => "PRIMARY",
                 Keyword::Metric => "METRIC",
                 Keyword::Key => "KEY",
+                Keyword::Index => "INDEX",
+                Keyword::Unique => "UNIQUE",
+                Keyword::Constraint => "CONSTRAINT",
+                Keyword::Check => "CHECK",
+                Keyword::Explain => "EXPLAIN",
+                Keyword::Analyze => "ANALYZE",
+                Keyword::Join => "JOIN",
+                Keyword::Inner => "INNER",
+                Keyword::Left => "LEFT",
+                Keyword::Outer => "OUTER",
+                Keyword::With => "WITH",
                 Keyword::Null => "NULL",
                 Keyword::Default => "DEFAULT",
                 Keyword::Asterisk => "*",
@@ -117,14 +237,60 @@ impl FromStr for Keyword {
             "insert" => Ok(Self::Insert),
             "into" => Ok(Self::Into),
             "values" => Ok(Self::Values),
+            "update" => Ok(Self::Update),
+            "set" => Ok(Self::Set),
+            "delete" => Ok(Self::Delete),
+            "drop" => Ok(Self::Drop),
+            "truncate" => Ok(Self::Truncate),
+            "database" => Ok(Self::Database),
+            "alter" => Ok(Self::Alter),
+            "add" => Ok(Self::Add),
+            "column" => Ok(Self::Column),
+            "rename" => Ok(Self::Rename),
+            "to" => Ok(Self::To),
             "table" => Ok(Self::Table),
             "if" => Ok(Self::If),
             "not" => Ok(Self::Not),
+            "and" => Ok(Self::And),
+            "or" => Ok(Self::Or),
+            "is" => Ok(Self::Is),
+            "in" => Ok(Self::In),
+            "between" => Ok(Self::Between),
+            "like" => Ok(Self::Like),
+            "escape" => Ok(Self::Escape),
+            "order" => Ok(Self::Order),
+            "by" => Ok(Self::By),
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            "nulls" => Ok(Self::Nulls),
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "limit" => Ok(Self::Limit),
+            "offset" => Ok(Self::Offset),
+            "group" => Ok(Self::Group),
+            "having" => Ok(Self::Having),
+            "cast" => Ok(Self::Cast),
+            "returning" => Ok(Self::Returning),
+            "on" => Ok(Self::On),
+            "conflict" => Ok(Self::Conflict),
+            "do" => Ok(Self::Do),
+            "nothing" => Ok(Self::Nothing),
             "exists" => Ok(Self::Exists),
             "nullable" => Ok(Self::Nullable),
             "primary" => Ok(Self::Primary),
             "metric" => Ok(Self::Metric),
             "key" => Ok(Self::Key),
+            "index" => Ok(Self::Index),
+            "unique" => Ok(Self::Unique),
+            "constraint" => Ok(Self::Constraint),
+            "check" => Ok(Self::Check),
+            "explain" => Ok(Self::Explain),
+            "analyze" => Ok(Self::Analyze),
+            "join" => Ok(Self::Join),
+            "inner" => Ok(Self::Inner),
+            "left" => Ok(Self::Left),
+            "outer" => Ok(Self::Outer),
+            "with" => Ok(Self::With),
             "null" => Ok(Self::Null),
             "default" => Ok(Self::Default),
             "*" => Ok(Self::Asterisk),
@@ -148,7 +314,7 @@ impl fmt::Display for TokenValue {
         match self {
             Self::Delimiting(value) => fmt::Display::fmt(&value, f),
             Self::Const(value) => fmt::Display::fmt(&value, f),
-            Self::Type(value) => value.fmt(f),
+            Self::Type(value) => Debug::fmt(value, f),
             Self::Function(value) => fmt::Display::fmt(&value, f),
             Self::String(value) => write!(f, "string `\"{}\"`", value),
             Self::Arbitrary(value) => write!(f, "arbitrary `{}`", value),
@@ -182,86 +348,319 @@ impl FromStr for TokenValue {
     }
 }
 
+/// Position of a piece of input: byte offset and length, plus 1-based line and column of the start,
+/// with the column counted in characters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub offset: usize,
+    pub length: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Empty span right after the last character of input.
+    pub fn get_end_of(input: &str) -> Self {
+        let last_line = input.rsplit('\n').next().unwrap_or_default();
+        Span {
+            offset: input.len(),
+            length: 0,
+            line: input.matches('\n').count() + 1,
+            column: last_line.chars().count() + 1,
+        }
+    }
+
+    /// Extend the span up to the end of another one, which must not start earlier.
+    fn extend_to(&mut self, other: &Span) {
+        self.length = other.offset + other.length - self.offset;
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Token {
     pub value: TokenValue,
-    pub line_number: usize,
+    pub span: Span,
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} at line {}", self.value, self.line_number)
+        write!(f, "{} at line {}", self.value, self.span.line)
+    }
+}
+
+/// Identifier as it has to be written in SQL: in double quotes if it'd be taken for something else otherwise,
+/// e.g. a keyword. Each part of a qualified identifier, as in `p.url`, is considered separately.
+pub fn quote_identifier(identifier: &str) -> String {
+    identifier
+        .split('.')
+        .map(|part| {
+            let is_plain = part
+                .chars()
+                .next()
+                .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+                && part
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_')
+                && TokenValue::from_str(part) == Ok(TokenValue::Arbitrary(part.to_string()));
+            match is_plain {
+                true => part.to_string(),
+                false => format!(
+                    "{}{}{}",
+                    Delimiter::IDENTIFIER_MARKER,
+                    part.replace(Delimiter::IDENTIFIER_MARKER, "\"\""),
+                    Delimiter::IDENTIFIER_MARKER
+                ),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+/// Comma-separated identifiers, each as it has to be written in SQL.
+pub fn quote_identifiers(identifiers: &[String]) -> String {
+    identifiers
+        .iter()
+        .map(|identifier| quote_identifier(identifier))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Text that a token is made from.
+#[derive(Default)]
+struct TokenCandidate {
+    text: String,
+    /// Whether the text is a quoted identifier, which is never taken for a keyword or anything else.
+    is_quoted: bool,
+    /// Span of input covered so far, including quotes and escape characters.
+    span: Option<Span>,
+}
+
+impl TokenCandidate {
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && !self.is_quoted
+    }
+
+    /// Extend the candidate to cover another character of input.
+    fn include(&mut self, character_span: Span) {
+        match &mut self.span {
+            Some(span) => span.extend_to(&character_span),
+            None => self.span = Some(character_span),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token {
+            value: match self.is_quoted {
+                true => TokenValue::Arbitrary(self.text),
+                false => TokenValue::from_str(&self.text).unwrap(),
+            },
+            span: self.span.unwrap_or_default(),
+        }
+    }
+}
+
+/// Move the current candidate to tokens, unless it's empty.
+fn end_candidate(tokens: &mut Vec<Token>, current_candidate: &mut TokenCandidate) {
+    if !current_candidate.is_empty() {
+        tokens.push(std::mem::take(current_candidate).into_token());
+    }
+}
+
+/// Characters of input, each along with its span.
+struct Characters<'i> {
+    char_indices: Peekable<CharIndices<'i>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'i> Characters<'i> {
+    fn new(input: &'i str) -> Self {
+        Characters {
+            char_indices: input.char_indices().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Consume the next character only if it's the expected one.
+    fn next_if_eq(&mut self, expected: char) -> Option<Span> {
+        match self.char_indices.peek() {
+            Some((_, character)) if *character == expected => self.next().map(|(_, span)| span),
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for Characters<'_> {
+    type Item = (char, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (offset, character) = self.char_indices.next()?;
+        let span = Span {
+            offset,
+            length: character.len_utf8(),
+            line: self.line,
+            column: self.column,
+        };
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some((character, span))
     }
 }
 
-pub fn tokenize_statement(input: &str) -> Vec<Token> {
+/// Break input up into tokens. Fails only if a string, a quoted identifier or a block comment is never closed.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, SyntaxError> {
     let mut tokens = Vec::<Token>::new();
-    for (line_index, line) in input.lines().enumerate() {
-        let mut token_candidates = Vec::<String>::new();
-        let mut current_candidate: String = "".to_string();
-        let mut is_current_character_escaped = false;
-        let mut is_current_character_inside_string = false;
-        for character in line.chars() {
-            // Act upon tokenization-level semantics, but only if the current character is not escaped with a backslash
-            if !is_current_character_escaped {
-                // Detect if the next character is escaped
-                if character == Delimiter::ESCAPE_CHARACTER {
-                    is_current_character_escaped = true;
-                    continue;
-                }
-                // Detect if this character starts/ends a string
-                if character == Delimiter::STRING_MARKER {
-                    current_candidate.push(character);
-                    if is_current_character_inside_string {
-                        token_candidates.push(current_candidate.clone());
-                        current_candidate.clear();
-                        is_current_character_inside_string = false;
-                    } else {
-                        is_current_character_inside_string = true;
+    let mut current_candidate = TokenCandidate::default();
+    let mut is_current_character_escaped = false;
+    let mut is_current_character_inside_string = false;
+    let mut is_current_character_inside_quotes = false;
+    let mut is_current_character_inside_line_comment = false;
+    let mut is_current_character_inside_block_comment = false;
+    // Where the current string, quoted identifier or block comment starts, for errors if it's never closed
+    let mut enclosure_opening_span = Span::default();
+    let mut characters = Characters::new(input);
+    while let Some((character, span)) = characters.next() {
+        if is_current_character_inside_line_comment {
+            if character == '\n' {
+                is_current_character_inside_line_comment = false;
+            }
+            continue;
+        }
+        if is_current_character_inside_block_comment {
+            if character == '*' && characters.next_if_eq('/').is_some() {
+                is_current_character_inside_block_comment = false;
+            }
+            continue;
+        }
+        // Quoted identifiers are taken literally, except for `""`, which stands for a double quote, and `.`,
+        // which is reserved for separating the parts of a qualified identifier
+        if is_current_character_inside_quotes {
+            current_candidate.include(span);
+            if character == '.' {
+                return Err(SyntaxError(
+                    "Quoted identifiers must not contain `.`.".to_string(),
+                    Some(span),
+                ));
+            }
+            if character == Delimiter::IDENTIFIER_MARKER {
+                match characters.next_if_eq(Delimiter::IDENTIFIER_MARKER) {
+                    Some(next_span) => {
+                        current_candidate.include(next_span);
+                        current_candidate.text.push(character);
                     }
-                    continue;
+                    None => is_current_character_inside_quotes = false,
                 }
-                if !is_current_character_inside_string {
-                    // End tokenization when a statement separator (semicolon) is encountered
-                    if character == Delimiter::STATEMENT_SEPARATOR {
-                        break;
-                    }
-                    // Recognize delimiters earlier, as they don't have to be separated by whitespace from other tokens
-                    if Delimiter::MEANINGFUL_CHARS.contains(&character) {
-                        if !current_candidate.is_empty() {
-                            token_candidates.push(current_candidate.clone());
-                            current_candidate.clear();
-                        }
-                        token_candidates.push(character.to_string());
+            } else {
+                current_candidate.text.push(character);
+            }
+            continue;
+        }
+        // Act upon tokenization-level semantics, but only if the current character is not escaped with a backslash
+        if !is_current_character_escaped {
+            // Operators don't have to be separated by whitespace from other tokens, but they may span
+            // multiple characters, so an operator token only ends where a non-operator character appears
+            if !is_current_character_inside_string
+                && !current_candidate.is_empty()
+                && Delimiter::OPERATOR_CHARS.contains(&character)
+                    != current_candidate
+                        .text
+                        .starts_with(Delimiter::OPERATOR_CHARS)
+            {
+                end_candidate(&mut tokens, &mut current_candidate);
+            }
+            // Detect if the next character is escaped
+            if character == Delimiter::ESCAPE_CHARACTER {
+                current_candidate.include(span);
+                is_current_character_escaped = true;
+                continue;
+            }
+            // Detect if this character starts/ends a string, which may span multiple lines
+            if character == Delimiter::STRING_MARKER {
+                current_candidate.include(span);
+                current_candidate.text.push(character);
+                if is_current_character_inside_string {
+                    // Same as in standard SQL, `''` inside a string stands for a single quote
+                    if let Some(next_span) = characters.next_if_eq(Delimiter::STRING_MARKER) {
+                        current_candidate.include(next_span);
                         continue;
                     }
-                    // Break up non-delimiter tokens on whitespace
-                    if character.is_ascii_whitespace() {
-                        if !current_candidate.is_empty() {
-                            token_candidates.push(current_candidate.clone());
-                            current_candidate.clear();
-                        }
+                    end_candidate(&mut tokens, &mut current_candidate);
+                    is_current_character_inside_string = false;
+                } else {
+                    is_current_character_inside_string = true;
+                    enclosure_opening_span = span;
+                }
+                continue;
+            }
+            if !is_current_character_inside_string {
+                // Comments separate tokens just like whitespace
+                if character == '-' && characters.next_if_eq('-').is_some() {
+                    end_candidate(&mut tokens, &mut current_candidate);
+                    is_current_character_inside_line_comment = true;
+                    continue;
+                }
+                if character == '/' {
+                    if let Some(next_span) = characters.next_if_eq('*') {
+                        end_candidate(&mut tokens, &mut current_candidate);
+                        is_current_character_inside_block_comment = true;
+                        enclosure_opening_span = span;
+                        enclosure_opening_span.extend_to(&next_span);
                         continue;
                     }
                 }
-            } else {
-                // Reset escape status for the next character
-                is_current_character_escaped = false;
+                // Detect if this character starts a quoted identifier
+                if character == Delimiter::IDENTIFIER_MARKER {
+                    current_candidate.include(span);
+                    current_candidate.is_quoted = true;
+                    is_current_character_inside_quotes = true;
+                    enclosure_opening_span = span;
+                    continue;
+                }
+                // Recognize delimiters earlier, as they don't have to be separated by whitespace from other tokens
+                if Delimiter::MEANINGFUL_CHARS.contains(&character) {
+                    end_candidate(&mut tokens, &mut current_candidate);
+                    tokens.push(Token {
+                        value: TokenValue::from_str(&character.to_string()).unwrap(),
+                        span,
+                    });
+                    continue;
+                }
+                // Break up non-delimiter tokens on whitespace
+                if character.is_ascii_whitespace() {
+                    end_candidate(&mut tokens, &mut current_candidate);
+                    continue;
+                }
             }
-            // The default case for a character is just being appended to the working token candidate string
-            current_candidate.push(character);
-        }
-        // Add line remainded to token candidates
-        if !current_candidate.is_empty() {
-            token_candidates.push(current_candidate);
+        } else {
+            // Reset escape status for the next character
+            is_current_character_escaped = false;
         }
-        // Process token candidates found on this line
-        tokens.extend(token_candidates.iter().map(|candidate| Token {
-            value: TokenValue::from_str(candidate).unwrap(),
-            line_number: line_index + 1,
-        }))
+        // The default case for a character is just being appended to the working token candidate string
+        current_candidate.include(span);
+        current_candidate.text.push(character);
+    }
+    let unterminated_enclosure = if is_current_character_inside_block_comment {
+        Some("block comment")
+    } else if is_current_character_inside_string {
+        Some("string literal")
+    } else if is_current_character_inside_quotes {
+        Some("quoted identifier")
+    } else {
+        None
+    };
+    if let Some(unterminated_enclosure) = unterminated_enclosure {
+        return Err(SyntaxError(
+            format!("Unterminated {}.", unterminated_enclosure),
+            Some(enclosure_opening_span),
+        ));
     }
-    tokens
+    end_candidate(&mut tokens, &mut current_candidate);
+    Ok(tokens)
 }
 
 #[cfg(test)]
@@ -277,113 +676,43 @@ mod tests {
             sent_at TIMESTAMP DEFAULT NOW()
         );";
 
-        let detected_tokens = tokenize_statement(STATEMENT);
+        let detected_tokens: Vec<(TokenValue, usize)> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.value, token.span.line))
+            .collect();
 
         let expected_tokens = [
-            Token {
-                value: TokenValue::Const(Keyword::Create),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Table),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::If),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Not),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Exists),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Arbitrary("test".to_string()),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 1,
-            },
+            (TokenValue::Const(Keyword::Create), 1),
+            (TokenValue::Const(Keyword::Table), 1),
+            (TokenValue::Const(Keyword::If), 1),
+            (TokenValue::Const(Keyword::Not), 1),
+            (TokenValue::Const(Keyword::Exists), 1),
+            (TokenValue::Arbitrary("test".to_string()), 1),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 1),
             // New line
-            Token {
-                value: TokenValue::Arbitrary("server_id".to_string()),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Nullable),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Type(DataTypeRaw::UInt64),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 2,
-            },
+            (TokenValue::Arbitrary("server_id".to_string()), 2),
+            (TokenValue::Const(Keyword::Nullable), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 2),
+            (TokenValue::Type(DataTypeRaw::UInt64), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 2),
+            (TokenValue::Delimiting(Delimiter::Comma), 2),
             // New line
-            Token {
-                value: TokenValue::Arbitrary("hash".to_string()),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Type(DataTypeRaw::UInt128),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Metric),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Key),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 3,
-            },
+            (TokenValue::Arbitrary("hash".to_string()), 3),
+            (TokenValue::Type(DataTypeRaw::UInt128), 3),
+            (TokenValue::Const(Keyword::Metric), 3),
+            (TokenValue::Const(Keyword::Key), 3),
+            (TokenValue::Delimiting(Delimiter::Comma), 3),
             // New line
-            Token {
-                value: TokenValue::Arbitrary("sent_at".to_string()),
-                line_number: 4,
-            },
-            Token {
-                value: TokenValue::Type(DataTypeRaw::Timestamp),
-                line_number: 4,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Default),
-                line_number: 4,
-            },
-            Token {
-                value: TokenValue::Function(Function::Now),
-                line_number: 4,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 4,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 4,
-            },
+            (TokenValue::Arbitrary("sent_at".to_string()), 4),
+            (TokenValue::Type(DataTypeRaw::Timestamp), 4),
+            (TokenValue::Const(Keyword::Default), 4),
+            (TokenValue::Function(Function::Now), 4),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 4),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 4),
             // New line
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 5,
-            },
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 5),
+            (TokenValue::Delimiting(Delimiter::StatementSeparator), 5),
         ];
         assert_eq!(&detected_tokens, &expected_tokens)
     }
@@ -394,61 +723,26 @@ mod tests {
             serverId nullable(Uint64)
         )";
 
-        let detected_tokens = tokenize_statement(STATEMENT);
+        let detected_tokens: Vec<(TokenValue, usize)> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.value, token.span.line))
+            .collect();
 
         let expected_tokens = [
-            Token {
-                value: TokenValue::Const(Keyword::Create),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Table),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::If),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Not),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Exists),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Arbitrary("TEST".to_string()),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Arbitrary("serverId".to_string()),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Nullable),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Type(DataTypeRaw::UInt64),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 3,
-            },
+            (TokenValue::Const(Keyword::Create), 1),
+            (TokenValue::Const(Keyword::Table), 1),
+            (TokenValue::Const(Keyword::If), 1),
+            (TokenValue::Const(Keyword::Not), 1),
+            (TokenValue::Const(Keyword::Exists), 1),
+            (TokenValue::Arbitrary("TEST".to_string()), 1),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 1),
+            (TokenValue::Arbitrary("serverId".to_string()), 2),
+            (TokenValue::Const(Keyword::Nullable), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 2),
+            (TokenValue::Type(DataTypeRaw::UInt64), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 3),
         ];
         assert_eq!(&detected_tokens, &expected_tokens)
     }
@@ -459,82 +753,241 @@ mod tests {
             (foo, bar, baz)
             VALUES ('123', '   x ', 'The \\'Moon\\'')";
 
-        let detected_tokens = tokenize_statement(STATEMENT);
+        let detected_tokens: Vec<(TokenValue, usize)> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.value, token.span.line))
+            .collect();
 
         let expected_tokens = [
-            Token {
-                value: TokenValue::Const(Keyword::Insert),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Into),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Arbitrary("test".to_string()),
-                line_number: 1,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Arbitrary("foo".to_string()),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Arbitrary("bar".to_string()),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Arbitrary("baz".to_string()),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 2,
-            },
-            Token {
-                value: TokenValue::Const(Keyword::Values),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisOpening),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::String("123".to_string()),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::String("   x ".to_string()),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::Comma),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::String("The \'Moon\'".to_string()),
-                line_number: 3,
-            },
-            Token {
-                value: TokenValue::Delimiting(Delimiter::ParenthesisClosing),
-                line_number: 3,
-            },
+            (TokenValue::Const(Keyword::Insert), 1),
+            (TokenValue::Const(Keyword::Into), 1),
+            (TokenValue::Arbitrary("test".to_string()), 1),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 2),
+            (TokenValue::Arbitrary("foo".to_string()), 2),
+            (TokenValue::Delimiting(Delimiter::Comma), 2),
+            (TokenValue::Arbitrary("bar".to_string()), 2),
+            (TokenValue::Delimiting(Delimiter::Comma), 2),
+            (TokenValue::Arbitrary("baz".to_string()), 2),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 2),
+            (TokenValue::Const(Keyword::Values), 3),
+            (TokenValue::Delimiting(Delimiter::ParenthesisOpening), 3),
+            (TokenValue::String("123".to_string()), 3),
+            (TokenValue::Delimiting(Delimiter::Comma), 3),
+            (TokenValue::String("   x ".to_string()), 3),
+            (TokenValue::Delimiting(Delimiter::Comma), 3),
+            (TokenValue::String("The \'Moon\'".to_string()), 3),
+            (TokenValue::Delimiting(Delimiter::ParenthesisClosing), 3),
         ];
         assert_eq!(&detected_tokens, &expected_tokens)
     }
+
+    #[test]
+    fn tokenization_supports_operators_without_whitespace() {
+        const STATEMENT: &str = "a<=1 AND b<>'<>' OR c!=2 AND NOT d>=e";
+
+        let detected_values: Vec<TokenValue> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect();
+
+        assert_eq!(
+            detected_values,
+            vec![
+                TokenValue::Arbitrary("a".to_string()),
+                TokenValue::Delimiting(Delimiter::LessThanOrEqual),
+                TokenValue::Arbitrary("1".to_string()),
+                TokenValue::Const(Keyword::And),
+                TokenValue::Arbitrary("b".to_string()),
+                TokenValue::Delimiting(Delimiter::NotEqual),
+                TokenValue::String("<>".to_string()),
+                TokenValue::Const(Keyword::Or),
+                TokenValue::Arbitrary("c".to_string()),
+                TokenValue::Delimiting(Delimiter::NotEqual),
+                TokenValue::Arbitrary("2".to_string()),
+                TokenValue::Const(Keyword::And),
+                TokenValue::Const(Keyword::Not),
+                TokenValue::Arbitrary("d".to_string()),
+                TokenValue::Delimiting(Delimiter::GreaterThanOrEqual),
+                TokenValue::Arbitrary("e".to_string()),
+            ]
+        )
+    }
+
+    #[test]
+    fn tokenization_skips_comments() {
+        const STATEMENT: &str = "SELECT a, -- the first column
+            /* the second
+            column */ b/**/FROM test -- no filter
+            WHERE a <> '-- not a /* comment */' AND b > -1";
+
+        let detected_values: Vec<TokenValue> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect();
+
+        assert_eq!(
+            detected_values,
+            vec![
+                TokenValue::Const(Keyword::Select),
+                TokenValue::Arbitrary("a".to_string()),
+                TokenValue::Delimiting(Delimiter::Comma),
+                TokenValue::Arbitrary("b".to_string()),
+                TokenValue::Const(Keyword::From),
+                TokenValue::Arbitrary("test".to_string()),
+                TokenValue::Const(Keyword::Where),
+                TokenValue::Arbitrary("a".to_string()),
+                TokenValue::Delimiting(Delimiter::NotEqual),
+                TokenValue::String("-- not a /* comment */".to_string()),
+                TokenValue::Const(Keyword::And),
+                TokenValue::Arbitrary("b".to_string()),
+                TokenValue::Delimiting(Delimiter::GreaterThan),
+                TokenValue::Arbitrary("-1".to_string()),
+            ]
+        )
+    }
+
+    #[test]
+    fn tokenization_supports_quoted_identifiers_and_doubled_quotes() {
+        const STATEMENT: &str = r#"SELECT "key", "My ""Column""", "p"."url" FROM "default"
+            WHERE x = 'It''s' OR y = 'a\'b'''"#;
+
+        let detected_values: Vec<TokenValue> = tokenize(STATEMENT)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.value)
+            .collect();
+
+        assert_eq!(
+            detected_values,
+            vec![
+                TokenValue::Const(Keyword::Select),
+                TokenValue::Arbitrary("key".to_string()),
+                TokenValue::Delimiting(Delimiter::Comma),
+                TokenValue::Arbitrary("My \"Column\"".to_string()),
+                TokenValue::Delimiting(Delimiter::Comma),
+                TokenValue::Arbitrary("p.url".to_string()),
+                TokenValue::Const(Keyword::From),
+                TokenValue::Arbitrary("default".to_string()),
+                TokenValue::Const(Keyword::Where),
+                TokenValue::Arbitrary("x".to_string()),
+                TokenValue::Delimiting(Delimiter::Equal),
+                TokenValue::String("It's".to_string()),
+                TokenValue::Const(Keyword::Or),
+                TokenValue::Arbitrary("y".to_string()),
+                TokenValue::Delimiting(Delimiter::Equal),
+                TokenValue::String("a'b'".to_string()),
+            ]
+        );
+        assert_eq!(quote_identifier("key"), r#""key""#);
+        assert_eq!(quote_identifier("My \"Column\""), r#""My ""Column""""#);
+        assert_eq!(quote_identifier("p.url"), "p.url");
+        assert_eq!(quote_identifier("Url"), "Url");
+    }
+
+    #[test]
+    fn tokenization_tracks_spans_across_lines() {
+        const STATEMENT: &str = "SELECT 'multi\nline', \"ünïcode\"\n  FROM t";
+
+        let detected_tokens = tokenize(STATEMENT).unwrap();
+
+        let span = |offset, length, line, column| Span {
+            offset,
+            length,
+            line,
+            column,
+        };
+        assert_eq!(
+            detected_tokens,
+            vec![
+                Token {
+                    value: TokenValue::Const(Keyword::Select),
+                    span: span(0, 6, 1, 1),
+                },
+                Token {
+                    value: TokenValue::String("multi\nline".to_string()),
+                    span: span(7, 12, 1, 8),
+                },
+                Token {
+                    value: TokenValue::Delimiting(Delimiter::Comma),
+                    span: span(19, 1, 2, 6),
+                },
+                Token {
+                    value: TokenValue::Arbitrary("ünïcode".to_string()),
+                    span: span(21, 11, 2, 8),
+                },
+                Token {
+                    value: TokenValue::Const(Keyword::From),
+                    span: span(35, 4, 3, 3),
+                },
+                Token {
+                    value: TokenValue::Arbitrary("t".to_string()),
+                    span: span(40, 1, 3, 8),
+                },
+            ]
+        );
+        assert_eq!(Span::get_end_of(STATEMENT), span(41, 0, 3, 9));
+    }
+
+    #[test]
+    fn tokenization_fails_with_dots_inside_quoted_identifiers() {
+        assert_eq!(
+            tokenize("SELECT \"a.b\" FROM t"),
+            Err(SyntaxError(
+                "Quoted identifiers must not contain `.`.".to_string(),
+                Some(Span {
+                    offset: 9,
+                    length: 1,
+                    line: 1,
+                    column: 10
+                })
+            ))
+        );
+        assert_eq!(
+            tokenize("SELECT \"a\".\"b\" FROM t").map(|tokens| tokens.len()),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn tokenization_fails_with_unterminated_enclosures() {
+        assert_eq!(
+            tokenize("SELECT 'abc, \"x\" FROM t"),
+            Err(SyntaxError(
+                "Unterminated string literal.".to_string(),
+                Some(Span {
+                    offset: 7,
+                    length: 1,
+                    line: 1,
+                    column: 8
+                })
+            ))
+        );
+        assert_eq!(
+            tokenize("SELECT \"abc, 'x' FROM t"),
+            Err(SyntaxError(
+                "Unterminated quoted identifier.".to_string(),
+                Some(Span {
+                    offset: 7,
+                    length: 1,
+                    line: 1,
+                    column: 8
+                })
+            ))
+        );
+        assert_eq!(
+            tokenize("SELECT 1 /* 2 */ /* 3 * / FROM t"),
+            Err(SyntaxError(
+                "Unterminated block comment.".to_string(),
+                Some(Span {
+                    offset: 17,
+                    length: 2,
+                    line: 1,
+                    column: 18
+                })
+            ))
+        );
+    }
 }
@@ -306,10 +306,10 @@ impl<'b> EncodableWithAssumption<'b> for DataInstance {
         if assumption.is_nullable {
             let (null_marker, rest) = bool::try_decode(blob)?;
             if null_marker {
+                Ok((DataInstance::Null, rest))
+            } else {
                 let (value, rest) = DataInstanceRaw::try_decode_assume(rest, assumption.raw_type)?;
                 Ok((DataInstance::Nullable(value), rest))
-            } else {
-                Ok((DataInstance::Null, rest))
             }
         } else {
             let (value, rest) = DataInstanceRaw::try_decode_assume(blob, assumption.raw_type)?;
@@ -3,23 +3,117 @@ use super::paging::{Page, PAGE_SIZE};
 use crate::config;
 use crate::constructs::components::TableDefinition;
 use crate::storage::encoding::PageIndex;
+use std::io;
 use std::io::SeekFrom;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use tokio::fs;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 
-fn determine_table_dir_path(config: &config::Config, schema: &str, table_name: &str) -> PathBuf {
+fn determine_schema_dir_path(config: &config::Config, schema: &str) -> PathBuf {
     Path::new(&config.data_directory) // $EMDRIVE_DATA_DIRECTORY
         .join(schema) // <$EMDRIVE_DATA_DIRECTORY>/<schema>
-        .join(table_name) // <$EMDRIVE_DATA_DIRECTORY>/<schema>/<table_name>
+}
+
+fn determine_table_dir_path(config: &config::Config, schema: &str, table_name: &str) -> PathBuf {
+    determine_schema_dir_path(config, schema).join(table_name) // <$EMDRIVE_DATA_DIRECTORY>/<schema>/<table_name>
+}
+
+/// Make sure that the path lies exactly `depth` levels within the data directory before it gets removed or moved,
+/// so that a name that slipped through validation cannot affect anything else, let alone outside of the data directory.
+fn ensure_within_data_directory(
+    config: &config::Config,
+    path: &Path,
+    depth: usize,
+) -> Result<(), io::Error> {
+    let is_within = path
+        .strip_prefix(&config.data_directory)
+        .is_ok_and(|relative_path| {
+            relative_path.components().count() == depth
+                && relative_path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+        });
+    match is_within {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Path `{}` doesn't lie where expected within the data directory",
+                path.display()
+            ),
+        )),
+    }
+}
+
+/// Name of the file holding the table's rows, in the B+ tree of the primary key.
+/// Files of secondary indexes lie next to it.
+pub const TABLE_DATA_FILE_NAME: &str = "0";
+
+fn determine_table_file_path(
+    config: &config::Config,
+    schema: &str,
+    table_name: &str,
+    file_name: &str,
+) -> PathBuf {
+    determine_table_dir_path(config, schema, table_name).join(file_name) // <$EMDRIVE_DATA_DIRECTORY>/<schema>/<table_name>/<file_name>
+}
+
+pub async fn does_schema_dir_exist(config: &config::Config, schema: &str) -> bool {
+    match fs::metadata(determine_schema_dir_path(config, schema)).await {
+        Ok(metadata) => metadata.is_dir(),
+        Err(_) => false,
+    }
+}
+
+pub async fn create_schema_dir(
+    config: &config::Config,
+    schema: &str,
+) -> Result<(), std::io::Error> {
+    fs::create_dir_all(determine_schema_dir_path(config, schema)).await
+}
+
+/// Remove the schema's directory along with all the tables in it.
+pub async fn remove_schema_dir(
+    config: &config::Config,
+    schema: &str,
+) -> Result<(), std::io::Error> {
+    let path = determine_schema_dir_path(config, schema);
+    ensure_within_data_directory(config, &path, 1)?;
+    fs::remove_dir_all(path).await
+}
+
+/// Move the table's directory, along with all the files in it, under the new table name.
+pub async fn rename_table_dir(
+    config: &config::Config,
+    schema: &str,
+    table_name: &str,
+    new_table_name: &str,
+) -> Result<(), std::io::Error> {
+    let path = determine_table_dir_path(config, schema, table_name);
+    let new_path = determine_table_dir_path(config, schema, new_table_name);
+    ensure_within_data_directory(config, &path, 2)?;
+    ensure_within_data_directory(config, &new_path, 2)?;
+    fs::rename(path, new_path).await
+}
+
+/// Remove the table's directory, including its data file and index files.
+pub async fn remove_table_dir(
+    config: &config::Config,
+    schema: &str,
+    table_name: &str,
+) -> Result<(), std::io::Error> {
+    let path = determine_table_dir_path(config, schema, table_name);
+    ensure_within_data_directory(config, &path, 2)?;
+    fs::remove_dir_all(path).await
 }
 
 pub async fn does_table_file_exist(
     config: &config::Config,
     schema: &str,
     table_name: &str,
+    file_name: &str,
 ) -> bool {
-    let path = determine_table_dir_path(config, schema, table_name).join("0");
+    let path = determine_table_file_path(config, schema, table_name, file_name);
     match fs::metadata(path).await {
         Ok(metadata) => metadata.is_file(),
         Err(_) => false,
@@ -30,21 +124,49 @@ pub async fn write_table_file(
     config: &config::Config,
     schema: &str,
     table_name: &str,
+    file_name: &str,
     data: WriteBlob,
 ) -> Result<(), std::io::Error> {
-    let dir_path = determine_table_dir_path(config, schema, table_name);
-    fs::create_dir_all(&dir_path).await?;
-    let file_path = dir_path.join("0");
-    fs::write(file_path, data).await
+    fs::create_dir_all(determine_table_dir_path(config, schema, table_name)).await?;
+    fs::write(
+        determine_table_file_path(config, schema, table_name, file_name),
+        data,
+    )
+    .await
+}
+
+/// Remove a single file of the table, e.g. that of a dropped index.
+pub async fn remove_table_file(
+    config: &config::Config,
+    schema: &str,
+    table_name: &str,
+    file_name: &str,
+) -> Result<(), std::io::Error> {
+    let path = determine_table_file_path(config, schema, table_name, file_name);
+    ensure_within_data_directory(config, &path, 3)?;
+    fs::remove_file(path).await
+}
+
+/// Number of pages in the table's file, including free ones.
+pub async fn count_table_pages(
+    config: &config::Config,
+    schema: &str,
+    table_name: &str,
+    file_name: &str,
+) -> Result<PageIndex, std::io::Error> {
+    let path = determine_table_file_path(config, schema, table_name, file_name);
+    let file_size = fs::metadata(path).await?.len();
+    Ok((file_size / PAGE_SIZE as u64) as PageIndex)
 }
 
 pub async fn seek_read_page(
     config: &config::Config,
     schema: &str,
     table_name: &str,
+    file_name: &str,
     page_index: PageIndex,
 ) -> Result<Vec<u8>, std::io::Error> {
-    let path = determine_table_dir_path(config, schema, table_name).join("0");
+    let path = determine_table_file_path(config, schema, table_name, file_name);
     let mut file = fs::File::open(path).await?;
     file.seek(SeekFrom::Start(page_index as u64 * PAGE_SIZE as u64))
         .await?;
@@ -53,15 +175,23 @@ pub async fn seek_read_page(
     Ok(buffer)
 }
 
+/// Read a page of a B+ tree file, whose rows are shaped like `table_definition`.
 pub async fn seek_read_decode_page(
     config: &config::Config,
     schema: &str,
     table_definition: &TableDefinition,
+    file_name: &str,
     page_index: PageIndex,
 ) -> Result<Page, String> {
-    let buffer = seek_read_page(config, schema, &table_definition.name, page_index)
-        .await
-        .unwrap();
+    let buffer = seek_read_page(
+        config,
+        schema,
+        &table_definition.name,
+        file_name,
+        page_index,
+    )
+    .await
+    .unwrap();
     let (page, _rest) = Page::try_decode_assume(&buffer, table_definition)?;
     Ok(page)
 }
@@ -70,13 +200,15 @@ pub async fn seek_write_page(
     config: &config::Config,
     schema: &str,
     table_name: &str,
+    file_name: &str,
     page_index: PageIndex,
     data: WriteBlob,
 ) -> Result<(), std::io::Error> {
-    let path = determine_table_dir_path(config, schema, table_name).join("0");
+    let path = determine_table_file_path(config, schema, table_name, file_name);
     let mut file = fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(false)
         .open(path)
         .await?;
     file.seek(SeekFrom::Start(page_index as u64 * PAGE_SIZE as u64))
@@ -110,6 +242,7 @@ mod filesystem_tests {
                         is_nullable: false,
                     },
                     primary_key: true,
+                    metric_key: false,
                     default: None,
                 },
                 ColumnDefinition {
@@ -119,6 +252,7 @@ mod filesystem_tests {
                         is_nullable: false,
                     },
                     primary_key: false,
+                    metric_key: false,
                     default: None,
                 },
             ],
@@ -138,10 +272,16 @@ mod filesystem_tests {
             .map(char::from)
             .collect();
         let data = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
-        write_table_file(&config, schema, &table_name, data.clone())
-            .await
-            .unwrap();
-        let read_data = seek_read_page(&config, schema, &table_name, 0)
+        write_table_file(
+            &config,
+            schema,
+            &table_name,
+            TABLE_DATA_FILE_NAME,
+            data.clone(),
+        )
+        .await
+        .unwrap();
+        let read_data = seek_read_page(&config, schema, &table_name, TABLE_DATA_FILE_NAME, 0)
             .await
             .unwrap();
         assert_eq!(data, read_data);
@@ -160,21 +300,23 @@ mod filesystem_tests {
             .map(char::from)
             .collect();
         let data = construct_blank_table();
-        write_table_file(&config, schema, &table_name, data)
+        write_table_file(&config, schema, &table_name, TABLE_DATA_FILE_NAME, data)
             .await
             .unwrap();
-        let read_data_0 = seek_read_page(&config, schema, &table_name, 0)
+        let read_data_0 = seek_read_page(&config, schema, &table_name, TABLE_DATA_FILE_NAME, 0)
             .await
             .unwrap();
         let (page_0, _rest) = Page::try_decode_assume(&read_data_0, &get_test_table()).unwrap();
         assert_eq!(
             page_0,
             Page::Meta {
-                layout_version: 0,
-                b_tree_root_page_index: 1
+                layout_version: 1,
+                b_tree_root_page_index: 1,
+                first_free_page_index: 0,
+                row_count: 0
             }
         );
-        let read_data_1 = seek_read_page(&config, schema, &table_name, 1)
+        let read_data_1 = seek_read_page(&config, schema, &table_name, TABLE_DATA_FILE_NAME, 1)
             .await
             .unwrap();
         let (page_1, _rest) = Page::try_decode_assume(&read_data_1, &get_test_table()).unwrap();
@@ -200,7 +342,7 @@ mod filesystem_tests {
             .map(char::from)
             .collect();
         let data = construct_blank_table();
-        write_table_file(&config, schema, &table_name, data)
+        write_table_file(&config, schema, &table_name, TABLE_DATA_FILE_NAME, data)
             .await
             .unwrap();
         let page = Page::BTreeLeaf {
@@ -216,13 +358,78 @@ mod filesystem_tests {
                 ]),
             ],
         };
-        seek_write_page(&config, schema, &table_name, 1, page.clone().into())
-            .await
-            .unwrap();
-        let read_data = seek_read_page(&config, schema, &table_name, 1)
+        seek_write_page(
+            &config,
+            schema,
+            &table_name,
+            TABLE_DATA_FILE_NAME,
+            1,
+            page.clone().into(),
+        )
+        .await
+        .unwrap();
+        let read_data = seek_read_page(&config, schema, &table_name, TABLE_DATA_FILE_NAME, 1)
             .await
             .unwrap();
         let (decoded_page, _rest) = Page::try_decode_assume(&read_data, &get_test_table()).unwrap();
         assert_eq!(page, decoded_page);
     }
+
+    #[tokio::test]
+    async fn nothing_outside_of_data_directory_is_removed_or_moved() {
+        let random_name = || -> String {
+            thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(char::from)
+                .collect()
+        };
+        let config = config::Config {
+            data_directory: format!("{}/{}", env!("TMPDIR"), random_name()),
+            ..Default::default()
+        };
+        let outside_path = format!("{}/{}", env!("TMPDIR"), random_name());
+        write_table_file(
+            &config,
+            &outside_path,
+            "victim",
+            TABLE_DATA_FILE_NAME,
+            construct_blank_table(),
+        )
+        .await
+        .unwrap();
+        write_table_file(
+            &config,
+            "test",
+            "a",
+            TABLE_DATA_FILE_NAME,
+            construct_blank_table(),
+        )
+        .await
+        .unwrap();
+
+        assert!(remove_schema_dir(&config, &outside_path).await.is_err());
+        assert!(remove_table_dir(&config, &outside_path, "victim")
+            .await
+            .is_err());
+        assert!(remove_table_dir(&config, "..", "test").await.is_err());
+        assert!(
+            remove_table_file(&config, &outside_path, "victim", TABLE_DATA_FILE_NAME)
+                .await
+                .is_err()
+        );
+        assert!(
+            rename_table_dir(&config, "test", "a", &format!("{}/moved", outside_path))
+                .await
+                .is_err()
+        );
+        assert!(rename_table_dir(&config, "test", "a", "b/c").await.is_err());
+        assert!(
+            does_table_file_exist(&config, &outside_path, "victim", TABLE_DATA_FILE_NAME).await
+        );
+        assert!(does_table_file_exist(&config, "test", "a", TABLE_DATA_FILE_NAME).await);
+
+        remove_table_dir(&config, "test", "a").await.unwrap();
+        assert!(!does_table_file_exist(&config, "test", "a", TABLE_DATA_FILE_NAME).await);
+    }
 }
@@ -10,14 +10,16 @@ use tracing::*;
 // Massively work-in-progress!
 
 pub struct Index<'a> {
+    schema: String,
     collection_name: String,
     data: Vec<u128>,
     config: &'a Config,
 }
 
 impl<'a> Index<'a> {
-    pub fn new(collection_name: &str, config: &'a Config) -> Self {
+    pub fn new(schema: &str, collection_name: &str, config: &'a Config) -> Self {
         let mut index = Index {
+            schema: schema.to_string(),
             collection_name: collection_name.to_string(),
             data: vec![],
             config,
@@ -32,9 +34,9 @@ impl<'a> Index<'a> {
 
     fn get_file_path(&self) -> path::PathBuf {
         path::Path::new(&self.config.data_directory)
-            .join("data")
+            .join(&self.schema)
             .join(&self.collection_name)
-            .join("index")
+            .join("metric")
     }
 
     fn read_file(&self) -> io::Result<Vec<u8>> {
@@ -52,7 +54,7 @@ impl<'a> Index<'a> {
     fn parse_index_raw_data(&self, raw_data: Vec<u8>) -> Vec<u128> {
         let entry_size = mem::size_of::<u128>();
         let raw_data_size = raw_data.len();
-        if raw_data_size % entry_size != 0 {
+        if !raw_data_size.is_multiple_of(entry_size) {
             panic!("Size of index data for collection `{}` must be a multiple of {} bytes, instead found {} bytes!", &self.collection_name, entry_size, raw_data_size);
         }
         raw_data
@@ -66,6 +68,31 @@ impl<'a> Index<'a> {
         self.sync_to_disk();
     }
 
+    pub fn remove(&mut self, value: u128) {
+        if let Some(position) = self.data.iter().position(|existing| *existing == value) {
+            self.data.swap_remove(position);
+            self.sync_to_disk();
+        }
+    }
+
+    pub fn replace(&mut self, old_value: u128, new_value: u128) {
+        match self.data.iter().position(|value| *value == old_value) {
+            Some(position) => self.data[position] = new_value,
+            None => self.data.push(new_value),
+        }
+        self.sync_to_disk();
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.sync_to_disk();
+    }
+
+    /// Remove the index file altogether, e.g. because the indexed column is gone.
+    pub fn destroy(self) -> io::Result<()> {
+        fs::remove_file(self.get_file_path())
+    }
+
     pub fn sync_from_disk(&mut self) -> Vec<u128> {
         let raw_data = self.read_file();
         let data = match raw_data {
@@ -94,7 +121,7 @@ impl<'a> Index<'a> {
             .data
             .iter()
             .flat_map(|x| {
-                let bytes: Vec<u8> = x.to_be_bytes().iter().copied().collect();
+                let bytes: Vec<u8> = x.to_be_bytes().to_vec();
                 bytes
             })
             .collect();
@@ -110,6 +137,7 @@ mod tests {
     #[test]
     fn raw_data_parsing_works() {
         let dummy_index = Index {
+            schema: "test".to_string(),
             collection_name: "test".to_string(),
             data: vec![],
             config: &Config::default(),
@@ -132,6 +160,7 @@ mod tests {
     )]
     fn raw_data_parsing_panics_when_data_is_wrong_size() {
         let dummy_index = Index {
+            schema: "test".to_string(),
             collection_name: "test".to_string(),
             data: vec![],
             config: &Config::default(),
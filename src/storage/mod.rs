@@ -1,6 +1,6 @@
 pub mod encoding;
 pub mod filesystem;
-mod index;
+pub mod index;
 pub mod paging;
 pub mod system;
 
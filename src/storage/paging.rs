@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, mem};
 
 use crate::constructs::components::{DataInstanceRaw, TableDefinition};
 
@@ -8,7 +8,7 @@ use super::encoding::*;
 pub const PAGE_SIZE: usize = 8 * 1024;
 
 /// Latest version of disk data layout. Useful for determining layout compatibility.
-const LATEST_LAYOUT_VERSION: u8 = 0;
+const LATEST_LAYOUT_VERSION: u8 = 1;
 
 pub fn empty_page_blob() -> WriteBlob {
     vec![0; PAGE_SIZE]
@@ -21,6 +21,8 @@ pub fn construct_blank_table() -> WriteBlob {
         &mut Page::Meta {
             layout_version: LATEST_LAYOUT_VERSION,
             b_tree_root_page_index: 1,
+            first_free_page_index: 0,
+            row_count: 0,
         }
         .into(),
     );
@@ -35,6 +37,11 @@ pub fn construct_blank_table() -> WriteBlob {
     core_blob
 }
 
+/// Values of the key columns of a row, which is what a B+ tree is ordered by. For table data that's
+/// just the PRIMARY KEY value, while B+ trees of indexes have composite keys. Keys are compared
+/// lexicographically, one column after another.
+pub type Key = Vec<DataInstanceRaw>;
+
 /// Possible core page types.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Page {
@@ -44,11 +51,20 @@ pub enum Page {
         layout_version: u8,
         /// Page index of the B+ tree root. This is the single leaf when tree height is 1, after that it's a node.
         b_tree_root_page_index: PageIndex,
+        /// Page index of the first page in the free list. 0 means that there are no free pages.
+        first_free_page_index: PageIndex,
+        /// Number of rows in the B+ tree, kept up to date on every write so that it's cheap to get.
+        row_count: GlobalCount,
+    },
+    /// Page that is not in use and can be reused, as part of the free list.
+    Free {
+        /// Page index of the next free page. 0 means that this is the last one.
+        next_free_page_index: PageIndex,
     },
     /// B+ tree node.
     BTreeNode {
         /// N primary keys.
-        primary_keys: Vec<DataInstanceRaw>,
+        primary_keys: Vec<Key>,
         /// N+1 pointers to child pages.
         child_page_indexes: Vec<PageIndex>,
     },
@@ -61,6 +77,52 @@ pub enum Page {
     },
 }
 
+impl Page {
+    /// How many bytes are needed to encode this page's contents. Must not exceed `PAGE_SIZE`.
+    pub fn encoded_size(&self) -> usize {
+        match self {
+            Self::Meta { .. } => {
+                1 + 1 + 2 * mem::size_of::<PageIndex>() + mem::size_of::<GlobalCount>()
+            }
+            Self::Free { .. } => 1 + mem::size_of::<PageIndex>(),
+            Self::BTreeNode {
+                primary_keys,
+                child_page_indexes,
+            } => {
+                1 + mem::size_of::<LocalCount>()
+                    + primary_keys
+                        .iter()
+                        .flatten()
+                        .map(|key_value| key_value.encoded_size())
+                        .sum::<usize>()
+                    + child_page_indexes.len() * mem::size_of::<PageIndex>()
+            }
+            Self::BTreeLeaf { rows, .. } => {
+                1 + mem::size_of::<PageIndex>()
+                    + mem::size_of::<LocalCount>()
+                    // Each row takes up its own size, plus the size of its address
+                    + rows
+                        .iter()
+                        .map(|row| mem::size_of::<LocalCount>() + row.encoded_size())
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    /// Whether this page's contents fit into a single page.
+    pub fn fits(&self) -> bool {
+        self.encoded_size() <= PAGE_SIZE
+    }
+
+    /// Rows of a B+ tree leaf.
+    pub fn into_rows(self) -> Vec<Row> {
+        match self {
+            Self::BTreeLeaf { rows, .. } => rows,
+            _ => panic!("Only a B+ tree leaf contains rows"),
+        }
+    }
+}
+
 impl From<Page> for WriteBlob {
     fn from(page: Page) -> WriteBlob {
         let mut page_blob: WriteBlob = empty_page_blob();
@@ -68,13 +130,27 @@ impl From<Page> for WriteBlob {
             Page::Meta {
                 layout_version,
                 b_tree_root_page_index,
+                first_free_page_index,
+                row_count,
             } => {
                 // 1. Page type marker
                 let position = 0x00u8.encode(&mut page_blob, 0);
                 // 2. Layout version
                 let position = layout_version.encode(&mut page_blob, position);
                 // 3. B+ tree root page index
-                let _final_position = b_tree_root_page_index.encode(&mut page_blob, position);
+                let position = b_tree_root_page_index.encode(&mut page_blob, position);
+                // 4. Free list head page index
+                let position = first_free_page_index.encode(&mut page_blob, position);
+                // 5. Row count
+                let _final_position = row_count.encode(&mut page_blob, position);
+            }
+            Page::Free {
+                next_free_page_index,
+            } => {
+                // 1. Page type marker
+                let position = 0x10u8.encode(&mut page_blob, 0);
+                // 2. Next free page index
+                let _final_position = next_free_page_index.encode(&mut page_blob, position);
             }
             Page::BTreeNode {
                 primary_keys,
@@ -88,8 +164,8 @@ impl From<Page> for WriteBlob {
                     .encode(&mut page_blob, position);
                 // 3. Primary keys
                 assert_eq!(primary_keys.len(), child_page_indexes.len() - 1);
-                for primary_key in primary_keys {
-                    position = primary_key.encode(&mut page_blob, position);
+                for key_value in primary_keys.into_iter().flatten() {
+                    position = key_value.encode(&mut page_blob, position);
                 }
                 // 4. Child page indexes
                 for child_page_index in child_page_indexes {
@@ -140,11 +216,25 @@ impl<'b> EncodableWithAssumption<'b> for Page {
             // Meta
             0x00 => {
                 let (layout_version, rest) = u8::try_decode(&blob[1..])?;
-                let (b_tree_root_page_index, _final_rest) = PageIndex::try_decode(rest)?;
+                let (b_tree_root_page_index, rest) = PageIndex::try_decode(rest)?;
+                let (first_free_page_index, rest) = PageIndex::try_decode(rest)?;
+                let (row_count, _final_rest) = GlobalCount::try_decode(rest)?;
                 Ok((
                     Self::Meta {
                         layout_version,
                         b_tree_root_page_index,
+                        first_free_page_index,
+                        row_count,
+                    },
+                    next_page,
+                ))
+            }
+            // Free
+            0x10 => {
+                let (next_free_page_index, _final_rest) = PageIndex::try_decode(&blob[1..])?;
+                Ok((
+                    Self::Free {
+                        next_free_page_index,
                     },
                     next_page,
                 ))
@@ -153,13 +243,20 @@ impl<'b> EncodableWithAssumption<'b> for Page {
             0x20 => {
                 let (arity, rest) = LocalCount::try_decode(&blob[1..])?;
                 let mut rest = rest;
-                let mut primary_keys: Vec<DataInstanceRaw> = Vec::with_capacity(arity as usize - 1);
+                let key_types: Vec<_> = assumption
+                    .get_key_indexes()
+                    .into_iter()
+                    .map(|column_index| assumption.columns[column_index].data_type.raw_type)
+                    .collect();
+                let mut primary_keys: Vec<Key> = Vec::with_capacity(arity as usize - 1);
                 for _ in 0..(arity as usize - 1) {
-                    let (primary_key, iteration_rest) = DataInstanceRaw::try_decode_assume(
-                        rest,
-                        assumption.get_primary_key().data_type.raw_type,
-                    )?;
-                    rest = iteration_rest;
+                    let mut primary_key = Vec::with_capacity(key_types.len());
+                    for key_type in &key_types {
+                        let (key_value, iteration_rest) =
+                            DataInstanceRaw::try_decode_assume(rest, *key_type)?;
+                        rest = iteration_rest;
+                        primary_key.push(key_value);
+                    }
                     primary_keys.push(primary_key);
                 }
                 let mut child_page_indexes: Vec<PageIndex> = Vec::with_capacity(arity as usize);
@@ -204,7 +301,7 @@ impl<'b> EncodableWithAssumption<'b> for Page {
                 ))
             }
             _ => Err(format!(
-                "Invalid page type marker byte {:#04x} - recognized values are: 0x00, 0x20, 0x21",
+                "Invalid page type marker byte {:#04x} - recognized values are: 0x00, 0x10, 0x20, 0x21",
                 blob[0]
             )),
         }
@@ -232,6 +329,7 @@ mod core_serialization_tests {
                         is_nullable: false,
                     },
                     primary_key: true,
+                    metric_key: false,
                     default: None,
                 },
                 ColumnDefinition {
@@ -241,6 +339,7 @@ mod core_serialization_tests {
                         is_nullable: false,
                     },
                     primary_key: false,
+                    metric_key: false,
                     default: None,
                 },
             ],
@@ -257,7 +356,9 @@ mod core_serialization_tests {
             page_0,
             Page::Meta {
                 layout_version: LATEST_LAYOUT_VERSION,
-                b_tree_root_page_index: 1
+                b_tree_root_page_index: 1,
+                first_free_page_index: 0,
+                row_count: 0
             }
         );
         let (page_1, _rest) = Page::try_decode_assume(rest, &tables_definition).unwrap();
@@ -356,7 +457,7 @@ mod core_serialization_tests {
     #[test]
     fn node_de_serialization_works() {
         let leaf_blob: WriteBlob = Page::BTreeNode {
-            primary_keys: vec![DataInstanceRaw::Uuid(Uuid::from_u128(123))],
+            primary_keys: vec![vec![DataInstanceRaw::Uuid(Uuid::from_u128(123))]],
             child_page_indexes: vec![3u32, 4u32],
         }
         .into();
@@ -364,7 +465,7 @@ mod core_serialization_tests {
         assert_eq!(
             leaf_page,
             Page::BTreeNode {
-                primary_keys: vec![DataInstanceRaw::Uuid(Uuid::from_u128(123))],
+                primary_keys: vec![vec![DataInstanceRaw::Uuid(Uuid::from_u128(123))]],
                 child_page_indexes: vec![3u32, 4u32],
             }
         );
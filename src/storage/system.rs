@@ -1,15 +1,26 @@
 use crate::constructs::components::{ColumnDefinition, DataType, DataTypeRaw, TableDefinition};
 
 pub const SYSTEM_SCHEMA_NAME: &str = "system";
+/// Schema in which user tables live.
+pub const DEFAULT_SCHEMA_NAME: &str = "public";
 
 pub enum SystemTable {
     Tables,
     Columns,
+    Indexes,
+    Constraints,
+    Statistics,
 }
 
 impl SystemTable {
     /// Array of all system tables.
-    pub const ALL: [Self; 2] = [Self::Tables, Self::Columns];
+    pub const ALL: [Self; 5] = [
+        Self::Tables,
+        Self::Columns,
+        Self::Indexes,
+        Self::Constraints,
+        Self::Statistics,
+    ];
 
     pub fn get_definition(&self) -> TableDefinition {
         match self {
@@ -23,6 +34,7 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: true,
+                        metric_key: false,
                         default: None,
                     },
                     ColumnDefinition {
@@ -32,6 +44,7 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: false,
+                        metric_key: false,
                         default: None,
                     },
                     ColumnDefinition {
@@ -41,6 +54,7 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: false,
+                        metric_key: false,
                         default: None,
                     },
                 ],
@@ -55,15 +69,27 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: true,
+                        metric_key: false,
                         default: None,
                     },
                     ColumnDefinition {
                         name: "table_id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "column_name".into(),
                         data_type: DataType {
                             raw_type: DataTypeRaw::String,
                             is_nullable: false,
                         },
                         primary_key: false,
+                        metric_key: false,
                         default: None,
                     },
                     ColumnDefinition {
@@ -73,6 +99,7 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: false,
+                        metric_key: false,
                         default: None,
                     },
                     ColumnDefinition {
@@ -82,6 +109,262 @@ impl SystemTable {
                             is_nullable: false,
                         },
                         primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "position".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::UInt16,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "is_primary_key".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Bool,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "is_metric_key".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Bool,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "default_value".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: true,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                ],
+            ),
+            Self::Indexes => TableDefinition::new(
+                "indexes".into(),
+                vec![
+                    ColumnDefinition {
+                        name: "id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: true,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "table_id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "index_name".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "column_names".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "is_unique".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Bool,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                ],
+            ),
+            Self::Constraints => TableDefinition::new(
+                "constraints".into(),
+                vec![
+                    ColumnDefinition {
+                        name: "id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: true,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "table_id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "constraint_name".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "kind".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "definition".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                ],
+            ),
+            Self::Statistics => TableDefinition::new(
+                "statistics".into(),
+                vec![
+                    ColumnDefinition {
+                        name: "id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: true,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "table_id".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Uuid,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "column_name".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "row_count".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::UInt64,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "sampled_row_count".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::UInt64,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "null_count".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::UInt64,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "distinct_count".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::UInt64,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "histogram_bounds".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "distance_counts".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::String,
+                            is_nullable: true,
+                        },
+                        primary_key: false,
+                        metric_key: false,
+                        default: None,
+                    },
+                    ColumnDefinition {
+                        name: "analyzed_at".into(),
+                        data_type: DataType {
+                            raw_type: DataTypeRaw::Timestamp,
+                            is_nullable: false,
+                        },
+                        primary_key: false,
+                        metric_key: false,
                         default: None,
                     },
                 ],